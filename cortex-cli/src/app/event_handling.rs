@@ -5,7 +5,7 @@ use cortex_core::{
     ActivePanel,
 };
 use cortex_tui::{Dialog, Event};
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use crossterm::{
     event::DisableMouseCapture,
     execute,
@@ -13,8 +13,11 @@ use crossterm::{
     terminal::{disable_raw_mode, Clear, ClearType, LeaveAlternateScreen},
 };
 use std::io;
+#[cfg(feature = "ssh")]
+use tokio::sync::mpsc;
 
-use super::App;
+use crate::command::CommandProcessor;
+use super::{App, SavedSearchInput};
 
 impl App {
     /// Main input event handling loop
@@ -50,6 +53,14 @@ impl App {
 
     /// Handle input when no dialog is active
     async fn handle_input(&mut self, key: KeyEvent) -> Result<bool> {
+        let interrupt = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if interrupt && self.state.command_running {
+            if let Some(cancel) = self.command_cancel.take() {
+                cancel.cancel();
+                self.state.set_status_message("Cancelling the command");
+                return Ok(true);
+            }
+        }
         if let Some(action) = self.shortcut_manager.get_action(key.code, key.modifiers) {
             if !self.handle_action(action).await? {
                 return Ok(false);
@@ -67,8 +78,11 @@ impl App {
                     }
                 }
                 KeyCode::Esc => {
-                    self.state.command_line.clear();
-                    self.state.command_cursor = 0;
+                    let cancelled = self.cancel_vfs_listing();
+                    if !cancelled {
+                        self.state.command_line.clear();
+                        self.state.command_cursor = 0;
+                    }
                 }
                 _ => {}
             }
@@ -93,17 +107,56 @@ impl App {
                     panel.update_view_offset(size.height as usize - 5);
                 }
             }
+            Action::NavigateToParent if Self::in_git_history(self.state.active_panel()) => {
+                // Follow the listing's ".." so trees step back to the revision list first
+                let parent = self
+                    .state
+                    .active_panel()
+                    .vfs_entries
+                    .iter()
+                    .find(|entry| entry.name == "..")
+                    .map(|entry| entry.path.clone());
+                match parent {
+                    Some(path) => self.open_vfs_path(path)?,
+                    None => {
+                        self.cancel_vfs_listing();
+                        self.state.navigate_back_from_vfs()?;
+                        self.refresh_needed = true;
+                    }
+                }
+            }
+            Action::NavigateToParent if self.state.active_panel().is_using_vfs() => {
+                self.cancel_vfs_listing();
+                self.state.navigate_back_from_vfs()?;
+                self.refresh_needed = true;
+            }
+            Action::NavigateToParent if self.state.active_panel().search_listing.is_some() => {
+                // Leave the results for the directory they were searched from
+                let root = self.state.active_panel().current_dir.clone();
+                self.navigate_to_directory(root)?;
+            }
             Action::NavigateToParent => {
                 let current_dir = self.state.active_panel().current_dir.clone();
                 if let Some(parent) = current_dir.parent() {
                     let _ = self.navigate_to_directory(parent.to_path_buf());
                 }
             }
+            Action::NavigateInto if self.state.active_panel().is_using_vfs() => {
+                let current_entry = self.state.active_panel().current_vfs_entry().cloned();
+                if let Some(entry) = current_entry {
+                    if matches!(entry.entry_type, cortex_core::VfsEntryType::Directory) {
+                        self.open_vfs_path(entry.path)?;
+                    }
+                }
+            }
             Action::NavigateInto => {
                 let current_entry = self.state.active_panel().current_entry().cloned();
                 if let Some(entry) = current_entry {
                     if entry.file_type == cortex_core::fs::FileType::Directory {
-                        if entry.name == ".." {
+                        if Self::is_git_dir_of_repo(self.state.active_panel(), &entry) {
+                            // A repository's .git opens its history, like entering an archive
+                            self.open_git_history();
+                        } else if entry.name == ".." {
                             let current_dir = self.state.active_panel().current_dir.clone();
                             if let Some(parent) = current_dir.parent() {
                                 let _ = self.navigate_to_directory(parent.to_path_buf());
@@ -128,9 +181,19 @@ impl App {
             Action::Delete => self.handle_delete_operation().await?,
             Action::CreateDirectory => self.handle_create_directory_operation().await?,
             Action::Rename => self.handle_rename_operation().await?,
+            Action::MultiRename => self.handle_multi_rename_operation(),
             Action::NewFile => self.handle_new_file_operation().await?,
             Action::ViewFile => self.handle_view_file_operation().await?,
             Action::EditFile => self.handle_edit_file_operation().await?,
+            Action::HexEditFile => {
+                if self.state.active_panel().is_using_vfs() {
+                    self.state.set_status_message("Hex editing works on local files only");
+                } else if let Some(entry) = self.state.active_panel().current_entry().cloned() {
+                    if entry.file_type == cortex_core::fs::FileType::File {
+                        self.open_hex_editor(&entry.path, 0);
+                    }
+                }
+            }
 
             // Sorting
             Action::SortByName => {
@@ -169,12 +232,34 @@ impl App {
 
             // View
             Action::QuickFilter => self.handle_quick_filter_operation().await?,
-            Action::FindInFiles => {
-                self.state.set_status_message("Find in files not yet implemented");
+            Action::Search => {
+                self.dialog = Some(Dialog::Search(cortex_tui::SearchDialog::new()));
             }
+            Action::FindInFiles => {
+                let mut dialog = cortex_tui::SearchDialog::new();
+                dialog.criteria.search_type = cortex_core::SearchType::Contains;
+                dialog.criteria.search_in_files = true;
+                self.dialog = Some(Dialog::Search(dialog));
+            }
+            Action::FuzzyFind => self.open_fuzzy_finder(),
+            Action::RerunSearch => match self.saved_searches.last().cloned() {
+                Some(last) => self.run_saved_search(last)?,
+                None => self.state.set_status_message("No search to rerun"),
+            },
             Action::GoToLine => {
                 self.state.set_status_message("Go to line not yet implemented");
             }
+            Action::QuickView => {
+                if self.state.quick_view.take().is_some() {
+                    if let Some(cancel) = self.quick_view_cancel.take() {
+                        cancel.cancel();
+                    }
+                    self.quick_view_rx = None;
+                    self.quick_view_due = None;
+                } else {
+                    self.state.quick_view = Some(cortex_core::QuickView::default());
+                }
+            }
             Action::ToggleTreeView => {
                 self.state.set_status_message("Tree view not yet implemented");
             }
@@ -208,10 +293,10 @@ impl App {
                 self.state.set_status_message("Tree panel not yet implemented");
             }
             Action::ChangeDriveLeft => {
-                self.state.set_status_message("Change drive not yet implemented");
+                self.open_drive_menu(ActivePanel::Left).await;
             }
             Action::ChangeDriveRight => {
-                self.state.set_status_message("Change drive not yet implemented");
+                self.open_drive_menu(ActivePanel::Right).await;
             }
 
             // Bookmarks and Quick Directories
@@ -236,6 +321,7 @@ impl App {
             Action::CreateArchive => {
                 self.state.set_status_message("Creating archives not yet implemented");
             }
+            Action::GitHistory => self.open_git_history(),
             Action::SftpConnect => {
                 self.state.set_status_message("SFTP connection not yet implemented");
             }
@@ -299,6 +385,7 @@ impl App {
             Action::Help => {
                 self.dialog = Some(Dialog::Help(cortex_tui::HelpDialog::new()));
             }
+            Action::CommandPalette => self.open_command_palette(),
 
             // Command Line
             Action::ShellCommand => {
@@ -341,16 +428,25 @@ impl App {
                         dialog.toggle_selection();
                     }
                     KeyCode::Enter => {
-                        if dialog.selected {
-                            if let Some(operation) = self.state.pending_operation.take() {
-                                self.execute_operation(operation).await?;
+                        let confirmed = dialog.selected;
+                        if self.state.pending_operation.is_none() && self.remote_edit.is_some() {
+                            self.dialog = None;
+                            self.resolve_remote_edit_conflict(confirmed).await;
+                        } else {
+                            if confirmed {
+                                if let Some(operation) = self.state.pending_operation.take() {
+                                    self.execute_operation(operation).await?;
+                                }
                             }
+                            self.dialog = None;
+                            self.state.pending_operation = None;
                         }
-                        self.dialog = None;
-                        self.state.pending_operation = None;
                     }
                     KeyCode::Esc => {
                         self.dialog = None;
+                        if self.state.pending_operation.is_none() && self.remote_edit.is_some() {
+                            self.resolve_remote_edit_conflict(false).await;
+                        }
                         self.state.pending_operation = None;
                     }
                     _ => {}
@@ -371,6 +467,11 @@ impl App {
                         dialog.move_cursor_right();
                     }
                     KeyCode::Enter => {
+                        if let Some(input) = self.saved_search_input.take() {
+                            let name = dialog.value.clone();
+                            self.dialog = None;
+                            return self.name_saved_search(input, &name).map(|_| true);
+                        }
                         if let Some(op) = self.state.pending_operation.take() {
                             match op {
                                 FileOperation::CreateDir { path } => {
@@ -403,6 +504,13 @@ impl App {
                                 FileOperation::Filter { .. } => {
                                     self.state.active_panel_mut().apply_filter(&dialog.value);
                                 }
+                                FileOperation::MultiRename { paths, .. } => {
+                                    let new_operation = FileOperation::MultiRename {
+                                        paths,
+                                        pattern: dialog.value.clone(),
+                                    };
+                                    self.execute_operation(new_operation).await?;
+                                }
                                 _ => {}
                             }
                         }
@@ -410,11 +518,191 @@ impl App {
                     }
                     KeyCode::Esc => {
                         self.dialog = None;
+                        self.saved_search_input = None;
                         self.state.pending_operation = None;
                     }
                     _ => {}
                 }
             }
+            Some(Dialog::Progress(_)) => {
+                if key.code == KeyCode::Esc {
+                    if let Some(cancel) = self.transfer_cancel.take() {
+                        cancel.cancel();
+                        self.state.set_status_message("Transfer cancelled");
+                    }
+                    self.dialog = None;
+                }
+            }
+            Some(Dialog::Drive(dialog)) => {
+                match key.code {
+                    KeyCode::Up => dialog.move_up(),
+                    KeyCode::Down => dialog.move_down(),
+                    KeyCode::Enter => {
+                        let target = dialog.target;
+                        let location = dialog.selected().map(|item| item.location.clone());
+                        self.dialog = None;
+                        if let Some(location) = location {
+                            self.state.active_panel = target;
+                            self.open_vfs_path(location)?;
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.dialog = None;
+                    }
+                    _ => {}
+                }
+            }
+            Some(Dialog::CommandPalette(dialog)) => {
+                match key.code {
+                    KeyCode::Char(c) => dialog.insert_char(c),
+                    KeyCode::Backspace => dialog.delete_char(),
+                    KeyCode::Left => dialog.move_cursor_left(),
+                    KeyCode::Right => dialog.move_cursor_right(),
+                    KeyCode::Up => dialog.move_selection_up(),
+                    KeyCode::Down => dialog.move_selection_down(),
+                    KeyCode::Enter => {
+                        let command = dialog.get_selected_command();
+                        self.dialog = None;
+                        if let Some(command) = command {
+                            let command = command.strip_prefix('/').unwrap_or(&command).to_string();
+                            return self.handle_palette_command(&command).await;
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.dialog = None;
+                    }
+                    _ => {}
+                }
+            }
+            Some(Dialog::Search(dialog)) => match dialog.state {
+                cortex_tui::SearchState::Setup => match key.code {
+                    KeyCode::Enter => {
+                        dialog.criteria.file_extensions.retain(|ext| !ext.is_empty());
+                        if let Err(e) = dialog.apply_inputs() {
+                            self.state.set_status_message(format!("Invalid search: {}", e));
+                        } else {
+                            let criteria = &dialog.criteria;
+                            let filtered = criteria.conditions.is_some()
+                                || criteria.size_filter.is_some()
+                                || criteria.date_filter.is_some();
+                            if !criteria.pattern.is_empty() || filtered {
+                                self.start_search();
+                            }
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.dialog = None;
+                    }
+                    KeyCode::Tab => dialog.next_field(),
+                    KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => match c {
+                        't' => dialog.cycle_search_type(),
+                        's' => dialog.toggle_case_sensitive(),
+                        'f' => dialog.toggle_search_in_files(),
+                        'h' => dialog.toggle_include_hidden(),
+                        'r' => dialog.toggle_include_subdirs(),
+                        'a' => dialog.toggle_search_archives(),
+                        'g' => dialog.toggle_respect_ignore_files(),
+                        _ => {}
+                    },
+                    KeyCode::Char(c) => dialog.insert_char(c),
+                    KeyCode::Backspace => dialog.delete_char(),
+                    _ => {}
+                },
+                cortex_tui::SearchState::Searching => {
+                    if key.code == KeyCode::Esc {
+                        // Keep what was found so far
+                        if let Some(cancel) = self.search_cancel.take() {
+                            cancel.cancel();
+                        }
+                        self.search_rx = None;
+                        dialog.state = cortex_tui::SearchState::Results;
+                        self.state.set_status_message(format!(
+                            "Search cancelled: {} results found",
+                            dialog.results.len()
+                        ));
+                    }
+                }
+                cortex_tui::SearchState::Results => match key.code {
+                    KeyCode::Up => dialog.move_selection_up(),
+                    KeyCode::Down => dialog.move_selection_down(),
+                    KeyCode::F(7) => dialog.state = cortex_tui::SearchState::Setup,
+                    KeyCode::F(9) => self.feed_search_results(),
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => self.open_replace(),
+                    KeyCode::Enter | KeyCode::F(3) | KeyCode::F(4) => {
+                        let path = dialog.get_selected_path();
+                        let location = dialog.get_selected_location();
+                        self.dialog = None;
+                        if let Some(location) = location {
+                            // Archive members and remote files are shown from their directory
+                            if let Some(parent) = location.parent() {
+                                self.open_vfs_path(parent)?;
+                            }
+                            if let Some(path) = path {
+                                self.state.set_status_message(format!("Found: {}", path.display()));
+                            }
+                        } else if let Some(path) = path {
+                            self.reveal_path(&path)?;
+                            match key.code {
+                                KeyCode::F(3) => self.handle_view_file_operation().await?,
+                                KeyCode::F(4) => self.handle_edit_file_operation().await?,
+                                _ => {}
+                            }
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.dialog = None;
+                    }
+                    _ => {}
+                },
+            },
+            Some(Dialog::FuzzyFinder(dialog)) => match key.code {
+                KeyCode::Enter => {
+                    let path = dialog.selected_path();
+                    self.dialog = None;
+                    self.finder_rx = None;
+                    if let Some(path) = path {
+                        self.reveal_path(&path)?;
+                    }
+                }
+                KeyCode::Esc => {
+                    self.dialog = None;
+                    self.finder_rx = None;
+                }
+                KeyCode::Up => dialog.move_selection_up(),
+                KeyCode::Down => dialog.move_selection_down(),
+                KeyCode::PageUp => dialog.page_up(10),
+                KeyCode::PageDown => dialog.page_down(10),
+                KeyCode::Backspace => dialog.delete_char(),
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => dialog.clear_query(),
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => dialog.insert_char(c),
+                _ => {}
+            },
+            Some(Dialog::Replace(dialog)) => match dialog.state {
+                cortex_tui::ReplaceState::Setup => match key.code {
+                    KeyCode::Enter => self.plan_replace(),
+                    KeyCode::Esc => self.dialog = None,
+                    KeyCode::Tab => dialog.next_field(),
+                    KeyCode::Backspace => dialog.delete_char(),
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => dialog.insert_char(c),
+                    _ => {}
+                },
+                cortex_tui::ReplaceState::Planning => {
+                    if key.code == KeyCode::Esc {
+                        self.dialog = None;
+                        self.replace_rx = None;
+                    }
+                }
+                cortex_tui::ReplaceState::Preview => match key.code {
+                    KeyCode::Up => dialog.move_selection_up(),
+                    KeyCode::Down => dialog.move_selection_down(),
+                    KeyCode::PageUp => dialog.page_up(10),
+                    KeyCode::PageDown => dialog.page_down(10),
+                    KeyCode::Char(' ') => dialog.toggle_selected(),
+                    KeyCode::Enter => self.apply_replace()?,
+                    KeyCode::Esc => dialog.state = cortex_tui::ReplaceState::Setup,
+                    _ => {}
+                },
+            },
             Some(Dialog::Error(_)) => {
                 if matches!(key.code, KeyCode::Enter | KeyCode::Esc) {
                     self.dialog = None;
@@ -524,10 +812,469 @@ impl App {
                     }
                 }
             }
+            Some(Dialog::Editor(dialog)) => {
+                if dialog.save_as_mode {
+                    match key.code {
+                        KeyCode::Char(c) => dialog.save_as_input.push(c),
+                        KeyCode::Backspace => {
+                            dialog.save_as_input.pop();
+                        }
+                        KeyCode::Enter if !dialog.save_as_input.is_empty() => {
+                            dialog.save_as_mode = false;
+                            // Relative paths are taken from the file's folder
+                            let target = dialog
+                                .editor
+                                .path
+                                .parent()
+                                .unwrap_or(std::path::Path::new(""))
+                                .join(std::mem::take(&mut dialog.save_as_input));
+                            let (encoding, line_ending) = (dialog.editor.encoding, dialog.editor.line_ending);
+                            if let Err(e) = dialog.editor.save_as(&target, encoding, line_ending) {
+                                dialog.editor.status_message = Some(format!("Save failed: {}", e));
+                            }
+                        }
+                        KeyCode::Esc => {
+                            dialog.save_as_mode = false;
+                            dialog.save_as_input.clear();
+                        }
+                        _ => {}
+                    }
+                } else if dialog.search_mode {
+                    match key.code {
+                        KeyCode::Char(c) => {
+                            dialog.search_input.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            dialog.search_input.pop();
+                        }
+                        KeyCode::Enter if !dialog.search_input.is_empty() => {
+                            dialog.editor.search(&dialog.search_input);
+                            dialog.search_mode = false;
+                        }
+                        KeyCode::Esc => {
+                            dialog.search_mode = false;
+                            dialog.search_input.clear();
+                        }
+                        _ => {}
+                    }
+                } else {
+                    match (key.code, key.modifiers) {
+                        (KeyCode::Esc, _) => {
+                            if dialog.editor.modified {
+                                let filename = dialog.editor.title.clone();
+                                self.pending_editor = Some(dialog.clone());
+                                self.dialog = Some(Dialog::SaveConfirm(
+                                    cortex_tui::SaveConfirmDialog::new(filename),
+                                ));
+                            } else {
+                                self.dialog = None;
+                                self.remote_edit = None;
+                            }
+                        }
+                        (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                            if let Err(e) = dialog.editor.save() {
+                                dialog.editor.status_message = Some(format!("Save failed: {}", e));
+                            } else {
+                                self.sync_remote_edit(false).await;
+                            }
+                        }
+                        (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                            dialog.search_mode = true;
+                            dialog.search_input.clear();
+                        }
+                        (KeyCode::Char('z'), KeyModifiers::CONTROL) => dialog.editor.undo(),
+                        (KeyCode::Char('y'), KeyModifiers::CONTROL) => dialog.editor.redo(),
+                        (KeyCode::Char('t'), KeyModifiers::CONTROL) => {
+                            dialog.editor.toggle_syntax_highlighting()
+                        }
+                        (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+                            if let Err(e) = dialog.editor.cycle_encoding() {
+                                dialog.editor.status_message = Some(format!("Cannot reopen: {}", e));
+                            }
+                        }
+                        (KeyCode::Char('l'), KeyModifiers::CONTROL) => dialog.editor.toggle_line_ending(),
+                        (KeyCode::F(2), _) => {
+                            if self.remote_edit.is_some() {
+                                dialog.editor.status_message = Some("Save as works on local files only".to_string());
+                            } else {
+                                dialog.save_as_mode = true;
+                                dialog.save_as_input = dialog.editor.path.display().to_string();
+                            }
+                        }
+                        (KeyCode::Up, _) => dialog.editor.move_cursor_up(),
+                        (KeyCode::Down, _) => dialog.editor.move_cursor_down(),
+                        (KeyCode::Left, _) => dialog.editor.move_cursor_left(),
+                        (KeyCode::Right, _) => dialog.editor.move_cursor_right(),
+                        (KeyCode::Home, _) => dialog.editor.move_cursor_home(),
+                        (KeyCode::End, _) => dialog.editor.move_cursor_end(),
+                        (KeyCode::PageUp, _) => {
+                            let height = self.terminal.size()?.height as usize;
+                            dialog.editor.move_cursor_page_up(height.saturating_sub(8));
+                        }
+                        (KeyCode::PageDown, _) => {
+                            let height = self.terminal.size()?.height as usize;
+                            dialog.editor.move_cursor_page_down(height.saturating_sub(8));
+                        }
+                        (KeyCode::Enter, _) => dialog.editor.insert_newline(),
+                        (KeyCode::Backspace, _) => dialog.editor.delete_char(),
+                        (KeyCode::Delete, _) => dialog.editor.delete_forward(),
+                        (KeyCode::Char(c), _) => dialog.editor.insert_char(c),
+                        _ => {}
+                    }
+                }
+            }
+            Some(Dialog::HexEditor(dialog)) => {
+                let page = cortex_tui::HexEditorDialog::content_height(self.terminal.size()?);
+                dialog.editor.status_message = None;
+                if dialog.search_mode || dialog.goto_mode {
+                    let input = match dialog.search_mode {
+                        true => &mut dialog.search_input,
+                        false => &mut dialog.goto_input,
+                    };
+                    match key.code {
+                        KeyCode::Char(c) => input.push(c),
+                        KeyCode::Backspace => {
+                            input.pop();
+                        }
+                        KeyCode::Enter => {
+                            let input = std::mem::take(input);
+                            let result = match dialog.search_mode {
+                                true => dialog.editor.search(&input).and_then(|found| match found {
+                                    Some(_) => Ok(()),
+                                    None => Err(anyhow::anyhow!("{} not found", input)),
+                                }),
+                                false => dialog.editor.goto(&input),
+                            };
+                            if let Err(e) = result {
+                                dialog.editor.status_message = Some(e.to_string());
+                            }
+                            dialog.search_mode = false;
+                            dialog.goto_mode = false;
+                        }
+                        KeyCode::Esc => {
+                            input.clear();
+                            dialog.search_mode = false;
+                            dialog.goto_mode = false;
+                        }
+                        _ => {}
+                    }
+                    return Ok(true);
+                }
+
+                if key.code != KeyCode::Esc {
+                    dialog.confirm_discard = false;
+                }
+                match (key.code, key.modifiers) {
+                    (KeyCode::Esc, _) => {
+                        if dialog.editor.modified && !dialog.confirm_discard {
+                            dialog.confirm_discard = true;
+                        } else {
+                            self.dialog = None;
+                        }
+                    }
+                    (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                        if let Err(e) = dialog.editor.save() {
+                            dialog.editor.status_message = Some(format!("Save failed: {}", e));
+                        }
+                    }
+                    (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                        dialog.search_mode = true;
+                        dialog.search_input.clear();
+                    }
+                    (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                        dialog.goto_mode = true;
+                        dialog.goto_input.clear();
+                    }
+                    (KeyCode::F(3), _) => {
+                        let found = dialog.editor.search_next();
+                        dialog.editor.status_message = found.is_none().then(|| "No match".to_string());
+                    }
+                    (KeyCode::Char('z'), KeyModifiers::CONTROL) => dialog.editor.undo(),
+                    (KeyCode::Char('y'), KeyModifiers::CONTROL) => dialog.editor.redo(),
+                    (KeyCode::Tab, _) => dialog.editor.toggle_column(),
+                    (KeyCode::Insert, _) => dialog.editor.toggle_insert_mode(),
+                    (KeyCode::Up, _) => dialog.editor.move_up(1),
+                    (KeyCode::Down, _) => dialog.editor.move_down(1),
+                    (KeyCode::Left, _) => dialog.editor.move_left(),
+                    (KeyCode::Right, _) => dialog.editor.move_right(),
+                    (KeyCode::PageUp, _) => dialog.editor.move_up(page),
+                    (KeyCode::PageDown, _) => dialog.editor.move_down(page),
+                    (KeyCode::Home, KeyModifiers::CONTROL) => dialog.editor.move_to(0),
+                    (KeyCode::End, KeyModifiers::CONTROL) => {
+                        let end = dialog.editor.data.len();
+                        dialog.editor.move_to(end);
+                    }
+                    (KeyCode::Home, _) => dialog.editor.move_row_start(),
+                    (KeyCode::End, _) => dialog.editor.move_row_end(),
+                    (KeyCode::Delete, _) => dialog.editor.delete_byte(),
+                    (KeyCode::Backspace, _) => dialog.editor.delete_byte_before(),
+                    (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => dialog.editor.type_char(c),
+                    _ => {}
+                }
+            }
+            Some(Dialog::Viewer(dialog)) => {
+                let page = cortex_tui::ViewerDialog::content_height(self.terminal.size()?);
+                dialog.message = None;
+                if dialog.search_mode {
+                    match key.code {
+                        KeyCode::Char(c) => dialog.search_input.push(c),
+                        KeyCode::Backspace => {
+                            dialog.search_input.pop();
+                        }
+                        KeyCode::Enter if !dialog.search_input.is_empty() => {
+                            let lookup = dialog.viewer.search(&dialog.search_input);
+                            dialog.search_mode = false;
+                            dialog.message = Some(format!("Searching for {}...", dialog.search_input));
+                            self.start_viewer_lookup(lookup);
+                        }
+                        KeyCode::Esc => {
+                            dialog.search_mode = false;
+                            dialog.search_input.clear();
+                        }
+                        _ => {}
+                    }
+                    return Ok(true);
+                }
+                if dialog.query_mode {
+                    match key.code {
+                        KeyCode::Char(c) => dialog.query_input.push(c),
+                        KeyCode::Backspace => {
+                            dialog.query_input.pop();
+                        }
+                        KeyCode::Enter => {
+                            dialog.query_mode = false;
+                            if let Some(tree) = dialog.viewer.tree() {
+                                dialog.message = Some(match tree.set_query(&dialog.query_input) {
+                                    Ok(_) if tree.query.is_none() => "Showing the whole document".to_string(),
+                                    Ok(1) => "1 match".to_string(),
+                                    Ok(matches) => format!("{} matches", matches),
+                                    Err(e) => e.to_string(),
+                                });
+                            }
+                        }
+                        KeyCode::Esc => dialog.query_mode = false,
+                        _ => {}
+                    }
+                    return Ok(true);
+                }
+
+                // JSON, YAML and TOML files open as a tree of their nodes
+                if dialog.viewer.tree_mode && dialog.viewer.document.is_some() {
+                    match key.code {
+                        KeyCode::Esc | KeyCode::F(3) => {
+                            self.dialog = None;
+                            self.vfs_view = None;
+                            self.viewer_monitor = None;
+                            self.viewer_events = None;
+                            self.cancel_viewer_lookup();
+                        }
+                        KeyCode::Char('v') | KeyCode::Char('V') => {
+                            let page_loaded = dialog
+                                .viewer
+                                .toggle_tree_mode()
+                                .and_then(|_| dialog.viewer.load_content(page));
+                            if let Err(e) = page_loaded {
+                                dialog.message = Some(e.to_string());
+                            }
+                        }
+                        _ => {
+                            let Some(tree) = dialog.viewer.tree() else {
+                                return Ok(true);
+                            };
+                            match key.code {
+                                KeyCode::Up => tree.move_by(-1),
+                                KeyCode::Down => tree.move_by(1),
+                                KeyCode::PageUp => tree.move_by(-(page as isize)),
+                                KeyCode::PageDown => tree.move_by(page as isize),
+                                KeyCode::Home => tree.move_by(isize::MIN),
+                                KeyCode::End => tree.move_by(isize::MAX),
+                                KeyCode::Enter | KeyCode::Char(' ') => tree.toggle(),
+                                KeyCode::Right => tree.expand(),
+                                KeyCode::Left => tree.collapse(),
+                                KeyCode::Char('*') => tree.expand_all(),
+                                KeyCode::Char('-') => tree.collapse_all(),
+                                KeyCode::Char('j') | KeyCode::Char('J') => tree.toggle_pretty(),
+                                KeyCode::Char('.') => {
+                                    dialog.query_input = tree.query.clone().unwrap_or_else(|| ".".to_string());
+                                    dialog.query_mode = true;
+                                }
+                                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                    if let Some(path) = tree.selected_path() {
+                                        let copied = cortex_platform::get_clipboard_handler().copy_text(&path);
+                                        dialog.message = Some(match copied {
+                                            Ok(()) => format!("Copied {}", path),
+                                            Err(e) => format!("Cannot copy {}: {}", path, e),
+                                        });
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    return Ok(true);
+                }
+
+                let moved = if dialog.goto_mode {
+                    match key.code {
+                        KeyCode::Char(c) => {
+                            dialog.goto_input.push(c);
+                            return Ok(true);
+                        }
+                        KeyCode::Backspace => {
+                            dialog.goto_input.pop();
+                            return Ok(true);
+                        }
+                        KeyCode::Enter => {
+                            dialog.goto_mode = false;
+                            let target = std::mem::take(&mut dialog.goto_input);
+                            match dialog.viewer.goto(&target) {
+                                Ok(Some(lookup)) => {
+                                    dialog.message = Some(format!("Going to line {}...", target.trim()));
+                                    self.start_viewer_lookup(lookup);
+                                    return Ok(true);
+                                }
+                                Ok(None) => Ok(()),
+                                Err(e) => Err(anyhow::anyhow!("Cannot go to {}: {}", target, e)),
+                            }
+                        }
+                        KeyCode::Esc => {
+                            dialog.goto_mode = false;
+                            dialog.goto_input.clear();
+                            return Ok(true);
+                        }
+                        _ => return Ok(true),
+                    }
+                } else {
+                    match key.code {
+                        KeyCode::Esc | KeyCode::F(3) => {
+                            self.dialog = None;
+                            self.vfs_view = None;
+                            self.viewer_monitor = None;
+                            self.viewer_events = None;
+                            self.cancel_viewer_lookup();
+                            return Ok(true);
+                        }
+                        KeyCode::Up => dialog.viewer.scroll_up(1),
+                        KeyCode::Down => dialog.viewer.scroll_down(1),
+                        KeyCode::PageUp => dialog.viewer.page_up(page),
+                        KeyCode::PageDown => dialog.viewer.page_down(page),
+                        KeyCode::Home => {
+                            dialog.viewer.home();
+                            Ok(())
+                        }
+                        KeyCode::End => dialog.viewer.end(),
+                        KeyCode::Char('h') | KeyCode::Char('H') => dialog.viewer.toggle_hex_mode(),
+                        KeyCode::Char('w') | KeyCode::Char('W') => {
+                            dialog.viewer.toggle_wrap();
+                            Ok(())
+                        }
+                        KeyCode::Char('s') | KeyCode::Char('S') => {
+                            dialog.viewer.toggle_syntax_highlighting();
+                            Ok(())
+                        }
+                        KeyCode::Char('c') | KeyCode::Char('C') => dialog.viewer.cycle_encoding(),
+                        KeyCode::Char('v') | KeyCode::Char('V') => dialog.viewer.toggle_tree_mode(),
+                        KeyCode::Char('g') | KeyCode::Char('G') => {
+                            dialog.goto_mode = true;
+                            dialog.goto_input.clear();
+                            return Ok(true);
+                        }
+                        KeyCode::Char('e') | KeyCode::Char('E') => {
+                            if self.vfs_view.is_some() {
+                                dialog.message = Some("Hex editing works on local files only".to_string());
+                                return Ok(true);
+                            }
+                            let (path, offset) = (dialog.viewer.path.clone(), dialog.viewer.top);
+                            self.viewer_monitor = None;
+                            self.viewer_events = None;
+                            self.cancel_viewer_lookup();
+                            self.open_hex_editor(&path, offset as usize);
+                            return Ok(true);
+                        }
+                        KeyCode::Char('t') | KeyCode::Char('T') => dialog.viewer.toggle_follow(),
+                        KeyCode::Char('p') | KeyCode::Char('P') => dialog.viewer.toggle_follow_pause(),
+                        KeyCode::Char('/') => {
+                            dialog.search_mode = true;
+                            dialog.search_input.clear();
+                            return Ok(true);
+                        }
+                        KeyCode::Char('f') | KeyCode::Char('F') => {
+                            if let Some(lookup) = dialog.viewer.search_next() {
+                                dialog.message = Some("Searching...".to_string());
+                                self.start_viewer_lookup(lookup);
+                            }
+                            return Ok(true);
+                        }
+                        _ => return Ok(true),
+                    }
+                };
+                if let Err(e) = moved.and_then(|_| dialog.viewer.load_content(page)) {
+                    dialog.message = Some(e.to_string());
+                }
+
+                // Watch the file only while following it
+                let following = dialog.viewer.follow != cortex_tui::Follow::Off;
+                // The directory the file really is in, past any symlink
+                let path = dialog.viewer.canonical_path.clone();
+                if !following {
+                    self.viewer_monitor = None;
+                    self.viewer_events = None;
+                } else if self.viewer_events.is_none() {
+                    self.watch_followed_file(&path).await;
+                }
+            }
+            Some(Dialog::SaveConfirm(dialog)) => {
+                let choice = match key.code {
+                    KeyCode::Left | KeyCode::BackTab => {
+                        dialog.prev_choice();
+                        None
+                    }
+                    KeyCode::Right | KeyCode::Tab => {
+                        dialog.next_choice();
+                        None
+                    }
+                    KeyCode::Enter => Some(dialog.selection),
+                    KeyCode::Char('s') | KeyCode::Char('S') => Some(cortex_tui::SaveChoice::Save),
+                    KeyCode::Char('d') | KeyCode::Char('D') => Some(cortex_tui::SaveChoice::DontSave),
+                    KeyCode::Char('c') | KeyCode::Char('C') | KeyCode::Esc => {
+                        Some(cortex_tui::SaveChoice::Cancel)
+                    }
+                    _ => None,
+                };
+
+                match choice {
+                    Some(cortex_tui::SaveChoice::Save) => {
+                        self.dialog = None;
+                        if let Some(mut editor_dialog) = self.pending_editor.take() {
+                            if let Err(e) = editor_dialog.editor.save() {
+                                self.dialog = Some(Dialog::Error(cortex_tui::ErrorDialog::new(
+                                    format!("Failed to save file: {}", e),
+                                )));
+                                self.remote_edit = None;
+                            } else {
+                                self.sync_remote_edit(false).await;
+                                if !matches!(self.dialog, Some(Dialog::Confirm(_))) {
+                                    self.remote_edit = None;
+                                }
+                            }
+                        }
+                    }
+                    Some(cortex_tui::SaveChoice::DontSave) => {
+                        self.dialog = None;
+                        self.pending_editor = None;
+                        self.remote_edit = None;
+                    }
+                    Some(cortex_tui::SaveChoice::Cancel) => {
+                        self.dialog = self.pending_editor.take().map(Dialog::Editor);
+                    }
+                    None => {}
+                }
+            }
             _ => {
                 // Handle other dialog types with basic Esc to close
                 if key.code == KeyCode::Esc {
                     self.dialog = None;
+                    self.vfs_view = None;
                 }
             }
         }
@@ -558,6 +1305,15 @@ impl App {
             }
         }
 
+        self.save_content_index();
+        if let Some(monitor) = self.index_monitor.take() {
+            let _ = monitor.stop().await;
+        }
+
+        if let Some(cancel) = self.command_cancel.take() {
+            cancel.cancel();
+        }
+
         // Close all channels by dropping the receivers
         self.operation_rx = None;
         self.search_rx = None;
@@ -603,6 +1359,49 @@ impl App {
         Ok(())
     }
 
+    /// Run a command picked from the command palette
+    async fn handle_palette_command(&mut self, command: &str) -> Result<bool> {
+        match command {
+            "exit" => return self.handle_exit().await,
+            "reload" => self.refresh_needed = true,
+            "filter" => return Box::pin(self.handle_action(Action::QuickFilter)).await,
+            "find" => return Box::pin(self.handle_action(Action::Search)).await,
+            "files" => self.open_fuzzy_finder(),
+            "hidden" => return Box::pin(self.handle_action(Action::ToggleHidden)).await,
+            "quickview" => return Box::pin(self.handle_action(Action::QuickView)).await,
+            "config" | "settings" => return Box::pin(self.handle_action(Action::Settings)).await,
+            "root" => self.navigate_to_directory(std::path::PathBuf::from("/"))?,
+            "index" => self.show_content_index_status(),
+            "index-rebuild" => self.rebuild_content_index(),
+            "replace-undo" => self.undo_replace(),
+            "search-rerun" => return Box::pin(self.handle_action(Action::RerunSearch)).await,
+            "search-save" if self.saved_searches.last().is_none() => {
+                self.state.set_status_message("No search to save yet");
+            }
+            "search-save" => {
+                self.dialog = Some(Dialog::Input(
+                    cortex_tui::InputDialog::new("Save Search", "Name for the last search:")
+                ));
+                self.saved_search_input = Some(SavedSearchInput::Save);
+            }
+            "search-delete" => {
+                self.dialog = Some(Dialog::Input(
+                    cortex_tui::InputDialog::new("Delete Saved Search", "Name of the search to delete:")
+                ));
+                self.saved_search_input = Some(SavedSearchInput::Delete);
+            }
+            command if command.starts_with("search:") => {
+                let name = &command["search:".len()..];
+                match self.saved_searches.get(name).cloned() {
+                    Some(search) => self.run_saved_search(search)?,
+                    None => self.state.set_status_message(format!("No saved search named '{}'", name)),
+                }
+            }
+            _ => self.state.set_status_message(format!("Command /{} not yet implemented", command)),
+        }
+        Ok(true)
+    }
+
     /// Handle command execution
     async fn handle_command_execution(&mut self) -> Result<bool> {
         let command = self.state.command_line.clone();
@@ -621,6 +1420,16 @@ impl App {
                 std::process::Command::new(exe).args(&args).spawn()?;
                 return Ok(false);
             }
+            cmd if cortex_core::VfsPath::is_url(cmd.strip_prefix("cd ").unwrap_or(cmd).trim()) => {
+                let url = cmd.strip_prefix("cd ").unwrap_or(cmd).trim();
+                match url.parse::<cortex_core::VfsPath>() {
+                    Ok(path) => self.open_vfs_path(path)?,
+                    Err(e) => self.state.set_status_message(format!("Invalid location: {}", e)),
+                }
+            }
+            cmd if cmd.starts_with("cd ") && self.state.active_panel().current_vfs_path.is_some() => {
+                self.change_remote_directory(cmd[3..].trim());
+            }
             cmd if cmd.starts_with("cd ") => {
                 let path = cmd[3..].trim();
                 let new_path = if path.starts_with('/') {
@@ -630,6 +1439,9 @@ impl App {
                 };
                 let _ = self.navigate_to_directory(new_path);
             }
+            _ if CommandProcessor::remote_target(self.state.active_panel()).is_some() => {
+                self.execute_remote_command(&command);
+            }
             _ => {
                 // Execute as system command
                 self.execute_system_command(&command).await?;
@@ -641,6 +1453,13 @@ impl App {
 
     /// Handle file operation requests
     async fn handle_copy_operation(&mut self) -> Result<()> {
+        if self.start_remote_transfer() {
+            return Ok(());
+        }
+        if self.state.active_panel().is_using_vfs() {
+            self.copy_vfs_entry_to_local().await;
+            return Ok(());
+        }
         if let Some(operation) = self.prepare_copy_operation() {
             let config = self.state.config_manager.get();
             if config.general.confirm_operations {
@@ -695,8 +1514,14 @@ impl App {
     async fn handle_rename_operation(&mut self) -> Result<()> {
         if let Some(entry) = self.state.active_panel().current_entry().cloned() {
             if entry.name != ".." {
+                // Search listings name entries by their relative path
+                let name = entry
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or(entry.name);
                 self.dialog = Some(Dialog::Input(
-                    cortex_tui::InputDialog::new("Rename", "Enter new name:").with_initial_value(&entry.name),
+                    cortex_tui::InputDialog::new("Rename", "Enter new name:").with_initial_value(&name),
                 ));
                 self.state.pending_operation = Some(FileOperation::Rename {
                     old_path: entry.path,
@@ -707,6 +1532,29 @@ impl App {
         Ok(())
     }
 
+    fn handle_multi_rename_operation(&mut self) {
+        let panel = self.state.active_panel();
+        let paths = if !panel.marked_files.is_empty() {
+            panel.marked_files.clone()
+        } else {
+            match panel.current_entry() {
+                Some(entry) if entry.name != ".." => vec![entry.path.clone()],
+                _ => return,
+            }
+        };
+        self.dialog = Some(Dialog::Input(
+            cortex_tui::InputDialog::new(
+                "Multi-Rename",
+                format!("Rename {} files ([N] name, [E] extension, [C] counter):", paths.len()),
+            )
+            .with_initial_value("[N].[E]"),
+        ));
+        self.state.pending_operation = Some(FileOperation::MultiRename {
+            paths,
+            pattern: String::new(),
+        });
+    }
+
     async fn handle_copy_as_operation(&mut self) -> Result<()> {
         if let Some(entry) = self.state.active_panel().current_entry().cloned() {
             if entry.name != ".." {
@@ -835,9 +1683,276 @@ impl App {
         Ok(())
     }
 
+    /// Copy between a local and an SFTP panel with the parallel transfer workers.
+    /// Returns false when neither panel is remote so the local copy path runs instead.
+    #[cfg(feature = "ssh")]
+    fn start_remote_transfer(&mut self) -> bool {
+        use cortex_core::remote::{ParallelTransfer, SshConnectionManager, TransferSettings};
+        use std::sync::Arc;
+
+        let active = self.state.active_panel();
+        let inactive = self.state.inactive_panel();
+        let download = CommandProcessor::remote_target(active);
+        let upload = CommandProcessor::remote_target(inactive);
+        let (target, is_download) = match (download, upload) {
+            (Some(target), None) => (target, true),
+            (None, Some(target)) => (target, false),
+            (Some(_), Some(_)) => {
+                self.state
+                    .set_status_message("Copying between two remote panels is not supported");
+                return true;
+            }
+            (None, None) => return false,
+        };
+
+        let remote_sources: Vec<String> = active
+            .current_vfs_entry()
+            .filter(|entry| entry.name != "..")
+            .and_then(|entry| match &entry.path {
+                cortex_core::VfsPath::Sftp { path, .. } => Some(path.clone()),
+                _ => None,
+            })
+            .into_iter()
+            .collect();
+        let local_sources: Vec<std::path::PathBuf> = if !active.marked_files.is_empty() {
+            active.marked_files.clone()
+        } else {
+            active
+                .current_entry()
+                .filter(|entry| entry.name != "..")
+                .map(|entry| entry.path.clone())
+                .into_iter()
+                .collect()
+        };
+        let local_dir = inactive.current_dir.clone();
+        if (is_download && remote_sources.is_empty()) || (!is_download && local_sources.is_empty()) {
+            return true;
+        }
+
+        let config = self.state.config_manager.get();
+        let settings = TransferSettings::from_config(&config.network);
+        let manager = SshConnectionManager::new().with_timeouts(
+            std::time::Duration::from_secs(config.network.connection_timeout),
+            std::time::Duration::from_secs(300),
+        );
+        let credentials = CommandProcessor::remote_credentials(&self.state, &target);
+        let (host, port, _, remote_dir) = target;
+        let manager = Arc::new(manager);
+        let transfer = ParallelTransfer::new(Arc::clone(&manager), host.clone(), port, credentials, settings);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let cancel = cortex_core::CancellationToken::new();
+        self.operation_rx = Some(rx);
+        self.transfer_cancel = Some(cancel.clone());
+        let title = if is_download { "Download" } else { "Upload" };
+        self.dialog = Some(Dialog::Progress(cortex_tui::ProgressDialog::new(
+            title,
+            format!("{} {}", if is_download { "from" } else { "to" }, host),
+        )));
+
+        tokio::spawn(async move {
+            let result = if is_download {
+                transfer.download(remote_sources, local_dir, tx, cancel).await
+            } else {
+                transfer.upload(local_sources, remote_dir, tx, cancel).await
+            };
+            if let Err(e) = result {
+                log::warn!("Remote transfer failed: {}", e);
+            }
+            let _ = tokio::task::spawn_blocking(move || manager.disconnect_all()).await;
+        });
+        true
+    }
+
+    #[cfg(not(feature = "ssh"))]
+    fn start_remote_transfer(&mut self) -> bool {
+        false
+    }
+
+    /// Browse the revisions of the active panel's repository
+    fn open_git_history(&mut self) {
+        let repo = self
+            .state
+            .active_panel()
+            .git_info
+            .as_ref()
+            .map(|info| info.root.clone());
+        match repo {
+            Some(repo) => self.start_vfs_listing(cortex_core::VfsPath::GitTree {
+                repo,
+                rev: String::new(),
+                path: String::new(),
+            }),
+            None => self.state.set_status_message("Not inside a git repository"),
+        }
+    }
+
+    /// Whether `entry` is the `.git` directory at the root of the panel's repository
+    fn is_git_dir_of_repo(panel: &cortex_core::PanelState, entry: &cortex_core::FileEntry) -> bool {
+        entry.name == ".git"
+            && panel
+                .git_info
+                .as_ref()
+                .is_some_and(|info| entry.path.parent() == Some(info.root.as_path()))
+    }
+
+    fn in_git_history(panel: &cortex_core::PanelState) -> bool {
+        matches!(panel.current_vfs_path, Some(cortex_core::VfsPath::GitTree { .. }))
+    }
+
+    /// Copy the selected VFS entry into the local directory of the other panel
+    async fn copy_vfs_entry_to_local(&mut self) {
+        let Some(entry) = self.state.active_panel().current_vfs_entry().cloned() else {
+            return;
+        };
+        if entry.name == ".." {
+            return;
+        }
+        if self.state.inactive_panel().is_using_vfs() {
+            self.state
+                .set_status_message("Copying between two virtual panels is not supported");
+            return;
+        }
+
+        let dest_dir = self.state.inactive_panel().current_dir.clone();
+        let name = entry.name.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            cortex_core::VirtualFileSystem::new().copy_to_local(&entry, &dest_dir)
+        })
+        .await;
+        match result {
+            Ok(Ok(bytes)) => {
+                self.state
+                    .set_status_message(format!("Copied {} ({} bytes)", name, bytes));
+                self.refresh_needed = true;
+            }
+            Ok(Err(e)) => self.state.set_status_message(format!("Copy failed: {}", e)),
+            Err(e) => self.state.set_status_message(format!("Copy failed: {}", e)),
+        }
+    }
+
+    /// View a VFS file through a temporary local copy that lives until the viewer closes
+    async fn handle_vfs_view_operation(&mut self) -> Result<()> {
+        let Some(entry) = self.state.active_panel().current_vfs_entry().cloned() else {
+            return Ok(());
+        };
+        if !matches!(entry.entry_type, cortex_core::VfsEntryType::File) {
+            return Ok(());
+        }
+
+        let viewer = cortex_core::RemoteEditSession::open(&self.vfs, entry.path.clone())
+            .await
+            .and_then(|copy| {
+                let mut viewer = cortex_tui::viewer::FileViewer::new(&copy.local_path)?;
+                viewer.load_content(cortex_tui::ViewerDialog::content_height(self.terminal.size()?))?;
+                Ok((copy, viewer))
+            });
+        match viewer {
+            Ok((copy, viewer)) => {
+                self.vfs_view = Some(copy);
+                self.dialog = Some(Dialog::Viewer(cortex_tui::ViewerDialog::new(viewer)));
+            }
+            Err(e) => {
+                self.dialog = Some(Dialog::Error(cortex_tui::ErrorDialog::new(format!(
+                    "Failed to open {}: {}",
+                    entry.name, e
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a command on the SFTP host of the active panel, streaming into the output pane
+    #[cfg(feature = "ssh")]
+    fn execute_remote_command(&mut self, command: &str) {
+        let Some(target) = CommandProcessor::remote_target(self.state.active_panel()) else {
+            return;
+        };
+        let expanded = CommandProcessor::expand_command(command, &self.state);
+        let credentials = CommandProcessor::remote_credentials(&self.state, &target);
+        let sessions = std::sync::Arc::clone(&self.ssh_sessions);
+
+        let cancel = cortex_core::CancellationToken::new();
+        if let Some(previous) = self.command_cancel.replace(cancel.clone()) {
+            previous.cancel();
+        }
+
+        let (tx, rx) = mpsc::channel(100);
+        self.command_output_rx = Some(rx);
+        self.state.set_command_running(true);
+        tokio::spawn(async move {
+            let result = CommandProcessor::execute_remote_streaming_command(
+                &expanded,
+                target,
+                credentials,
+                sessions,
+                cancel,
+                tx,
+            )
+            .await;
+            if let Err(e) = result {
+                log::warn!("Remote command failed: {}", e);
+            }
+        });
+    }
+
+    #[cfg(not(feature = "ssh"))]
+    fn execute_remote_command(&mut self, _command: &str) {
+        self.state
+            .set_status_message("Remote commands require SSH support (build with the `ssh` feature)");
+    }
+
+    /// `cd` inside an SFTP panel lists the new remote directory
+    fn change_remote_directory(&mut self, target: &str) {
+        let Some((host, port, username, current)) =
+            CommandProcessor::remote_target(self.state.active_panel())
+        else {
+            self.state.set_status_message("cd is only supported on SFTP panels");
+            return;
+        };
+
+        let path = if target.starts_with('/') {
+            std::path::PathBuf::from(target)
+        } else {
+            std::path::Path::new(&current).join(target)
+        };
+        // Resolve ".." and "." lexically; the server sees the final absolute path
+        let mut normalized = std::path::PathBuf::from("/");
+        for component in path.components() {
+            match component {
+                std::path::Component::ParentDir => {
+                    normalized.pop();
+                }
+                std::path::Component::Normal(part) => normalized.push(part),
+                _ => {}
+            }
+        }
+
+        self.start_vfs_listing(cortex_core::VfsPath::Sftp {
+            host,
+            port,
+            username,
+            path: normalized.to_string_lossy().to_string(),
+        });
+    }
+
     /// Handle mouse events
-    async fn handle_mouse_event(&mut self, _mouse_event: crossterm::event::MouseEvent) -> Result<()> {
-        // TODO: Implement mouse event handling
+    async fn handle_mouse_event(&mut self, mouse_event: crossterm::event::MouseEvent) -> Result<()> {
+        use crossterm::event::{MouseButton, MouseEventKind};
+
+        // TODO: Implement mouse handling beyond the status bar
+        if mouse_event.kind != MouseEventKind::Down(MouseButton::Left) || self.dialog.is_some() {
+            return Ok(());
+        }
+        // The info line sits just above the function key bar
+        let size = self.terminal.size()?;
+        if mouse_event.row + 2 == size.height {
+            let theme = self.state.theme_manager.get_current_theme();
+            let git_widget = cortex_tui::UI::git_widget_columns(&self.state, theme, size.width);
+            if git_widget.is_some_and(|columns| columns.contains(&mouse_event.column)) {
+                self.open_git_history();
+            }
+        }
         Ok(())
     }
 
@@ -848,6 +1963,9 @@ impl App {
     }
 
     async fn handle_edit_file_operation(&mut self) -> Result<()> {
+        if self.state.active_panel().is_using_vfs() {
+            return self.handle_remote_edit_operation().await;
+        }
         if let Some(entry) = self.state.active_panel().current_entry().cloned() {
             if entry.file_type == cortex_core::fs::FileType::File {
                 let editor = self.state.config_manager.get().general.editor;
@@ -859,14 +1977,136 @@ impl App {
         Ok(())
     }
 
+    /// Check out the selected remote file and open it in the configured editor
+    async fn handle_remote_edit_operation(&mut self) -> Result<()> {
+        if Self::in_git_history(self.state.active_panel()) {
+            self.state.set_status_message("Git history is read-only");
+            return Ok(());
+        }
+        let Some(entry) = self.state.active_panel().current_vfs_entry().cloned() else {
+            return Ok(());
+        };
+        if !matches!(entry.entry_type, cortex_core::VfsEntryType::File) {
+            return Ok(());
+        }
+
+        let session = match cortex_core::RemoteEditSession::open(&self.vfs, entry.path.clone()).await {
+            Ok(session) => session,
+            Err(e) => {
+                self.dialog = Some(Dialog::Error(cortex_tui::ErrorDialog::new(format!(
+                    "Failed to download {}: {}",
+                    entry.name, e
+                ))));
+                return Ok(());
+            }
+        };
+        let local_path = session.local_path.clone();
+        self.remote_edit = Some(session);
+
+        let general = self.state.config_manager.get().general;
+        if general.uses_builtin_editor() {
+            match cortex_tui::TextEditor::new(&local_path) {
+                Ok(editor) => {
+                    self.dialog = Some(Dialog::Editor(cortex_tui::EditorDialog::new(editor)));
+                }
+                Err(e) => {
+                    self.remote_edit = None;
+                    self.dialog = Some(Dialog::Error(cortex_tui::ErrorDialog::new(format!(
+                        "Failed to open file: {}",
+                        e
+                    ))));
+                }
+            }
+        } else {
+            self.suspend_and_run_command(&general.editor, &[&local_path.to_string_lossy()])
+                .await?;
+            self.sync_remote_edit(false).await;
+            if !matches!(self.dialog, Some(Dialog::Confirm(_))) {
+                self.remote_edit = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload the remote edit working copy, asking first if the remote file changed
+    async fn sync_remote_edit(&mut self, force: bool) {
+        let vfs = std::sync::Arc::clone(&self.vfs);
+        let Some(session) = self.remote_edit.as_mut() else {
+            return;
+        };
+        let name = session.local_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        match session.is_modified() {
+            Ok(true) => {}
+            Ok(false) => {
+                self.state.set_status_message(format!("{} unchanged, nothing uploaded", name));
+                return;
+            }
+            Err(e) => {
+                self.state.set_status_message(format!("Failed to read local copy of {}: {}", name, e));
+                return;
+            }
+        }
+
+        if !force {
+            // A failed check (e.g. the file was removed remotely) also asks before writing
+            if session.remote_changed(&vfs).await.unwrap_or(true) {
+                if let Some(Dialog::Editor(editor_dialog)) = self.dialog.take() {
+                    self.pending_editor = Some(editor_dialog);
+                }
+                self.dialog = Some(Dialog::Confirm(cortex_tui::ConfirmDialog::new(
+                    "Remote File Changed",
+                    format!("{} changed on the server since it was opened. Overwrite it?", name),
+                )));
+                return;
+            }
+        }
+
+        match session.upload(&vfs).await {
+            Ok(()) => {
+                self.state.set_status_message(format!("Uploaded {}", name));
+                self.refresh_needed = true;
+            }
+            Err(e) => {
+                let local_path = session.keep_local_copy().display().to_string();
+                self.dialog = Some(Dialog::Error(cortex_tui::ErrorDialog::new(format!(
+                    "Failed to upload {}: {}. Local copy kept at {}",
+                    name, e, local_path
+                ))));
+            }
+        }
+    }
+
+    /// Finish the overwrite prompt raised by `sync_remote_edit`
+    async fn resolve_remote_edit_conflict(&mut self, overwrite: bool) {
+        if overwrite {
+            self.sync_remote_edit(true).await;
+        } else if let Some(session) = self.remote_edit.as_mut() {
+            let local_path = session.keep_local_copy().display().to_string();
+            self.state
+                .set_status_message(format!("Upload skipped, local copy kept at {}", local_path));
+        }
+
+        if let Some(editor_dialog) = self.pending_editor.take() {
+            if self.dialog.is_none() {
+                self.dialog = Some(Dialog::Editor(editor_dialog));
+            }
+        } else {
+            self.remote_edit = None;
+        }
+    }
+
     async fn handle_view_file_operation(&mut self) -> Result<()> {
+        if self.state.active_panel().is_using_vfs() {
+            return self.handle_vfs_view_operation().await;
+        }
         if let Some(entry) = self.state.active_panel().current_entry().cloned() {
             if entry.file_type == cortex_core::fs::FileType::File {
                 match cortex_tui::viewer::FileViewer::new(&entry.path) {
                     Ok(mut viewer) => {
                         // Load initial content
-                        let height = self.terminal.size()?.height as usize;
-                        if let Err(e) = viewer.load_content(height.saturating_sub(5)) {
+                        let height = cortex_tui::ViewerDialog::content_height(self.terminal.size()?);
+                        if let Err(e) = viewer.load_content(height) {
                             self.dialog = Some(Dialog::Error(cortex_tui::ErrorDialog::new(format!(
                                 "Failed to read file: {}",
                                 e
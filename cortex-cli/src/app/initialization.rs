@@ -36,9 +36,16 @@ impl App {
         set_terminal_background(&mut terminal, theme)?;
         terminal.clear()?;
 
-        // Set initial paths if provided
+        // Set initial paths if provided; URLs are opened once the app exists
+        let mut initial_location = None;
         if let Some(path) = initial_path {
-            if path.is_dir() {
+            let text = path.to_string_lossy();
+            if cortex_core::VfsPath::is_url(&text) {
+                match text.parse::<cortex_core::VfsPath>() {
+                    Ok(location) => initial_location = Some(location),
+                    Err(e) => state.set_status_message(format!("Invalid location: {}", e)),
+                }
+            } else if path.is_dir() {
                 state.left_panel.current_dir = path.clone();
                 state.right_panel.current_dir = path;
             }
@@ -48,6 +55,7 @@ impl App {
         let (file_change_tx, file_change_rx) = mpsc::unbounded_channel();
         let (file_event_tx, file_event_rx) = mpsc::unbounded_channel();
         let (ai_response_tx, ai_response_rx) = mpsc::unbounded_channel();
+        let (index_tx, index_rx) = mpsc::unbounded_channel();
         let (config_reload_tx, config_reload_rx) = std::sync::mpsc::channel();
 
         // Set up configuration watcher
@@ -55,6 +63,19 @@ impl App {
             eprintln!("Warning: Failed to start config watcher: {}", e);
         }
 
+        // One VFS for background listings so remote sessions are shared and kept alive
+        let vfs_timeout = state.config_manager.get().network.connection_timeout;
+        #[cfg(feature = "ssh")]
+        let ssh_sessions = std::sync::Arc::new(cortex_core::remote::SshConnectionManager::new());
+        #[cfg(feature = "ssh")]
+        let sync_vfs = cortex_core::VirtualFileSystem::with_ssh_manager(ssh_sessions.clone());
+        #[cfg(not(feature = "ssh"))]
+        let sync_vfs = cortex_core::VirtualFileSystem::new();
+        let vfs = std::sync::Arc::new(
+            cortex_core::AsyncVirtualFileSystem::from_sync(sync_vfs)
+                .with_timeout(Duration::from_secs(vfs_timeout)),
+        );
+
         // Create the app instance
         let mut app = Self {
             state,
@@ -64,13 +85,40 @@ impl App {
             dialog: None,
             pending_editor: None,
             pending_config_dialog: None,
+            remote_edit: None,
+            vfs_view: None,
             operation_manager: OperationManager::new(),
             operation_rx: None,
             search_rx: None,
+            search_cancel: None,
+            finder_rx: None,
+            saved_searches: cortex_core::SavedSearches::load(),
+            saved_search_input: None,
+            replace_rx: None,
+            replace_undo: Vec::new(),
+            content_index: None,
+            index_monitor: None,
+            index_tx,
+            index_rx,
+            vfs,
+            #[cfg(feature = "ssh")]
+            ssh_sessions,
+            vfs_listings: Vec::new(),
+            last_keepalive: std::time::Instant::now(),
+            transfer_cancel: None,
             refresh_needed: false,
             file_change_rx: Some(file_change_rx),
             command_output_rx: None,
+            command_cancel: None,
             file_event_rx: Some(file_event_rx),
+            viewer_monitor: None,
+            viewer_events: None,
+            viewer_lookup_rx: None,
+            viewer_lookup_cancel: None,
+            quick_view_rx: None,
+            quick_view_cancel: None,
+            quick_view_due: None,
+            shown_picture: None,
             notification_manager: NotificationManager::new(),
             mouse_handler: MouseHandler::new(),
             context_menu: None,
@@ -101,6 +149,10 @@ impl App {
         }
         
         app.apply_configuration()?;
+
+        if let Some(location) = initial_location {
+            app.open_vfs_path(location)?;
+        }
         
         if let Err(e) = app.load_plugins().await {
             eprintln!("Warning: Failed to load plugins: {}", e);
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use cortex_core::{AppState, shortcuts::ShortcutManager};
+use cortex_core::{ActivePanel, AppState, shortcuts::ShortcutManager};
 use cortex_tui::{
     ConfigDialog, Dialog, EditorDialog, EventHandler,
     MouseHandler, MouseRegionManager, NotificationManager, ContextMenu
@@ -10,6 +10,9 @@ use tokio::sync::mpsc;
 
 use crate::operations::OperationManager;
 
+/// How long the cursor rests on an entry before QuickView loads it
+const QUICK_VIEW_DELAY: std::time::Duration = std::time::Duration::from_millis(150);
+
 // Sub-modules
 mod initialization;
 mod event_handling;
@@ -17,6 +20,13 @@ mod utilities;
 
 // The modules are used internally by the App implementation
 
+/// A saved search the input dialog is asking the name of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SavedSearchInput {
+    Save,
+    Delete,
+}
+
 /// Main application structure containing all state and UI components
 pub struct App {
     // Core state and UI
@@ -27,21 +37,59 @@ pub struct App {
     
     // Dialog management
     pub dialog: Option<Dialog>,
-    #[allow(dead_code)] // TODO: Implement editor dialog functionality
     pub pending_editor: Option<EditorDialog>,
     #[allow(dead_code)] // TODO: Implement config dialog functionality
     pub pending_config_dialog: Option<ConfigDialog>,
 
+    // Remote file currently checked out for editing
+    pub remote_edit: Option<cortex_core::RemoteEditSession>,
+    // Local copy backing a viewer opened on a VFS file
+    pub vfs_view: Option<cortex_core::RemoteEditSession>,
+
     // Operation management
     pub operation_manager: OperationManager,
     pub operation_rx: Option<mpsc::UnboundedReceiver<cortex_core::OperationProgress>>,
     pub search_rx: Option<mpsc::UnboundedReceiver<cortex_core::SearchProgress>>,
+    pub search_cancel: Option<cortex_core::CancellationToken>,
+    pub finder_rx: Option<mpsc::UnboundedReceiver<Vec<String>>>,
+    pub saved_searches: cortex_core::SavedSearches,
+    // What the open input dialog names, when it asks for a saved search
+    pub saved_search_input: Option<SavedSearchInput>,
+    pub replace_rx: Option<mpsc::UnboundedReceiver<Result<Vec<cortex_core::FileChanges>>>>,
+    // Applied replaces, most recent last
+    pub replace_undo: Vec<cortex_core::ReplaceUndo>,
+    pub content_index: Option<std::sync::Arc<cortex_core::ContentIndex>>,
+    pub index_monitor: Option<cortex_core::FileMonitorManager>,
+    pub index_tx: mpsc::UnboundedSender<Result<std::sync::Arc<cortex_core::ContentIndex>>>,
+    pub index_rx: mpsc::UnboundedReceiver<Result<std::sync::Arc<cortex_core::ContentIndex>>>,
+    pub vfs: std::sync::Arc<cortex_core::AsyncVirtualFileSystem>,
+    // SSH sessions shared by SFTP panels and remote commands
+    #[cfg(feature = "ssh")]
+    pub ssh_sessions: std::sync::Arc<cortex_core::remote::SshConnectionManager>,
+    pub vfs_listings: Vec<(ActivePanel, cortex_core::ListingHandle)>,
+    pub last_keepalive: std::time::Instant,
+    pub transfer_cancel: Option<cortex_core::CancellationToken>,
     pub refresh_needed: bool,
     
     // File system monitoring
     pub file_change_rx: Option<mpsc::UnboundedReceiver<()>>,
     pub command_output_rx: Option<mpsc::Receiver<String>>,
+    // Stops the remote command streaming into the output pane
+    pub command_cancel: Option<cortex_core::CancellationToken>,
     pub file_event_rx: Option<mpsc::UnboundedReceiver<cortex_core::FileMonitorEvent>>,
+    // Watches the file the viewer follows
+    pub viewer_monitor: Option<cortex_core::FileMonitor>,
+    pub viewer_events: Option<mpsc::UnboundedReceiver<cortex_core::FileMonitorEvent>>,
+    // Viewer search or line jump running in the background
+    pub viewer_lookup_rx: Option<mpsc::UnboundedReceiver<cortex_tui::LookupResult>>,
+    pub viewer_lookup_cancel: Option<cortex_core::CancellationToken>,
+
+    // QuickView preview loading, started once the cursor settles
+    pub quick_view_rx: Option<mpsc::UnboundedReceiver<(std::path::PathBuf, Result<cortex_core::Preview>)>>,
+    pub quick_view_cancel: Option<cortex_core::CancellationToken>,
+    pub quick_view_due: Option<std::time::Instant>,
+    // Picture last written over the frame with escape sequences
+    pub shown_picture: Option<cortex_tui::ImagePlacement>,
     
     // UI components
     pub notification_manager: NotificationManager,
@@ -73,8 +121,16 @@ impl App {
             self.process_operation_progress();
             self.process_ai_responses();
             self.process_search_progress();
+            self.process_index_updates().await;
+            self.process_finder_updates();
+            self.process_replace_plan();
+            self.process_vfs_listings();
+            self.process_connections();
             self.process_file_changes();
             self.process_file_events();
+            self.process_viewer_follow();
+            self.process_viewer_lookup();
+            self.process_quick_view();
             self.process_command_output().await;
             self.process_config_reload();
 
@@ -95,28 +151,61 @@ impl App {
 
     /// Draw the user interface
     fn draw_ui(&mut self) -> Result<()> {
+        let picture = self.draw_frame()?;
+        if picture == self.shown_picture {
+            return Ok(());
+        }
+        if self.shown_picture.take().is_some() {
+            // Kitty pictures sit above the text and sixel pixels stay until
+            // their cells are drawn again, so wipe the old one and repaint
+            if cortex_tui::GraphicsProtocol::current() == cortex_tui::GraphicsProtocol::Kitty {
+                io::Write::write_all(self.terminal.backend_mut(), cortex_tui::terminal_image::KITTY_DELETE_ALL.as_bytes())?;
+            }
+            self.terminal.clear()?;
+            self.draw_frame()?;
+        }
+        if let Some(picture) = picture {
+            picture.write_to(self.terminal.backend_mut())?;
+            self.shown_picture = Some(picture);
+        }
+        Ok(())
+    }
+
+    /// Draw one frame, returning the picture to write over it
+    fn draw_frame(&mut self) -> Result<Option<cortex_tui::ImagePlacement>> {
+        let mut picture = None;
         self.terminal.draw(|frame| {
-            cortex_tui::UI::draw(frame, &self.state);
+            picture = cortex_tui::UI::draw(frame, &self.state);
             
             if let Some(ref mut dialog) = self.dialog {
                 let theme = self.state.theme_manager.get_current_theme();
                 cortex_tui::dialogs::render_dialog(frame, dialog, theme);
+                // Dialogs cover the QuickView panel
+                picture = match dialog {
+                    Dialog::Viewer(dialog) => dialog.image.clone(),
+                    _ => None,
+                };
             }
             
             // Render notifications on top
             self.notification_manager.render(frame);
         })?;
         
-        Ok(())
+        Ok(picture)
     }
 
     /// Process operation progress updates
     fn process_operation_progress(&mut self) {
+        let mut updates = Vec::new();
         if let Some(rx) = &mut self.operation_rx {
-            if let Ok(progress) = rx.try_recv() {
-                self.handle_operation_progress(progress);
+            while let Ok(progress) = rx.try_recv() {
+                updates.push(progress);
             }
         }
+
+        for progress in updates {
+            self.handle_operation_progress(progress);
+        }
     }
 
     /// Process AI response messages
@@ -147,6 +236,67 @@ impl App {
         }
     }
 
+    /// Feed paths from the background indexer into the fuzzy finder
+    fn process_finder_updates(&mut self) {
+        let Some(rx) = &mut self.finder_rx else {
+            return;
+        };
+        let mut paths = Vec::new();
+        let finished = loop {
+            match rx.try_recv() {
+                Ok(batch) => paths.extend(batch),
+                Err(mpsc::error::TryRecvError::Empty) => break false,
+                Err(mpsc::error::TryRecvError::Disconnected) => break true,
+            }
+        };
+        if let Some(cortex_tui::Dialog::FuzzyFinder(dialog)) = &mut self.dialog {
+            if !paths.is_empty() {
+                dialog.add_paths(paths);
+            }
+            if finished {
+                dialog.finish_indexing();
+            }
+        }
+        if finished {
+            self.finder_rx = None;
+        }
+    }
+
+    /// Show a replace plan once it has been worked out
+    fn process_replace_plan(&mut self) {
+        let Some(rx) = &mut self.replace_rx else {
+            return;
+        };
+        let plan = match rx.try_recv() {
+            Ok(plan) => plan,
+            Err(mpsc::error::TryRecvError::Empty) => return,
+            Err(mpsc::error::TryRecvError::Disconnected) => {
+                self.replace_rx = None;
+                return;
+            }
+        };
+        self.replace_rx = None;
+        if let Some(cortex_tui::Dialog::Replace(dialog)) = &mut self.dialog {
+            match plan {
+                Ok(changes) => dialog.set_changes(changes),
+                Err(e) => {
+                    dialog.state = cortex_tui::ReplaceState::Setup;
+                    self.state.set_status_message(format!("Invalid pattern: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Adopt content indexes built or loaded in the background
+    async fn process_index_updates(&mut self) {
+        while let Ok(update) = self.index_rx.try_recv() {
+            match update {
+                Ok(index) => self.adopt_content_index(index).await,
+                Err(e) => self.state.set_status_message(format!("Indexing failed: {}", e)),
+            }
+        }
+    }
+
     /// Process file system change notifications
     fn process_file_changes(&mut self) {
         if let Some(rx) = &mut self.file_change_rx {
@@ -170,6 +320,120 @@ impl App {
         }
     }
 
+    /// Bring a viewer that follows its file up to date with the file
+    fn process_viewer_follow(&mut self) {
+        let Some(Dialog::Viewer(dialog)) = &mut self.dialog else {
+            return;
+        };
+        if dialog.viewer.follow == cortex_tui::Follow::Off {
+            return;
+        }
+        let changed = match &mut self.viewer_events {
+            Some(rx) => {
+                let mut changed = false;
+                while let Ok(event) = rx.try_recv() {
+                    changed |= match event {
+                        cortex_core::FileMonitorEvent::Created(path)
+                        | cortex_core::FileMonitorEvent::Modified(path)
+                        | cortex_core::FileMonitorEvent::Deleted(path)
+                        | cortex_core::FileMonitorEvent::Renamed { to: path, .. } => dialog.viewer.is_viewing(&path),
+                    };
+                }
+                changed
+            }
+            // Without a watcher, look at the file on every pass
+            None => true,
+        };
+        if changed {
+            if let Err(e) = dialog.viewer.refresh_follow() {
+                dialog.message = Some(format!("Cannot follow {}: {}", dialog.viewer.title, e));
+            }
+        }
+    }
+
+    /// Show where a viewer search or line jump landed
+    fn process_viewer_lookup(&mut self) {
+        let Some(rx) = &mut self.viewer_lookup_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::error::TryRecvError::Empty) => return,
+            Err(mpsc::error::TryRecvError::Disconnected) => {
+                self.viewer_lookup_rx = None;
+                return;
+            }
+        };
+        self.viewer_lookup_rx = None;
+        self.viewer_lookup_cancel = None;
+        if let Some(Dialog::Viewer(dialog)) = &mut self.dialog {
+            dialog.message = dialog.viewer.finish_lookup(result).err().map(|e| e.to_string());
+        }
+    }
+
+    /// Keep the QuickView panel on the active panel's current entry
+    fn process_quick_view(&mut self) {
+        if self.state.quick_view.is_none() {
+            return;
+        }
+        let panel = self.state.active_panel();
+        let target = match panel.is_using_vfs() {
+            true => None,
+            false => panel.current_entry().map(|entry| entry.path.clone()),
+        };
+        let remote = panel.is_using_vfs();
+        let Some(quick_view) = &mut self.state.quick_view else {
+            return;
+        };
+
+        if let Some(rx) = &mut self.quick_view_rx {
+            if let Ok((path, preview)) = rx.try_recv() {
+                self.quick_view_rx = None;
+                if quick_view.path.as_ref() == Some(&path) {
+                    quick_view.content = match preview {
+                        Ok(preview) => cortex_core::QuickViewContent::Ready(preview),
+                        Err(e) => cortex_core::QuickViewContent::Unavailable(format!("Cannot preview: {}", e)),
+                    };
+                }
+            }
+        }
+
+        if target != quick_view.path {
+            if let Some(cancel) = self.quick_view_cancel.take() {
+                cancel.cancel();
+            }
+            self.quick_view_rx = None;
+            quick_view.content = match (&target, remote) {
+                (Some(_), _) => cortex_core::QuickViewContent::Loading,
+                (None, true) => cortex_core::QuickViewContent::Unavailable("Quick View shows local files only".to_string()),
+                (None, false) => cortex_core::QuickViewContent::Unavailable("Nothing to preview".to_string()),
+            };
+            quick_view.path = target;
+            // Wait for the cursor to settle before reading anything
+            self.quick_view_due = quick_view
+                .path
+                .is_some()
+                .then(|| std::time::Instant::now() + QUICK_VIEW_DELAY);
+        }
+
+        if self.quick_view_due.is_some_and(|due| due <= std::time::Instant::now()) {
+            self.quick_view_due = None;
+            let Some(path) = quick_view.path.clone() else {
+                return;
+            };
+            let cancel = cortex_core::CancellationToken::new();
+            let (tx, rx) = mpsc::unbounded_channel();
+            self.quick_view_rx = Some(rx);
+            self.quick_view_cancel = Some(cancel.clone());
+            tokio::task::spawn_blocking(move || {
+                let preview = cortex_core::build_preview(&path, &cancel);
+                if !cancel.is_cancelled() {
+                    let _ = tx.send((path, preview));
+                }
+            });
+        }
+    }
+
     /// Process command output from background tasks
     async fn process_command_output(&mut self) {
         let mut command_outputs = Vec::new();
@@ -199,6 +463,69 @@ impl App {
         }
     }
 
+    /// Drain background VFS listings into their panels
+    fn process_vfs_listings(&mut self) {
+        let mut finished = Vec::new();
+        for (index, (side, handle)) in self.vfs_listings.iter_mut().enumerate() {
+            let panel = match side {
+                ActivePanel::Left => &mut self.state.left_panel,
+                ActivePanel::Right => &mut self.state.right_panel,
+            };
+            while let Some(update) = handle.try_next() {
+                match update {
+                    cortex_core::ListingUpdate::Entries(batch) => {
+                        panel.vfs_entries.extend(batch);
+                    }
+                    cortex_core::ListingUpdate::Complete => {
+                        finished.push(index);
+                        break;
+                    }
+                    cortex_core::ListingUpdate::Failed(e) => {
+                        self.state.status_message = Some(format!("Listing failed: {}", e));
+                        finished.push(index);
+                        break;
+                    }
+                }
+            }
+        }
+
+        for index in finished.into_iter().rev() {
+            let (side, _) = self.vfs_listings.remove(index);
+            self.state.panel_mut(side).loading = false;
+        }
+    }
+
+    /// Send periodic keepalives for remote panels and refresh their connection status
+    fn process_connections(&mut self) {
+        let remote = |path: &Option<cortex_core::VfsPath>| {
+            matches!(
+                path,
+                Some(cortex_core::VfsPath::Sftp { .. } | cortex_core::VfsPath::Ftp { .. })
+            )
+        };
+        if !remote(&self.state.left_panel.current_vfs_path)
+            && !remote(&self.state.right_panel.current_vfs_path)
+        {
+            self.state.left_panel.connection = None;
+            self.state.right_panel.connection = None;
+            return;
+        }
+
+        let interval = self.state.config_manager.get().network.keepalive_interval;
+        if interval > 0 && self.last_keepalive.elapsed().as_secs() >= interval {
+            self.last_keepalive = std::time::Instant::now();
+            let vfs = self.vfs.clone();
+            tokio::spawn(async move { vfs.keepalive().await });
+        }
+
+        for panel in [&mut self.state.left_panel, &mut self.state.right_panel] {
+            panel.connection = match panel.current_vfs_path {
+                Some(ref path) if remote(&panel.current_vfs_path) => self.vfs.connection_status(path),
+                _ => None,
+            };
+        }
+    }
+
     /// Process configuration reload notifications
     fn process_config_reload(&mut self) {
         if self.config_reload_rx.try_recv().is_ok() {
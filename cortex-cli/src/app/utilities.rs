@@ -1,14 +1,20 @@
 use anyhow::Result;
-use cortex_core::{PanelState, FileMonitorEvent, OperationProgress, SearchProgress};
-use std::path::PathBuf;
+use cortex_core::{ContentIndex, FileMonitorManager, PanelState, FileMonitorEvent, OperationProgress, SearchProgress};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use super::App;
+use super::{App, SavedSearchInput};
 
 impl App {
     /// Refresh a panel using the directory cache
     pub fn refresh_panel_with_cache(&mut self, panel: &mut PanelState) -> Result<()> {
         use cortex_core::FileSystem;
         
+        if panel.search_listing.is_some() {
+            panel.refresh_search_results();
+            return Ok(());
+        }
+
         // Use the global configuration setting for show_hidden
         let config = self.state.config_manager.get();
         let entries = FileSystem::list_directory(&panel.current_dir, config.general.show_hidden)?;
@@ -43,37 +49,102 @@ impl App {
                     "{}: {}% ({}/{})", message, percentage, current, total
                 ));
             }
+            OperationProgress::Transfer {
+                files_done,
+                files_total,
+                bytes_done,
+                bytes_total,
+                bytes_per_sec,
+                eta_secs,
+            } => {
+                if let Some(cortex_tui::Dialog::Progress(dialog)) = &mut self.dialog {
+                    dialog.update(
+                        bytes_done,
+                        bytes_total,
+                        format!("{} of {} files", files_done, files_total),
+                    );
+                    dialog.update_throughput(bytes_per_sec, eta_secs);
+                }
+            }
             OperationProgress::Completed { operation } => {
                 self.state.set_status_message(format!("Completed: {}", operation));
+                self.finish_transfer();
                 self.refresh_needed = true;
             }
             OperationProgress::Failed { operation, error } => {
                 self.state.set_status_message(format!("Error in {}: {}", operation, error));
+                self.finish_transfer();
+            }
+        }
+    }
+
+    /// Close the progress dialog of a finished remote transfer
+    fn finish_transfer(&mut self) {
+        if self.transfer_cancel.take().is_some() {
+            if matches!(self.dialog, Some(cortex_tui::Dialog::Progress(_))) {
+                self.dialog = None;
+            }
+            // Remote panels are not covered by the local refresh
+            for side in [cortex_core::ActivePanel::Left, cortex_core::ActivePanel::Right] {
+                let path = self.state.panel_mut(side).current_vfs_path.clone();
+                if let Some(path @ cortex_core::VfsPath::Sftp { .. }) = path {
+                    self.start_vfs_listing_on(side, path);
+                }
             }
         }
     }
 
     /// Handle search progress updates
     pub fn handle_search_progress(&mut self, progress: SearchProgress) {
+        let dialog = match &mut self.dialog {
+            Some(cortex_tui::Dialog::Search(dialog)) => Some(dialog),
+            _ => None,
+        };
         match progress {
             SearchProgress::Started { total_dirs } => {
+                if let Some(dialog) = dialog {
+                    dialog.search_progress = Some(cortex_tui::SearchProgressInfo {
+                        current_path: PathBuf::new(),
+                        searched: 0,
+                        total: total_dirs,
+                        found: 0,
+                    });
+                }
                 self.state.set_status_message(format!("Search started... {} directories", total_dirs));
             }
             SearchProgress::Searching { current_path, searched, total } => {
                 self.state.set_status_message(format!(
                     "Searching... {}: {}/{}", current_path.display(), searched, total
                 ));
+                if let Some(info) = dialog.and_then(|dialog| dialog.search_progress.as_mut()) {
+                    info.current_path = current_path;
+                    info.searched = searched;
+                    info.total = total;
+                }
             }
             SearchProgress::Found { result } => {
                 self.state.set_status_message(format!(
                     "Found: {}", result.path.display()
                 ));
+                if let Some(dialog) = dialog {
+                    if let Some(info) = dialog.search_progress.as_mut() {
+                        info.found += 1;
+                    }
+                    dialog.results.push(result);
+                }
             }
             SearchProgress::Completed { total_found, elapsed_ms } => {
                 self.state.set_status_message(format!(
                     "Search completed. Found {} results in {}ms.", total_found, elapsed_ms
                 ));
-                // TODO: Display search results in UI
+                if let Some(dialog) = dialog {
+                    // Results stream in from parallel workers; list them in path order
+                    dialog.results.sort_by(|a, b| a.path.cmp(&b.path));
+                    dialog.state = cortex_tui::SearchState::Results;
+                    dialog.selected_result = 0;
+                }
+                self.search_rx = None;
+                self.search_cancel = None;
             }
             SearchProgress::Error { path, error } => {
                 self.state.set_status_message(format!("Search error at {}: {}", path.display(), error));
@@ -81,6 +152,457 @@ impl App {
         }
     }
 
+    /// Run the search set up in the search dialog from the active panel's directory,
+    /// narrowing content searches with a saved content index when one covers it
+    pub fn start_search(&mut self) {
+        let Some(cortex_tui::Dialog::Search(dialog)) = &mut self.dialog else {
+            return;
+        };
+        let criteria = dialog.criteria.clone();
+        let mut engine = match cortex_core::SearchEngine::new(criteria.clone()) {
+            Ok(engine) => engine.with_vfs(self.vfs.clone()),
+            Err(e) => {
+                self.state.set_status_message(format!("Invalid search: {}", e));
+                return;
+            }
+        };
+        // A panel of earlier results is refined rather than searched from its root
+        let panel = self.state.active_panel();
+        let refine: Option<Vec<PathBuf>> = panel
+            .search_listing
+            .as_ref()
+            .map(|_| panel.entries.iter().map(|entry| entry.path.clone()).collect());
+        let search_path = panel.current_dir.clone();
+        let vfs_path = panel.current_vfs_path.clone();
+        if vfs_path.is_none() && refine.is_none() {
+            self.saved_searches.set_last(search_path.clone(), criteria.clone());
+            if let Err(e) = self.saved_searches.save() {
+                log::warn!("Could not remember the last search: {}", e);
+            }
+        }
+
+        dialog.state = cortex_tui::SearchState::Searching;
+        dialog.results.clear();
+        dialog.selected_result = 0;
+        dialog.search_progress = None;
+        dialog.search_root = match &vfs_path {
+            Some(vfs_path) => PathBuf::from(vfs_path.to_string()),
+            None => search_path.clone(),
+        };
+        let index = self
+            .content_index
+            .clone()
+            .filter(|index| search_path.starts_with(index.root()));
+        let index_tx = self.index_tx.clone();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.search_rx = Some(rx);
+        if let Some(previous) = self.search_cancel.take() {
+            previous.cancel();
+        }
+        let cancel = cortex_core::CancellationToken::new();
+        engine = engine.with_cancellation(cancel.clone());
+        self.search_cancel = Some(cancel);
+
+        tokio::spawn(async move {
+            let index = match index {
+                Some(index) => Some(index),
+                None if criteria.search_in_files => {
+                    let dir = search_path.clone();
+                    let loaded = tokio::task::spawn_blocking(move || cortex_core::ContentIndex::find_for(&dir))
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(Arc::new);
+                    if let Some(index) = &loaded {
+                        let _ = index_tx.send(Ok(index.clone()));
+                    }
+                    loaded
+                }
+                None => None,
+            };
+            if let Some(index) = &index {
+                engine = engine.with_index(index);
+            }
+            let result = match (vfs_path, refine) {
+                (Some(vfs_path), _) => engine.search_vfs(&vfs_path, tx).await,
+                (None, Some(files)) => engine.search_files(&files, tx).await,
+                (None, None) => engine.search(&search_path, tx).await,
+            };
+            if let Err(e) = result {
+                log::error!("Search failed: {}", e);
+            }
+        });
+
+        self.state.set_status_message(format!("Searching for '{}'...", criteria.pattern));
+    }
+
+    /// Save the last search under `name`, or delete the saved search of that name
+    pub fn name_saved_search(&mut self, input: SavedSearchInput, name: &str) -> Result<()> {
+        let name = name.trim();
+        match input {
+            SavedSearchInput::Save if name.is_empty() => {
+                self.state.set_status_message("A saved search needs a name");
+            }
+            SavedSearchInput::Save => match self.saved_searches.last().cloned() {
+                Some(last) => {
+                    self.saved_searches.insert(cortex_core::SavedSearch {
+                        name: name.to_string(),
+                        ..last
+                    });
+                    self.saved_searches.save()?;
+                    self.state.set_status_message(format!("Saved search '{}'", name));
+                }
+                None => self.state.set_status_message("No search to save yet"),
+            },
+            SavedSearchInput::Delete if self.saved_searches.remove(name) => {
+                self.saved_searches.save()?;
+                self.state.set_status_message(format!("Deleted saved search '{}'", name));
+            }
+            SavedSearchInput::Delete => {
+                self.state.set_status_message(format!("No saved search named '{}'", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Open the search dialog on a saved search and run it from its root
+    pub fn run_saved_search(&mut self, search: cortex_core::SavedSearch) -> Result<()> {
+        if !search.root.is_dir() {
+            self.state.set_status_message(format!("Search root {} no longer exists", search.root.display()));
+            return Ok(());
+        }
+        self.open_vfs_path(cortex_core::VfsPath::Local(search.root))?;
+        self.dialog = Some(cortex_tui::Dialog::Search(cortex_tui::SearchDialog::with_criteria(
+            search.criteria,
+        )));
+        self.start_search();
+        Ok(())
+    }
+
+    /// The command palette, with an entry for each saved search
+    pub fn open_command_palette(&mut self) {
+        let saved = self.saved_searches.searches().iter().map(|search| {
+            let criteria = &search.criteria;
+            let what = match &criteria.conditions {
+                Some(conditions) if criteria.pattern.is_empty() => conditions.to_string(),
+                _ => criteria.pattern.clone(),
+            };
+            cortex_tui::CommandInfo {
+                name: format!("/search:{}", search.name),
+                description: format!("'{}' in {}", what, search.root.display()),
+                shortcut: None,
+                category: "Saved Searches".to_string(),
+            }
+        });
+        self.dialog = Some(cortex_tui::Dialog::CommandPalette(
+            cortex_tui::CommandPaletteDialog::new().with_commands(saved.collect()),
+        ));
+    }
+
+    /// Open the fuzzy finder over the active panel's tree, indexing it in the background
+    pub fn open_fuzzy_finder(&mut self) {
+        if self.state.active_panel().is_using_vfs() {
+            self.state.set_status_message("The file finder only works on local directories");
+            return;
+        }
+        let root = match &self.state.active_panel().search_listing {
+            Some(listing) => listing.root.clone(),
+            None => self.state.active_panel().current_dir.clone(),
+        };
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.finder_rx = Some(rx);
+        self.dialog = Some(cortex_tui::Dialog::FuzzyFinder(cortex_tui::FuzzyFinderDialog::new(
+            root.clone(),
+        )));
+
+        // Closing the finder drops the receiver, which stops the walk
+        tokio::task::spawn_blocking(move || {
+            cortex_core::fuzzy::collect_files(&root, |batch| tx.send(batch).is_ok());
+        });
+    }
+
+    /// Offer a regex replace over the local files the search dialog found
+    pub fn open_replace(&mut self) {
+        let Some(cortex_tui::Dialog::Search(dialog)) = &self.dialog else {
+            return;
+        };
+        let files: Vec<PathBuf> = dialog
+            .results
+            .iter()
+            .filter(|result| result.location.is_none())
+            .map(|result| result.path.clone())
+            .collect();
+        if files.is_empty() {
+            self.state.set_status_message("No local files to replace in");
+            return;
+        }
+        // Content searches carry over their pattern
+        let find = match dialog.criteria.search_in_files {
+            true => cortex_core::replace::content_regex(&dialog.criteria),
+            false => String::new(),
+        };
+        let root = dialog.search_root.clone();
+        self.dialog = Some(cortex_tui::Dialog::Replace(cortex_tui::ReplaceDialog::new(files, root, find)));
+    }
+
+    /// Work out what the replace dialog's pattern changes, in the background
+    pub fn plan_replace(&mut self) {
+        let Some(cortex_tui::Dialog::Replace(dialog)) = &mut self.dialog else {
+            return;
+        };
+        if dialog.find.is_empty() {
+            return;
+        }
+        dialog.state = cortex_tui::ReplaceState::Planning;
+        let files = dialog.files.clone();
+        let find = dialog.find.clone();
+        let replacement = dialog.replacement.clone();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.replace_rx = Some(rx);
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(cortex_core::replace::plan_replacements(&files, &find, &replacement));
+        });
+    }
+
+    /// Write the hunks left enabled in the replace preview
+    pub fn apply_replace(&mut self) -> Result<()> {
+        let Some(cortex_tui::Dialog::Replace(dialog)) = &self.dialog else {
+            return Ok(());
+        };
+        let (replacements, files) = dialog.totals();
+        if replacements == 0 {
+            self.state.set_status_message("No replacements selected");
+            return Ok(());
+        }
+        let applied = cortex_core::replace::backup_root()
+            .and_then(|backups| cortex_core::replace::apply_replacements(&dialog.changes, &backups));
+        match applied {
+            Ok(undo) => {
+                self.replace_undo.push(undo);
+                // Older backups are gone by now
+                if self.replace_undo.len() > cortex_core::replace::MAX_REPLACE_BACKUPS {
+                    self.replace_undo.remove(0);
+                }
+                self.dialog = None;
+                self.refresh_needed = true;
+                self.state.set_status_message(format!(
+                    "Replaced {} matches in {} files (/replace-undo to revert)",
+                    replacements, files
+                ));
+            }
+            Err(e) => self.state.set_status_message(format!("Replace failed, nothing changed: {}", e)),
+        }
+        Ok(())
+    }
+
+    /// Restore the files the most recent replace changed
+    pub fn undo_replace(&mut self) {
+        let Some(undo) = self.replace_undo.pop() else {
+            self.state.set_status_message("No replace to undo");
+            return;
+        };
+        match undo.undo() {
+            Ok(files) => {
+                self.refresh_needed = true;
+                self.state.set_status_message(format!("Restored {} files", files));
+            }
+            Err(e) => {
+                self.state.set_status_message(format!("Cannot undo replace: {}", e));
+                self.replace_undo.push(undo);
+            }
+        }
+    }
+
+    /// Replace the active panel's listing with the search dialog's results
+    pub fn feed_search_results(&mut self) {
+        let Some(cortex_tui::Dialog::Search(dialog)) = &self.dialog else {
+            return;
+        };
+        if !dialog.results.is_empty() && dialog.results.iter().all(|result| result.location.is_some()) {
+            self.state
+                .set_status_message("Results inside archives or remote locations cannot be listed in a panel");
+            return;
+        }
+        let Some(cortex_tui::Dialog::Search(dialog)) = self.dialog.take() else {
+            return;
+        };
+        if self.state.active_panel().is_using_vfs() {
+            self.cancel_vfs_listing();
+            if let Err(e) = self.state.navigate_back_from_vfs() {
+                log::warn!("Failed to leave VFS panel: {}", e);
+            }
+        }
+        let left_out = self.state.active_panel_mut().show_search_results(
+            dialog.search_root,
+            dialog.criteria.pattern,
+            &dialog.results,
+        );
+        let listed = dialog.results.len() - left_out;
+        if left_out == 0 {
+            self.state.set_status_message(format!("{} search results in panel", listed));
+        } else {
+            self.state.set_status_message(format!(
+                "{} search results in panel, {} inside archives or remote locations left out",
+                listed, left_out
+            ));
+        }
+    }
+
+    /// Show the active panel's directory with `path` selected
+    pub fn reveal_path(&mut self, path: &Path) -> Result<()> {
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+        if self.state.active_panel().is_using_vfs() {
+            self.cancel_vfs_listing();
+            self.state.navigate_back_from_vfs()?;
+        }
+        self.navigate_to_directory(parent.to_path_buf())?;
+        let panel = self.state.active_panel_mut();
+        if let Some(index) = panel.entries.iter().position(|entry| entry.path == path) {
+            panel.selected_index = index;
+        }
+        Ok(())
+    }
+
+    /// Open a local file in the hex editor with the cursor at `offset`
+    pub fn open_hex_editor(&mut self, path: &Path, offset: usize) {
+        match cortex_tui::HexEditor::new(path) {
+            Ok(mut editor) => {
+                editor.move_to(offset);
+                self.dialog = Some(cortex_tui::Dialog::HexEditor(cortex_tui::HexEditorDialog::new(editor)));
+            }
+            Err(e) => {
+                self.dialog = Some(cortex_tui::Dialog::Error(cortex_tui::ErrorDialog::new(format!(
+                    "Failed to open {}: {}",
+                    path.display(),
+                    e
+                ))));
+            }
+        }
+    }
+
+    /// Run a viewer search or line jump in the background, in place of any
+    /// still running
+    pub fn start_viewer_lookup(&mut self, lookup: cortex_tui::Lookup) {
+        self.cancel_viewer_lookup();
+        let cancel = cortex_core::CancellationToken::new();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.viewer_lookup_rx = Some(rx);
+        self.viewer_lookup_cancel = Some(cancel.clone());
+        tokio::task::spawn_blocking(move || {
+            let result = lookup.run(&cancel);
+            if !cancel.is_cancelled() {
+                let _ = tx.send(result);
+            }
+        });
+    }
+
+    pub fn cancel_viewer_lookup(&mut self) {
+        if let Some(cancel) = self.viewer_lookup_cancel.take() {
+            cancel.cancel();
+        }
+        self.viewer_lookup_rx = None;
+    }
+
+    /// Watch the directory of the file the viewer follows, which also catches
+    /// the file being rotated; without a watcher the file is polled instead
+    pub async fn watch_followed_file(&mut self, path: &Path) {
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let watch = async {
+            let (mut monitor, events) = cortex_core::FileMonitor::new()?;
+            monitor.start().await?;
+            monitor.watch_path(&dir, false).await?;
+            anyhow::Ok((monitor, events))
+        };
+        match watch.await {
+            Ok((monitor, events)) => {
+                self.viewer_monitor = Some(monitor);
+                self.viewer_events = Some(events);
+            }
+            Err(e) => log::warn!("Polling {} instead of watching it: {}", path.display(), e),
+        }
+    }
+
+    /// Keep a freshly built or loaded content index up to date with file changes
+    pub async fn adopt_content_index(&mut self, index: Arc<ContentIndex>) {
+        if let Some(monitor) = self.index_monitor.take() {
+            let _ = monitor.stop().await;
+        }
+        let status = index.status();
+        let monitor = async {
+            let monitor = FileMonitorManager::new().await?;
+            monitor.start().await?;
+            index.watch(&monitor).await?;
+            anyhow::Ok(monitor)
+        };
+        match monitor.await {
+            Ok(monitor) => self.index_monitor = Some(monitor),
+            Err(e) => log::warn!("Content index of {} will not follow changes: {}", status.root.display(), e),
+        }
+        self.content_index = Some(index);
+        self.state.set_status_message(format!(
+            "Content index ready: {} files under {}",
+            status.files,
+            status.root.display()
+        ));
+    }
+
+    /// Index the active panel's directory, or the root of the index covering it
+    pub fn rebuild_content_index(&mut self) {
+        if self.state.active_panel().is_using_vfs() {
+            self.state.set_status_message("Only local directories can be indexed");
+            return;
+        }
+        let dir = self.state.active_panel().current_dir.clone();
+        let root = match &self.content_index {
+            Some(index) if dir.starts_with(index.root()) => index.root().to_path_buf(),
+            _ => dir,
+        };
+        self.state.set_status_message(format!("Indexing {}...", root.display()));
+
+        let tx = self.index_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let index = ContentIndex::build(&root).and_then(|index| {
+                index.save()?;
+                Ok(Arc::new(index))
+            });
+            let _ = tx.send(index);
+        });
+    }
+
+    /// Describe the content index covering the active panel
+    pub fn show_content_index_status(&mut self) {
+        let dir = self.state.active_panel().current_dir.clone();
+        let message = match &self.content_index {
+            Some(index) if dir.starts_with(index.root()) => {
+                let status = index.status();
+                let built_at: chrono::DateTime<chrono::Local> = status.built_at.into();
+                format!(
+                    "Content index of {}: {} files, {} trigrams, built {}{}",
+                    status.root.display(),
+                    status.files,
+                    status.trigrams,
+                    built_at.format("%Y-%m-%d %H:%M"),
+                    if status.unsaved_changes { " (unsaved changes)" } else { "" }
+                )
+            }
+            _ => format!("No content index covers {}; use /index-rebuild to create one", dir.display()),
+        };
+        self.state.set_status_message(message);
+    }
+
+    /// Write unsaved index changes to disk
+    pub fn save_content_index(&self) {
+        if let Some(index) = &self.content_index {
+            if index.status().unsaved_changes {
+                if let Err(e) = index.save() {
+                    log::warn!("Failed to save content index: {}", e);
+                }
+            }
+        }
+    }
+
     /// Handle file system events
     pub fn handle_file_event(&mut self, event: FileMonitorEvent) {
         match event {
@@ -122,6 +644,11 @@ impl App {
     pub fn handle_command_output(&mut self, output: String) {
         // Display command output in the status line or a dedicated area
         self.state.set_status_message(format!("Command: {}", output.trim()));
+        if output.starts_with("[COMPLETED]") {
+            self.state.set_command_running(false);
+            self.command_cancel = None;
+        }
+        self.state.add_command_output(output);
     }
 
     /// Navigate to a specific directory in the active panel
@@ -131,6 +658,7 @@ impl App {
                 let active_panel = self.state.active_panel_mut();
                 active_panel.current_dir = path;
                 active_panel.selected_index = 0;
+                active_panel.search_listing = None;
             }
             // Refresh after updating the path
             let mut active_panel = self.state.active_panel().clone();
@@ -166,9 +694,34 @@ impl App {
                 // TODO: Implement trash restoration
                 self.state.set_status_message("Trash restoration not yet implemented");
             }
-            cortex_core::FileOperation::Rename { old_path: _, new_name: _ } => {
-                // TODO: Implement rename operation
-                self.state.set_status_message("Rename operation not yet implemented");
+            cortex_core::FileOperation::Rename { old_path, new_name } => {
+                let new_path = old_path.with_file_name(&new_name);
+                std::fs::rename(&old_path, &new_path)?;
+                self.state.left_panel.rename_listed(&old_path, &new_path);
+                self.state.right_panel.rename_listed(&old_path, &new_path);
+            }
+            cortex_core::FileOperation::MultiRename { paths, pattern } => {
+                let mut renamed = 0;
+                for (counter, old_path) in paths.iter().enumerate() {
+                    let new_name = cortex_core::expand_rename_pattern(&pattern, old_path, counter + 1);
+                    let new_path = old_path.with_file_name(&new_name);
+                    if new_path == *old_path {
+                        continue;
+                    }
+                    if new_path.exists() {
+                        self.state.set_status_message(format!(
+                            "Skipped {}: {} already exists",
+                            old_path.display(),
+                            new_name
+                        ));
+                        continue;
+                    }
+                    std::fs::rename(old_path, &new_path)?;
+                    self.state.left_panel.rename_listed(old_path, &new_path);
+                    self.state.right_panel.rename_listed(old_path, &new_path);
+                    renamed += 1;
+                }
+                self.state.set_status_message(format!("Renamed {} of {} files", renamed, paths.len()));
             }
             cortex_core::FileOperation::CopyToClipboard { paths: _ } => {
                 // TODO: Implement clipboard copy
@@ -195,6 +748,114 @@ impl App {
         Ok(())
     }
 
+    /// List a VFS directory in the background, filling the active panel as entries arrive
+    pub fn start_vfs_listing(&mut self, path: cortex_core::VfsPath) {
+        self.start_vfs_listing_on(self.state.active_panel, path);
+    }
+
+    /// List a VFS directory in the background into the given panel
+    pub fn start_vfs_listing_on(&mut self, side: cortex_core::ActivePanel, path: cortex_core::VfsPath) {
+        self.vfs_listings.retain(|(s, _)| *s != side);
+
+        let handle = self.vfs.list_entries(path.clone());
+
+        let panel = self.state.panel_mut(side);
+        panel.current_vfs_path = Some(path);
+        panel.vfs_entries.clear();
+        panel.filtered_vfs_entries.clear();
+        panel.selected_index = 0;
+        panel.view_offset = 0;
+        panel.loading = true;
+        self.vfs_listings.push((side, handle));
+    }
+
+    /// Open a location in the active panel, leaving VFS mode when it is a local path
+    pub fn open_vfs_path(&mut self, path: cortex_core::VfsPath) -> Result<()> {
+        match path {
+            cortex_core::VfsPath::Local(dir) => {
+                self.cancel_vfs_listing();
+                self.state.navigate_back_from_vfs()?;
+                self.navigate_to_directory(dir)?;
+            }
+            path => self.start_vfs_listing(path),
+        }
+        Ok(())
+    }
+
+    /// Show the drive menu for one panel: mounted filesystems, saved connections and bookmarks
+    pub async fn open_drive_menu(&mut self, side: cortex_core::ActivePanel) {
+        let current_dir = match side {
+            cortex_core::ActivePanel::Left => self.state.left_panel.current_dir.clone(),
+            cortex_core::ActivePanel::Right => self.state.right_panel.current_dir.clone(),
+        };
+
+        // Saved credentials are keyed "user:port@host"
+        let mut remotes: Vec<cortex_core::VfsPath> = self
+            .state
+            .remote_connections
+            .keys()
+            .filter_map(|key| {
+                let (user_port, host) = key.split_once('@')?;
+                let (username, port) = user_port.rsplit_once(':')?;
+                Some(cortex_core::VfsPath::Sftp {
+                    host: host.to_string(),
+                    port: port.parse().ok()?,
+                    username: username.to_string(),
+                    path: "/".to_string(),
+                })
+            })
+            .collect();
+        remotes.sort_by_key(|path| path.to_string());
+
+        let mut bookmarks: Vec<(u8, PathBuf)> = self
+            .state
+            .config_manager
+            .get()
+            .general
+            .quick_dirs
+            .into_iter()
+            .collect();
+        bookmarks.sort_by_key(|(slot, _)| *slot);
+
+        // Mounts that don't answer within the timeout are listed without sizes
+        let probe = tokio::task::spawn_blocking(|| {
+            cortex_core::mounts::mounted_filesystems(std::time::Duration::from_secs(2))
+        })
+        .await;
+        let mounts = match probe {
+            Ok((mounts, 0)) => mounts,
+            Ok((mounts, unanswered)) => {
+                self.state.set_status_message(format!(
+                    "{} mounted filesystem(s) not answering, their sizes are left out",
+                    unanswered
+                ));
+                mounts
+            }
+            Err(_) => cortex_core::mounts::mount_points(),
+        };
+
+        self.dialog = Some(cortex_tui::Dialog::Drive(cortex_tui::DriveDialog::new(
+            side,
+            &current_dir,
+            mounts,
+            remotes,
+            bookmarks,
+        )));
+    }
+
+    /// Cancel the background listing of the active panel, keeping what has arrived so far
+    pub fn cancel_vfs_listing(&mut self) -> bool {
+        let side = self.state.active_panel;
+        let before = self.vfs_listings.len();
+        self.vfs_listings.retain(|(s, _)| *s != side);
+        if self.vfs_listings.len() == before {
+            return false;
+        }
+        self.state.active_panel_mut().loading = false;
+        self.state.set_status_message("Listing cancelled");
+        true
+    }
+
     /// Connect to an SFTP server
     #[allow(dead_code)] // TODO: Implement when SFTP functionality is fully integrated
     pub async fn connect_sftp(
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use cortex_core::AppState;
+use cortex_core::{AppState, PanelState, RemoteCredentials, VfsPath};
 use std::path::{Path, PathBuf};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command as AsyncCommand;
@@ -15,22 +15,27 @@ impl CommandProcessor {
     /// %D - opposite panel directory
     /// %p - full path of current file
     /// %P - full paths of marked files
-    #[allow(dead_code)]
+    ///
+    /// On SFTP panels the directory and path variables expand to remote paths.
     pub fn expand_command(command: &str, state: &AppState) -> String {
+        Self::expand_for_panels(command, state.active_panel(), state.inactive_panel())
+    }
+
+    fn expand_for_panels(command: &str, active_panel: &PanelState, inactive_panel: &PanelState) -> String {
         let mut expanded = command.to_string();
-        let active_panel = state.active_panel();
-        let inactive_panel = state.inactive_panel();
 
         // Get current file
-        let current_file = active_panel
-            .current_entry()
-            .map(|e| e.name.clone())
-            .unwrap_or_default();
-
-        let current_path = active_panel
-            .current_entry()
-            .map(|e| e.path.to_string_lossy().to_string())
-            .unwrap_or_default();
+        let (current_file, current_path) = if Self::remote_target(active_panel).is_some() {
+            active_panel
+                .current_vfs_entry()
+                .map(|e| (e.name.clone(), Self::remote_path(&e.path).unwrap_or_default()))
+                .unwrap_or_default()
+        } else {
+            active_panel
+                .current_entry()
+                .map(|e| (e.name.clone(), e.path.to_string_lossy().to_string()))
+                .unwrap_or_default()
+        };
 
         // Get marked files or current file
         let marked_files = if !active_panel.marked_files.is_empty() {
@@ -63,14 +68,41 @@ impl CommandProcessor {
         // Replace variables
         expanded = expanded.replace("%f", &current_file);
         expanded = expanded.replace("%F", &marked_files);
-        expanded = expanded.replace("%d", &active_panel.current_dir.to_string_lossy());
-        expanded = expanded.replace("%D", &inactive_panel.current_dir.to_string_lossy());
+        expanded = expanded.replace("%d", &Self::panel_dir(active_panel));
+        expanded = expanded.replace("%D", &Self::panel_dir(inactive_panel));
         expanded = expanded.replace("%p", &current_path);
         expanded = expanded.replace("%P", &marked_paths);
 
         expanded
     }
 
+    /// Host, port, user and remote directory of a panel browsing an SFTP server
+    pub fn remote_target(panel: &PanelState) -> Option<(String, u16, String, String)> {
+        match &panel.current_vfs_path {
+            Some(VfsPath::Sftp {
+                host,
+                port,
+                username,
+                path,
+            }) => Some((host.clone(), *port, username.clone(), path.clone())),
+            _ => None,
+        }
+    }
+
+    fn remote_path(path: &VfsPath) -> Option<String> {
+        match path {
+            VfsPath::Sftp { path, .. } => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    fn panel_dir(panel: &PanelState) -> String {
+        match Self::remote_target(panel) {
+            Some((_, _, _, path)) => path,
+            None => panel.current_dir.to_string_lossy().to_string(),
+        }
+    }
+
     #[allow(dead_code)]
     pub async fn execute_command(command: &str, state: &AppState) -> Result<String> {
         let expanded = Self::expand_command(command, state);
@@ -82,11 +114,7 @@ impl CommandProcessor {
         }
 
         match parts[0] {
-            "pwd" => Ok(state
-                .active_panel()
-                .current_dir
-                .to_string_lossy()
-                .to_string()),
+            "pwd" => Ok(Self::panel_dir(state.active_panel())),
             "exit" | "quit" => Ok("exit".to_string()),
             "/monitor" => {
                 if state.is_file_monitoring_active() {
@@ -110,6 +138,89 @@ impl CommandProcessor {
         }
     }
 
+    /// Credentials stored for the panel's connection, falling back to agent auth
+    #[cfg_attr(not(feature = "ssh"), allow(dead_code))]
+    pub fn remote_credentials(
+        state: &AppState,
+        (host, port, username, _): &(String, u16, String, String),
+    ) -> RemoteCredentials {
+        let key = format!("{}:{}@{}", username, port, host);
+        state
+            .remote_connections
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| RemoteCredentials {
+                username: username.clone(),
+                password: None,
+                private_key_path: None,
+                passphrase: None,
+            })
+    }
+
+    /// Run `command` over an SSH exec channel in the remote directory, streaming
+    /// output lines the same way `execute_streaming_command_in_dir` does locally
+    #[cfg(feature = "ssh")]
+    pub async fn execute_remote_streaming_command(
+        command: &str,
+        (host, port, _, remote_dir): (String, u16, String, String),
+        credentials: RemoteCredentials,
+        sessions: std::sync::Arc<cortex_core::remote::SshConnectionManager>,
+        cancel: cortex_core::CancellationToken,
+        output_sender: mpsc::Sender<String>,
+    ) -> Result<i32> {
+        use cortex_core::remote::ExecLine;
+
+        let _ = output_sender
+            .send(format!("[STARTED] Running: {}", command))
+            .await;
+        let _ = output_sender
+            .send(format!("[WORKING DIR] {}@{}:{}", credentials.username, host, remote_dir))
+            .await;
+
+        let remote_command = format!("cd {} && {}", Self::shell_quote(&remote_dir), command);
+        let line_sender = output_sender.clone();
+        let exit_code = tokio::task::spawn_blocking(move || {
+            // A pooled session of its own, so no new handshake per command and
+            // the panel keeps working while it runs
+            let session = sessions.get_or_create_command_session(&host, port, &credentials)?;
+            sessions.exec_streaming(&session, &remote_command, &cancel, |line| match line {
+                ExecLine::Stdout(line) if !line.trim().is_empty() => {
+                    let _ = line_sender.blocking_send(line);
+                }
+                ExecLine::Stderr(line) if !line.trim().is_empty() => {
+                    let _ = line_sender.blocking_send(format!("[ERROR] {}", line));
+                }
+                _ => {}
+            })
+        })
+        .await?;
+
+        match exit_code {
+            Ok(exit_code) => {
+                let _ = output_sender
+                    .send(format!(
+                        "[COMPLETED] Process finished with exit code: {}",
+                        exit_code
+                    ))
+                    .await;
+                Ok(exit_code)
+            }
+            Err(e) => {
+                let _ = output_sender.send(format!("[ERROR] {}", e)).await;
+                let _ = output_sender
+                    .send("[COMPLETED] Process finished with exit code: -1".to_string())
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Quote a value for a POSIX shell
+    #[cfg_attr(not(feature = "ssh"), allow(dead_code))]
+    fn shell_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+
     #[allow(dead_code)]
     async fn execute_external_command(command: &str) -> Result<String> {
         let output = if cfg!(target_os = "windows") {
@@ -246,3 +357,53 @@ impl CommandProcessor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cortex_core::{VfsEntry, VfsEntryType};
+
+    fn sftp_path(path: &str) -> VfsPath {
+        VfsPath::Sftp {
+            host: "example.com".to_string(),
+            port: 22,
+            username: "deploy".to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_command_on_remote_panel() {
+        let mut active = PanelState::new(PathBuf::from("/home/local"));
+        active.current_vfs_path = Some(sftp_path("/srv/app"));
+        active.vfs_entries = vec![VfsEntry {
+            name: "app.log".to_string(),
+            path: sftp_path("/srv/app/app.log"),
+            entry_type: VfsEntryType::File,
+            size: 0,
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            permissions: String::new(),
+            compressed_size: None,
+        }];
+        let inactive = PanelState::new(PathBuf::from("/tmp/other"));
+
+        let expand = |command| CommandProcessor::expand_for_panels(command, &active, &inactive);
+        assert_eq!(expand("tail %f"), "tail app.log");
+        assert_eq!(expand("ls %d"), "ls /srv/app");
+        assert_eq!(expand("cat %p"), "cat /srv/app/app.log");
+        assert_eq!(expand("gzip %P"), "gzip \"/srv/app/app.log\"");
+        // The other panel is local, so %D stays a local path
+        assert_eq!(expand("cp %p %D"), "cp /srv/app/app.log /tmp/other");
+    }
+
+    #[test]
+    fn test_remote_target_and_shell_quote() {
+        let mut panel = PanelState::new(PathBuf::from("/home/local"));
+        assert!(CommandProcessor::remote_target(&panel).is_none());
+
+        panel.current_vfs_path = Some(sftp_path("/srv/it's here"));
+        let target = CommandProcessor::remote_target(&panel).unwrap();
+        assert_eq!(target, ("example.com".to_string(), 22, "deploy".to_string(), "/srv/it's here".to_string()));
+        assert_eq!(CommandProcessor::shell_quote(&target.3), "'/srv/it'\\''s here'");
+    }
+}
@@ -1,3 +1,4 @@
+use cortex_core::fuzzy_match;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -235,35 +236,27 @@ impl CommandPalette {
             self.filtered_commands = self.commands.clone();
             self.sort_commands();
         } else {
-            let search = if query.starts_with('/') {
-                &query[1..]
-            } else {
-                query
-            }.to_lowercase();
+            let search = query.strip_prefix('/').unwrap_or(query);
             
-            self.filtered_commands = self.commands
+            // Rank by fuzzy match, names counting double
+            let mut scored: Vec<(i64, &Command)> = self.commands
                 .iter()
-                .filter(|cmd| {
-                    let name_match = cmd.name[1..].to_lowercase().contains(&search);
-                    let desc_match = cmd.description.to_lowercase().contains(&search);
-                    let category_match = cmd.category.as_str().to_lowercase().contains(&search);
-                    name_match || desc_match || category_match
+                .filter_map(|cmd| {
+                    let name = fuzzy_match(search, &cmd.name[1..]).map(|m| m.score * 2);
+                    let other = [cmd.description.as_str(), cmd.category.as_str()]
+                        .into_iter()
+                        .filter_map(|text| fuzzy_match(search, text))
+                        .map(|m| m.score)
+                        .max();
+                    Some((name.max(other)?, cmd))
                 })
-                .cloned()
                 .collect();
-            
-            // Sort by relevance (name matches first, then description matches)
-            self.filtered_commands.sort_by(|a, b| {
-                let a_name_match = a.name[1..].to_lowercase().starts_with(&search);
-                let b_name_match = b.name[1..].to_lowercase().starts_with(&search);
-                
-                match (a_name_match, b_name_match) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.category.as_str().cmp(b.category.as_str())
-                        .then_with(|| a.name.cmp(&b.name))
-                }
+            scored.sort_by(|(a_score, a), (b_score, b)| {
+                b_score.cmp(a_score)
+                    .then_with(|| a.category.as_str().cmp(b.category.as_str()))
+                    .then_with(|| a.name.cmp(&b.name))
             });
+            self.filtered_commands = scored.into_iter().map(|(_, cmd)| cmd.clone()).collect();
         }
         
         // Reset selection if out of bounds
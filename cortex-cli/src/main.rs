@@ -22,7 +22,7 @@ use update::UpdateManager;
 #[command(name = "cortex")]
 #[command(about = "A modern orthodox file manager", long_about = None)]
 struct Args {
-    #[arg(help = "Directory to open")]
+    #[arg(help = "Directory or URL to open (e.g. sftp://user@host/path)")]
     path: Option<PathBuf>,
 
     #[arg(short, long, help = "Show version information")]
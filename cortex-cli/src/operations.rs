@@ -353,6 +353,9 @@ impl OperationManager {
             FileOperation::Filter { .. } => {
                 todo!()
             }
+            FileOperation::MultiRename { .. } => {
+                todo!()
+            }
         }
 
         Ok(())
@@ -452,6 +455,10 @@ impl OperationManager {
             FileOperation::Filter { filter } => {
                 ("Filter", format!("Apply filter '{}'?", filter))
             }
+            FileOperation::MultiRename { paths, pattern } => (
+                "Multi-Rename",
+                format!("Rename {} item(s) using '{}'?", paths.len(), pattern),
+            ),
         };
 
         Dialog::Confirm(ConfirmDialog::new(title, message))
@@ -0,0 +1,526 @@
+// Async counterpart of the VFS provider layer so slow providers never block the UI
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Notify};
+
+use crate::vfs::{ConnectionStatus, VfsEntry, VfsPath, VfsProvider, VirtualFileSystem};
+
+/// Entries per listing batch
+const LISTING_BATCH_SIZE: usize = 256;
+
+/// Default limit for a single VFS operation
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Cooperative cancellation shared between a VFS request and its caller
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Async VFS provider; listings are streamed in batches
+#[async_trait]
+pub trait AsyncVfsProvider: Send + Sync {
+    fn can_handle(&self, path: &VfsPath) -> bool;
+    async fn list_entries(
+        &self,
+        path: &VfsPath,
+        batches: mpsc::Sender<Vec<VfsEntry>>,
+        cancel: CancellationToken,
+    ) -> Result<()>;
+    async fn read_file(&self, path: &VfsPath, cancel: CancellationToken) -> Result<Vec<u8>>;
+    async fn write_file(&self, path: &VfsPath, data: Vec<u8>, cancel: CancellationToken) -> Result<()>;
+    async fn create_directory(&self, path: &VfsPath) -> Result<()>;
+    async fn delete(&self, path: &VfsPath) -> Result<()>;
+    async fn get_info(&self, path: &VfsPath) -> Result<VfsEntry>;
+
+    fn connection_status(&self, _path: &VfsPath) -> Option<ConnectionStatus> {
+        None
+    }
+
+    async fn keepalive(&self) {}
+}
+
+/// Runs a synchronous provider on the blocking thread pool.
+///
+/// Cancelling stops waiting for the result; the blocking call itself runs to completion.
+pub struct SyncProviderAdapter {
+    // Only `None` while dropping
+    provider: Option<Arc<dyn VfsProvider>>,
+}
+
+impl SyncProviderAdapter {
+    pub fn new(provider: impl VfsProvider + 'static) -> Self {
+        Self {
+            provider: Some(Arc::new(provider)),
+        }
+    }
+
+    fn from_boxed(provider: Box<dyn VfsProvider>) -> Self {
+        Self {
+            provider: Some(Arc::from(provider)),
+        }
+    }
+
+    fn provider(&self) -> &Arc<dyn VfsProvider> {
+        self.provider.as_ref().expect("provider is set until drop")
+    }
+
+    async fn run<T, F>(&self, cancel: &CancellationToken, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn VfsProvider) -> Result<T> + Send + 'static,
+    {
+        let provider = Arc::clone(self.provider());
+        let task = tokio::task::spawn_blocking(move || f(provider.as_ref()));
+        tokio::select! {
+            result = task => result.map_err(|e| anyhow::anyhow!("VFS task failed: {}", e))?,
+            _ = cancel.cancelled() => Err(anyhow::anyhow!("Operation cancelled")),
+        }
+    }
+}
+
+impl Drop for SyncProviderAdapter {
+    fn drop(&mut self) {
+        // Remote providers own a Tokio runtime, which panics if dropped on an async worker
+        if let Some(provider) = self.provider.take() {
+            if tokio::runtime::Handle::try_current().is_ok() {
+                std::thread::spawn(move || drop(provider));
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncVfsProvider for SyncProviderAdapter {
+    fn can_handle(&self, path: &VfsPath) -> bool {
+        self.provider().can_handle(path)
+    }
+
+    async fn list_entries(
+        &self,
+        path: &VfsPath,
+        batches: mpsc::Sender<Vec<VfsEntry>>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let path = path.clone();
+        let task_cancel = cancel.clone();
+        self.run(&cancel, move |p| {
+            p.list_entries_batched(&path, LISTING_BATCH_SIZE, &mut |batch| {
+                // Stop reading once cancelled or nobody is listening any more
+                !task_cancel.is_cancelled() && batches.blocking_send(batch).is_ok()
+            })
+        })
+        .await
+    }
+
+    async fn read_file(&self, path: &VfsPath, cancel: CancellationToken) -> Result<Vec<u8>> {
+        let path = path.clone();
+        self.run(&cancel, move |p| {
+            let mut content = Vec::new();
+            p.read_file(&path)?.read_to_end(&mut content)?;
+            Ok(content)
+        })
+        .await
+    }
+
+    async fn write_file(&self, path: &VfsPath, data: Vec<u8>, cancel: CancellationToken) -> Result<()> {
+        let path = path.clone();
+        self.run(&cancel, move |p| {
+            p.write_file(&path, Box::new(std::io::Cursor::new(data)))
+        })
+        .await
+    }
+
+    async fn create_directory(&self, path: &VfsPath) -> Result<()> {
+        let path = path.clone();
+        self.run(&CancellationToken::new(), move |p| p.create_directory(&path))
+            .await
+    }
+
+    async fn delete(&self, path: &VfsPath) -> Result<()> {
+        let path = path.clone();
+        self.run(&CancellationToken::new(), move |p| p.delete(&path)).await
+    }
+
+    async fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        let path = path.clone();
+        self.run(&CancellationToken::new(), move |p| p.get_info(&path))
+            .await
+    }
+
+    fn connection_status(&self, path: &VfsPath) -> Option<ConnectionStatus> {
+        self.provider().connection_status(path)
+    }
+
+    async fn keepalive(&self) {
+        let _ = self
+            .run(&CancellationToken::new(), |p| {
+                p.keepalive();
+                Ok(())
+            })
+            .await;
+    }
+}
+
+/// Progress of a background listing started by `AsyncVirtualFileSystem::list_entries`
+#[derive(Debug)]
+pub enum ListingUpdate {
+    Entries(Vec<VfsEntry>),
+    Complete,
+    Failed(anyhow::Error),
+}
+
+/// Receiving end of a background listing; dropping it cancels the listing
+pub struct ListingHandle {
+    pub path: VfsPath,
+    batches: mpsc::Receiver<Vec<VfsEntry>>,
+    outcome: oneshot::Receiver<Result<()>>,
+    cancel: CancellationToken,
+    finished: bool,
+}
+
+impl ListingHandle {
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Non-blocking poll for the next update, for use from the UI loop
+    pub fn try_next(&mut self) -> Option<ListingUpdate> {
+        if self.finished {
+            return None;
+        }
+        match self.batches.try_recv() {
+            Ok(batch) => Some(ListingUpdate::Entries(batch)),
+            Err(mpsc::error::TryRecvError::Empty) => None,
+            Err(mpsc::error::TryRecvError::Disconnected) => match self.outcome.try_recv() {
+                Ok(result) => Some(self.finish(result)),
+                Err(oneshot::error::TryRecvError::Empty) => None,
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    Some(self.finish(Err(anyhow::anyhow!("Listing task ended unexpectedly"))))
+                }
+            },
+        }
+    }
+
+    /// Wait for the next update; returns `None` once the listing has finished
+    pub async fn next(&mut self) -> Option<ListingUpdate> {
+        if self.finished {
+            return None;
+        }
+        if let Some(batch) = self.batches.recv().await {
+            return Some(ListingUpdate::Entries(batch));
+        }
+        let result = (&mut self.outcome)
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Listing task ended unexpectedly")));
+        Some(self.finish(result))
+    }
+
+    fn finish(&mut self, result: Result<()>) -> ListingUpdate {
+        self.finished = true;
+        match result {
+            Ok(()) => ListingUpdate::Complete,
+            Err(e) => ListingUpdate::Failed(e),
+        }
+    }
+}
+
+impl Drop for ListingHandle {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// Async virtual file system. Listings and metadata calls time out; reads and writes
+/// take as long as the transfer needs and are stopped through their cancellation token
+pub struct AsyncVirtualFileSystem {
+    providers: Vec<Arc<dyn AsyncVfsProvider>>,
+    timeout: Duration,
+}
+
+impl Default for AsyncVirtualFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncVirtualFileSystem {
+    /// All built-in providers, adapted from the synchronous VFS
+    pub fn new() -> Self {
+        Self::from_sync(VirtualFileSystem::new())
+    }
+
+    pub fn from_sync(vfs: VirtualFileSystem) -> Self {
+        Self {
+            providers: vfs
+                .into_providers()
+                .into_iter()
+                .map(|p| Arc::new(SyncProviderAdapter::from_boxed(p)) as Arc<dyn AsyncVfsProvider>)
+                .collect(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Register a native async provider; it takes precedence over adapted ones
+    pub fn with_provider(mut self, provider: impl AsyncVfsProvider + 'static) -> Self {
+        self.providers.insert(0, Arc::new(provider));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn provider_for(&self, path: &VfsPath) -> Result<Arc<dyn AsyncVfsProvider>> {
+        self.providers
+            .iter()
+            .find(|p| p.can_handle(path))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No provider found for path"))
+    }
+
+    async fn with_deadline<T>(&self, operation: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+        tokio::time::timeout(self.timeout, operation)
+            .await
+            .map_err(|_| anyhow::anyhow!("Operation timed out after {}s", self.timeout.as_secs()))?
+    }
+
+    /// Start listing `path` in the background
+    pub fn list_entries(&self, path: VfsPath) -> ListingHandle {
+        let (batch_tx, batch_rx) = mpsc::channel(8);
+        let (outcome_tx, outcome_rx) = oneshot::channel();
+        let cancel = CancellationToken::new();
+
+        let provider = self.provider_for(&path);
+        let timeout = self.timeout;
+        let task_path = path.clone();
+        let task_cancel = cancel.clone();
+        tokio::spawn(async move {
+            let result = match provider {
+                Ok(provider) => {
+                    let listing = provider.list_entries(&task_path, batch_tx, task_cancel);
+                    tokio::time::timeout(timeout, listing)
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(anyhow::anyhow!("Listing timed out after {}s", timeout.as_secs()))
+                        })
+                }
+                Err(e) => Err(e),
+            };
+            let _ = outcome_tx.send(result);
+        });
+
+        ListingHandle {
+            path,
+            batches: batch_rx,
+            outcome: outcome_rx,
+            cancel,
+            finished: false,
+        }
+    }
+
+    pub async fn read_file(&self, path: &VfsPath, cancel: CancellationToken) -> Result<Vec<u8>> {
+        self.provider_for(path)?.read_file(path, cancel).await
+    }
+
+    pub async fn write_file(&self, path: &VfsPath, data: Vec<u8>, cancel: CancellationToken) -> Result<()> {
+        self.provider_for(path)?.write_file(path, data, cancel).await
+    }
+
+    pub async fn create_directory(&self, path: &VfsPath) -> Result<()> {
+        let provider = self.provider_for(path)?;
+        self.with_deadline(provider.create_directory(path)).await
+    }
+
+    pub async fn delete(&self, path: &VfsPath) -> Result<()> {
+        let provider = self.provider_for(path)?;
+        self.with_deadline(provider.delete(path)).await
+    }
+
+    pub async fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        let provider = self.provider_for(path)?;
+        self.with_deadline(provider.get_info(path)).await
+    }
+
+    pub fn connection_status(&self, path: &VfsPath) -> Option<ConnectionStatus> {
+        self.provider_for(path).ok()?.connection_status(path)
+    }
+
+    /// Probe every provider's open connections; slow probes give up at the timeout
+    pub async fn keepalive(&self) {
+        let probes = self.providers.iter().map(|provider| provider.keepalive());
+        let _ = tokio::time::timeout(self.timeout, futures::future::join_all(probes)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_local_listing_streams_in_batches() {
+        let dir = TempDir::new().unwrap();
+        for i in 0..(LISTING_BATCH_SIZE + 10) {
+            std::fs::write(dir.path().join(format!("file{}.txt", i)), "x").unwrap();
+        }
+
+        let vfs = AsyncVirtualFileSystem::new();
+        let mut handle = vfs.list_entries(VfsPath::Local(dir.path().to_path_buf()));
+        let mut batches = 0;
+        let mut entries = 0;
+        loop {
+            match handle.next().await {
+                Some(ListingUpdate::Entries(batch)) => {
+                    batches += 1;
+                    entries += batch.len();
+                }
+                Some(ListingUpdate::Complete) => break,
+                Some(ListingUpdate::Failed(e)) => panic!("listing failed: {}", e),
+                None => unreachable!(),
+            }
+        }
+        assert_eq!(batches, 2);
+        // Every file plus the ".." entry
+        assert_eq!(entries, LISTING_BATCH_SIZE + 11);
+        assert!(handle.next().await.is_none());
+    }
+
+    /// Holds listings (after handing over one batch) and reads open until released
+    struct GatedProvider {
+        release: std::sync::Mutex<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl VfsProvider for GatedProvider {
+        fn can_handle(&self, _path: &VfsPath) -> bool {
+            true
+        }
+        fn list_entries(&self, _path: &VfsPath) -> Result<Vec<VfsEntry>> {
+            unreachable!("listings go through list_entries_batched")
+        }
+        fn list_entries_batched(
+            &self,
+            path: &VfsPath,
+            _batch_size: usize,
+            sink: &mut dyn FnMut(Vec<VfsEntry>) -> bool,
+        ) -> Result<()> {
+            let entry = VfsEntry {
+                name: "first".to_string(),
+                path: path.clone(),
+                entry_type: crate::vfs::VfsEntryType::File,
+                size: 0,
+                modified: std::time::SystemTime::UNIX_EPOCH,
+                permissions: String::new(),
+                compressed_size: None,
+            };
+            sink(vec![entry]);
+            self.release.lock().unwrap().recv()?;
+            Ok(())
+        }
+        fn read_file(&self, _path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+            self.release.lock().unwrap().recv()?;
+            Ok(Box::new(std::io::Cursor::new(b"slow".to_vec())))
+        }
+        fn write_file(&self, _path: &VfsPath, _data: Box<dyn Read + Send>) -> Result<()> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+        fn create_directory(&self, _path: &VfsPath) -> Result<()> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+        fn delete(&self, _path: &VfsPath) -> Result<()> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+        fn get_info(&self, _path: &VfsPath) -> Result<VfsEntry> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batches_arrive_before_the_listing_ends() {
+        let (release, gate) = std::sync::mpsc::channel();
+        let vfs = AsyncVirtualFileSystem::new().with_provider(SyncProviderAdapter::new(GatedProvider {
+            release: std::sync::Mutex::new(gate),
+        }));
+        let mut handle = vfs.list_entries(VfsPath::Local("/gated".into()));
+
+        let first = tokio::time::timeout(Duration::from_secs(5), handle.next()).await.unwrap();
+        assert!(matches!(first, Some(ListingUpdate::Entries(batch)) if batch[0].name == "first"));
+
+        release.send(()).unwrap();
+        assert!(matches!(handle.next().await, Some(ListingUpdate::Complete)));
+    }
+
+    #[tokio::test]
+    async fn test_transfers_outlast_the_operation_timeout() {
+        let (release, gate) = std::sync::mpsc::channel();
+        let vfs = AsyncVirtualFileSystem::new()
+            .with_provider(SyncProviderAdapter::new(GatedProvider {
+                release: std::sync::Mutex::new(gate),
+            }))
+            .with_timeout(Duration::from_millis(50));
+
+        let releaser = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            release.send(()).unwrap();
+        });
+        let content = vfs
+            .read_file(&VfsPath::Local("/gated".into()), CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(content, b"slow");
+        releaser.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_token_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+        token.cancel();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(token.is_cancelled());
+    }
+}
@@ -101,6 +101,12 @@ pub struct NetworkConfig {
     pub known_hosts: Vec<String>,
     #[serde(default = "default_false")]
     pub enable_compression: bool,
+    #[serde(default = "default_transfer_workers")]
+    pub transfer_workers: usize,
+    #[serde(default)]
+    pub transfer_rate_limit: u64, // KiB/s per transfer, 0 = unlimited
+    #[serde(default = "default_keepalive_interval")]
+    pub keepalive_interval: u64, // seconds, 0 = disabled
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -239,6 +245,13 @@ impl Default for GeneralConfig {
     }
 }
 
+impl GeneralConfig {
+    /// `editor = "builtin"` (or an empty value) selects the built-in text editor
+    pub fn uses_builtin_editor(&self) -> bool {
+        matches!(self.editor.trim(), "" | "builtin" | "internal")
+    }
+}
+
 impl Default for PanelConfig {
     fn default() -> Self {
         Self {
@@ -280,6 +293,9 @@ impl Default for NetworkConfig {
             verify_ssl: false,
             known_hosts: Vec::new(),
             enable_compression: false,
+            transfer_workers: default_transfer_workers(),
+            transfer_rate_limit: 0,
+            keepalive_interval: default_keepalive_interval(),
         }
     }
 }
@@ -320,6 +336,12 @@ fn default_plugin_dir() -> String {
 fn default_connection_timeout() -> u64 {
     30
 }
+fn default_transfer_workers() -> usize {
+    4
+}
+fn default_keepalive_interval() -> u64 {
+    30
+}
 fn default_ai_provider() -> String {
     "ollama".to_string()
 }
@@ -0,0 +1,557 @@
+// Persistent trigram index of file contents, used to narrow find-in-files
+// searches down to files that can possibly match before reading them.
+
+use crate::file_monitor::{ChangeNotification, FileMonitorEvent, FileMonitorManager};
+use crate::search::{SearchCriteria, SearchType};
+use anyhow::Result;
+use regex_syntax::hir::literal::Extractor;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+const MAGIC: &[u8; 4] = b"CTXI";
+const FORMAT_VERSION: u32 = 1;
+/// Larger files are left out of the index and always read during a search
+const MAX_INDEXED_SIZE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct IndexStatus {
+    pub root: PathBuf,
+    pub files: usize,
+    pub trigrams: usize,
+    pub built_at: SystemTime,
+    /// Changes applied since the index was last written to disk
+    pub unsaved_changes: bool,
+}
+
+struct IndexedFile {
+    path: PathBuf,
+    size: u64,
+    modified: u64, // nanoseconds since the epoch
+    trigrams: Vec<u32>,
+}
+
+#[derive(Default)]
+struct IndexData {
+    files: Vec<Option<IndexedFile>>,
+    by_path: HashMap<PathBuf, u32>,
+    postings: HashMap<u32, Vec<u32>>,
+    built_at: Option<SystemTime>,
+    dirty: bool,
+    /// Bumped whenever compaction renumbers the files
+    generation: u64,
+}
+
+/// Trigram index over every file below `root`
+pub struct ContentIndex {
+    root: PathBuf,
+    data: RwLock<IndexData>,
+}
+
+/// Files of an index that may contain matches for one search
+pub struct IndexFilter {
+    index: Arc<ContentIndex>,
+    candidates: HashSet<u32>,
+    generation: u64,
+}
+
+impl ContentIndex {
+    /// Index every file below `root`
+    pub fn build(root: &Path) -> Result<Self> {
+        let index = Self {
+            root: root.to_path_buf(),
+            data: RwLock::new(IndexData::default()),
+        };
+        index.rebuild()?;
+        Ok(index)
+    }
+
+    /// Load the saved index of the nearest directory at or above `path`
+    pub fn find_for(path: &Path) -> Option<Self> {
+        path.ancestors()
+            .find(|dir| Self::index_file(dir).is_some_and(|file| file.exists()))
+            .and_then(|root| match Self::load(root) {
+                Ok(index) => Some(index),
+                Err(e) => {
+                    log::warn!("Ignoring content index for {}: {}", root.display(), e);
+                    None
+                }
+            })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Re-read every file below the root, replacing the current contents
+    pub fn rebuild(&self) -> Result<()> {
+        let mut data = IndexData::default();
+        for entry in WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+        {
+            if let Some(file) = index_file_contents(entry.path()) {
+                data.insert(file);
+            }
+        }
+        data.built_at = Some(SystemTime::now());
+        data.dirty = true;
+        *self.data.write().unwrap() = data;
+        Ok(())
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        let data = self.data.read().unwrap();
+        IndexStatus {
+            root: self.root.clone(),
+            files: data.by_path.len(),
+            trigrams: data.postings.len(),
+            built_at: data.built_at.unwrap_or(UNIX_EPOCH),
+            unsaved_changes: data.dirty,
+        }
+    }
+
+    /// Re-index `path`, or everything below it for a directory
+    pub fn update_path(&self, path: &Path) {
+        if !path.starts_with(&self.root) || path.components().any(|c| c.as_os_str() == ".git") {
+            return;
+        }
+        if path.is_dir() {
+            for entry in WalkDir::new(path).into_iter().filter_map(|entry| entry.ok()) {
+                if entry.file_type().is_file() {
+                    self.update_path(entry.path());
+                }
+            }
+            return;
+        }
+
+        let file = index_file_contents(path);
+        let mut data = self.data.write().unwrap();
+        data.remove(path);
+        if let Some(file) = file {
+            data.insert(file);
+        }
+        data.dirty = true;
+    }
+
+    /// Forget `path` and anything below it
+    pub fn remove_path(&self, path: &Path) {
+        let mut data = self.data.write().unwrap();
+        let removed: Vec<PathBuf> = data
+            .by_path
+            .keys()
+            .filter(|indexed| indexed.starts_with(path))
+            .cloned()
+            .collect();
+        for indexed in removed {
+            data.remove(&indexed);
+            data.dirty = true;
+        }
+    }
+
+    fn apply(&self, event: &FileMonitorEvent) {
+        match event {
+            FileMonitorEvent::Created(path) | FileMonitorEvent::Modified(path) => {
+                if path.exists() {
+                    self.update_path(path);
+                } else {
+                    self.remove_path(path);
+                }
+            }
+            FileMonitorEvent::Deleted(path) => self.remove_path(path),
+            FileMonitorEvent::Renamed { from, to } => {
+                self.remove_path(from);
+                self.update_path(to);
+            }
+        }
+    }
+
+    /// Watch the root recursively and apply every change to the index
+    pub async fn watch(self: &Arc<Self>, monitor: &FileMonitorManager) -> Result<()> {
+        monitor.watch_directory(&self.root, true).await?;
+        let index = Arc::downgrade(self);
+        monitor
+            .register_change_callback(Arc::new(move |change: ChangeNotification| {
+                if let Some(index) = index.upgrade() {
+                    tokio::task::spawn_blocking(move || index.apply(&change.event));
+                }
+            }))
+            .await;
+        Ok(())
+    }
+
+    /// Narrow a content search down to candidate files, or `None` when the
+    /// pattern has no literal text long enough to look up
+    pub fn filter(self: &Arc<Self>, criteria: &SearchCriteria) -> Option<IndexFilter> {
+        let literals = required_literals(criteria)?;
+        let data = self.data.read().unwrap();
+        let mut candidates = HashSet::new();
+        for literal in literals {
+            candidates.extend(data.files_with_all(&trigrams_of(&literal)));
+        }
+        Some(IndexFilter {
+            index: Arc::clone(self),
+            candidates,
+            generation: data.generation,
+        })
+    }
+
+    fn index_file(root: &Path) -> Option<PathBuf> {
+        let name = format!("{:016x}.idx", fnv1a(root.to_string_lossy().as_bytes()));
+        Some(dirs::cache_dir()?.join("cortex").join("index").join(name))
+    }
+
+    /// Write the index to the cache directory, dropping the slots of removed
+    /// files first
+    pub fn save(&self) -> Result<()> {
+        let target = Self::index_file(&self.root)
+            .ok_or_else(|| anyhow::anyhow!("No cache directory for the content index"))?;
+        fs::create_dir_all(target.parent().unwrap_or(Path::new(".")))?;
+        self.data.write().unwrap().compact();
+
+        let temp = target.with_extension("tmp");
+        {
+            let data = self.data.read().unwrap();
+            let mut out = BufWriter::new(File::create(&temp)?);
+            out.write_all(MAGIC)?;
+            write_u32(&mut out, FORMAT_VERSION)?;
+            let built_at = data.built_at.unwrap_or(UNIX_EPOCH).duration_since(UNIX_EPOCH)?;
+            write_u64(&mut out, built_at.as_secs())?;
+            write_bytes(&mut out, self.root.to_string_lossy().as_bytes())?;
+
+            let files: Vec<&IndexedFile> = data.files.iter().flatten().collect();
+            write_u32(&mut out, files.len() as u32)?;
+            for file in files {
+                write_bytes(&mut out, file.path.to_string_lossy().as_bytes())?;
+                write_u64(&mut out, file.size)?;
+                write_u64(&mut out, file.modified)?;
+                write_u32(&mut out, file.trigrams.len() as u32)?;
+                for trigram in &file.trigrams {
+                    write_u32(&mut out, *trigram)?;
+                }
+            }
+            out.flush()?;
+        }
+        fs::rename(&temp, &target)?;
+        self.data.write().unwrap().dirty = false;
+        Ok(())
+    }
+
+    fn load(root: &Path) -> Result<Self> {
+        let source = Self::index_file(root)
+            .ok_or_else(|| anyhow::anyhow!("No cache directory for the content index"))?;
+        let mut input = BufReader::new(File::open(source)?);
+
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != FORMAT_VERSION {
+            return Err(anyhow::anyhow!("unsupported index format"));
+        }
+        let built_at = UNIX_EPOCH + Duration::from_secs(read_u64(&mut input)?);
+        if PathBuf::from(read_string(&mut input)?) != root {
+            return Err(anyhow::anyhow!("index belongs to another directory"));
+        }
+
+        let mut data = IndexData {
+            built_at: Some(built_at),
+            ..IndexData::default()
+        };
+        for _ in 0..read_u32(&mut input)? {
+            let path = PathBuf::from(read_string(&mut input)?);
+            let size = read_u64(&mut input)?;
+            let modified = read_u64(&mut input)?;
+            let count = read_u32(&mut input)? as usize;
+            let mut trigrams = Vec::with_capacity(count);
+            for _ in 0..count {
+                trigrams.push(read_u32(&mut input)?);
+            }
+            data.insert(IndexedFile {
+                path,
+                size,
+                modified,
+                trigrams,
+            });
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            data: RwLock::new(data),
+        })
+    }
+}
+
+impl IndexData {
+    fn insert(&mut self, file: IndexedFile) {
+        let id = self.files.len() as u32;
+        for trigram in &file.trigrams {
+            self.postings.entry(*trigram).or_default().push(id);
+        }
+        self.by_path.insert(file.path.clone(), id);
+        self.files.push(Some(file));
+    }
+
+    fn remove(&mut self, path: &Path) {
+        let Some(id) = self.by_path.remove(path) else {
+            return;
+        };
+        if let Some(file) = self.files[id as usize].take() {
+            for trigram in file.trigrams {
+                if let Some(ids) = self.postings.get_mut(&trigram) {
+                    ids.retain(|other| *other != id);
+                    if ids.is_empty() {
+                        self.postings.remove(&trigram);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renumber the live files so removed ones stop taking up slots
+    fn compact(&mut self) {
+        if self.files.iter().all(Option::is_some) {
+            return;
+        }
+        let files = std::mem::take(&mut self.files);
+        self.by_path.clear();
+        self.postings.clear();
+        for file in files.into_iter().flatten() {
+            self.insert(file);
+        }
+        self.generation += 1;
+    }
+
+    fn files_with_all(&self, trigrams: &[u32]) -> Vec<u32> {
+        let mut lists: Vec<&Vec<u32>> = Vec::with_capacity(trigrams.len());
+        for trigram in trigrams {
+            match self.postings.get(trigram) {
+                Some(ids) => lists.push(ids),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|ids| ids.len());
+        let Some((first, rest)) = lists.split_first() else {
+            return Vec::new();
+        };
+        let rest: Vec<HashSet<u32>> = rest.iter().map(|ids| ids.iter().copied().collect()).collect();
+        first
+            .iter()
+            .copied()
+            .filter(|id| rest.iter().all(|ids| ids.contains(id)))
+            .collect()
+    }
+}
+
+impl IndexFilter {
+    /// False only when the index is current for `path` and rules it out
+    pub fn may_match(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        let data = self.index.data.read().unwrap();
+        let Some(&id) = data.by_path.get(path) else {
+            return true;
+        };
+        // The candidates were numbered before a compaction
+        if data.generation != self.generation {
+            return true;
+        }
+        match &data.files[id as usize] {
+            Some(file) if file.size == metadata.len() && file.modified == modified_nanos(metadata) => {
+                self.candidates.contains(&id)
+            }
+            _ => true,
+        }
+    }
+}
+
+fn modified_nanos(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+fn index_file_contents(path: &Path) -> Option<IndexedFile> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_INDEXED_SIZE {
+        return None;
+    }
+    let content = fs::read(path).ok()?;
+    // Content search only reads UTF-8 text, so other files can never match
+    let trigrams = match std::str::from_utf8(&content) {
+        Ok(_) => trigrams_of(&content),
+        Err(_) => Vec::new(),
+    };
+    Some(IndexedFile {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        modified: modified_nanos(&metadata),
+        trigrams,
+    })
+}
+
+/// Distinct ASCII-case-folded byte trigrams of `bytes`
+fn trigrams_of(bytes: &[u8]) -> Vec<u32> {
+    let mut trigrams: Vec<u32> = bytes
+        .windows(3)
+        .map(|w| {
+            (w[0].to_ascii_lowercase() as u32) << 16
+                | (w[1].to_ascii_lowercase() as u32) << 8
+                | w[2].to_ascii_lowercase() as u32
+        })
+        .collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams
+}
+
+/// Literal strings one of which must appear in any matching line
+fn required_literals(criteria: &SearchCriteria) -> Option<Vec<Vec<u8>>> {
+    let pattern = match criteria.search_type {
+        SearchType::Regex => criteria.pattern.clone(),
+        SearchType::Exact | SearchType::Contains => regex::escape(&criteria.pattern),
+        // Mirrors the wildcard matcher's translation; leading wildcards are
+        // dropped since they would hide the literal prefix that follows
+        SearchType::Wildcard => criteria
+            .pattern
+            .trim_start_matches(['*', '?'])
+            .replace('.', r"\.")
+            .replace('*', ".*")
+            .replace('?', "."),
+    };
+    let hir = regex_syntax::Parser::new().parse(&pattern).ok()?;
+    let seq = Extractor::new().extract(&hir);
+    let literals = seq.literals()?;
+    if literals.is_empty() {
+        return None;
+    }
+
+    let mut required = Vec::new();
+    for literal in literals {
+        let bytes = literal.as_bytes();
+        // Case folding in the index only covers ASCII
+        if bytes.len() < 3 || (!criteria.case_sensitive && !bytes.is_ascii()) {
+            return None;
+        }
+        required.push(bytes.to_vec());
+    }
+    Some(required)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn write_u32(out: &mut impl Write, value: u32) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(out: &mut impl Write, value: u64) -> std::io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    write_u32(out, bytes.len() as u32)?;
+    out.write_all(bytes)
+}
+
+fn read_u32(input: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(input: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_string(input: &mut impl Read) -> Result<String> {
+    let mut buf = vec![0u8; read_u32(input)? as usize];
+    input.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn criteria(pattern: &str, search_type: SearchType) -> SearchCriteria {
+        SearchCriteria {
+            pattern: pattern.to_string(),
+            search_type,
+            case_sensitive: false,
+            search_in_files: true,
+            include_hidden: false,
+            include_subdirs: true,
+            max_depth: None,
+            file_extensions: Vec::new(),
+            size_filter: None,
+            date_filter: None,
+            search_archives: false,
+            respect_ignore_files: false,
+            conditions: None,
+        }
+    }
+
+    #[test]
+    fn test_index_narrows_candidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.rs"), "fn connect_sftp() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn list_entries() {}\n").unwrap();
+        fs::write(root.join("c.bin"), [0xff, 0xfe, b'c', b'o', b'n']).unwrap();
+
+        let index = Arc::new(ContentIndex::build(root).unwrap());
+        assert_eq!(index.status().files, 3);
+
+        let filter = index.filter(&criteria("CONNECT", SearchType::Contains)).unwrap();
+        let may_match = |name: &str| {
+            let path = root.join(name);
+            filter.may_match(&path, &fs::metadata(&path).unwrap())
+        };
+        assert!(may_match("a.rs"));
+        assert!(!may_match("b.rs"));
+        assert!(!may_match("c.bin"));
+
+        // Alternations keep every branch; short literals cannot be looked up
+        let filter = index.filter(&criteria("connect|entries", SearchType::Regex)).unwrap();
+        let path = root.join("b.rs");
+        assert!(filter.may_match(&path, &fs::metadata(&path).unwrap()));
+        let filter = index.filter(&criteria("*entries*", SearchType::Wildcard)).unwrap();
+        assert!(filter.may_match(&path, &fs::metadata(&path).unwrap()));
+        assert!(!filter.may_match(&root.join("a.rs"), &fs::metadata(root.join("a.rs")).unwrap()));
+        assert!(index.filter(&criteria("fn", SearchType::Contains)).is_none());
+        assert!(index.filter(&criteria(".*", SearchType::Regex)).is_none());
+
+        // A file changed behind the index's back is always searched
+        fs::write(root.join("b.rs"), "fn connect() {}\n// edited\n").unwrap();
+        let filter = index.filter(&criteria("connect", SearchType::Contains)).unwrap();
+        assert!(filter.may_match(&path, &fs::metadata(&path).unwrap()));
+
+        index.update_path(&path);
+        index.remove_path(&root.join("a.rs"));
+        let filter = index.filter(&criteria("connect", SearchType::Contains)).unwrap();
+        assert_eq!(filter.candidates.len(), 1);
+        assert_eq!(index.status().files, 2);
+
+        // Compaction drops the removed files' slots and keeps lookups working
+        fs::write(root.join("d.rs"), "fn entries() {}\n").unwrap();
+        index.update_path(&root.join("d.rs"));
+        index.data.write().unwrap().compact();
+        assert_eq!(index.data.read().unwrap().files.len(), 3);
+        let d = root.join("d.rs");
+        assert!(filter.may_match(&d, &fs::metadata(&d).unwrap()));
+        let filter = index.filter(&criteria("connect", SearchType::Contains)).unwrap();
+        assert!(filter.may_match(&path, &fs::metadata(&path).unwrap()));
+        assert!(!filter.may_match(&d, &fs::metadata(&d).unwrap()));
+    }
+}
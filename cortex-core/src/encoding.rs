@@ -0,0 +1,294 @@
+// Text encodings: guessing how a file's bytes spell its text, decoding them
+// for the viewer and editor, and encoding edited text back
+
+use anyhow::Result;
+use encoding_rs::{DecoderResult, Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251, WINDOWS_1252};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    /// UTF-8 behind a byte-order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Windows1251,
+    Windows1252,
+    Latin1,
+    ShiftJis,
+}
+
+impl TextEncoding {
+    /// Every encoding, in the order they are cycled through
+    pub const ALL: [TextEncoding; 8] = [
+        Self::Utf8,
+        Self::Utf8Bom,
+        Self::Utf16Le,
+        Self::Utf16Be,
+        Self::Windows1251,
+        Self::Windows1252,
+        Self::Latin1,
+        Self::ShiftJis,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 BOM",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Windows1251 => "Windows-1251",
+            Self::Windows1252 => "Windows-1252",
+            Self::Latin1 => "ISO-8859-1",
+            Self::ShiftJis => "Shift_JIS",
+        }
+    }
+
+    /// The encoding after this one in `ALL`, wrapping around
+    pub fn next(self) -> Self {
+        let at = Self::ALL.iter().position(|&encoding| encoding == self).unwrap_or(0);
+        Self::ALL[(at + 1) % Self::ALL.len()]
+    }
+
+    /// Byte-order mark written at the start of the file; UTF-16 always gets one
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8Bom => &[0xEF, 0xBB, 0xBF],
+            Self::Utf16Le => &[0xFF, 0xFE],
+            Self::Utf16Be => &[0xFE, 0xFF],
+            _ => &[],
+        }
+    }
+
+    /// Latin-1 is decoded by hand, as encoding_rs treats it as Windows-1252
+    fn codec(self) -> &'static Encoding {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => UTF_8,
+            Self::Utf16Le => UTF_16LE,
+            Self::Utf16Be => UTF_16BE,
+            Self::Windows1251 => WINDOWS_1251,
+            Self::Windows1252 | Self::Latin1 => WINDOWS_1252,
+            Self::ShiftJis => SHIFT_JIS,
+        }
+    }
+
+    /// Decode text, dropping a leading byte-order mark; bytes that are not
+    /// valid in the encoding become U+FFFD
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        match self {
+            Self::Latin1 => bytes.iter().map(|&byte| byte as char).collect(),
+            _ => self.codec().decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+
+    /// The code unit a line ends with; in UTF-16 it only counts at an even
+    /// offset, as other characters have a `0A` byte too
+    pub fn newline(self) -> &'static [u8] {
+        match self {
+            Self::Utf16Le => &[0x0A, 0],
+            Self::Utf16Be => &[0, 0x0A],
+            _ => b"\n",
+        }
+    }
+
+    /// Decode one line cut out of a file before its `newline`, dropping a
+    /// trailing `\r`
+    pub fn decode_line(self, bytes: &[u8]) -> String {
+        let mut text = self.decode(bytes);
+        if text.ends_with('\r') {
+            text.pop();
+        }
+        text
+    }
+
+    /// Encode text for saving, byte-order mark included; fails when the text
+    /// has characters the encoding cannot represent
+    pub fn encode(self, text: &str) -> Result<Vec<u8>> {
+        let mut bytes = self.bom().to_vec();
+        let representable = match self {
+            Self::Utf8 | Self::Utf8Bom => {
+                bytes.extend_from_slice(text.as_bytes());
+                true
+            }
+            Self::Utf16Le => {
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+                true
+            }
+            Self::Utf16Be => {
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+                true
+            }
+            Self::Latin1 => text.chars().all(|ch| match u8::try_from(ch) {
+                Ok(byte) => {
+                    bytes.push(byte);
+                    true
+                }
+                Err(_) => false,
+            }),
+            _ => {
+                let (encoded, _, had_errors) = self.codec().encode(text);
+                bytes.extend_from_slice(&encoded);
+                !had_errors
+            }
+        };
+        match representable {
+            true => Ok(bytes),
+            false => Err(anyhow::anyhow!("The text has characters {} cannot represent", self.name())),
+        }
+    }
+}
+
+/// Guess the encoding of a file from its first bytes: a byte-order mark
+/// decides, then UTF-16 by its zero bytes and UTF-8 by being valid; other
+/// text is taken for Shift_JIS, Windows-1251 or Windows-1252 by which bytes
+/// it uses
+pub fn detect_encoding(bytes: &[u8]) -> TextEncoding {
+    if bytes.starts_with(TextEncoding::Utf8Bom.bom()) {
+        return TextEncoding::Utf8Bom;
+    }
+    if bytes.starts_with(TextEncoding::Utf16Le.bom()) {
+        return TextEncoding::Utf16Le;
+    }
+    if bytes.starts_with(TextEncoding::Utf16Be.bom()) {
+        return TextEncoding::Utf16Be;
+    }
+
+    // Mostly-ASCII UTF-16 has a zero in every other byte
+    let pairs = bytes.len() / 2;
+    if pairs > 0 {
+        let zeros_at = |parity: usize| bytes.iter().skip(parity).step_by(2).filter(|&&byte| byte == 0).count();
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if odd * 10 > pairs * 4 && even * 20 < pairs {
+            return TextEncoding::Utf16Le;
+        }
+        if even * 10 > pairs * 4 && odd * 20 < pairs {
+            return TextEncoding::Utf16Be;
+        }
+    }
+
+    // A character cut off by the end of the sample still counts as valid
+    if !std::str::from_utf8(bytes).is_err_and(|e| e.error_len().is_some()) {
+        return TextEncoding::Utf8;
+    }
+
+    let high = bytes.iter().filter(|&&byte| byte >= 0x80).count();
+    // Most Japanese characters start with a byte that is rare in the
+    // single-byte code pages
+    let lead_bytes = bytes.iter().filter(|byte| (0x81..=0x9F).contains(*byte)).count();
+    if lead_bytes * 4 >= high && decodes_cleanly(SHIFT_JIS, bytes) {
+        return TextEncoding::ShiftJis;
+    }
+    // Cyrillic letters fill the top of Windows-1251, while Western text
+    // has the odd accented letter among plain ASCII
+    let upper = bytes.iter().filter(|&&byte| byte >= 0xC0).count();
+    let ascii_letters = bytes.iter().filter(|byte| byte.is_ascii_alphabetic()).count();
+    match upper > ascii_letters {
+        true => TextEncoding::Windows1251,
+        false => TextEncoding::Windows1252,
+    }
+}
+
+/// Whether `bytes` are valid in `encoding`, apart from a character cut off
+/// at the end
+fn decodes_cleanly(encoding: &'static Encoding, bytes: &[u8]) -> bool {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let Some(capacity) = decoder.max_utf8_buffer_length_without_replacement(bytes.len()) else {
+        return false;
+    };
+    let mut text = String::with_capacity(capacity);
+    let (result, _) = decoder.decode_to_string_without_replacement(bytes, &mut text, false);
+    result == DecoderResult::InputEmpty
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// The style of the first line break in `text`, LF when there is none
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(at) if text[..at].ends_with('\r') => Self::CrLf,
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Lf => Self::CrLf,
+            Self::CrLf => Self::Lf,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding(b"plain ascii"), TextEncoding::Utf8);
+        assert_eq!(detect_encoding("naïve café".as_bytes()), TextEncoding::Utf8);
+        // Cut in the middle of "é"
+        assert_eq!(detect_encoding(&"café".as_bytes()[..4]), TextEncoding::Utf8);
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFtext"), TextEncoding::Utf8Bom);
+        assert_eq!(detect_encoding(b"\xFF\xFEh\0i\0"), TextEncoding::Utf16Le);
+        assert_eq!(detect_encoding(b"\0h\0e\0l\0l\0o"), TextEncoding::Utf16Be);
+        assert_eq!(detect_encoding(b"h\0e\0l\0l\0o\0"), TextEncoding::Utf16Le);
+
+        let encoded = |encoding: &'static Encoding, text: &str| encoding.encode(text).0.into_owned();
+        assert_eq!(
+            detect_encoding(&encoded(SHIFT_JIS, "日本語のテキストです")),
+            TextEncoding::ShiftJis
+        );
+        assert_eq!(
+            detect_encoding(&encoded(WINDOWS_1251, "Привет, мир! Это тест.")),
+            TextEncoding::Windows1251
+        );
+        assert_eq!(
+            detect_encoding(&encoded(WINDOWS_1252, "Un café crème, s'il vous plaît")),
+            TextEncoding::Windows1252
+        );
+    }
+
+    #[test]
+    fn test_round_trip_and_lines() {
+        let text = "Grüße, мир, 日本\r\nnext";
+        for encoding in [TextEncoding::Utf8, TextEncoding::Utf8Bom, TextEncoding::Utf16Le, TextEncoding::Utf16Be] {
+            assert_eq!(encoding.decode(&encoding.encode(text).unwrap()), text);
+        }
+        assert_eq!(TextEncoding::Utf16Be.encode("A").unwrap(), vec![0xFE, 0xFF, 0, b'A']);
+        assert_eq!(TextEncoding::Latin1.encode("é").unwrap(), vec![0xE9]);
+        assert_eq!(TextEncoding::Latin1.decode(&[0x80, 0xE9]), "\u{80}é");
+        assert_eq!(TextEncoding::Windows1252.decode(&[0x80, 0xE9]), "€é");
+        assert!(TextEncoding::Latin1.encode("€").is_err());
+        assert!(TextEncoding::Windows1251.encode("日本").is_err());
+
+        // "ab\r\ncd" in UTF-16LE, cut around its newline
+        let bytes = TextEncoding::Utf16Le.encode("ab\r\ncd").unwrap();
+        let cut = 2 + 6;
+        assert_eq!(&bytes[cut..cut + 2], TextEncoding::Utf16Le.newline());
+        assert_eq!(TextEncoding::Utf16Le.decode_line(&bytes[..cut]), "ab");
+        assert_eq!(TextEncoding::Utf16Le.decode_line(&bytes[cut + 2..]), "cd");
+
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(TextEncoding::ShiftJis.next(), TextEncoding::Utf8);
+    }
+}
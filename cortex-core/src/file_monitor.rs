@@ -49,6 +49,8 @@ impl FileMonitor {
     pub async fn start(&mut self) -> Result<()> {
         let sender = self.event_sender.clone();
         let callbacks = self.callbacks.clone();
+        // notify delivers events on its own thread, outside the runtime
+        let runtime = tokio::runtime::Handle::current();
 
         let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
             match res {
@@ -70,7 +72,7 @@ impl FileMonitor {
 
                         // Call registered callbacks
                         let callbacks_clone = callbacks.clone();
-                        tokio::spawn(async move {
+                        runtime.spawn(async move {
                             let callbacks = callbacks_clone.read().await;
                             for callback in callbacks.iter() {
                                 callback(notification.clone());
@@ -0,0 +1,143 @@
+// Fuzzy matching shared by the file finder and the command palette
+
+use ignore::WalkBuilder;
+use std::path::Path;
+
+/// A candidate the query matched, with the char positions that matched
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 12;
+const BONUS_PATH_START: i64 = 10;
+const BONUS_WORD_START: i64 = 8;
+const BONUS_CAMEL: i64 = 6;
+const BONUS_FILE_NAME: i64 = 20;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP: i64 = 1;
+
+/// Score `candidate` against `query`, fzf style: every query char must occur
+/// in order. Matching ignores case unless the query has an uppercase letter.
+/// Runs, word starts and matches in the last path component score higher.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let case_sensitive = query.iter().any(|c| c.is_uppercase());
+    let eq = |a: char, b: char| match case_sensitive {
+        true => a == b,
+        false => a == b || a.to_lowercase().eq(b.to_lowercase()),
+    };
+    let chars: Vec<char> = candidate.chars().collect();
+
+    // The earliest position where the whole query has been seen...
+    let mut next = 0;
+    let end = chars.iter().position(|&c| {
+        if eq(c, query[next]) {
+            next += 1;
+        }
+        next == query.len()
+    })?;
+    // ...then the latest start before it, giving the tightest window
+    let mut next = query.len();
+    let start = (0..=end).rev().find(|&i| {
+        if eq(chars[i], query[next - 1]) {
+            next -= 1;
+        }
+        next == 0
+    })?;
+
+    let mut positions = Vec::with_capacity(query.len());
+    for (i, &c) in chars.iter().enumerate().take(end + 1).skip(start) {
+        if positions.len() < query.len() && eq(c, query[positions.len()]) {
+            positions.push(i);
+        }
+    }
+
+    let name_start = chars.iter().rposition(|&c| c == '/' || c == '\\').map_or(0, |i| i + 1);
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &i in &positions {
+        score += SCORE_MATCH + boundary_bonus(&chars, i);
+        match previous {
+            Some(p) if p + 1 == i => score += BONUS_CONSECUTIVE,
+            Some(p) => score -= PENALTY_GAP_START + PENALTY_GAP * (i - p - 2) as i64,
+            None => {}
+        }
+        previous = Some(i);
+    }
+    if positions[0] >= name_start {
+        score += BONUS_FILE_NAME;
+    }
+    // Among equal matches, prefer shorter candidates
+    score -= (chars.len() / 16) as i64;
+
+    Some(FuzzyMatch { score, positions })
+}
+
+fn boundary_bonus(chars: &[char], i: usize) -> i64 {
+    let Some(&before) = i.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return BONUS_PATH_START;
+    };
+    match before {
+        '/' | '\\' => BONUS_PATH_START,
+        '_' | '-' | '.' | ' ' => BONUS_WORD_START,
+        _ if before.is_lowercase() && chars[i].is_uppercase() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+/// Paths of the files below `root`, relative to it, skipping hidden and
+/// git-ignored ones. They arrive in batches; `send` returns false to stop.
+pub fn collect_files(root: &Path, mut send: impl FnMut(Vec<String>) -> bool) {
+    const BATCH: usize = 512;
+    let mut batch = Vec::with_capacity(BATCH);
+    let walker = WalkBuilder::new(root).require_git(false).build();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+            continue;
+        }
+        if let Ok(relative) = entry.path().strip_prefix(root) {
+            batch.push(relative.to_string_lossy().into_owned());
+        }
+        if batch.len() == BATCH && !send(std::mem::replace(&mut batch, Vec::with_capacity(BATCH))) {
+            return;
+        }
+    }
+    if !batch.is_empty() {
+        send(batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match_ranking() {
+        assert!(fuzzy_match("xyz", "src/main.rs").is_none());
+        assert!(fuzzy_match("msr", "src/main.rs").is_none());
+
+        let m = fuzzy_match("mrs", "src/main.rs").unwrap();
+        assert_eq!(m.positions, vec![4, 9, 10]);
+
+        // Smart case: an uppercase letter makes the query case sensitive
+        assert!(fuzzy_match("readme", "README.md").is_some());
+        assert!(fuzzy_match("Readme", "readme.md").is_none());
+
+        let score = |query, candidate| fuzzy_match(query, candidate).unwrap().score;
+        // Matches in the file name beat matches in directories
+        assert!(score("main", "src/main.rs") > score("main", "maintenance/src/lib.rs"));
+        // Word starts beat scattered letters
+        assert!(score("sd", "search_dialog.rs") > score("sd", "used.rs"));
+        // Runs beat gaps
+        assert!(score("vfs", "src/vfs.rs") > score("vfs", "src/view_files.rs"));
+    }
+}
@@ -1,10 +1,16 @@
-use std::path::Path;
-use std::process::Command;
+use crate::vfs::{VfsEntry, VfsEntryType, VfsPath, VfsProvider};
+use anyhow::Result;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const SHORT_COMMIT_HASH_LEN: usize = 7;
+const RECENT_COMMITS: usize = 50;
 
 #[derive(Debug, Clone)]
 pub struct GitInfo {
+    pub root: PathBuf,
     pub branch: String,
     pub is_dirty: bool,
     pub ahead: usize,
@@ -17,6 +23,8 @@ pub fn get_git_info(path: &Path) -> Option<GitInfo> {
         return None;
     }
 
+    let root = get_repository_root(path).unwrap_or_else(|| path.to_path_buf());
+
     // Get current branch
     let branch = get_current_branch(path)?;
 
@@ -27,6 +35,7 @@ pub fn get_git_info(path: &Path) -> Option<GitInfo> {
     let (ahead, behind) = get_ahead_behind_count(path);
 
     Some(GitInfo {
+        root,
         branch,
         is_dirty,
         ahead,
@@ -42,6 +51,21 @@ fn is_git_repository(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+fn get_repository_root(path: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["-C", path.to_str().unwrap_or("."), "rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !root.is_empty() {
+            return Some(PathBuf::from(root));
+        }
+    }
+    None
+}
+
 fn get_current_branch(path: &Path) -> Option<String> {
     let output = Command::new("git")
         .args([
@@ -138,3 +162,354 @@ fn get_ahead_behind_count(path: &Path) -> (usize, usize) {
 
     (0, 0)
 }
+
+/// Fail on a revision git would take for an option, as revisions come from
+/// typed `git-rev://` URLs
+pub(crate) fn check_rev(rev: &str) -> Result<()> {
+    if rev.starts_with('-') {
+        return Err(anyhow::anyhow!("Invalid revision {}", rev));
+    }
+    Ok(())
+}
+
+/// Read-only view of repository history through the git CLI.
+///
+/// `VfsPath::GitTree` with an empty `rev` lists branches, tags and recent commits;
+/// with a rev it lists the tree at `path` as it was in that revision.
+pub struct GitTreeProvider;
+
+impl GitTreeProvider {
+    fn git(repo: &Path) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(repo);
+        command
+    }
+
+    fn run(repo: &Path, args: &[&str]) -> Result<Vec<u8>> {
+        let output = Self::git(repo).args(args).output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    fn commit_time(repo: &Path, rev: &str) -> SystemTime {
+        check_rev(rev)
+            .and_then(|_| Self::run(repo, &["show", "-s", "--format=%ct", rev, "--"]))
+            .ok()
+            .and_then(|out| String::from_utf8_lossy(&out).trim().parse().ok())
+            .map(unix_time)
+            .unwrap_or(UNIX_EPOCH)
+    }
+
+    fn list_revisions(repo: &Path) -> Result<Vec<VfsEntry>> {
+        let mut entries = vec![parent_entry(VfsPath::Local(repo.to_path_buf()))];
+        let revision = |rev: &str, time: &str, permissions: &str| VfsEntry {
+            name: rev.to_string(),
+            path: VfsPath::GitTree {
+                repo: repo.to_path_buf(),
+                rev: rev.to_string(),
+                path: String::new(),
+            },
+            entry_type: VfsEntryType::Directory,
+            size: 0,
+            modified: time.parse().map(unix_time).unwrap_or(UNIX_EPOCH),
+            permissions: permissions.to_string(),
+            compressed_size: None,
+        };
+
+        let refs = Self::run(
+            repo,
+            &[
+                "for-each-ref",
+                "--format=%(refname:short)%09%(creatordate:unix)%09%(refname)",
+                "refs/heads",
+                "refs/tags",
+            ],
+        )?;
+        for line in String::from_utf8_lossy(&refs).lines() {
+            let mut fields = line.split('\t');
+            if let (Some(name), Some(time), Some(full)) = (fields.next(), fields.next(), fields.next()) {
+                let kind = if full.starts_with("refs/tags/") { "tag" } else { "branch" };
+                entries.push(revision(name, time, kind));
+            }
+        }
+
+        // An empty repository has no HEAD to walk
+        if let Ok(log) = Self::run(
+            repo,
+            &["log", &format!("-n{}", RECENT_COMMITS), "--format=%h%x09%ct%x09%s"],
+        ) {
+            for line in String::from_utf8_lossy(&log).lines() {
+                let mut fields = line.splitn(3, '\t');
+                if let (Some(hash), Some(time)) = (fields.next(), fields.next()) {
+                    entries.push(revision(hash, time, fields.next().unwrap_or("commit")));
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+impl VfsProvider for GitTreeProvider {
+    fn can_handle(&self, path: &VfsPath) -> bool {
+        matches!(path, VfsPath::GitTree { .. })
+    }
+
+    fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+        let VfsPath::GitTree { repo, rev, path } = path else {
+            return Err(anyhow::anyhow!("GitTreeProvider can only handle git paths"));
+        };
+        if rev.is_empty() {
+            return Self::list_revisions(repo);
+        }
+        check_rev(rev)?;
+
+        let dir = path.trim_matches('/');
+        let tree = Self::run(repo, &["ls-tree", "-l", "-z", &format!("{}:{}", rev, dir)])?;
+        let modified = Self::commit_time(repo, rev);
+
+        let mut entries = vec![parent_entry(git_tree_parent(repo, rev, dir))];
+        entries.extend(parse_ls_tree(&tree, repo, rev, dir, modified));
+        Ok(entries)
+    }
+
+    fn read_file(&self, path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+        let VfsPath::GitTree { repo, rev, path } = path else {
+            return Err(anyhow::anyhow!("GitTreeProvider can only handle git paths"));
+        };
+        check_rev(rev)?;
+        let mut child = Self::git(repo)
+            .args(["cat-file", "blob", &format!("{}:{}", rev, path.trim_matches('/'))])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("git cat-file produced no output"))?;
+        Ok(Box::new(BlobReader {
+            child,
+            stdout,
+            finished: false,
+        }))
+    }
+
+    fn write_file(&self, _path: &VfsPath, _data: Box<dyn Read + Send>) -> Result<()> {
+        Err(anyhow::anyhow!("Git history is read-only"))
+    }
+
+    fn create_directory(&self, _path: &VfsPath) -> Result<()> {
+        Err(anyhow::anyhow!("Git history is read-only"))
+    }
+
+    fn delete(&self, _path: &VfsPath) -> Result<()> {
+        Err(anyhow::anyhow!("Git history is read-only"))
+    }
+
+    fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        let VfsPath::GitTree { repo, rev, path: file } = path else {
+            return Err(anyhow::anyhow!("GitTreeProvider can only handle git paths"));
+        };
+        check_rev(rev)?;
+        let file = file.trim_matches('/');
+        if rev.is_empty() || file.is_empty() {
+            return Ok(VfsEntry {
+                name: if rev.is_empty() { "history".to_string() } else { rev.clone() },
+                path: path.clone(),
+                entry_type: VfsEntryType::Directory,
+                size: 0,
+                modified: Self::commit_time(repo, if rev.is_empty() { "HEAD" } else { rev }),
+                permissions: String::new(),
+                compressed_size: None,
+            });
+        }
+
+        let (dir, _) = file.rsplit_once('/').unwrap_or(("", file));
+        let listing = Self::run(repo, &["ls-tree", "-l", "-z", rev, "--", file])?;
+        parse_ls_tree(&listing, repo, rev, dir, Self::commit_time(repo, rev))
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} does not exist in {}", file, rev))
+    }
+}
+
+/// Blob contents streamed from `git cat-file`; the end of the stream fails if git
+/// did, and the process is reaped on drop
+struct BlobReader {
+    child: Child,
+    stdout: ChildStdout,
+    finished: bool,
+}
+
+impl Read for BlobReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() && !self.finished {
+            self.finished = true;
+            let status = self.child.wait()?;
+            if !status.success() {
+                let mut message = String::new();
+                if let Some(mut stderr) = self.child.stderr.take() {
+                    let _ = stderr.read_to_string(&mut message);
+                }
+                return Err(std::io::Error::other(format!("git cat-file failed: {}", message.trim())));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for BlobReader {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn unix_time(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn parent_entry(path: VfsPath) -> VfsEntry {
+    VfsEntry {
+        name: "..".to_string(),
+        path,
+        entry_type: VfsEntryType::Directory,
+        size: 0,
+        modified: SystemTime::now(),
+        permissions: String::new(),
+        compressed_size: None,
+    }
+}
+
+/// Where `..` leads from a tree: up one directory, then to the revision list
+fn git_tree_parent(repo: &Path, rev: &str, dir: &str) -> VfsPath {
+    let (rev, path) = match dir.rsplit_once('/') {
+        Some((parent, _)) => (rev.to_string(), parent.to_string()),
+        None if !dir.is_empty() => (rev.to_string(), String::new()),
+        None => (String::new(), String::new()),
+    };
+    VfsPath::GitTree {
+        repo: repo.to_path_buf(),
+        rev,
+        path,
+    }
+}
+
+/// Parse `git ls-tree -l -z` records: `<mode> <type> <object> <size>\t<name>\0`
+fn parse_ls_tree(output: &[u8], repo: &Path, rev: &str, dir: &str, modified: SystemTime) -> Vec<VfsEntry> {
+    output
+        .split(|b| *b == 0)
+        .filter_map(|record| {
+            let record = String::from_utf8_lossy(record);
+            let (meta, name) = record.split_once('\t')?;
+            let mut fields = meta.split_whitespace();
+            let (mode, kind, _object, size) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+            // Listings by pathspec report the full path; keep only the file name
+            let name = name.rsplit('/').next().unwrap_or(name).to_string();
+
+            let entry_type = match (kind, mode) {
+                ("tree", _) => VfsEntryType::Directory,
+                (_, "120000") => VfsEntryType::Symlink,
+                _ => VfsEntryType::File,
+            };
+            let path = if dir.is_empty() { name.clone() } else { format!("{}/{}", dir, name) };
+
+            Some(VfsEntry {
+                name,
+                path: VfsPath::GitTree {
+                    repo: repo.to_path_buf(),
+                    rev: rev.to_string(),
+                    path,
+                },
+                entry_type,
+                size: size.parse().unwrap_or(0),
+                modified,
+                permissions: mode.to_string(),
+                compressed_size: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ls_tree_and_parents() {
+        let repo = Path::new("/repo");
+        let output = b"100644 blob 3b18e51 12\tREADME.md\x00040000 tree 9a2c3f1 -\tsrc\x00120000 blob 1f2e3d4 7\tlink\x00";
+        let entries = parse_ls_tree(output, repo, "v1.2", "docs", UNIX_EPOCH);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].name, "README.md");
+        assert_eq!(entries[0].size, 12);
+        assert!(matches!(entries[1].entry_type, VfsEntryType::Directory));
+        assert!(matches!(entries[2].entry_type, VfsEntryType::Symlink));
+        assert!(matches!(&entries[1].path, VfsPath::GitTree { rev, path, .. } if rev == "v1.2" && path == "docs/src"));
+
+        assert!(matches!(git_tree_parent(repo, "v1.2", "docs/api"), VfsPath::GitTree { rev, path, .. } if rev == "v1.2" && path == "docs"));
+        assert!(matches!(git_tree_parent(repo, "v1.2", "docs"), VfsPath::GitTree { rev, path, .. } if rev == "v1.2" && path.is_empty()));
+        assert!(matches!(git_tree_parent(repo, "v1.2", ""), VfsPath::GitTree { rev, .. } if rev.is_empty()));
+    }
+
+    #[test]
+    fn test_read_blob_reports_git_failures() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let status = GitTreeProvider::git(dir.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.path().join("notes.txt"), "first draft").unwrap();
+        git(&["add", "notes.txt"]);
+        git(&["commit", "-q", "-m", "initial"]);
+
+        let blob = |file: &str| VfsPath::GitTree {
+            repo: dir.path().to_path_buf(),
+            rev: "HEAD".to_string(),
+            path: file.to_string(),
+        };
+        let mut content = String::new();
+        GitTreeProvider
+            .read_file(&blob("notes.txt"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "first draft");
+
+        let mut missing = Vec::new();
+        let error = GitTreeProvider
+            .read_file(&blob("missing.txt"))
+            .unwrap()
+            .read_to_end(&mut missing)
+            .unwrap_err();
+        assert!(error.to_string().contains("git cat-file failed"));
+
+        // A revision that reads as an option never reaches git
+        let written = dir.path().join("written");
+        let option = VfsPath::GitTree {
+            repo: dir.path().to_path_buf(),
+            rev: format!("--output={}", written.display()),
+            path: String::new(),
+        };
+        assert!(GitTreeProvider.list_entries(&option).is_err());
+        assert!(GitTreeProvider.get_info(&option).is_err());
+        assert!(GitTreeProvider.read_file(&option).is_err());
+        assert!(!written.exists());
+    }
+}
@@ -1,16 +1,29 @@
 pub mod ai;
+pub mod async_vfs;
 pub mod cache;
 pub mod config;
+pub mod encoding;
+pub mod content_index;
 pub mod file_monitor;
 pub mod fs;
+pub mod fuzzy;
 pub mod git;
+pub mod line_index;
 pub mod memory;
+pub mod mounts;
 pub mod operations;
-#[cfg(feature = "ssh")]
+pub mod quick_view;
+#[cfg(any(feature = "ssh", feature = "s3"))]
 pub mod remote;
+pub mod remote_edit;
+pub mod replace;
+pub mod saved_searches;
 pub mod search;
+pub mod search_condition;
 pub mod shortcuts;
 pub mod state;
+pub mod structured;
+pub mod syntax;
 pub mod terminal;
 pub mod theme;
 pub mod vfs;
@@ -18,27 +31,43 @@ pub mod virtual_scroll;
 #[cfg(feature = "windowed")]
 pub mod window;
 
+pub use async_vfs::{
+    AsyncVfsProvider, AsyncVirtualFileSystem, CancellationToken, ListingHandle, ListingUpdate,
+    SyncProviderAdapter,
+};
 pub use cache::{CacheConfig, CacheRefresher, CacheStatistics, DirectoryCache};
 pub use config::{Config, ConfigManager};
+pub use encoding::{detect_encoding, LineEnding, TextEncoding};
+pub use content_index::{ContentIndex, IndexFilter, IndexStatus};
 pub use cortex_plugins::{LuaPlugin, PluginContext, PluginEvent, PluginInfo, PluginManager};
 pub use file_monitor::{
     ChangeNotification, EventCallback, FileMonitor, FileMonitorEvent, FileMonitorManager,
 };
 pub use fs::{FileEntry, FileSystem, FileType};
-pub use git::{get_git_info, GitInfo};
+pub use fuzzy::{fuzzy_match, FuzzyMatch};
+pub use git::{get_git_info, GitInfo, GitTreeProvider};
+pub use line_index::LineIndex;
 pub use memory::{
     CompressedFileEntry, MemoryManager, MemoryStats, ObjectPool, PathTable, StringPool,
 };
 pub use operations::{
-    DefaultOperationHandler, Operation, OperationHandler, OperationProgress, OperationQueue,
+    expand_rename_pattern, DefaultOperationHandler, Operation, OperationHandler, OperationProgress,
+    OperationQueue,
 };
+pub use quick_view::{build_preview, Picture, Preview, QuickView, QuickViewContent};
+pub use remote_edit::RemoteEditSession;
+pub use replace::{FileChanges, ReplaceHunk, ReplaceUndo};
+pub use saved_searches::{SavedSearch, SavedSearches};
 pub use search::{
     DateFilter, SearchCriteria, SearchEngine, SearchProgress, SearchResult, SearchType, SizeFilter,
 };
+pub use search_condition::SearchCondition;
 pub use shortcuts::{Action, KeyBinding, ShortcutManager, VimMode};
-pub use state::{ActivePanel, AppState, FileOperation, PanelState, SortMode};
+pub use state::{ActivePanel, AppState, FileOperation, PanelState, SearchListing, SortMode};
+pub use structured::{DocumentError, DocumentFormat, DocumentTree};
+pub use syntax::{detect_language, Highlighter, Language, TokenKind};
 pub use theme::{Theme, ThemeManager, ThemeMode};
-pub use vfs::{RemoteCredentials, VfsEntry, VfsEntryType, VfsPath, VfsProvider, VirtualFileSystem};
+pub use vfs::{ConnectionStatus, RemoteCredentials, VfsEntry, VfsEntryType, VfsPath, VfsProvider, VirtualFileSystem};
 pub use virtual_scroll::{
     VirtualScrollConfig, VirtualScrollManager, VirtualScrollStats, VirtualScroller,
 };
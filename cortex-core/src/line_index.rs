@@ -0,0 +1,379 @@
+// Reading files too big to load whole: a sparse index remembers where every
+// STRIDE-th line starts as the file is scanned, and lines are read from any
+// byte offset by seeking rather than reading from the start. Lines end at a
+// newline as the encoding spells it, so in UTF-16 only a whole `0A 00` or
+// `00 0A` code unit at an even offset counts
+
+use crate::async_vfs::CancellationToken;
+use crate::encoding::TextEncoding;
+use anyhow::Result;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Lines between two remembered line starts
+const STRIDE: u64 = 1024;
+const CHUNK_SIZE: usize = 1024 * 1024;
+const READ_SIZE: usize = 64 * 1024;
+/// Lines read at a time while searching
+const SEARCH_BATCH: usize = 4096;
+
+/// Where lines start in a file, found lazily and kept as the file grows
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    path: PathBuf,
+    encoding: TextEncoding,
+    /// Start of line `i * STRIDE`
+    checkpoints: Vec<u64>,
+    scanned_bytes: u64,
+    /// Newlines within the scanned bytes
+    scanned_lines: u64,
+}
+
+impl LineIndex {
+    pub fn new(path: impl AsRef<Path>, encoding: TextEncoding) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            encoding,
+            checkpoints: vec![0],
+            scanned_bytes: 0,
+            scanned_lines: 0,
+        }
+    }
+
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    pub fn scanned_bytes(&self) -> u64 {
+        self.scanned_bytes
+    }
+
+    /// Keep the index when `len` shows the file only grew, and start over
+    /// when it got shorter, as when a log is truncated or rotated
+    pub fn refresh(&mut self, len: u64) {
+        if len < self.scanned_bytes {
+            *self = Self::new(&self.path, self.encoding);
+        }
+    }
+
+    fn scan_until(&mut self, done: impl Fn(&Self) -> bool) -> Result<()> {
+        if done(self) {
+            return Ok(());
+        }
+        let mut reader = UnitReader::open(&self.path, self.scanned_bytes, CHUNK_SIZE)?;
+        let width = self.encoding.newline().len();
+        while !done(self) {
+            let buffer = reader.fill_buf()?;
+            // A code unit cut off by the end of the file waits for the rest
+            let whole = buffer.len() - buffer.len() % width;
+            if whole == 0 {
+                break;
+            }
+            for end in newline_ends(&buffer[..whole], self.encoding) {
+                self.scanned_lines += 1;
+                if self.scanned_lines.is_multiple_of(STRIDE) {
+                    self.checkpoints.push(self.scanned_bytes + end as u64);
+                }
+            }
+            self.scanned_bytes += whole as u64;
+            reader.consume(whole);
+        }
+        Ok(())
+    }
+
+    /// Byte offset of zero-based line `line`, scanning as far as needed;
+    /// `None` when the file has fewer lines
+    pub fn line_start(&mut self, line: u64) -> Result<Option<u64>> {
+        self.scan_until(|index| index.scanned_lines >= line)?;
+        if line > self.scanned_lines {
+            return Ok(None);
+        }
+        let checkpoint = self.checkpoints[(line / STRIDE) as usize];
+        let start = skip_lines(&self.path, checkpoint, line % STRIDE, self.encoding)?;
+        // A final newline ends the last line rather than starting another
+        if line > 0 && start >= File::open(&self.path)?.metadata()?.len() {
+            return Ok(None);
+        }
+        Ok(Some(start))
+    }
+
+    /// Number of the line `offset` falls in, when the index reaches that far
+    pub fn line_at(&self, offset: u64) -> Result<Option<u64>> {
+        if offset > self.scanned_bytes {
+            return Ok(None);
+        }
+        let checkpoint = self.checkpoints.partition_point(|&start| start <= offset) - 1;
+        let start = self.checkpoints[checkpoint];
+        let mut newlines = 0;
+        let mut reader = UnitReader::open(&self.path, start, READ_SIZE)?;
+        let mut remaining = offset - start;
+        while remaining > 0 {
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+            let take = buffer.len().min(remaining as usize);
+            newlines += newline_ends(&buffer[..take], self.encoding).count() as u64;
+            reader.consume(take);
+            remaining -= take as u64;
+        }
+        Ok(Some(checkpoint as u64 * STRIDE + newlines))
+    }
+}
+
+/// Reads a file forward in whole buffers from a code unit boundary, so no
+/// newline is split between two reads
+struct UnitReader {
+    file: File,
+    buffer: Vec<u8>,
+    /// The unread part of `buffer`
+    pos: usize,
+    len: usize,
+}
+
+impl UnitReader {
+    fn open(path: &Path, from: u64, capacity: usize) -> Result<Self> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(from))?;
+        Ok(Self {
+            file,
+            buffer: vec![0; capacity],
+            pos: 0,
+            len: 0,
+        })
+    }
+
+    /// The unread bytes, reading on once they are used up; empty at the end
+    /// of the file
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos == self.len {
+            self.pos = 0;
+            self.len = 0;
+            while self.len < self.buffer.len() {
+                match self.file.read(&mut self.buffer[self.len..])? {
+                    0 => break,
+                    read => self.len += read,
+                }
+            }
+        }
+        Ok(&self.buffer[self.pos..self.len])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos += amount;
+    }
+}
+
+/// Offsets just past each newline in `bytes`, which start at a code unit
+/// boundary
+fn newline_ends(bytes: &[u8], encoding: TextEncoding) -> impl DoubleEndedIterator<Item = usize> + '_ {
+    let newline = encoding.newline();
+    bytes
+        .chunks_exact(newline.len())
+        .enumerate()
+        .filter(move |(_, unit)| *unit == newline)
+        .map(move |(i, _)| (i + 1) * newline.len())
+}
+
+/// `offset` moved back to the start of the code unit it falls in
+fn unit_start(offset: u64, encoding: TextEncoding) -> u64 {
+    offset - offset % encoding.newline().len() as u64
+}
+
+/// Offset reached after passing `count` newlines from `from`, or the end of
+/// the file if it has fewer
+pub fn skip_lines(path: &Path, from: u64, count: u64, encoding: TextEncoding) -> Result<u64> {
+    let from = unit_start(from, encoding);
+    let mut reader = UnitReader::open(path, from, READ_SIZE)?;
+    let mut offset = from;
+    let mut remaining = count;
+    while remaining > 0 {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        let mut consumed = buffer.len();
+        for end in newline_ends(buffer, encoding) {
+            remaining -= 1;
+            if remaining == 0 {
+                consumed = end;
+                break;
+            }
+        }
+        reader.consume(consumed);
+        offset += consumed as u64;
+    }
+    Ok(offset)
+}
+
+/// Start of the line `count` lines above the one `offset` falls in; a
+/// `count` of zero gives the start of that line itself
+pub fn lines_before(path: &Path, offset: u64, count: u64, encoding: TextEncoding) -> Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; READ_SIZE];
+    // The newline ending the line above each line passed, and the target's own
+    let mut remaining = count + 1;
+    let mut end = unit_start(offset, encoding);
+    while end > 0 {
+        let start = end.saturating_sub(buffer.len() as u64);
+        let chunk = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        for line_start in newline_ends(chunk, encoding).rev() {
+            remaining -= 1;
+            if remaining == 0 {
+                return Ok(start + line_start as u64);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Up to `count` lines from `from`, each with its start offset and cut to
+/// `max_len` bytes and decoded from `encoding`, plus the offset after the
+/// last one; a trailing `\r` is dropped
+pub fn read_lines(
+    path: &Path,
+    from: u64,
+    count: usize,
+    max_len: usize,
+    encoding: TextEncoding,
+) -> Result<(Vec<(u64, String)>, u64)> {
+    let from = unit_start(from, encoding);
+    let mut reader = UnitReader::open(path, from, READ_SIZE)?;
+    let mut lines = Vec::with_capacity(count);
+    let mut offset = from;
+    while lines.len() < count {
+        let start = offset;
+        let mut line = Vec::new();
+        let mut ended = false;
+        loop {
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+            let (consumed, newline) = match newline_ends(buffer, encoding).next() {
+                Some(end) => (end, encoding.newline().len()),
+                None => (buffer.len(), 0),
+            };
+            let keep = (max_len.saturating_sub(line.len())).min(consumed - newline);
+            line.extend_from_slice(&buffer[..keep]);
+            reader.consume(consumed);
+            offset += consumed as u64;
+            if newline > 0 {
+                ended = true;
+                break;
+            }
+        }
+        if !ended && offset == start {
+            break;
+        }
+        lines.push((start, encoding.decode_line(&line)));
+    }
+    Ok((lines, offset))
+}
+
+/// Start of the first line from `from` on that contains `term`, ignoring
+/// case, once cut to `max_len` bytes and decoded; `None` when no line does or
+/// `cancel` stops the search
+pub fn find_line(
+    path: &Path,
+    from: u64,
+    term: &str,
+    max_len: usize,
+    encoding: TextEncoding,
+    cancel: &CancellationToken,
+) -> Result<Option<u64>> {
+    let term = term.to_lowercase();
+    let mut offset = from;
+    while !cancel.is_cancelled() {
+        let (lines, end) = read_lines(path, offset, SEARCH_BATCH, max_len, encoding)?;
+        if let Some((start, _)) = lines.iter().find(|(_, line)| line.to_lowercase().contains(&term)) {
+            return Ok(Some(*start));
+        }
+        if lines.is_empty() {
+            break;
+        }
+        offset = end;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_index_and_seeking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        let content: String = (0..3000).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, &content).unwrap();
+        let start_of = |line: usize| content.find(&format!("line {}\n", line)).unwrap() as u64;
+
+        let mut index = LineIndex::new(&path, TextEncoding::Utf8);
+        assert_eq!(index.line_start(0).unwrap(), Some(0));
+        assert_eq!(index.line_start(2500).unwrap(), Some(start_of(2500)));
+        assert_eq!(index.line_start(3000).unwrap(), None);
+        assert_eq!(index.line_at(start_of(2049) + 3).unwrap(), Some(2049));
+
+        assert_eq!(skip_lines(&path, start_of(10), 5, TextEncoding::Utf8).unwrap(), start_of(15));
+        assert_eq!(lines_before(&path, start_of(1500), 3, TextEncoding::Utf8).unwrap(), start_of(1497));
+        assert_eq!(lines_before(&path, start_of(1500) + 2, 0, TextEncoding::Utf8).unwrap(), start_of(1500));
+        assert_eq!(lines_before(&path, start_of(2), 10, TextEncoding::Utf8).unwrap(), 0);
+
+        let (lines, end) = read_lines(&path, start_of(2998), 5, 6, TextEncoding::Utf8).unwrap();
+        assert_eq!(lines, vec![(start_of(2998), "line 2".to_string()), (start_of(2999), "line 2".to_string())]);
+        assert_eq!(end, content.len() as u64);
+
+        let cancel = CancellationToken::new();
+        let find = |from, term| find_line(&path, from, term, 1024, TextEncoding::Utf8, &cancel).unwrap();
+        assert_eq!(find(0, "LINE 2999"), Some(start_of(2999)));
+        assert_eq!(find(start_of(20), "line 1"), Some(start_of(100)));
+        assert_eq!(find(start_of(2999), "line 1"), None);
+
+        // Appending keeps the index, truncating drops it
+        std::fs::write(&path, format!("{}line 3000\n", content)).unwrap();
+        index.refresh(content.len() as u64 + 10);
+        assert_eq!(index.line_start(3000).unwrap(), Some(content.len() as u64));
+        std::fs::write(&path, "short\n").unwrap();
+        index.refresh(6);
+        assert_eq!(index.scanned_bytes(), 0);
+        assert_eq!(index.line_start(1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_utf16_lines_split_on_whole_newlines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("utf16.txt");
+        // 上 is U+4E0A, whose low byte looks like a newline
+        let text = "上下\r\n上\nend";
+        for encoding in [TextEncoding::Utf16Le, TextEncoding::Utf16Be] {
+            let bytes = encoding.encode(text).unwrap();
+            std::fs::write(&path, &bytes).unwrap();
+            // Byte-order mark, 上下\r\n, then 上\n
+            let (second, third) = (2 + 8, 2 + 8 + 4);
+
+            let (lines, end) = read_lines(&path, 0, 10, 1024, encoding).unwrap();
+            let expected = vec![(0, "上下".to_string()), (second, "上".to_string()), (third, "end".to_string())];
+            assert_eq!(lines, expected, "{}", encoding.name());
+            assert_eq!(end, bytes.len() as u64);
+
+            assert_eq!(skip_lines(&path, 0, 2, encoding).unwrap(), third);
+            assert_eq!(lines_before(&path, third + 3, 1, encoding).unwrap(), second);
+            // Inside the newline unit still counts as the line it ends
+            assert_eq!(lines_before(&path, second - 1, 0, encoding).unwrap(), 0);
+
+            let mut index = LineIndex::new(&path, encoding);
+            assert_eq!(index.line_start(1).unwrap(), Some(second));
+            assert_eq!(index.line_start(2).unwrap(), Some(third));
+            assert_eq!(index.line_start(3).unwrap(), None);
+            assert_eq!(index.line_at(third + 2).unwrap(), Some(2));
+
+            let cancel = CancellationToken::new();
+            assert_eq!(find_line(&path, 2, "END", 1024, encoding, &cancel).unwrap(), Some(third));
+        }
+    }
+}
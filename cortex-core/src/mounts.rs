@@ -0,0 +1,201 @@
+// Mounted filesystems for the drive selector
+
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountPoint {
+    pub path: PathBuf,
+    pub device: String,
+    pub fs_type: String,
+    pub total: u64,
+    pub available: u64,
+}
+
+/// Filesystems without a backing store that only clutter a drive list
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts",
+    "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "proc", "pstore",
+    "ramfs", "rpc_pipefs", "securityfs", "selinuxfs", "sysfs", "tracefs", "fuse.gvfsd-fuse",
+    "fuse.portal",
+];
+
+/// Mounts whose `statvfs` call has not returned yet
+static PENDING_SPACE_QUERIES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Real filesystems currently mounted, with their size and free space, and how many
+/// of them left their sizes out. Each mount is asked on its own thread, since
+/// `statvfs` blocks for as long as a dead network mount does; one that has not
+/// answered within `timeout` is listed without sizes and is not asked again until
+/// its earlier call returns.
+pub fn mounted_filesystems(timeout: Duration) -> (Vec<MountPoint>, usize) {
+    let mut mounts = mount_points();
+    let (tx, rx) = mpsc::channel();
+    for (index, mount) in mounts.iter().enumerate() {
+        let mut pending = PENDING_SPACE_QUERIES.lock().unwrap();
+        if pending.contains(&mount.path) {
+            continue;
+        }
+        pending.push(mount.path.clone());
+
+        let path = mount.path.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let space = disk_space(&path);
+            PENDING_SPACE_QUERIES.lock().unwrap().retain(|pending| *pending != path);
+            let _ = tx.send((index, space));
+        });
+    }
+    drop(tx);
+
+    let deadline = Instant::now() + timeout;
+    let mut answered = 0;
+    while let Ok((index, space)) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        answered += 1;
+        if let Some((total, available)) = space {
+            mounts[index].total = total;
+            mounts[index].available = available;
+        }
+    }
+    let unanswered = mounts.len() - answered;
+    (mounts, unanswered)
+}
+
+/// Real filesystems currently mounted, without asking them for their size; stays
+/// quick when a network mount hangs
+pub fn mount_points() -> Vec<MountPoint> {
+    #[cfg(target_os = "linux")]
+    let mounts = std::fs::read_to_string("/proc/self/mountinfo")
+        .map(|text| parse_mountinfo(&text))
+        .unwrap_or_default();
+    #[cfg(not(target_os = "linux"))]
+    let mounts = vec![(PathBuf::from("/"), String::new(), String::new())];
+
+    mounts
+        .into_iter()
+        .map(|(path, device, fs_type)| MountPoint {
+            path,
+            device,
+            fs_type,
+            total: 0,
+            available: 0,
+        })
+        .collect()
+}
+
+/// Parse `/proc/self/mountinfo` into (mount point, device, fs type), dropping
+/// pseudo filesystems. A path mounted over keeps only its topmost mount.
+pub fn parse_mountinfo(text: &str) -> Vec<(PathBuf, String, String)> {
+    let mut mounts: Vec<(PathBuf, String, String)> = Vec::new();
+    for line in text.lines() {
+        // "<id> <parent> <maj:min> <root> <mount point> <options> [optional...] - <type> <source> <super options>"
+        let Some((before, after)) = line.split_once(" - ") else {
+            continue;
+        };
+        let Some(mount_point) = before.split(' ').nth(4) else {
+            continue;
+        };
+        let mut after = after.split(' ');
+        let (Some(fs_type), Some(device)) = (after.next(), after.next()) else {
+            continue;
+        };
+
+        let path = PathBuf::from(unescape_octal(mount_point));
+        if is_pseudo(fs_type, &path) {
+            continue;
+        }
+        mounts.retain(|(existing, _, _)| *existing != path);
+        mounts.push((path, unescape_octal(device), fs_type.to_string()));
+    }
+    mounts
+}
+
+fn is_pseudo(fs_type: &str, path: &Path) -> bool {
+    PSEUDO_FILESYSTEMS.contains(&fs_type)
+        // Runtime tmpfs mounts; /tmp and /dev/shm are worth browsing
+        || (fs_type == "tmpfs" && (path.starts_with("/run") || path.starts_with("/sys")))
+        // Snap packages are each a read-only squashfs image
+        || (fs_type == "squashfs" && path.starts_with("/snap"))
+}
+
+/// mountinfo escapes space, tab, newline and backslash as `\ooo`
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 4) {
+            Some(digits) if bytes[i] == b'\\' => std::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Total and available bytes of the filesystem holding `path`
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+#[cfg(not(unix))]
+pub fn disk_space(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mounted_filesystems_returns_by_the_timeout() {
+        let started = Instant::now();
+        let (mounts, unanswered) = mounted_filesystems(Duration::from_millis(500));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(mounts.len(), mount_points().len());
+        assert!(unanswered <= mounts.len());
+    }
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let text = "\
+22 28 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+28 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+30 28 0:25 / /run rw,nosuid,nodev shared:5 - tmpfs tmpfs rw,size=3271952k,mode=755
+31 28 0:26 / /tmp rw,nosuid,nodev shared:6 - tmpfs tmpfs rw
+45 28 7:1 / /snap/core/1 ro,nodev,relatime shared:20 - squashfs /dev/loop1 ro
+52 28 8:17 / /media/usb\\040stick rw,relatime shared:30 - vfat /dev/sdb1 rw
+53 28 8:18 / /media/usb\\040stick rw,relatime shared:31 - exfat /dev/sdb2 rw
+";
+        let mounts = parse_mountinfo(text);
+        let paths: Vec<_> = mounts.iter().map(|(path, _, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("/"), PathBuf::from("/tmp"), PathBuf::from("/media/usb stick")]
+        );
+        assert_eq!(mounts[0].1, "/dev/nvme0n1p2");
+        // The later mount over the same path wins
+        assert_eq!(mounts[2].2, "exfat");
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::sync::mpsc;
 
 #[derive(Debug, Clone)]
@@ -26,6 +26,15 @@ pub enum OperationProgress {
         total: u64,
         message: String,
     },
+    /// Aggregate progress of a multi-file transfer
+    Transfer {
+        files_done: usize,
+        files_total: usize,
+        bytes_done: u64,
+        bytes_total: u64,
+        bytes_per_sec: u64,
+        eta_secs: Option<u64>,
+    },
     Completed {
         operation: String,
     },
@@ -145,6 +154,22 @@ impl OperationHandler for DefaultOperationHandler {
     }
 }
 
+/// New file name for multi-rename: `[N]` is the name without extension,
+/// `[E]` the extension and `[C]` a counter starting at 1
+pub fn expand_rename_pattern(pattern: &str, path: &Path, counter: usize) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let extension = path.extension().map(|e| e.to_string_lossy()).unwrap_or_default();
+    let pattern = if extension.is_empty() {
+        pattern.replace(".[E]", "")
+    } else {
+        pattern.to_string()
+    };
+    pattern
+        .replace("[N]", &stem)
+        .replace("[E]", &extension)
+        .replace("[C]", &counter.to_string())
+}
+
 pub struct OperationQueue {
     operations: Vec<Operation>,
     handler: Box<dyn OperationHandler>,
@@ -171,3 +196,16 @@ impl OperationQueue {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_rename_pattern() {
+        let path = Path::new("/photos/IMG_0042.JPG");
+        assert_eq!(expand_rename_pattern("holiday_[C].[E]", path, 3), "holiday_3.JPG");
+        assert_eq!(expand_rename_pattern("[N]-old.[E]", path, 1), "IMG_0042-old.JPG");
+        assert_eq!(expand_rename_pattern("[N].[E].bak", Path::new("Makefile"), 1), "Makefile.bak");
+    }
+}
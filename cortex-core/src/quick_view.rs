@@ -0,0 +1,325 @@
+// Previews for the QuickView panel: the entry under the cursor is read in
+// the background and summed up by kind, and a preview that is still loading
+// when the cursor moves on gets cancelled
+
+use crate::async_vfs::CancellationToken;
+use crate::encoding::{detect_encoding, TextEncoding};
+use crate::syntax::{detect_language, Highlighter, TokenKind};
+use crate::vfs::{self, VfsEntry};
+use anyhow::Result;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+/// Bytes read from the start of a file to preview it
+const PREVIEW_BYTES: usize = 64 * 1024;
+/// Text lines kept for a preview
+pub const PREVIEW_LINES: usize = 500;
+/// Bytes kept for the hex dump of a binary file
+const HEX_PREVIEW_BYTES: usize = 4096;
+/// Longest side of a decoded preview picture
+pub const PICTURE_MAX_SIDE: u32 = 1024;
+
+/// The inactive panel turned into a preview of the active panel's entry
+#[derive(Debug, Clone, Default)]
+pub struct QuickView {
+    /// Entry being previewed, `None` when there is nothing to show
+    pub path: Option<PathBuf>,
+    pub content: QuickViewContent,
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum QuickViewContent {
+    #[default]
+    Loading,
+    Ready(Preview),
+    Unavailable(String),
+}
+
+/// A line of text with its syntax tokens
+pub type HighlightedLine = (String, Vec<(Range<usize>, TokenKind)>);
+
+#[derive(Debug, Clone)]
+pub enum Preview {
+    Text {
+        lines: Vec<HighlightedLine>,
+        language: Option<&'static str>,
+        size: u64,
+        /// More of the file follows the lines shown
+        truncated: bool,
+    },
+    Directory(DirectorySummary),
+    Archive {
+        entries: Vec<VfsEntry>,
+        size: u64,
+    },
+    Image {
+        info: ImageInfo,
+        size: u64,
+        /// Decoded pixels, when the format can be decoded
+        picture: Option<Arc<Picture>>,
+    },
+    Binary {
+        bytes: Vec<u8>,
+        size: u64,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectorySummary {
+    pub files: u64,
+    pub directories: u64,
+    pub size: u64,
+    /// Entries that could not be read
+    pub unreadable: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    pub format: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decoded image pixels, 8-bit RGBA in rows from the top
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picture {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Picture {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let at = (y as usize * self.width as usize + x as usize) * 4;
+        [self.rgba[at], self.rgba[at + 1], self.rgba[at + 2], self.rgba[at + 3]]
+    }
+}
+
+/// Decode a PNG, JPEG, GIF (first frame) or WebP image, shrunk to fit in
+/// `max_side` pixels each way
+pub fn load_picture(path: &Path, max_side: u32) -> Result<Picture> {
+    let mut image = image::ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    if image.width() > max_side || image.height() > max_side {
+        image = image.thumbnail(max_side, max_side);
+    }
+    let image = image.into_rgba8();
+    Ok(Picture {
+        width: image.width(),
+        height: image.height(),
+        rgba: image.into_raw(),
+    })
+}
+
+/// Work out the preview for `path`; stops early with an error once `cancel`
+/// is set
+pub fn build_preview(path: &Path, cancel: &CancellationToken) -> Result<Preview> {
+    let metadata = std::fs::metadata(path)?;
+    if metadata.is_dir() {
+        return summarize_directory(path, cancel).map(Preview::Directory);
+    }
+    let size = metadata.len();
+    if vfs::is_archive(path) {
+        return Ok(Preview::Archive {
+            entries: vfs::archive_entries(path)?,
+            size,
+        });
+    }
+
+    let mut head = Vec::with_capacity(PREVIEW_BYTES.min(size as usize));
+    File::open(path)?
+        .take(PREVIEW_BYTES as u64)
+        .read_to_end(&mut head)?;
+    if let Some(info) = image_info(&head) {
+        // Formats that cannot be decoded still show their details
+        let picture = load_picture(path, PICTURE_MAX_SIDE).ok().map(Arc::new);
+        return Ok(Preview::Image { info, size, picture });
+    }
+    let encoding = detect_encoding(&head);
+    if is_binary(&head, encoding) {
+        head.truncate(HEX_PREVIEW_BYTES);
+        return Ok(Preview::Binary { bytes: head, size });
+    }
+
+    let text = encoding.decode(&head);
+    let mut lines: Vec<&str> = text.lines().collect();
+    let mut truncated = size > head.len() as u64;
+    // The last line read may have been cut off mid-way
+    if truncated && lines.len() > 1 {
+        lines.pop();
+    }
+    if lines.len() > PREVIEW_LINES {
+        lines.truncate(PREVIEW_LINES);
+        truncated = true;
+    }
+    let language = detect_language(path, lines.first().copied().unwrap_or_default());
+    let mut highlighter = language.map(Highlighter::new);
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let tokens = match &mut highlighter {
+                Some(highlighter) => highlighter.highlight_line(row, line),
+                None => Vec::new(),
+            };
+            (line.to_string(), tokens)
+        })
+        .collect();
+
+    Ok(Preview::Text {
+        lines,
+        language: language.map(|language| language.name),
+        size,
+        truncated,
+    })
+}
+
+/// Count what a directory holds, at any depth
+pub fn summarize_directory(path: &Path, cancel: &CancellationToken) -> Result<DirectorySummary> {
+    let mut summary = DirectorySummary::default();
+    for entry in WalkDir::new(path).min_depth(1) {
+        if cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("Preview cancelled"));
+        }
+        match entry {
+            Ok(entry) if entry.file_type().is_dir() => summary.directories += 1,
+            Ok(entry) => {
+                summary.files += 1;
+                summary.size += entry.metadata().map_or(0, |metadata| metadata.len());
+            }
+            Err(_) => summary.unreadable += 1,
+        }
+    }
+    Ok(summary)
+}
+
+/// Text files have no NUL bytes, apart from UTF-16 text, which has no
+/// control characters besides line breaks and tabs
+fn is_binary(head: &[u8], encoding: TextEncoding) -> bool {
+    match encoding {
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => encoding
+            .decode(head)
+            .chars()
+            .any(|ch| ch.is_control() && !ch.is_whitespace()),
+        _ => head.contains(&0),
+    }
+}
+
+/// Format and dimensions of a PNG, GIF, BMP, JPEG or WebP image, from the
+/// first bytes of the file
+pub fn image_info(head: &[u8]) -> Option<ImageInfo> {
+    let u16_be = |at: usize| Some(u16::from_be_bytes(head.get(at..at + 2)?.try_into().ok()?) as u32);
+    let u16_le = |at: usize| Some(u16::from_le_bytes(head.get(at..at + 2)?.try_into().ok()?) as u32);
+    let u32_be = |at: usize| Some(u32::from_be_bytes(head.get(at..at + 4)?.try_into().ok()?));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(head.get(at..at + 4)?.try_into().ok()?));
+    let u24_le = |at: usize| Some(u32_le(at)? & 0x00FF_FFFF);
+
+    let (format, width, height) = if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("PNG", u32_be(16)?, u32_be(20)?)
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        ("GIF", u16_le(6)?, u16_le(8)?)
+    } else if head.starts_with(b"BM") && [12, 40, 52, 56, 108, 124].contains(&u32_le(14)?) {
+        // Bottom-up bitmaps store a negative height
+        ("BMP", u32_le(18)?, (u32_le(22)? as i32).unsigned_abs())
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        match head.get(12..16)? {
+            b"VP8 " => ("WebP", u16_le(26)? & 0x3FFF, u16_le(28)? & 0x3FFF),
+            b"VP8L" => {
+                let bits = u32_le(21)?;
+                ("WebP", (bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1)
+            }
+            b"VP8X" => ("WebP", u24_le(24)? + 1, u24_le(27)? + 1),
+            _ => return None,
+        }
+    } else if head.starts_with(&[0xFF, 0xD8]) {
+        // Walk the segments up to the start-of-frame marker
+        let mut at = 2;
+        loop {
+            if *head.get(at)? != 0xFF {
+                return None;
+            }
+            let marker = *head.get(at + 1)?;
+            let is_frame = (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker);
+            if is_frame {
+                break ("JPEG", u16_be(at + 7)?, u16_be(at + 5)?);
+            }
+            at += 2 + u16_be(at + 2)? as usize;
+        }
+    } else {
+        return None;
+    };
+    Some(ImageInfo { format, width, height })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_info() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(
+            image_info(&png),
+            Some(ImageInfo { format: "PNG", width: 640, height: 480 })
+        );
+
+        let gif = b"GIF89a\x20\x00\x10\x00";
+        assert_eq!(image_info(gif).map(|info| (info.width, info.height)), Some((32, 16)));
+
+        // An APP0 segment, then a baseline frame of 100x200
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0xC8, 0x00,
+            0x64,
+        ];
+        assert_eq!(
+            image_info(&jpeg),
+            Some(ImageInfo { format: "JPEG", width: 100, height: 200 })
+        );
+
+        assert_eq!(image_info(b"plain text"), None);
+        assert_eq!(image_info(b"\x89PNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn test_build_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/main.rs"), "fn main() {}\n// done\n").unwrap();
+        std::fs::write(dir.path().join("data.bin"), [0u8, 1, 2, 255]).unwrap();
+        std::fs::write(dir.path().join("utf16.txt"), b"\xFF\xFEh\0i\0\n\0").unwrap();
+        let cancel = CancellationToken::new();
+
+        match build_preview(&dir.path().join("sub/main.rs"), &cancel).unwrap() {
+            Preview::Text { lines, language, truncated, .. } => {
+                assert_eq!(language, Some("Rust"));
+                assert_eq!(lines.len(), 2);
+                assert_eq!(lines[1].1, vec![(0..7, TokenKind::Comment)]);
+                assert!(!truncated);
+            }
+            other => panic!("expected text, got {:?}", other),
+        }
+        match build_preview(&dir.path().join("utf16.txt"), &cancel).unwrap() {
+            Preview::Text { lines, .. } => assert_eq!(lines[0].0, "hi"),
+            other => panic!("expected text, got {:?}", other),
+        }
+        match build_preview(&dir.path().join("data.bin"), &cancel).unwrap() {
+            Preview::Binary { bytes, size } => assert_eq!((bytes, size), (vec![0, 1, 2, 255], 4)),
+            other => panic!("expected binary, got {:?}", other),
+        }
+        match build_preview(dir.path(), &cancel).unwrap() {
+            Preview::Directory(summary) => assert_eq!(
+                summary,
+                DirectorySummary { files: 3, directories: 1, size: 33, unreadable: 0 }
+            ),
+            other => panic!("expected a directory, got {:?}", other),
+        }
+
+        cancel.cancel();
+        assert!(build_preview(dir.path(), &cancel).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::remote::reconnect::{connect_with_retries, retry_on_dropped, RETRY_BACKOFF};
+use crate::remote::ConnectionStatus;
 use crate::vfs::{VfsEntry, VfsEntryType, VfsPath, VfsProvider};
 use anyhow::Result;
 use std::io::Read;
@@ -14,15 +16,21 @@ pub struct FtpCredentials {
     pub use_tls: bool,
 }
 
+/// Connections idle longer than this are probed with NOOP before reuse
+const PROBE_AFTER: Duration = Duration::from_secs(30);
+
 struct FtpConnectionInfo {
     stream: Arc<Mutex<AsyncFtpStream>>,
+    host: String,
+    port: u16,
     last_used: Instant,
-    #[allow(dead_code)]
+    // What the connection logged in with, reused to reconnect it
     credentials: FtpCredentials,
 }
 
 pub struct FtpProvider {
     connections: Arc<RwLock<HashMap<String, FtpConnectionInfo>>>,
+    statuses: Arc<RwLock<HashMap<String, ConnectionStatus>>>,
     runtime: Arc<Runtime>,
     connection_timeout: Duration,
     idle_timeout: Duration,
@@ -41,6 +49,7 @@ impl FtpProvider {
         
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            statuses: Arc::new(RwLock::new(HashMap::new())),
             runtime: Arc::new(runtime),
             connection_timeout: Duration::from_secs(30),
             idle_timeout: Duration::from_secs(600),
@@ -69,44 +78,99 @@ impl FtpProvider {
         port: u16,
         credentials: &FtpCredentials,
     ) -> Result<Arc<Mutex<AsyncFtpStream>>> {
-        let connection_key = format!("{}:{}@{}", credentials.username, port, host);
+        let connection_key = Self::connection_key(host, port, &credentials.username);
         
         self.cleanup_idle_connections()?;
         
-        {
-            let connections = self.connections.read().unwrap();
-            if let Some(conn_info) = connections.get(&connection_key) {
-                return Ok(conn_info.stream.clone());
+        let existing = self
+            .connections
+            .read()
+            .unwrap()
+            .get(&connection_key)
+            .map(|info| (info.stream.clone(), info.last_used, info.credentials.clone()));
+        let mut credentials = credentials.clone();
+        if let Some((stream, last_used, stored)) = existing {
+            if last_used.elapsed() < PROBE_AFTER || self.is_alive(&stream) {
+                if let Some(info) = self.connections.write().unwrap().get_mut(&connection_key) {
+                    info.last_used = Instant::now();
+                }
+                return Ok(stream);
             }
+            log::info!("FTP connection {} is dead, reconnecting", connection_key);
+            self.connections.write().unwrap().remove(&connection_key);
+            self.set_status(&connection_key, ConnectionStatus::Reconnecting);
+            credentials = stored;
         }
         
-        let mut last_error = None;
-        for attempt in 0..self.max_retries {
-            if attempt > 0 {
-                std::thread::sleep(Duration::from_millis(500 * (1 << attempt)));
+        let connected = connect_with_retries(self.max_retries, RETRY_BACKOFF, || {
+            self.create_new_connection(host, port, &credentials)
+        });
+        match connected {
+            Ok(stream) => {
+                let stream_arc = Arc::new(Mutex::new(stream));
+                let mut connections = self.connections.write().unwrap();
+                connections.insert(
+                    connection_key.clone(),
+                    FtpConnectionInfo {
+                        stream: stream_arc.clone(),
+                        host: host.to_string(),
+                        port,
+                        last_used: Instant::now(),
+                        credentials: credentials.clone(),
+                    },
+                );
+                self.set_status(&connection_key, ConnectionStatus::Connected);
+                Ok(stream_arc)
             }
-            
-            match self.create_new_connection(host, port, credentials) {
-                Ok(stream) => {
-                    let stream_arc = Arc::new(Mutex::new(stream));
-                    let mut connections = self.connections.write().unwrap();
-                    connections.insert(
-                        connection_key,
-                        FtpConnectionInfo {
-                            stream: stream_arc.clone(),
-                            last_used: Instant::now(),
-                            credentials: credentials.clone(),
-                        },
-                    );
-                    return Ok(stream_arc);
-                }
-                Err(e) => {
-                    last_error = Some(e);
-                }
+            Err(e) => {
+                self.set_status(&connection_key, ConnectionStatus::Offline);
+                Err(e)
             }
         }
-        
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Failed to create FTP connection")))
+    }
+
+    fn connection_key(host: &str, port: u16, username: &str) -> String {
+        format!("{}:{}@{}", username, port, host)
+    }
+
+    fn set_status(&self, key: &str, status: ConnectionStatus) {
+        self.statuses.write().unwrap().insert(key.to_string(), status);
+    }
+
+    fn is_alive(&self, stream: &Arc<Mutex<AsyncFtpStream>>) -> bool {
+        self.runtime.block_on(async {
+            let mut stream = stream.lock().unwrap();
+            stream.noop().await.is_ok()
+        })
+    }
+
+    /// Run `op` on the connection for `host`. When it fails and the connection no
+    /// longer answers NOOP, idempotent operations are retried once after reconnecting.
+    fn with_connection<T>(
+        &self,
+        host: &str,
+        port: u16,
+        credentials: &FtpCredentials,
+        idempotent: bool,
+        op: impl Fn(&Arc<Mutex<AsyncFtpStream>>) -> Result<T>,
+    ) -> Result<T> {
+        let key = Self::connection_key(host, port, &credentials.username);
+        // Credentials the dropped connection logged in with, to log in again
+        let stored = std::cell::Cell::new(None);
+        retry_on_dropped(
+            self.get_or_create_connection(host, port, credentials)?,
+            |stream| self.is_alive(stream),
+            || {
+                stored.set(self.connections.write().unwrap().remove(&key).map(|info| info.credentials));
+                self.set_status(&key, ConnectionStatus::Reconnecting);
+            },
+            || {
+                let credentials = stored.take().unwrap_or_else(|| credentials.clone());
+                self.get_or_create_connection(host, port, &credentials)
+            },
+            idempotent,
+            op,
+        )
     }
 
     fn create_new_connection(
@@ -222,11 +286,11 @@ impl VfsProvider for FtpProvider {
                     use_tls: false,
                 };
                 
-                let stream = self.get_or_create_connection(host, *port, &credentials)?;
-                
-                let list_result = self.runtime.block_on(async {
-                    let mut stream = stream.lock().unwrap();
-                    stream.list(Some(remote_path)).await
+                let list_result = self.with_connection(host, *port, &credentials, true, |stream| {
+                    Ok(self.runtime.block_on(async {
+                        let mut stream = stream.lock().unwrap();
+                        stream.list(Some(remote_path)).await
+                    })?)
                 })?;
                 
                 let entries: Vec<VfsEntry> = list_result
@@ -249,14 +313,14 @@ impl VfsProvider for FtpProvider {
                     use_tls: false,
                 };
                 
-                let stream = self.get_or_create_connection(host, *port, &credentials)?;
-                
-                let data = self.runtime.block_on(async {
-                    let mut stream = stream.lock().unwrap();
-                    let mut cursor = stream.retr_as_stream(remote_path).await?;
-                    let mut buffer = Vec::new();
-                    futures_util::AsyncReadExt::read_to_end(&mut cursor, &mut buffer).await?;
-                    Ok::<Vec<u8>, anyhow::Error>(buffer)
+                let data = self.with_connection(host, *port, &credentials, true, |stream| {
+                    self.runtime.block_on(async {
+                        let mut stream = stream.lock().unwrap();
+                        let mut cursor = stream.retr_as_stream(remote_path).await?;
+                        let mut buffer = Vec::new();
+                        futures_util::AsyncReadExt::read_to_end(&mut cursor, &mut buffer).await?;
+                        Ok::<Vec<u8>, anyhow::Error>(buffer)
+                    })
                 })?;
                 
                 Ok(Box::new(std::io::Cursor::new(data)))
@@ -274,15 +338,17 @@ impl VfsProvider for FtpProvider {
                     use_tls: false,
                 };
                 
-                let stream = self.get_or_create_connection(host, *port, &credentials)?;
-                
                 let mut buffer = Vec::new();
                 data.read_to_end(&mut buffer)?;
                 
-                self.runtime.block_on(async {
-                    let mut stream = stream.lock().unwrap();
-                    let mut cursor = futures_util::io::Cursor::new(buffer);
-                    stream.put_file(remote_path, &mut cursor).await
+                // The whole file is buffered, so re-sending it after a reconnect is safe
+                self.with_connection(host, *port, &credentials, true, |stream| {
+                    self.runtime.block_on(async {
+                        let mut stream = stream.lock().unwrap();
+                        let mut cursor = futures_util::io::Cursor::new(buffer.as_slice());
+                        stream.put_file(remote_path, &mut cursor).await
+                    })?;
+                    Ok(())
                 })?;
                 
                 Ok(())
@@ -300,11 +366,12 @@ impl VfsProvider for FtpProvider {
                     use_tls: false,
                 };
                 
-                let stream = self.get_or_create_connection(host, *port, &credentials)?;
-                
-                self.runtime.block_on(async {
-                    let mut stream = stream.lock().unwrap();
-                    stream.mkdir(remote_path).await
+                self.with_connection(host, *port, &credentials, false, |stream| {
+                    self.runtime.block_on(async {
+                        let mut stream = stream.lock().unwrap();
+                        stream.mkdir(remote_path).await
+                    })?;
+                    Ok(())
                 })?;
                 
                 Ok(())
@@ -322,19 +389,17 @@ impl VfsProvider for FtpProvider {
                     use_tls: false,
                 };
                 
-                let stream = self.get_or_create_connection(host, *port, &credentials)?;
-                
-                let result = self.runtime.block_on(async {
-                    let mut stream = stream.lock().unwrap();
-                    
-                    match stream.rm(remote_path).await {
-                        Ok(_) => Ok(()),
-                        Err(_) => stream.rmdir(remote_path).await,
-                    }
-                });
-                
-                result?;
-                Ok(())
+                self.with_connection(host, *port, &credentials, false, |stream| {
+                    self.runtime.block_on(async {
+                        let mut stream = stream.lock().unwrap();
+                        
+                        match stream.rm(remote_path).await {
+                            Ok(_) => Ok(()),
+                            Err(_) => stream.rmdir(remote_path).await,
+                        }
+                    })?;
+                    Ok(())
+                })
             }
             _ => Err(anyhow::anyhow!("FtpProvider can only handle FTP paths")),
         }
@@ -349,11 +414,11 @@ impl VfsProvider for FtpProvider {
                     use_tls: false,
                 };
                 
-                let stream = self.get_or_create_connection(host, *port, &credentials)?;
-                
-                let size = self.runtime.block_on(async {
-                    let mut stream = stream.lock().unwrap();
-                    stream.size(remote_path).await
+                let size = self.with_connection(host, *port, &credentials, true, |stream| {
+                    Ok(self.runtime.block_on(async {
+                        let mut stream = stream.lock().unwrap();
+                        stream.size(remote_path).await
+                    })?)
                 }).ok();
                 
                 let name = std::path::Path::new(remote_path)
@@ -374,4 +439,39 @@ impl VfsProvider for FtpProvider {
             _ => Err(anyhow::anyhow!("FtpProvider can only handle FTP paths")),
         }
     }
-}
\ No newline at end of file
+
+    fn connection_status(&self, path: &VfsPath) -> Option<ConnectionStatus> {
+        match path {
+            VfsPath::Ftp { host, port, username, .. } => {
+                let key = Self::connection_key(host, *port, username);
+                self.statuses.read().unwrap().get(&key).copied()
+            }
+            _ => None,
+        }
+    }
+
+    fn keepalive(&self) {
+        let connections: Vec<_> = self
+            .connections
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(key, info)| {
+                (key.clone(), info.stream.clone(), info.host.clone(), info.port, info.credentials.clone())
+            })
+            .collect();
+
+        for (key, stream, host, port, credentials) in connections {
+            if self.is_alive(&stream) {
+                if let Some(info) = self.connections.write().unwrap().get_mut(&key) {
+                    info.last_used = Instant::now();
+                }
+                continue;
+            }
+            log::info!("FTP keepalive failed for {}, reconnecting", key);
+            self.connections.write().unwrap().remove(&key);
+            self.set_status(&key, ConnectionStatus::Reconnecting);
+            let _ = self.get_or_create_connection(&host, port, &credentials);
+        }
+    }
+}
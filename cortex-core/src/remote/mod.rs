@@ -3,13 +3,29 @@ pub mod ssh_manager;
 #[cfg(feature = "ssh")]
 pub mod sftp_provider;
 #[cfg(feature = "ssh")]
+pub mod scp_provider;
+#[cfg(feature = "ssh")]
 pub mod ftp_provider;
-#[cfg(all(feature = "ssh", test))]
+#[cfg(feature = "ssh")]
+pub mod transfer;
+#[cfg(feature = "ssh")]
+mod reconnect;
+#[cfg(feature = "s3")]
+pub mod s3_provider;
+#[cfg(test)]
 mod tests;
 
+pub use crate::vfs::ConnectionStatus;
+
 #[cfg(feature = "ssh")]
-pub use ssh_manager::SshConnectionManager;
+pub use ssh_manager::{ExecLine, SshConnectionManager};
 #[cfg(feature = "ssh")]
 pub use sftp_provider::SftpProvider;
 #[cfg(feature = "ssh")]
-pub use ftp_provider::FtpProvider;
\ No newline at end of file
+pub use scp_provider::ScpProvider;
+#[cfg(feature = "ssh")]
+pub use ftp_provider::FtpProvider;
+#[cfg(feature = "ssh")]
+pub use transfer::{ParallelTransfer, TransferSettings};
+#[cfg(feature = "s3")]
+pub use s3_provider::{S3Credentials, S3Provider};
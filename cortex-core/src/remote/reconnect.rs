@@ -0,0 +1,237 @@
+// Reconnect steps shared by the pooled SSH sessions and FTP connections
+
+use anyhow::Result;
+use std::time::Duration;
+
+/// Pause before the second connection attempt; it doubles with every attempt after
+pub(crate) const RETRY_BACKOFF: Duration = Duration::from_millis(1000);
+
+/// Call `connect` up to `max_retries` times, backing off between attempts
+pub(crate) fn connect_with_retries<C>(
+    max_retries: u32,
+    backoff: Duration,
+    mut connect: impl FnMut() -> Result<C>,
+) -> Result<C> {
+    let mut last_error = None;
+    for attempt in 0..max_retries {
+        if attempt > 0 {
+            std::thread::sleep(backoff * (1 << (attempt - 1)));
+        }
+        match connect() {
+            Ok(connection) => return Ok(connection),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No connection attempts allowed")))
+}
+
+/// Run `op` on `connection`. When it fails and the connection no longer answers,
+/// `forget` drops it from the pool, and idempotent operations run once more on the
+/// connection `reconnect` hands back
+pub(crate) fn retry_on_dropped<C, T>(
+    connection: C,
+    is_alive: impl Fn(&C) -> bool,
+    forget: impl FnOnce(),
+    reconnect: impl FnOnce() -> Result<C>,
+    idempotent: bool,
+    op: impl Fn(&C) -> Result<T>,
+) -> Result<T> {
+    match op(&connection) {
+        Err(e) if !is_alive(&connection) => {
+            forget();
+            if !idempotent {
+                return Err(e);
+            }
+            log::info!("Connection dropped, retrying: {}", e);
+            op(&reconnect()?)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_vfs::{AsyncVirtualFileSystem, ListingUpdate, SyncProviderAdapter};
+    use crate::vfs::{ConnectionStatus, VfsEntry, VfsEntryType, VfsPath, VfsProvider};
+    use std::io::Read;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    type FakeConnection = Arc<AtomicBool>;
+
+    fn is_alive(connection: &FakeConnection) -> bool {
+        connection.load(Ordering::SeqCst)
+    }
+
+    /// Remote provider whose first connection attempt fails each time it connects,
+    /// and whose session can be cut from the test
+    #[derive(Clone, Default)]
+    struct FlakyProvider {
+        connection: Arc<Mutex<Option<FakeConnection>>>,
+        status: Arc<Mutex<Option<ConnectionStatus>>>,
+        refuse_next: Arc<AtomicBool>,
+        cut_next_call: Arc<AtomicBool>,
+        connects: Arc<AtomicUsize>,
+    }
+
+    impl FlakyProvider {
+        fn connect(&self) -> Result<FakeConnection> {
+            if let Some(connection) = self.connection.lock().unwrap().clone().filter(is_alive) {
+                return Ok(connection);
+            }
+            self.refuse_next.store(true, Ordering::SeqCst);
+            let connection = connect_with_retries(3, Duration::from_millis(1), || {
+                match self.refuse_next.swap(false, Ordering::SeqCst) {
+                    true => Err(anyhow::anyhow!("connection refused")),
+                    false => Ok(Arc::new(AtomicBool::new(true))),
+                }
+            })?;
+            self.connects.fetch_add(1, Ordering::SeqCst);
+            *self.status.lock().unwrap() = Some(ConnectionStatus::Connected);
+            *self.connection.lock().unwrap() = Some(connection.clone());
+            Ok(connection)
+        }
+
+        fn forget(&self) {
+            self.connection.lock().unwrap().take();
+            *self.status.lock().unwrap() = Some(ConnectionStatus::Reconnecting);
+        }
+
+        /// The session dies while idle
+        fn drop_connection(&self) {
+            if let Some(connection) = self.connection.lock().unwrap().as_ref() {
+                connection.store(false, Ordering::SeqCst);
+            }
+        }
+
+        /// Run a call that loses the session halfway if `cut_next_call` is set
+        fn call<T: Clone>(&self, idempotent: bool, result: T) -> Result<T> {
+            let connection = self.connect()?;
+            retry_on_dropped(connection, is_alive, || self.forget(), || self.connect(), idempotent, |connection| {
+                if self.cut_next_call.swap(false, Ordering::SeqCst) {
+                    connection.store(false, Ordering::SeqCst);
+                }
+                match is_alive(connection) {
+                    true => Ok(result.clone()),
+                    false => Err(anyhow::anyhow!("broken pipe")),
+                }
+            })
+        }
+
+        fn connects(&self) -> usize {
+            self.connects.load(Ordering::SeqCst)
+        }
+    }
+
+    impl VfsProvider for FlakyProvider {
+        fn can_handle(&self, path: &VfsPath) -> bool {
+            matches!(path, VfsPath::Ftp { .. })
+        }
+        fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+            self.call(true, vec![self.get_info(path)?])
+        }
+        fn read_file(&self, _path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+        fn write_file(&self, _path: &VfsPath, _data: Box<dyn Read + Send>) -> Result<()> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+        fn create_directory(&self, _path: &VfsPath) -> Result<()> {
+            Err(anyhow::anyhow!("unsupported"))
+        }
+        fn delete(&self, _path: &VfsPath) -> Result<()> {
+            self.call(false, ())
+        }
+        fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+            Ok(VfsEntry {
+                name: "remote.txt".to_string(),
+                path: path.clone(),
+                entry_type: VfsEntryType::File,
+                size: 0,
+                modified: std::time::SystemTime::UNIX_EPOCH,
+                permissions: String::new(),
+                compressed_size: None,
+            })
+        }
+        fn connection_status(&self, _path: &VfsPath) -> Option<ConnectionStatus> {
+            *self.status.lock().unwrap()
+        }
+        fn keepalive(&self) {
+            let connection = self.connection.lock().unwrap().clone();
+            if connection.is_some_and(|connection| !is_alive(&connection)) {
+                self.forget();
+                let _ = self.connect();
+            }
+        }
+    }
+
+    async fn list(vfs: &AsyncVirtualFileSystem, path: &VfsPath) -> Result<usize> {
+        let mut handle = vfs.list_entries(path.clone());
+        let mut entries = 0;
+        loop {
+            match handle.next().await {
+                Some(ListingUpdate::Entries(batch)) => entries += batch.len(),
+                Some(ListingUpdate::Complete) => return Ok(entries),
+                Some(ListingUpdate::Failed(e)) => return Err(e),
+                None => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_connect_with_retries() {
+        let mut calls = 0;
+        let connected = connect_with_retries(3, Duration::from_millis(1), || {
+            calls += 1;
+            match calls {
+                1 => Err(anyhow::anyhow!("timed out")),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(connected.unwrap(), 2);
+
+        let mut calls = 0;
+        let failed: Result<()> = connect_with_retries(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(anyhow::anyhow!("attempt {}", calls))
+        });
+        assert_eq!(failed.unwrap_err().to_string(), "attempt 3");
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_and_retries_reconnect_dropped_sessions() {
+        let provider = FlakyProvider::default();
+        let vfs = AsyncVirtualFileSystem::new().with_provider(SyncProviderAdapter::new(provider.clone()));
+        let path = VfsPath::Ftp {
+            host: "files.example.com".to_string(),
+            port: 21,
+            username: "anonymous".to_string(),
+            path: "/".to_string(),
+        };
+
+        // The first attempt is refused, the retry gets through
+        assert_eq!(list(&vfs, &path).await.unwrap(), 1);
+        assert_eq!(provider.connects(), 1);
+        assert_eq!(vfs.connection_status(&path), Some(ConnectionStatus::Connected));
+
+        // A keepalive notices the idle session died and reconnects it
+        provider.drop_connection();
+        vfs.keepalive().await;
+        assert_eq!(provider.connects(), 2);
+        assert_eq!(vfs.connection_status(&path), Some(ConnectionStatus::Connected));
+
+        // A listing that loses its session halfway is retried on a new one
+        provider.cut_next_call.store(true, Ordering::SeqCst);
+        assert_eq!(list(&vfs, &path).await.unwrap(), 1);
+        assert_eq!(provider.connects(), 3);
+
+        // A delete is not repeated: the error surfaces and the next call reconnects
+        provider.cut_next_call.store(true, Ordering::SeqCst);
+        assert!(vfs.delete(&path).await.is_err());
+        assert_eq!(vfs.connection_status(&path), Some(ConnectionStatus::Reconnecting));
+        assert!(vfs.delete(&path).await.is_ok());
+        assert_eq!(provider.connects(), 4);
+        assert_eq!(vfs.connection_status(&path), Some(ConnectionStatus::Connected));
+    }
+}
@@ -0,0 +1,964 @@
+use crate::vfs::{VfsEntry, VfsEntryType, VfsPath, VfsProvider};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{Client, Method, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tokio::runtime::Runtime;
+
+/// Objects larger than this are uploaded with the multipart API
+const DEFAULT_MULTIPART_THRESHOLD: usize = 16 * 1024 * 1024;
+/// S3 requires every part except the last to be at least 5 MiB
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Characters left unescaped by SigV4 URI encoding (RFC 3986 unreserved set)
+const AWS_URI_ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[derive(Debug, Clone)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
+impl S3Credentials {
+    /// Load credentials from the environment, falling back to the AWS
+    /// shared credentials/config files for the active profile
+    pub fn load() -> Result<Self> {
+        if let Some(credentials) = Self::from_env() {
+            return Ok(credentials);
+        }
+
+        let profile = std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+        Self::from_profile(&profile)
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let access_key_id = std::env::var("AWS_ACCESS_KEY_ID").ok()?;
+        let secret_access_key = std::env::var("AWS_SECRET_ACCESS_KEY").ok()?;
+
+        Some(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            region: Self::env_region().unwrap_or_else(|| "us-east-1".to_string()),
+        })
+    }
+
+    pub fn from_profile(profile: &str) -> Result<Self> {
+        let aws_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?
+            .join(".aws");
+
+        let credentials_file = std::env::var("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| aws_dir.join("credentials"));
+        let config_file = std::env::var("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| aws_dir.join("config"));
+
+        let credentials = std::fs::read_to_string(&credentials_file)
+            .map(|content| parse_ini_section(&content, profile))
+            .unwrap_or_default();
+
+        // The config file prefixes non-default sections with "profile "
+        let config_section = if profile == "default" {
+            profile.to_string()
+        } else {
+            format!("profile {}", profile)
+        };
+        let config = std::fs::read_to_string(&config_file)
+            .map(|content| parse_ini_section(&content, &config_section))
+            .unwrap_or_default();
+
+        let lookup = |key: &str| credentials.get(key).or_else(|| config.get(key)).cloned();
+
+        let access_key_id = lookup("aws_access_key_id").ok_or_else(|| {
+            anyhow::anyhow!("No S3 credentials found for profile '{}'", profile)
+        })?;
+        let secret_access_key = lookup("aws_secret_access_key").ok_or_else(|| {
+            anyhow::anyhow!("Profile '{}' has no aws_secret_access_key", profile)
+        })?;
+
+        Ok(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: lookup("aws_session_token"),
+            region: Self::env_region()
+                .or_else(|| lookup("region"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+        })
+    }
+
+    fn env_region() -> Option<String> {
+        std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .ok()
+    }
+}
+
+/// Extract `key = value` pairs from one `[section]` of an INI-style file
+fn parse_ini_section(content: &str, section: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut in_section = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name.trim() == section;
+            continue;
+        }
+
+        if in_section {
+            if let Some((key, value)) = line.split_once('=') {
+                values.insert(key.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+    }
+
+    values
+}
+
+/// AWS Signature Version 4 request signer for the `s3` service
+pub struct SigV4Signer<'a> {
+    credentials: &'a S3Credentials,
+}
+
+impl<'a> SigV4Signer<'a> {
+    pub fn new(credentials: &'a S3Credentials) -> Self {
+        Self { credentials }
+    }
+
+    /// Compute the `Authorization` header for a request.
+    ///
+    /// `headers` must contain every header that will be sent and signed,
+    /// including `host`, `x-amz-date` and `x-amz-content-sha256`.
+    pub fn authorization(
+        &self,
+        method: &str,
+        canonical_uri: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+        payload_hash: &str,
+        timestamp: DateTime<Utc>,
+    ) -> String {
+        let amz_date = timestamp.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = timestamp.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.credentials.region);
+
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k), uri_encode(v)))
+            .collect();
+        query.sort();
+        let canonical_query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut headers: Vec<(String, String)> = headers
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+            .collect();
+        headers.sort();
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, canonical_uri, canonical_query, canonical_headers, signed_headers, payload_hash
+        );
+
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let secret = format!("AWS4{}", self.credentials.secret_access_key);
+        let k_date = hmac_sha256(secret.as_bytes(), date.as_bytes());
+        let k_region = hmac_sha256(&k_date, self.credentials.region.as_bytes());
+        let k_service = hmac_sha256(&k_region, b"s3");
+        let k_signing = hmac_sha256(&k_service, b"aws4_request");
+        let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.access_key_id, scope, signed_headers, signature
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn uri_encode(value: &str) -> String {
+    utf8_percent_encode(value, AWS_URI_ENCODE).to_string()
+}
+
+/// Encode an object key for use in a path, keeping `/` separators intact
+fn encode_key(key: &str) -> String {
+    key.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResult {
+    #[serde(default)]
+    contents: Vec<ObjectInfo>,
+    #[serde(default)]
+    common_prefixes: Vec<CommonPrefix>,
+    #[serde(default)]
+    is_truncated: bool,
+    next_continuation_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ObjectInfo {
+    key: String,
+    #[serde(default)]
+    size: u64,
+    last_modified: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CommonPrefix {
+    prefix: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListAllMyBucketsResult {
+    #[serde(default)]
+    buckets: BucketList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BucketList {
+    #[serde(default)]
+    bucket: Vec<BucketInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BucketInfo {
+    name: String,
+    creation_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InitiateMultipartUploadResult {
+    upload_id: String,
+}
+
+/// VFS provider for S3-compatible object storage (AWS, MinIO, Ceph RGW).
+///
+/// Keys are mapped onto a directory tree by treating `/` as the separator:
+/// common prefixes become directories and objects become files. Requests use
+/// path-style addressing so custom endpoints work without DNS wildcards.
+/// The runtime, HTTP client and credentials are only set up on first use, so
+/// a VFS that never touches S3 doesn't pay for them
+pub struct S3Provider {
+    runtime: OnceLock<Runtime>,
+    client: OnceLock<Client>,
+    credentials: OnceLock<Option<S3Credentials>>,
+    multipart_threshold: usize,
+    part_size: usize,
+}
+
+impl Default for S3Provider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl S3Provider {
+    /// Create a provider using credentials from the environment or AWS profile files
+    pub fn new() -> Self {
+        Self {
+            runtime: OnceLock::new(),
+            client: OnceLock::new(),
+            credentials: OnceLock::new(),
+            multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+            part_size: MIN_PART_SIZE * 2,
+        }
+    }
+
+    pub fn with_credentials(credentials: S3Credentials) -> Self {
+        let provider = Self::new();
+        let _ = provider.credentials.set(Some(credentials));
+        provider
+    }
+
+    fn runtime(&self) -> &Runtime {
+        self.runtime
+            .get_or_init(|| Runtime::new().expect("Failed to create Tokio runtime"))
+    }
+
+    fn client(&self) -> &Client {
+        self.client.get_or_init(|| {
+            Client::builder()
+                .connect_timeout(Duration::from_secs(30))
+                .build()
+                .expect("Failed to create HTTP client")
+        })
+    }
+
+    /// Set the object size above which uploads switch to multipart, and the part size
+    pub fn with_multipart(mut self, threshold: usize, part_size: usize) -> Self {
+        self.multipart_threshold = threshold;
+        self.part_size = part_size.max(MIN_PART_SIZE);
+        self
+    }
+
+    fn credentials(&self) -> Result<&S3Credentials> {
+        let credentials = self.credentials.get_or_init(|| match S3Credentials::load() {
+            Ok(credentials) => Some(credentials),
+            Err(e) => {
+                log::debug!("S3 credentials not available: {}", e);
+                None
+            }
+        });
+        credentials.as_ref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No S3 credentials configured (set AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY or ~/.aws/credentials)"
+            )
+        })
+    }
+
+    /// Send a signed request and return the response body, failing on non-2xx status
+    async fn send(
+        &self,
+        method: Method,
+        endpoint: &str,
+        bucket: &str,
+        key: &str,
+        query: &[(String, String)],
+        body: Vec<u8>,
+    ) -> Result<(reqwest::header::HeaderMap, Vec<u8>)> {
+        let (status, headers, bytes) = self.send_unchecked(method, endpoint, bucket, key, query, body).await?;
+        if !status.is_success() {
+            return Err(status_error(status, &bytes));
+        }
+        Ok((headers, bytes))
+    }
+
+    /// Send a signed request and return the response whatever its status
+    async fn send_unchecked(
+        &self,
+        method: Method,
+        endpoint: &str,
+        bucket: &str,
+        key: &str,
+        query: &[(String, String)],
+        body: Vec<u8>,
+    ) -> Result<(StatusCode, reqwest::header::HeaderMap, Vec<u8>)> {
+        let credentials = self.credentials()?;
+        let base = url::Url::parse(endpoint)?;
+        let host = match base.port() {
+            Some(port) => format!("{}:{}", base.host_str().unwrap_or_default(), port),
+            None => base.host_str().unwrap_or_default().to_string(),
+        };
+
+        let mut canonical_uri = String::from("/");
+        if !bucket.is_empty() {
+            canonical_uri.push_str(&uri_encode(bucket));
+            if !key.is_empty() {
+                canonical_uri.push('/');
+                canonical_uri.push_str(&encode_key(key));
+            }
+        }
+
+        let query_string = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", uri_encode(k), uri_encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url = if query_string.is_empty() {
+            format!("{}://{}{}", base.scheme(), host, canonical_uri)
+        } else {
+            format!("{}://{}{}?{}", base.scheme(), host, canonical_uri, query_string)
+        };
+
+        let now = Utc::now();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let mut headers = vec![
+            ("host".to_string(), host),
+            ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+            ("x-amz-date".to_string(), now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+        if let Some(ref token) = credentials.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+
+        let authorization = SigV4Signer::new(credentials).authorization(
+            method.as_str(),
+            &canonical_uri,
+            query,
+            &headers,
+            &payload_hash,
+            now,
+        );
+
+        let mut request = self.client().request(method, &url);
+        for (name, value) in headers.iter().filter(|(name, _)| name != "host") {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let response_headers = response.headers().clone();
+        let bytes = response.bytes().await?.to_vec();
+
+        Ok((status, response_headers, bytes))
+    }
+
+    async fn list_buckets(&self, endpoint: &str) -> Result<Vec<VfsEntry>> {
+        let (_, body) = self.send(Method::GET, endpoint, "", "", &[], Vec::new()).await?;
+        let result: ListAllMyBucketsResult = quick_xml::de::from_str(&String::from_utf8_lossy(&body))?;
+
+        Ok(result
+            .buckets
+            .bucket
+            .into_iter()
+            .map(|bucket| VfsEntry {
+                path: VfsPath::S3 {
+                    endpoint: endpoint.to_string(),
+                    bucket: bucket.name.clone(),
+                    key: String::new(),
+                },
+                name: bucket.name,
+                entry_type: VfsEntryType::Directory,
+                size: 0,
+                modified: bucket
+                    .creation_date
+                    .as_deref()
+                    .and_then(parse_iso8601)
+                    .unwrap_or(SystemTime::UNIX_EPOCH),
+                permissions: String::new(),
+                compressed_size: None,
+            })
+            .collect())
+    }
+
+    /// List objects below `prefix`; with `delimited` only the immediate level is returned
+    async fn list_objects(
+        &self,
+        endpoint: &str,
+        bucket: &str,
+        prefix: &str,
+        delimited: bool,
+    ) -> Result<(Vec<ObjectInfo>, Vec<String>)> {
+        let mut objects = Vec::new();
+        let mut prefixes = Vec::new();
+        self.list_object_pages(endpoint, bucket, prefix, delimited, &mut |page_objects, page_prefixes| {
+            objects.extend(page_objects);
+            prefixes.extend(page_prefixes);
+            true
+        })
+        .await?;
+        Ok((objects, prefixes))
+    }
+
+    /// Like `list_objects`, but hands each page to `on_page` as it arrives; returning false stops
+    async fn list_object_pages(
+        &self,
+        endpoint: &str,
+        bucket: &str,
+        prefix: &str,
+        delimited: bool,
+        on_page: &mut dyn FnMut(Vec<ObjectInfo>, Vec<String>) -> bool,
+    ) -> Result<()> {
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ];
+            if delimited {
+                query.push(("delimiter".to_string(), "/".to_string()));
+            }
+            if let Some(ref token) = continuation_token {
+                query.push(("continuation-token".to_string(), token.clone()));
+            }
+
+            let (_, body) = self
+                .send(Method::GET, endpoint, bucket, "", &query, Vec::new())
+                .await?;
+            let result: ListBucketResult = quick_xml::de::from_str(&String::from_utf8_lossy(&body))?;
+
+            let prefixes = result.common_prefixes.into_iter().map(|p| p.prefix).collect();
+            if !on_page(result.contents, prefixes) {
+                return Ok(());
+            }
+
+            match result.next_continuation_token {
+                Some(token) if result.is_truncated => continuation_token = Some(token),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    async fn upload_multipart(
+        &self,
+        endpoint: &str,
+        bucket: &str,
+        key: &str,
+        first_part: Vec<u8>,
+        data: &mut (dyn Read + Send),
+    ) -> Result<()> {
+        let (_, body) = self
+            .send(
+                Method::POST,
+                endpoint,
+                bucket,
+                key,
+                &[("uploads".to_string(), String::new())],
+                Vec::new(),
+            )
+            .await?;
+        let upload: InitiateMultipartUploadResult =
+            quick_xml::de::from_str(&String::from_utf8_lossy(&body))?;
+
+        let result = self
+            .upload_parts(endpoint, bucket, key, &upload.upload_id, first_part, data)
+            .await;
+
+        if result.is_err() {
+            // Don't leave orphaned parts behind; they are billed until aborted
+            let _ = self
+                .send(
+                    Method::DELETE,
+                    endpoint,
+                    bucket,
+                    key,
+                    &[("uploadId".to_string(), upload.upload_id.clone())],
+                    Vec::new(),
+                )
+                .await;
+        }
+
+        result
+    }
+
+    async fn upload_parts(
+        &self,
+        endpoint: &str,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        first_part: Vec<u8>,
+        data: &mut (dyn Read + Send),
+    ) -> Result<()> {
+        let mut etags = Vec::new();
+        let mut part = first_part;
+        let mut part_number = 1;
+
+        while !part.is_empty() {
+            let query = vec![
+                ("partNumber".to_string(), part_number.to_string()),
+                ("uploadId".to_string(), upload_id.to_string()),
+            ];
+            let (headers, _) = self
+                .send(Method::PUT, endpoint, bucket, key, &query, part)
+                .await?;
+            let etag = headers
+                .get("etag")
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| anyhow::anyhow!("S3 did not return an ETag for part {}", part_number))?
+                .to_string();
+            etags.push((part_number, etag));
+
+            part = read_chunk(data, self.part_size)?;
+            part_number += 1;
+        }
+
+        let mut complete = String::from("<CompleteMultipartUpload>");
+        for (number, etag) in &etags {
+            complete.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                number,
+                xml_escape(etag)
+            ));
+        }
+        complete.push_str("</CompleteMultipartUpload>");
+
+        let (_, body) = self
+            .send(
+                Method::POST,
+                endpoint,
+                bucket,
+                key,
+                &[("uploadId".to_string(), upload_id.to_string())],
+                complete.into_bytes(),
+            )
+            .await?;
+
+        // CompleteMultipartUpload can report an error inside a 200 response
+        let body = String::from_utf8_lossy(&body);
+        if body.contains("<Error>") {
+            return Err(anyhow::anyhow!(
+                "S3 multipart upload failed: {}",
+                extract_error_message(body.as_bytes())
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn object_entry(&self, endpoint: &str, bucket: &str, object: ObjectInfo) -> VfsEntry {
+        VfsEntry {
+            name: object.key.rsplit('/').next().unwrap_or_default().to_string(),
+            entry_type: VfsEntryType::File,
+            size: object.size,
+            modified: object
+                .last_modified
+                .as_deref()
+                .and_then(parse_iso8601)
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+            permissions: String::new(),
+            compressed_size: None,
+            path: VfsPath::S3 {
+                endpoint: endpoint.to_string(),
+                bucket: bucket.to_string(),
+                key: object.key,
+            },
+        }
+    }
+}
+
+/// Read up to `size` bytes, returning fewer only at end of input
+fn read_chunk(data: &mut (dyn Read + Send), size: usize) -> Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(size);
+    Read::take(&mut *data, size as u64).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Normalize a key into a listing prefix ("" for the bucket root, otherwise ending in `/`)
+fn directory_prefix(key: &str) -> String {
+    let key = key.trim_start_matches('/');
+    if key.is_empty() || key.ends_with('/') {
+        key.to_string()
+    } else {
+        format!("{}/", key)
+    }
+}
+
+fn parse_iso8601(value: &str) -> Option<SystemTime> {
+    DateTime::parse_from_rfc3339(value).ok().map(SystemTime::from)
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn status_error(status: StatusCode, body: &[u8]) -> anyhow::Error {
+    anyhow::anyhow!("S3 request failed ({}): {}", status, extract_error_message(body))
+}
+
+fn extract_error_message(body: &[u8]) -> String {
+    let body = String::from_utf8_lossy(body);
+    let tag = |name: &str| {
+        let open = format!("<{}>", name);
+        let close = format!("</{}>", name);
+        body.find(&open).and_then(|start| {
+            let rest = &body[start + open.len()..];
+            rest.find(&close).map(|end| rest[..end].to_string())
+        })
+    };
+
+    match (tag("Code"), tag("Message")) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        (Some(code), None) => code,
+        _ => body.trim().to_string(),
+    }
+}
+
+impl VfsProvider for S3Provider {
+    fn can_handle(&self, path: &VfsPath) -> bool {
+        matches!(path, VfsPath::S3 { .. })
+    }
+
+    fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+        let mut entries = Vec::new();
+        self.list_entries_batched(path, usize::MAX, &mut |batch| {
+            entries.extend(batch);
+            true
+        })?;
+        Ok(entries)
+    }
+
+    fn list_entries_batched(
+        &self,
+        path: &VfsPath,
+        batch_size: usize,
+        sink: &mut dyn FnMut(Vec<VfsEntry>) -> bool,
+    ) -> Result<()> {
+        match path {
+            VfsPath::S3 { endpoint, bucket, key } => {
+                if bucket.is_empty() {
+                    let buckets = self.runtime().block_on(self.list_buckets(endpoint))?;
+                    for batch in buckets.chunks(batch_size) {
+                        if !sink(batch.to_vec()) {
+                            break;
+                        }
+                    }
+                    return Ok(());
+                }
+
+                // Each page of up to 1000 keys is handed on as soon as it is parsed
+                let prefix = directory_prefix(key);
+                let mut sink_page = |objects: Vec<ObjectInfo>, prefixes: Vec<String>| {
+                    let mut entries: Vec<VfsEntry> = prefixes
+                        .into_iter()
+                        .map(|dir| VfsEntry {
+                            name: dir
+                                .trim_end_matches('/')
+                                .rsplit('/')
+                                .next()
+                                .unwrap_or_default()
+                                .to_string(),
+                            path: VfsPath::S3 {
+                                endpoint: endpoint.clone(),
+                                bucket: bucket.clone(),
+                                key: dir,
+                            },
+                            entry_type: VfsEntryType::Directory,
+                            size: 0,
+                            modified: SystemTime::UNIX_EPOCH,
+                            permissions: String::new(),
+                            compressed_size: None,
+                        })
+                        .collect();
+
+                    // Skip the zero-byte placeholder object that marks the directory itself
+                    entries.extend(
+                        objects
+                            .into_iter()
+                            .filter(|object| object.key != prefix)
+                            .map(|object| self.object_entry(endpoint, bucket, object)),
+                    );
+
+                    entries.chunks(batch_size).all(|batch| sink(batch.to_vec()))
+                };
+                self.runtime()
+                    .block_on(self.list_object_pages(endpoint, bucket, &prefix, true, &mut sink_page))
+            }
+            _ => Err(anyhow::anyhow!("S3Provider can only handle S3 paths")),
+        }
+    }
+
+    fn read_file(&self, path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+        match path {
+            VfsPath::S3 { endpoint, bucket, key } => {
+                let (_, body) = self.runtime().block_on(self.send(
+                    Method::GET,
+                    endpoint,
+                    bucket,
+                    key,
+                    &[],
+                    Vec::new(),
+                ))?;
+                Ok(Box::new(std::io::Cursor::new(body)))
+            }
+            _ => Err(anyhow::anyhow!("S3Provider can only handle S3 paths")),
+        }
+    }
+
+    fn write_file(&self, path: &VfsPath, mut data: Box<dyn Read + Send>) -> Result<()> {
+        match path {
+            VfsPath::S3 { endpoint, bucket, key } => {
+                // Read one byte past the threshold to decide between a single PUT and multipart
+                let head = read_chunk(data.as_mut(), self.multipart_threshold + 1)?;
+
+                self.runtime().block_on(async {
+                    if head.len() <= self.multipart_threshold {
+                        self.send(Method::PUT, endpoint, bucket, key, &[], head).await?;
+                        return Ok(());
+                    }
+
+                    let mut first_part = head;
+                    let rest = if first_part.len() > self.part_size {
+                        first_part.split_off(self.part_size)
+                    } else {
+                        Vec::new()
+                    };
+                    let mut remaining = std::io::Cursor::new(rest).chain(data.as_mut());
+
+                    self.upload_multipart(endpoint, bucket, key, first_part, &mut remaining)
+                        .await
+                })
+            }
+            _ => Err(anyhow::anyhow!("S3Provider can only handle S3 paths")),
+        }
+    }
+
+    fn create_directory(&self, path: &VfsPath) -> Result<()> {
+        match path {
+            VfsPath::S3 { endpoint, bucket, key } => {
+                // Directories only exist implicitly; a trailing-slash object keeps empty ones visible
+                let marker = directory_prefix(key);
+                self.runtime().block_on(self.send(
+                    Method::PUT,
+                    endpoint,
+                    bucket,
+                    &marker,
+                    &[],
+                    Vec::new(),
+                ))?;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("S3Provider can only handle S3 paths")),
+        }
+    }
+
+    fn delete(&self, path: &VfsPath) -> Result<()> {
+        match path {
+            VfsPath::S3 { endpoint, bucket, key } => self.runtime().block_on(async {
+                // A key naming an object is a file; only directory markers and
+                // implicit directories take their children with them
+                if !key.is_empty() && !key.ends_with('/') {
+                    let (status, _, body) = self
+                        .send_unchecked(Method::HEAD, endpoint, bucket, key, &[], Vec::new())
+                        .await?;
+                    if status.is_success() {
+                        self.send(Method::DELETE, endpoint, bucket, key, &[], Vec::new())
+                            .await?;
+                        return Ok(());
+                    }
+                    // Only a key that is definitely absent may be a directory; a denied or
+                    // failed HEAD must never turn into deleting everything below it
+                    if status != StatusCode::NOT_FOUND {
+                        return Err(status_error(status, &body));
+                    }
+                }
+
+                // Deleting a directory removes every object below its prefix
+                let prefix = directory_prefix(key);
+                if !prefix.is_empty() {
+                    let (objects, _) = self.list_objects(endpoint, bucket, &prefix, false).await?;
+                    for object in objects {
+                        self.send(Method::DELETE, endpoint, bucket, &object.key, &[], Vec::new())
+                            .await?;
+                    }
+                }
+
+                Ok(())
+            }),
+            _ => Err(anyhow::anyhow!("S3Provider can only handle S3 paths")),
+        }
+    }
+
+    fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        match path {
+            VfsPath::S3 { endpoint, bucket, key } => {
+                let name = key
+                    .trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .filter(|n| !n.is_empty())
+                    .unwrap_or(bucket)
+                    .to_string();
+
+                let directory = VfsEntry {
+                    name: name.clone(),
+                    path: path.clone(),
+                    entry_type: VfsEntryType::Directory,
+                    size: 0,
+                    modified: SystemTime::UNIX_EPOCH,
+                    permissions: String::new(),
+                    compressed_size: None,
+                };
+
+                if key.is_empty() || key.ends_with('/') {
+                    return Ok(directory);
+                }
+
+                let head = self.runtime().block_on(self.send(
+                    Method::HEAD,
+                    endpoint,
+                    bucket,
+                    key,
+                    &[],
+                    Vec::new(),
+                ));
+
+                match head {
+                    Ok((headers, _)) => {
+                        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+                        Ok(VfsEntry {
+                            name,
+                            path: path.clone(),
+                            entry_type: VfsEntryType::File,
+                            size: header("content-length")
+                                .and_then(|v| v.parse().ok())
+                                .unwrap_or(0),
+                            modified: header("last-modified")
+                                .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+                                .map(SystemTime::from)
+                                .unwrap_or(SystemTime::UNIX_EPOCH),
+                            permissions: String::new(),
+                            compressed_size: None,
+                        })
+                    }
+                    Err(e) => {
+                        // No object with this key; it may still be a prefix with children
+                        let (objects, prefixes) = self.runtime().block_on(self.list_objects(
+                            endpoint,
+                            bucket,
+                            &directory_prefix(key),
+                            true,
+                        ))?;
+                        if objects.is_empty() && prefixes.is_empty() {
+                            Err(e)
+                        } else {
+                            Ok(directory)
+                        }
+                    }
+                }
+            }
+            _ => Err(anyhow::anyhow!("S3Provider can only handle S3 paths")),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use crate::remote::ssh_manager::{RemoteCredentials, SshConnectionManager};
+use crate::vfs::{VfsEntry, VfsEntryType, VfsPath, VfsProvider};
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Browses SFTP paths on hosts without an SFTP subsystem (e.g. dropbear), listing
+/// with `ls`/`stat` over exec channels and moving file contents with SCP.
+pub struct ScpProvider {
+    connection_manager: Arc<SshConnectionManager>,
+    credentials: RemoteCredentials,
+}
+
+impl ScpProvider {
+    pub fn new(connection_manager: Arc<SshConnectionManager>, credentials: RemoteCredentials) -> Self {
+        Self {
+            connection_manager,
+            credentials,
+        }
+    }
+
+    fn credentials_for(&self, username: &str) -> RemoteCredentials {
+        if self.credentials.username.is_empty() {
+            RemoteCredentials {
+                username: username.to_string(),
+                ..self.credentials.clone()
+            }
+        } else {
+            self.credentials.clone()
+        }
+    }
+
+    /// Run a shell command, returning its stdout or failing with its stderr
+    fn run(&self, host: &str, port: u16, username: &str, command: &str) -> Result<String> {
+        let credentials = self.credentials_for(username);
+        let session = self.connection_manager.get_or_create_session(host, port, &credentials)?;
+        let (status, stdout, stderr) = self.connection_manager.exec_capture(&session, command)?;
+        if status != 0 {
+            let message = stderr.trim();
+            return Err(anyhow::anyhow!(
+                "`{}` failed on {}: {}",
+                command,
+                host,
+                if message.is_empty() { "non-zero exit status" } else { message }
+            ));
+        }
+        Ok(stdout)
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn join_remote(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Parse one line of `ls -la` output into (permissions, size, mtime, name).
+/// Symlink targets are dropped from the name; `.` and `..` are skipped.
+/// Device files list "major, minor" instead of a size and get a size of 0.
+fn parse_ls_line(line: &str, now: NaiveDateTime) -> Option<(String, u64, SystemTime, String)> {
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(9);
+    let mut field_count = 8;
+    while fields.len() < field_count {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+        // "1," is a major number with the minor number still to come
+        if fields.len() == 5 && fields[4].ends_with(',') {
+            field_count = 9;
+        }
+    }
+    if field_count == 9 {
+        fields.remove(5);
+    }
+
+    let permissions = fields[0];
+    if permissions.len() < 10 || rest.is_empty() {
+        return None;
+    }
+    let size = match fields[4].contains(',') {
+        true => 0,
+        false => fields[4].parse().ok()?,
+    };
+
+    let name = match permissions.starts_with('l') {
+        true => rest.split(" -> ").next().unwrap_or(rest),
+        false => rest,
+    };
+    if name == "." || name == ".." {
+        return None;
+    }
+
+    Some((
+        permissions.to_string(),
+        size,
+        parse_ls_time(fields[5], fields[6], fields[7], now).unwrap_or(UNIX_EPOCH),
+        name.to_string(),
+    ))
+}
+
+/// `ls` prints "Mon DD HH:MM" for recent files and "Mon DD YYYY" for older ones
+fn parse_ls_time(month: &str, day: &str, time_or_year: &str, now: NaiveDateTime) -> Option<SystemTime> {
+    let (year, time) = match time_or_year.parse::<i32>() {
+        Ok(year) => (year, "00:00"),
+        Err(_) => (now.year(), time_or_year),
+    };
+    let mut parsed =
+        NaiveDateTime::parse_from_str(&format!("{} {} {} {}", year, month, day, time), "%Y %b %d %H:%M").ok()?;
+    // Without a year the date is within the last six months, so it may be last year's
+    if time_or_year.contains(':') && parsed > now + chrono::Duration::days(1) {
+        parsed = NaiveDate::from_ymd_opt(year - 1, parsed.month(), parsed.day())?.and_time(parsed.time());
+    }
+    let secs = chrono::Local.from_local_datetime(&parsed).earliest()?.timestamp();
+    Some(UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64))
+}
+
+fn entry_type_for(permissions: &str) -> VfsEntryType {
+    match permissions.chars().next() {
+        Some('d') => VfsEntryType::Directory,
+        Some('l') => VfsEntryType::Symlink,
+        _ => VfsEntryType::File,
+    }
+}
+
+/// Parse `stat -c '%f %s %Y'` output: raw mode in hex, size, mtime
+fn parse_stat(output: &str) -> Option<(u32, u64, u64)> {
+    let mut fields = output.split_whitespace();
+    let mode = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let size = fields.next()?.parse().ok()?;
+    let mtime = fields.next()?.parse().ok()?;
+    Some((mode, size, mtime))
+}
+
+fn format_mode(mode: u32) -> String {
+    (0..9)
+        .map(|bit| {
+            let set = mode & (0o400 >> bit) != 0;
+            match (set, bit % 3) {
+                (false, _) => '-',
+                (true, 0) => 'r',
+                (true, 1) => 'w',
+                (true, _) => 'x',
+            }
+        })
+        .collect()
+}
+
+impl VfsProvider for ScpProvider {
+    fn can_handle(&self, path: &VfsPath) -> bool {
+        matches!(path, VfsPath::Sftp { .. })
+    }
+
+    fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+        match path {
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let output = self.run(host, *port, username, &format!("LC_ALL=C ls -la {}", shell_quote(remote_path)))?;
+                let username = self.credentials_for(username).username;
+                let now = chrono::Local::now().naive_local();
+
+                Ok(output
+                    .lines()
+                    .filter_map(|line| parse_ls_line(line, now))
+                    .map(|(permissions, size, modified, name)| VfsEntry {
+                        path: VfsPath::Sftp {
+                            host: host.clone(),
+                            port: *port,
+                            username: username.clone(),
+                            path: join_remote(remote_path, &name),
+                        },
+                        name,
+                        entry_type: entry_type_for(&permissions),
+                        size,
+                        modified,
+                        permissions: permissions[1..10].to_string(),
+                        compressed_size: None,
+                    })
+                    .collect())
+            }
+            _ => Err(anyhow::anyhow!("ScpProvider can only handle SFTP paths")),
+        }
+    }
+
+    fn read_file(&self, path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+        match path {
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let credentials = self.credentials_for(username);
+                let session = self.connection_manager.get_or_create_session(host, *port, &credentials)?;
+                let session = session.lock().unwrap();
+                let (mut channel, stat) = session
+                    .scp_recv(Path::new(remote_path))
+                    .map_err(|e| anyhow::anyhow!("SCP download of {} failed: {}", remote_path, e))?;
+
+                // SCP trails the contents with a status byte, so read exactly `size` bytes
+                let mut data = Vec::with_capacity(stat.size() as usize);
+                (&mut channel).take(stat.size()).read_to_end(&mut data)?;
+                channel.send_eof()?;
+                channel.wait_eof()?;
+                channel.close()?;
+                channel.wait_close()?;
+
+                Ok(Box::new(std::io::Cursor::new(data)))
+            }
+            _ => Err(anyhow::anyhow!("ScpProvider can only handle SFTP paths")),
+        }
+    }
+
+    fn write_file(&self, path: &VfsPath, mut data: Box<dyn Read + Send>) -> Result<()> {
+        match path {
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let mut buffer = Vec::new();
+                data.read_to_end(&mut buffer)?;
+
+                let credentials = self.credentials_for(username);
+                let session = self.connection_manager.get_or_create_session(host, *port, &credentials)?;
+                let session = session.lock().unwrap();
+                let mut channel = session
+                    .scp_send(Path::new(remote_path), 0o644, buffer.len() as u64, None)
+                    .map_err(|e| anyhow::anyhow!("SCP upload of {} failed: {}", remote_path, e))?;
+                channel.write_all(&buffer)?;
+                channel.send_eof()?;
+                channel.wait_eof()?;
+                channel.close()?;
+                channel.wait_close()?;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("ScpProvider can only handle SFTP paths")),
+        }
+    }
+
+    fn create_directory(&self, path: &VfsPath) -> Result<()> {
+        match path {
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                self.run(host, *port, username, &format!("mkdir {}", shell_quote(remote_path)))?;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("ScpProvider can only handle SFTP paths")),
+        }
+    }
+
+    fn delete(&self, path: &VfsPath) -> Result<()> {
+        match path {
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let quoted = shell_quote(remote_path);
+                let command = format!("if [ -d {0} ] && [ ! -L {0} ]; then rmdir {0}; else rm {0}; fi", quoted);
+                self.run(host, *port, username, &command)?;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("ScpProvider can only handle SFTP paths")),
+        }
+    }
+
+    fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        match path {
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let output = self.run(host, *port, username, &format!("stat -c '%f %s %Y' {}", shell_quote(remote_path)))?;
+                let (mode, size, mtime) = parse_stat(&output)
+                    .ok_or_else(|| anyhow::anyhow!("Unexpected stat output: {}", output.trim()))?;
+
+                let entry_type = match mode & 0o170000 {
+                    0o040000 => VfsEntryType::Directory,
+                    0o120000 => VfsEntryType::Symlink,
+                    _ => VfsEntryType::File,
+                };
+                let name = Path::new(remote_path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| remote_path.clone());
+
+                Ok(VfsEntry {
+                    name,
+                    path: path.clone(),
+                    entry_type,
+                    size,
+                    modified: UNIX_EPOCH + Duration::from_secs(mtime),
+                    permissions: format_mode(mode),
+                    compressed_size: None,
+                })
+            }
+            _ => Err(anyhow::anyhow!("ScpProvider can only handle SFTP paths")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_busybox_ls_output() {
+        let now = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let output = "total 12\n\
+            drwxr-xr-x    2 root     root          4096 Mar  9 08:15 .\n\
+            drwxr-xr-x   18 root     root          4096 Jan  1  2023 ..\n\
+            -rw-r--r--    1 root     root           220 Dec 24 18:30 my file.txt\n\
+            lrwxrwxrwx    1 root     root            11 Jan  1  2023 sh -> /bin/busybox\n\
+            crw-rw-rw-    1 root     root        1,   3 Mar  9 08:15 null\n\
+            brw-rw----    1 root     disk        8,0 Mar  9 08:15 sda\n";
+        let entries: Vec<_> = output.lines().filter_map(|line| parse_ls_line(line, now)).collect();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!((entries[2].1, entries[2].3.as_str()), (0, "null"));
+        assert_eq!((entries[3].1, entries[3].3.as_str()), (0, "sda"));
+        assert_eq!(entries[0].0, "-rw-r--r--");
+        assert_eq!(entries[0].1, 220);
+        assert_eq!(entries[0].3, "my file.txt");
+        assert_eq!(entries[1].3, "sh");
+        assert!(matches!(entry_type_for(&entries[1].0), VfsEntryType::Symlink));
+
+        // "Dec 24" seen in March belongs to the previous year
+        let dec = chrono::Local.from_local_datetime(
+            &NaiveDate::from_ymd_opt(2023, 12, 24).unwrap().and_hms_opt(18, 30, 0).unwrap(),
+        );
+        let expected = UNIX_EPOCH + Duration::from_secs(dec.earliest().unwrap().timestamp() as u64);
+        assert_eq!(entries[0].2, expected);
+    }
+
+    #[test]
+    fn test_parse_stat_output() {
+        let (mode, size, mtime) = parse_stat("41ed 4096 1700000000\n").unwrap();
+        assert_eq!(mode & 0o170000, 0o040000);
+        assert_eq!(format_mode(mode), "rwxr-xr-x");
+        assert_eq!((size, mtime), (4096, 1700000000));
+    }
+}
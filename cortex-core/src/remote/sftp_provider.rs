@@ -1,25 +1,80 @@
 use crate::vfs::{VfsEntry, VfsEntryType, VfsPath, VfsProvider};
 use crate::remote::ssh_manager::{RemoteCredentials, SshConnectionManager};
+use crate::remote::reconnect::retry_on_dropped;
+use crate::remote::scp_provider::ScpProvider;
+use crate::remote::ConnectionStatus;
 use anyhow::Result;
-use ssh2::{FileStat, OpenFlags, OpenType};
+use ssh2::{ErrorCode, FileStat, OpenFlags, OpenType, Sftp};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::UNIX_EPOCH;
 
+// libssh2's code for reading past the last directory entry
+const LIBSSH2_ERROR_FILE: i32 = -16;
+
 pub struct SftpProvider {
     connection_manager: Arc<SshConnectionManager>,
     credentials: RemoteCredentials,
+    // Used instead of SFTP for hosts that lack the subsystem
+    scp: ScpProvider,
 }
 
 impl SftpProvider {
     pub fn new(connection_manager: Arc<SshConnectionManager>, credentials: RemoteCredentials) -> Self {
         Self {
+            scp: ScpProvider::new(connection_manager.clone(), credentials.clone()),
             connection_manager,
             credentials,
         }
     }
 
+    /// The SCP fallback when the host behind `path` cannot negotiate SFTP
+    fn fallback(&self, path: &VfsPath) -> Result<Option<&ScpProvider>> {
+        match path {
+            VfsPath::Sftp { host, port, username, .. } => {
+                let credentials = self.credentials_for(username);
+                let supported = self.connection_manager.supports_sftp(host, *port, &credentials)?;
+                Ok((!supported).then_some(&self.scp))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Credentials for `username`; a provider without its own falls back to agent auth
+    fn credentials_for(&self, username: &str) -> RemoteCredentials {
+        if self.credentials.username.is_empty() {
+            RemoteCredentials {
+                username: username.to_string(),
+                ..self.credentials.clone()
+            }
+        } else {
+            self.credentials.clone()
+        }
+    }
+
+    /// Run `op` on an SFTP channel. Idempotent operations that fail because the
+    /// connection dropped are retried once on a fresh session.
+    fn with_sftp<T>(
+        &self,
+        host: &str,
+        port: u16,
+        username: &str,
+        idempotent: bool,
+        op: impl Fn(&Sftp) -> Result<T>,
+    ) -> Result<T> {
+        let credentials = self.credentials_for(username);
+        let manager = &self.connection_manager;
+        retry_on_dropped(
+            manager.get_or_create_session(host, port, &credentials)?,
+            |session| manager.is_session_alive(session),
+            || manager.invalidate(host, port, &credentials.username),
+            || manager.get_or_create_session(host, port, &credentials),
+            idempotent,
+            |session| manager.create_sftp(session).and_then(|sftp| op(&sftp)),
+        )
+    }
+
     fn convert_file_stat(&self, stat: FileStat, name: String, path: VfsPath) -> VfsEntry {
         let entry_type = if stat.is_dir() {
             VfsEntryType::Directory
@@ -65,64 +120,88 @@ impl VfsProvider for SftpProvider {
     }
 
     fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+        let mut entries = Vec::new();
+        self.list_entries_batched(path, usize::MAX, &mut |batch| {
+            entries.extend(batch);
+            true
+        })?;
+        Ok(entries)
+    }
+
+    fn list_entries_batched(
+        &self,
+        path: &VfsPath,
+        batch_size: usize,
+        sink: &mut dyn FnMut(Vec<VfsEntry>) -> bool,
+    ) -> Result<()> {
+        if let Some(scp) = self.fallback(path)? {
+            return scp.list_entries_batched(path, batch_size, sink);
+        }
         match path {
-            VfsPath::Sftp { host, port, username: _, path: remote_path } => {
-                let session = self.connection_manager.get_or_create_session(
-                    host,
-                    *port,
-                    &self.credentials,
-                )?;
-                
-                let sftp = self.connection_manager.create_sftp(&session)?;
-                let entries = sftp.readdir(Path::new(remote_path))?;
-                
-                Ok(entries
-                    .into_iter()
-                    .map(|(path_buf, stat)| {
-                        let name = path_buf
-                            .file_name()
-                            .map(|n| n.to_string_lossy().to_string())
-                            .unwrap_or_default();
-                        
-                        let full_path = if remote_path.ends_with('/') {
-                            format!("{}{}", remote_path, name)
-                        } else {
-                            format!("{}/{}", remote_path, name)
-                        };
-                        
-                        self.convert_file_stat(
-                            stat,
-                            name,
-                            VfsPath::Sftp {
-                                host: host.clone(),
-                                port: *port,
-                                username: self.credentials.username.clone(),
-                                path: full_path,
-                            },
-                        )
-                    })
-                    .collect())
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                // Only opening the directory is retried; entries already handed out can't be taken back
+                let mut dir = self.with_sftp(host, *port, username, true, |sftp| {
+                    Ok(sftp.opendir(Path::new(remote_path))?)
+                })?;
+                let username = self.credentials_for(username).username;
+
+                let mut entries = Vec::new();
+                loop {
+                    let (file_name, stat) = match dir.readdir() {
+                        Ok(entry) => entry,
+                        Err(e) if e.code() == ErrorCode::Session(LIBSSH2_ERROR_FILE) => break,
+                        Err(e) => return Err(e.into()),
+                    };
+                    let name = file_name.to_string_lossy().to_string();
+                    if name == "." || name == ".." {
+                        continue;
+                    }
+
+                    let full_path = if remote_path.ends_with('/') {
+                        format!("{}{}", remote_path, name)
+                    } else {
+                        format!("{}/{}", remote_path, name)
+                    };
+
+                    entries.push(self.convert_file_stat(
+                        stat,
+                        name,
+                        VfsPath::Sftp {
+                            host: host.clone(),
+                            port: *port,
+                            username: username.clone(),
+                            path: full_path,
+                        },
+                    ));
+
+                    if entries.len() >= batch_size && !sink(std::mem::take(&mut entries)) {
+                        return Ok(());
+                    }
+                }
+
+                if !entries.is_empty() {
+                    sink(entries);
+                }
+                Ok(())
             }
             _ => Err(anyhow::anyhow!("SftpProvider can only handle SFTP paths")),
         }
     }
 
     fn read_file(&self, path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+        if let Some(scp) = self.fallback(path)? {
+            return scp.read_file(path);
+        }
         match path {
-            VfsPath::Sftp { host, port, username: _, path: remote_path } => {
-                let session = self.connection_manager.get_or_create_session(
-                    host,
-                    *port,
-                    &self.credentials,
-                )?;
-                
-                let sftp = self.connection_manager.create_sftp(&session)?;
-                let file = sftp.open_mode(
-                    Path::new(remote_path),
-                    OpenFlags::READ,
-                    0,
-                    OpenType::File,
-                )?;
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let file = self.with_sftp(host, *port, username, true, |sftp| {
+                    Ok(sftp.open_mode(
+                        Path::new(remote_path),
+                        OpenFlags::READ,
+                        0,
+                        OpenType::File,
+                    )?)
+                })?;
                 
                 struct SftpFileReader {
                     file: ssh2::File,
@@ -143,85 +222,73 @@ impl VfsProvider for SftpProvider {
     }
 
     fn write_file(&self, path: &VfsPath, mut data: Box<dyn Read + Send>) -> Result<()> {
+        if let Some(scp) = self.fallback(path)? {
+            return scp.write_file(path, data);
+        }
         match path {
-            VfsPath::Sftp { host, port, username: _, path: remote_path } => {
-                let session = self.connection_manager.get_or_create_session(
-                    host,
-                    *port,
-                    &self.credentials,
-                )?;
-                
-                let sftp = self.connection_manager.create_sftp(&session)?;
-                let mut file = sftp.open_mode(
-                    Path::new(remote_path),
-                    OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
-                    0o644,
-                    OpenType::File,
-                )?;
-                
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                // Buffered up front, so a truncating rewrite is safe to repeat
                 let mut buffer = Vec::new();
                 data.read_to_end(&mut buffer)?;
-                file.write_all(&buffer)?;
                 
-                Ok(())
+                self.with_sftp(host, *port, username, true, |sftp| {
+                    let mut file = sftp.open_mode(
+                        Path::new(remote_path),
+                        OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+                        0o644,
+                        OpenType::File,
+                    )?;
+                    file.write_all(&buffer)?;
+                    Ok(())
+                })
             }
             _ => Err(anyhow::anyhow!("SftpProvider can only handle SFTP paths")),
         }
     }
 
     fn create_directory(&self, path: &VfsPath) -> Result<()> {
+        if let Some(scp) = self.fallback(path)? {
+            return scp.create_directory(path);
+        }
         match path {
-            VfsPath::Sftp { host, port, username: _, path: remote_path } => {
-                let session = self.connection_manager.get_or_create_session(
-                    host,
-                    *port,
-                    &self.credentials,
-                )?;
-                
-                let sftp = self.connection_manager.create_sftp(&session)?;
-                sftp.mkdir(Path::new(remote_path), 0o755)?;
-                
-                Ok(())
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                self.with_sftp(host, *port, username, false, |sftp| {
+                    Ok(sftp.mkdir(Path::new(remote_path), 0o755)?)
+                })
             }
             _ => Err(anyhow::anyhow!("SftpProvider can only handle SFTP paths")),
         }
     }
 
     fn delete(&self, path: &VfsPath) -> Result<()> {
+        if let Some(scp) = self.fallback(path)? {
+            return scp.delete(path);
+        }
         match path {
-            VfsPath::Sftp { host, port, username: _, path: remote_path } => {
-                let session = self.connection_manager.get_or_create_session(
-                    host,
-                    *port,
-                    &self.credentials,
-                )?;
-                
-                let sftp = self.connection_manager.create_sftp(&session)?;
-                
-                let stat = sftp.stat(Path::new(remote_path))?;
-                if stat.is_dir() {
-                    sftp.rmdir(Path::new(remote_path))?;
-                } else {
-                    sftp.unlink(Path::new(remote_path))?;
-                }
-                
-                Ok(())
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                self.with_sftp(host, *port, username, false, |sftp| {
+                    let stat = sftp.stat(Path::new(remote_path))?;
+                    if stat.is_dir() {
+                        sftp.rmdir(Path::new(remote_path))?;
+                    } else {
+                        sftp.unlink(Path::new(remote_path))?;
+                    }
+                    Ok(())
+                })
             }
             _ => Err(anyhow::anyhow!("SftpProvider can only handle SFTP paths")),
         }
     }
 
     fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        if let Some(scp) = self.fallback(path)? {
+            return scp.get_info(path);
+        }
         match path {
-            VfsPath::Sftp { host, port, username: _, path: remote_path } => {
-                let session = self.connection_manager.get_or_create_session(
-                    host,
-                    *port,
-                    &self.credentials,
-                )?;
-                
-                let sftp = self.connection_manager.create_sftp(&session)?;
-                let stat = sftp.stat(Path::new(remote_path))?;
+            VfsPath::Sftp { host, port, username, path: remote_path } => {
+                let stat = self.with_sftp(host, *port, username, true, |sftp| {
+                    Ok(sftp.stat(Path::new(remote_path))?)
+                })?;
                 
                 let name = Path::new(remote_path)
                     .file_name()
@@ -233,4 +300,18 @@ impl VfsProvider for SftpProvider {
             _ => Err(anyhow::anyhow!("SftpProvider can only handle SFTP paths")),
         }
     }
+
+    fn connection_status(&self, path: &VfsPath) -> Option<ConnectionStatus> {
+        match path {
+            VfsPath::Sftp { host, port, username, .. } => {
+                let username = self.credentials_for(username).username;
+                self.connection_manager.status(host, *port, &username)
+            }
+            _ => None,
+        }
+    }
+
+    fn keepalive(&self) {
+        self.connection_manager.send_keepalives();
+    }
 }
\ No newline at end of file
@@ -1,11 +1,22 @@
+use crate::async_vfs::CancellationToken;
+use crate::remote::reconnect::{connect_with_retries, RETRY_BACKOFF};
+use crate::remote::ConnectionStatus;
 use anyhow::Result;
 use ssh2::{Session, Sftp};
 use std::collections::HashMap;
+use std::io::Read;
 use std::net::TcpStream;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
+/// Worker slot remote commands run on, so a long-running command never holds
+/// up the listings, transfers and keepalives of the shared session
+const COMMAND_SLOT: usize = usize::MAX;
+
+/// How long a host that refused SFTP keeps falling back to SCP before it is asked again
+const SFTP_RECHECK_INTERVAL: Duration = Duration::from_secs(300);
+
 #[derive(Debug, Clone)]
 pub struct RemoteCredentials {
     pub username: String,
@@ -14,15 +25,36 @@ pub struct RemoteCredentials {
     pub passphrase: Option<String>,
 }
 
+/// One line of output from a remote command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+impl ExecLine {
+    fn new(stream: usize, bytes: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(bytes).trim_end_matches(['\r', '\n']).to_string();
+        match stream {
+            0 => Self::Stdout(text),
+            _ => Self::Stderr(text),
+        }
+    }
+}
+
 struct SessionInfo {
     session: Arc<Mutex<Session>>,
     last_used: Instant,
-    #[allow(dead_code)]
+    // What the session authenticated with, reused to reconnect it
     credentials: RemoteCredentials,
+    broken: bool,
 }
 
 pub struct SshConnectionManager {
     sessions: Arc<RwLock<HashMap<String, SessionInfo>>>,
+    statuses: Arc<RwLock<HashMap<String, ConnectionStatus>>>,
+    // Whether each host offers SFTP, and when that was found out
+    sftp_support: Arc<RwLock<HashMap<String, (bool, Instant)>>>,
     connection_timeout: Duration,
     idle_timeout: Duration,
     max_retries: u32,
@@ -38,6 +70,8 @@ impl SshConnectionManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            statuses: Arc::new(RwLock::new(HashMap::new())),
+            sftp_support: Arc::new(RwLock::new(HashMap::new())),
             connection_timeout: Duration::from_secs(30),
             idle_timeout: Duration::from_secs(600),
             max_retries: 3,
@@ -61,50 +95,154 @@ impl SshConnectionManager {
         port: u16,
         credentials: &RemoteCredentials,
     ) -> Result<Arc<Mutex<Session>>> {
-        let session_key = format!("{}:{}@{}", credentials.username, port, host);
+        self.get_or_create_worker_session(host, port, credentials, 0)
+    }
+
+    /// Session that remote commands run on, apart from the shared one
+    pub fn get_or_create_command_session(
+        &self,
+        host: &str,
+        port: u16,
+        credentials: &RemoteCredentials,
+    ) -> Result<Arc<Mutex<Session>>> {
+        self.get_or_create_worker_session(host, port, credentials, COMMAND_SLOT)
+    }
+
+    /// Session for parallel transfer worker `slot`; slot 0 is the regular shared session.
+    /// libssh2 serialises every call on a session, so each worker needs its own
+    /// connection to actually overlap round trips.
+    ///
+    /// A pooled session that no longer answers is dropped and re-established with
+    /// the credentials it originally authenticated with.
+    pub fn get_or_create_worker_session(
+        &self,
+        host: &str,
+        port: u16,
+        credentials: &RemoteCredentials,
+        slot: usize,
+    ) -> Result<Arc<Mutex<Session>>> {
+        let base_key = Self::session_key(host, port, &credentials.username);
+        let session_key = match slot {
+            0 => base_key.clone(),
+            COMMAND_SLOT => format!("{}#exec", base_key),
+            slot => format!("{}#{}", base_key, slot),
+        };
         
         self.cleanup_idle_sessions()?;
         
-        {
-            let sessions = self.sessions.read().unwrap();
-            if let Some(session_info) = sessions.get(&session_key) {
-                if self.is_session_valid(&session_info.session)? {
-                    let mut sessions = self.sessions.write().unwrap();
-                    if let Some(info) = sessions.get_mut(&session_key) {
-                        info.last_used = Instant::now();
-                    }
-                    return Ok(session_info.session.clone());
+        let existing = self
+            .sessions
+            .read()
+            .unwrap()
+            .get(&session_key)
+            .map(|info| (info.session.clone(), info.credentials.clone(), info.broken));
+        let mut credentials = credentials.clone();
+        if let Some((session, stored, broken)) = existing {
+            if !broken && self.is_session_alive(&session) {
+                if let Some(info) = self.sessions.write().unwrap().get_mut(&session_key) {
+                    info.last_used = Instant::now();
                 }
+                return Ok(session);
+            }
+            log::info!("SSH session {} is dead, reconnecting", session_key);
+            self.sessions.write().unwrap().remove(&session_key);
+            if slot == 0 {
+                self.set_status(&base_key, ConnectionStatus::Reconnecting);
             }
+            credentials = stored;
         }
         
-        let mut last_error = None;
-        for attempt in 0..self.max_retries {
-            if attempt > 0 {
-                std::thread::sleep(Duration::from_millis(500 * (1 << attempt)));
-            }
-            
-            match self.create_new_session(host, port, credentials) {
-                Ok(session) => {
-                    let session_arc = Arc::new(Mutex::new(session));
-                    let mut sessions = self.sessions.write().unwrap();
-                    sessions.insert(
-                        session_key,
-                        SessionInfo {
-                            session: session_arc.clone(),
-                            last_used: Instant::now(),
-                            credentials: credentials.clone(),
-                        },
-                    );
-                    return Ok(session_arc);
+        let connected = connect_with_retries(self.max_retries, RETRY_BACKOFF, || {
+            self.create_new_session(host, port, &credentials)
+        });
+        match connected {
+            Ok(session) => {
+                let session_arc = Arc::new(Mutex::new(session));
+                let mut sessions = self.sessions.write().unwrap();
+                sessions.insert(
+                    session_key,
+                    SessionInfo {
+                        session: session_arc.clone(),
+                        last_used: Instant::now(),
+                        credentials,
+                        broken: false,
+                    },
+                );
+                if slot == 0 {
+                    self.set_status(&base_key, ConnectionStatus::Connected);
                 }
-                Err(e) => {
-                    last_error = Some(e);
+                Ok(session_arc)
+            }
+            Err(e) => {
+                if slot == 0 {
+                    self.set_status(&base_key, ConnectionStatus::Offline);
                 }
+                Err(e)
             }
         }
-        
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Failed to create SSH session")))
+    }
+
+    fn session_key(host: &str, port: u16, username: &str) -> String {
+        format!("{}:{}@{}", username, port, host)
+    }
+
+    fn set_status(&self, key: &str, status: ConnectionStatus) {
+        self.statuses.write().unwrap().insert(key.to_string(), status);
+    }
+
+    /// Last known health of the shared session for this host and user
+    pub fn status(&self, host: &str, port: u16, username: &str) -> Option<ConnectionStatus> {
+        self.statuses
+            .read()
+            .unwrap()
+            .get(&Self::session_key(host, port, username))
+            .copied()
+    }
+
+    /// Mark a session that failed mid-operation so the next call reconnects it
+    pub fn invalidate(&self, host: &str, port: u16, username: &str) {
+        let key = Self::session_key(host, port, username);
+        if let Some(info) = self.sessions.write().unwrap().get_mut(&key) {
+            info.broken = true;
+            self.set_status(&key, ConnectionStatus::Reconnecting);
+        }
+    }
+
+    /// Send a keepalive on every pooled session, reconnecting the ones that stopped answering
+    pub fn send_keepalives(&self) {
+        let pooled: Vec<(String, Arc<Mutex<Session>>, bool)> = self
+            .sessions
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| !key.contains('#'))
+            .map(|(key, info)| (key.clone(), info.session.clone(), info.broken))
+            .collect();
+
+        for (key, session, broken) in pooled {
+            if !broken && self.is_session_alive(&session) {
+                continue;
+            }
+            let Some((username, host, port)) = Self::parse_session_key(&key) else {
+                continue;
+            };
+            let credentials = RemoteCredentials {
+                username,
+                password: None,
+                private_key_path: None,
+                passphrase: None,
+            };
+            // Stored credentials take over once the dead session is found
+            if let Err(e) = self.get_or_create_session(&host, port, &credentials) {
+                log::warn!("Reconnecting {} failed: {}", key, e);
+            }
+        }
+    }
+
+    fn parse_session_key(key: &str) -> Option<(String, String, u16)> {
+        let (user_port, host) = key.rsplit_once('@')?;
+        let (username, port) = user_port.rsplit_once(':')?;
+        Some((username.to_string(), host.to_string(), port.parse().ok()?))
     }
 
     fn create_new_session(
@@ -146,9 +284,10 @@ impl SshConnectionManager {
         Ok(session)
     }
 
-    fn is_session_valid(&self, session: &Arc<Mutex<Session>>) -> Result<bool> {
+    /// Whether the session still answers; a keepalive fails fast on a dropped socket
+    pub fn is_session_alive(&self, session: &Arc<Mutex<Session>>) -> bool {
         let session = session.lock().unwrap();
-        Ok(session.authenticated())
+        session.authenticated() && session.keepalive_send().is_ok()
     }
 
     fn cleanup_idle_sessions(&self) -> Result<()> {
@@ -156,13 +295,16 @@ impl SshConnectionManager {
         let now = Instant::now();
         
         sessions.retain(|_, info| {
-            if now.duration_since(info.last_used) > self.idle_timeout {
-                if let Ok(session) = info.session.lock() {
+            if now.duration_since(info.last_used) <= self.idle_timeout {
+                return true;
+            }
+            // A session still locked is running a command, so not idle
+            match info.session.try_lock() {
+                Ok(session) => {
                     let _ = session.disconnect(None, "idle timeout", None);
+                    false
                 }
-                false
-            } else {
-                true
+                Err(_) => true,
             }
         });
         
@@ -174,17 +316,168 @@ impl SshConnectionManager {
         session.sftp().map_err(|e| anyhow::anyhow!("Failed to create SFTP channel: {}", e))
     }
 
+    /// Whether the host offers the SFTP subsystem. Negotiated per host and user and
+    /// cached; servers such as dropbear often ship without it.
+    pub fn supports_sftp(&self, host: &str, port: u16, credentials: &RemoteCredentials) -> Result<bool> {
+        let key = Self::session_key(host, port, &credentials.username);
+        if let Some(&(supported, checked)) = self.sftp_support.read().unwrap().get(&key) {
+            // A refusal may have been transient, so it is only trusted for a while
+            if supported || checked.elapsed() < SFTP_RECHECK_INTERVAL {
+                return Ok(supported);
+            }
+        }
+
+        let session = self.get_or_create_session(host, port, credentials)?;
+        let supported = match self.create_sftp(&session) {
+            Ok(_) => true,
+            Err(e) if self.is_session_alive(&session) => {
+                log::info!("{} has no SFTP subsystem, falling back to SCP: {}", host, e);
+                false
+            }
+            Err(e) => return Err(e),
+        };
+        self.sftp_support.write().unwrap().insert(key, (supported, Instant::now()));
+        Ok(supported)
+    }
+
+    /// Run `command` over an exec channel and collect its exit status, stdout and stderr
+    pub fn exec_capture(&self, session: &Arc<Mutex<Session>>, command: &str) -> Result<(i32, String, String)> {
+        let session = session.lock().unwrap();
+        let mut channel = session
+            .channel_session()
+            .map_err(|e| anyhow::anyhow!("Failed to open exec channel: {}", e))?;
+        channel.exec(command)?;
+
+        // Read both streams together; draining stdout first deadlocks once stderr fills its window
+        let mut output = [Vec::new(), Vec::new()];
+        session.set_blocking(false);
+        let polled = Self::poll_streams(&mut channel, &CancellationToken::new(), &mut |stream, data| {
+            output[stream].extend_from_slice(data)
+        });
+        session.set_blocking(true);
+        polled?;
+        let [stdout, stderr] = output;
+
+        channel.wait_close()?;
+        Ok((
+            channel.exit_status()?,
+            String::from_utf8_lossy(&stdout).into_owned(),
+            String::from_utf8_lossy(&stderr).into_owned(),
+        ))
+    }
+
+    /// Run `command` over an exec channel, handing each output line to `on_line` as it
+    /// arrives. Both streams are polled so a chatty stderr never stalls stdout. The
+    /// session stays locked until the command exits or `cancel` stops it, so give it
+    /// one from `get_or_create_command_session`.
+    pub fn exec_streaming(
+        &self,
+        session: &Arc<Mutex<Session>>,
+        command: &str,
+        cancel: &CancellationToken,
+        mut on_line: impl FnMut(ExecLine),
+    ) -> Result<i32> {
+        let session = session.lock().unwrap();
+        let mut channel = session
+            .channel_session()
+            .map_err(|e| anyhow::anyhow!("Failed to open exec channel: {}", e))?;
+        channel.exec(command)?;
+
+        session.set_blocking(false);
+        let streamed = Self::pump_output(&mut channel, cancel, &mut on_line);
+        if cancel.is_cancelled() {
+            // Closing the channel hangs up on the remote command
+            let _ = channel.close();
+        }
+        session.set_blocking(true);
+        streamed?;
+
+        channel.wait_close()?;
+        Ok(channel.exit_status()?)
+    }
+
+    /// Read stdout and stderr of a non-blocking channel until both reach EOF
+    fn pump_output(
+        channel: &mut ssh2::Channel,
+        cancel: &CancellationToken,
+        on_line: &mut impl FnMut(ExecLine),
+    ) -> Result<()> {
+        let mut pending = [Vec::new(), Vec::new()];
+        Self::poll_streams(channel, cancel, &mut |stream, data| {
+            pending[stream].extend_from_slice(data);
+            while let Some(end) = pending[stream].iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = pending[stream].drain(..=end).collect();
+                on_line(ExecLine::new(stream, &line));
+            }
+        })?;
+        for (stream, rest) in pending.iter().enumerate() {
+            if !rest.is_empty() {
+                on_line(ExecLine::new(stream, rest));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read stdout (stream 0) and stderr (stream 1) of a non-blocking channel side by
+    /// side until both reach EOF, handing every chunk to `on_data`
+    fn poll_streams(
+        channel: &mut ssh2::Channel,
+        cancel: &CancellationToken,
+        on_data: &mut impl FnMut(usize, &[u8]),
+    ) -> Result<()> {
+        let mut open = [true, true];
+        let mut buffer = [0u8; 8192];
+        while open.iter().any(|&stream_open| stream_open) {
+            if cancel.is_cancelled() {
+                return Err(anyhow::anyhow!("Command cancelled"));
+            }
+            let mut progressed = false;
+            for stream in 0..2 {
+                if !open[stream] {
+                    continue;
+                }
+                let read = match stream {
+                    0 => channel.read(&mut buffer),
+                    _ => channel.stderr().read(&mut buffer),
+                };
+                match read {
+                    Ok(0) if channel.eof() => open[stream] = false,
+                    Ok(0) => {}
+                    Ok(read) => {
+                        progressed = true;
+                        on_data(stream, &buffer[..read]);
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if !progressed {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        Ok(())
+    }
+
     pub fn disconnect(&self, host: &str, port: u16, username: &str) -> Result<()> {
         let session_key = format!("{}:{}@{}", username, port, host);
+        let worker_prefix = format!("{}#", session_key);
         let mut sessions = self.sessions.write().unwrap();
-        
-        if let Some(info) = sessions.remove(&session_key) {
+
+        // Worker sessions go away together with the main one
+        let mut result = Ok(());
+        sessions.retain(|key, info| {
+            if key != &session_key && !key.starts_with(&worker_prefix) {
+                return true;
+            }
             if let Ok(session) = info.session.lock() {
-                session.disconnect(None, "manual disconnect", None)?;
+                if let Err(e) = session.disconnect(None, "manual disconnect", None) {
+                    result = Err(e.into());
+                }
             }
-        }
-        
-        Ok(())
+            false
+        });
+
+        result
     }
 
     pub fn disconnect_all(&self) -> Result<()> {
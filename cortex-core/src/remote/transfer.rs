@@ -0,0 +1,538 @@
+// Parallel, rate-limited SFTP transfers with aggregate progress reporting.
+// Hosts without the SFTP subsystem are served over SCP instead.
+
+use crate::async_vfs::CancellationToken;
+use crate::config::NetworkConfig;
+use crate::operations::OperationProgress;
+use crate::remote::scp_provider::ScpProvider;
+use crate::remote::ssh_manager::{RemoteCredentials, SshConnectionManager};
+use crate::vfs::{VfsEntryType, VfsPath, VfsProvider};
+use anyhow::Result;
+use ssh2::{Channel, OpenFlags, OpenType, Session, Sftp};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferJob {
+    pub direction: TransferDirection,
+    pub local_path: PathBuf,
+    pub remote_path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TransferSettings {
+    pub workers: usize,
+    /// Bytes per second for each individual transfer
+    pub rate_limit: Option<u64>,
+}
+
+impl TransferSettings {
+    pub fn from_config(config: &NetworkConfig) -> Self {
+        Self {
+            workers: config.transfer_workers.max(1),
+            rate_limit: (config.transfer_rate_limit > 0).then(|| config.transfer_rate_limit * 1024),
+        }
+    }
+}
+
+/// Keeps a single transfer under its byte-per-second limit
+struct Throttle {
+    limit: Option<u64>,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    /// How long to pause after moving `bytes` more to stay on schedule
+    fn delay_after(&mut self, bytes: u64, elapsed: Duration) -> Duration {
+        self.bytes += bytes;
+        match self.limit {
+            Some(limit) if limit > 0 => {
+                Duration::from_secs_f64(self.bytes as f64 / limit as f64).saturating_sub(elapsed)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Counters shared by all workers of one batch
+struct TransferStats {
+    started: Instant,
+    files_total: usize,
+    bytes_total: u64,
+    files_done: AtomicUsize,
+    bytes_done: AtomicU64,
+}
+
+impl TransferStats {
+    fn new(jobs: &[TransferJob]) -> Self {
+        Self {
+            started: Instant::now(),
+            files_total: jobs.len(),
+            bytes_total: jobs.iter().map(|j| j.size).sum(),
+            files_done: AtomicUsize::new(0),
+            bytes_done: AtomicU64::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> OperationProgress {
+        let bytes_done = self.bytes_done.load(Ordering::Relaxed);
+        let bytes_per_sec = transfer_rate(bytes_done, self.started.elapsed());
+        OperationProgress::Transfer {
+            files_done: self.files_done.load(Ordering::Relaxed),
+            files_total: self.files_total,
+            bytes_done,
+            bytes_total: self.bytes_total,
+            bytes_per_sec,
+            eta_secs: estimate_remaining(self.bytes_total.saturating_sub(bytes_done), bytes_per_sec),
+        }
+    }
+}
+
+fn transfer_rate(bytes: u64, elapsed: Duration) -> u64 {
+    let secs = elapsed.as_secs_f64();
+    if secs <= 0.0 {
+        0
+    } else {
+        (bytes as f64 / secs) as u64
+    }
+}
+
+fn estimate_remaining(remaining: u64, bytes_per_sec: u64) -> Option<u64> {
+    if remaining == 0 {
+        Some(0)
+    } else if bytes_per_sec == 0 {
+        None
+    } else {
+        Some(remaining.div_ceil(bytes_per_sec))
+    }
+}
+
+/// Copies files between the local disk and one SFTP host using several workers,
+/// each on its own pooled session from the `SshConnectionManager`
+pub struct ParallelTransfer {
+    manager: Arc<SshConnectionManager>,
+    host: String,
+    port: u16,
+    credentials: RemoteCredentials,
+    settings: TransferSettings,
+}
+
+impl ParallelTransfer {
+    pub fn new(
+        manager: Arc<SshConnectionManager>,
+        host: impl Into<String>,
+        port: u16,
+        credentials: RemoteCredentials,
+        settings: TransferSettings,
+    ) -> Self {
+        Self {
+            manager,
+            host: host.into(),
+            port,
+            credentials,
+            settings,
+        }
+    }
+
+    /// Upload local files and directories into `remote_dir`
+    pub async fn upload(
+        &self,
+        sources: Vec<PathBuf>,
+        remote_dir: String,
+        progress: mpsc::UnboundedSender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let planner = self.planner().await?;
+        let jobs = tokio::task::spawn_blocking(move || -> Result<Vec<TransferJob>> {
+            let mut jobs = Vec::new();
+            for source in sources {
+                let name = source.file_name().unwrap_or_default().to_string_lossy().to_string();
+                plan_upload(&planner, &source, &join_remote(&remote_dir, &name), &mut jobs)?;
+            }
+            Ok(jobs)
+        })
+        .await??;
+        self.run(jobs, progress, cancel).await
+    }
+
+    /// Download remote files and directories into `local_dir`
+    pub async fn download(
+        &self,
+        sources: Vec<String>,
+        local_dir: PathBuf,
+        progress: mpsc::UnboundedSender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let planner = self.planner().await?;
+        let jobs = tokio::task::spawn_blocking(move || -> Result<Vec<TransferJob>> {
+            let mut jobs = Vec::new();
+            for source in sources {
+                let name = source.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+                plan_download(&planner, &source, &local_dir.join(name), &mut jobs)?;
+            }
+            Ok(jobs)
+        })
+        .await??;
+        self.run(jobs, progress, cancel).await
+    }
+
+    async fn planner(&self) -> Result<Planner> {
+        let manager = Arc::clone(&self.manager);
+        let (host, port, credentials) = (self.host.clone(), self.port, self.credentials.clone());
+        tokio::task::spawn_blocking(move || {
+            if !manager.supports_sftp(&host, port, &credentials)? {
+                let base = VfsPath::Sftp {
+                    host,
+                    port,
+                    username: credentials.username.clone(),
+                    path: String::new(),
+                };
+                return Ok(Planner::Shell(ScpProvider::new(manager, credentials), base));
+            }
+            let session = manager.get_or_create_session(&host, port, &credentials)?;
+            Ok(Planner::Sftp(manager.create_sftp(&session)?))
+        })
+        .await?
+    }
+
+    /// Run prepared jobs across the worker pool, reporting throughput as they go
+    pub async fn run(
+        &self,
+        jobs: Vec<TransferJob>,
+        progress: mpsc::UnboundedSender<OperationProgress>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let operation = format!("Transfer of {} files", jobs.len());
+        let _ = progress.send(OperationProgress::Started {
+            operation: operation.clone(),
+        });
+
+        let stats = Arc::new(TransferStats::new(&jobs));
+        let workers = self.settings.workers.clamp(1, jobs.len().max(1));
+        let queue = Arc::new(Mutex::new(VecDeque::from(jobs)));
+        let errors = Arc::new(Mutex::new(Vec::new()));
+
+        let mut handles = Vec::new();
+        for slot in 0..workers {
+            let manager = Arc::clone(&self.manager);
+            let (host, port, credentials) = (self.host.clone(), self.port, self.credentials.clone());
+            let (queue, stats, errors, cancel) =
+                (Arc::clone(&queue), Arc::clone(&stats), Arc::clone(&errors), cancel.clone());
+            let rate_limit = self.settings.rate_limit;
+
+            handles.push(tokio::task::spawn_blocking(move || -> Result<()> {
+                let session = manager.get_or_create_worker_session(&host, port, &credentials, slot)?;
+                let sftp = match manager.supports_sftp(&host, port, &credentials)? {
+                    true => Some(manager.create_sftp(&session)?),
+                    false => None,
+                };
+                while !cancel.is_cancelled() {
+                    let Some(job) = queue.lock().unwrap().pop_front() else {
+                        break;
+                    };
+                    let result = match sftp {
+                        Some(ref sftp) => transfer_one(sftp, &job, rate_limit, &stats, &cancel),
+                        None => transfer_one_scp(&session.lock().unwrap(), &job, rate_limit, &stats, &cancel),
+                    };
+                    match result {
+                        Ok(()) => {
+                            stats.files_done.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => errors
+                            .lock()
+                            .unwrap()
+                            .push(format!("{}: {}", job.remote_path, e)),
+                    }
+                }
+                Ok(())
+            }));
+        }
+
+        let mut all_workers = futures::future::join_all(handles);
+        let mut ticker = tokio::time::interval(REPORT_INTERVAL);
+        let results = loop {
+            tokio::select! {
+                results = &mut all_workers => break results,
+                _ = ticker.tick() => {
+                    let _ = progress.send(stats.snapshot());
+                }
+            }
+        };
+        let _ = progress.send(stats.snapshot());
+
+        let mut errors = std::mem::take(&mut *errors.lock().unwrap());
+        for result in results {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => errors.push(e.to_string()),
+                Err(e) => errors.push(format!("worker failed: {}", e)),
+            }
+        }
+
+        if cancel.is_cancelled() {
+            let _ = progress.send(OperationProgress::Failed {
+                operation,
+                error: "Cancelled".to_string(),
+            });
+            return Err(anyhow::anyhow!("Transfer cancelled"));
+        }
+        if let Some(first) = errors.first() {
+            let error = format!("{} error(s), first: {}", errors.len(), first);
+            let _ = progress.send(OperationProgress::Failed {
+                operation,
+                error: error.clone(),
+            });
+            return Err(anyhow::anyhow!(error));
+        }
+
+        let _ = progress.send(OperationProgress::Completed { operation });
+        Ok(())
+    }
+}
+
+fn join_remote(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// Remote directory access while planning: SFTP, or shell commands for hosts without it
+enum Planner {
+    Sftp(Sftp),
+    Shell(ScpProvider, VfsPath),
+}
+
+impl Planner {
+    fn at(base: &VfsPath, remote: &str) -> VfsPath {
+        match base {
+            VfsPath::Sftp { host, port, username, .. } => VfsPath::Sftp {
+                host: host.clone(),
+                port: *port,
+                username: username.clone(),
+                path: remote.to_string(),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Whether `remote` is a directory, and its size
+    fn stat(&self, remote: &str) -> Result<(bool, u64)> {
+        match self {
+            Planner::Sftp(sftp) => {
+                let stat = sftp.stat(Path::new(remote))?;
+                Ok((stat.is_dir(), stat.size.unwrap_or(0)))
+            }
+            Planner::Shell(shell, base) => {
+                let entry = shell.get_info(&Self::at(base, remote))?;
+                Ok((matches!(entry.entry_type, VfsEntryType::Directory), entry.size))
+            }
+        }
+    }
+
+    fn mkdir(&self, remote: &str) -> Result<()> {
+        match self {
+            Planner::Sftp(sftp) => Ok(sftp.mkdir(Path::new(remote), 0o755)?),
+            Planner::Shell(shell, base) => shell.create_directory(&Self::at(base, remote)),
+        }
+    }
+
+    fn read_dir(&self, remote: &str) -> Result<Vec<String>> {
+        match self {
+            Planner::Sftp(sftp) => Ok(sftp
+                .readdir(Path::new(remote))?
+                .into_iter()
+                .map(|(child, _)| child.file_name().unwrap_or_default().to_string_lossy().to_string())
+                .filter(|name| name != "." && name != "..")
+                .collect()),
+            Planner::Shell(shell, base) => Ok(shell
+                .list_entries(&Self::at(base, remote))?
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect()),
+        }
+    }
+}
+
+/// Create remote directories up front and queue every file below `local`
+fn plan_upload(planner: &Planner, local: &Path, remote: &str, jobs: &mut Vec<TransferJob>) -> Result<()> {
+    let metadata = fs::metadata(local)?;
+    if metadata.is_dir() {
+        if planner.stat(remote).is_err() {
+            planner.mkdir(remote)?;
+        }
+        for entry in fs::read_dir(local)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            plan_upload(planner, &entry.path(), &join_remote(remote, &name), jobs)?;
+        }
+    } else {
+        jobs.push(TransferJob {
+            direction: TransferDirection::Upload,
+            local_path: local.to_path_buf(),
+            remote_path: remote.to_string(),
+            size: metadata.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Create local directories up front and queue every file below `remote`
+fn plan_download(planner: &Planner, remote: &str, local: &Path, jobs: &mut Vec<TransferJob>) -> Result<()> {
+    let (is_dir, size) = planner.stat(remote)?;
+    if is_dir {
+        fs::create_dir_all(local)?;
+        for name in planner.read_dir(remote)? {
+            plan_download(planner, &join_remote(remote, &name), &local.join(&name), jobs)?;
+        }
+    } else {
+        jobs.push(TransferJob {
+            direction: TransferDirection::Download,
+            local_path: local.to_path_buf(),
+            remote_path: remote.to_string(),
+            size,
+        });
+    }
+    Ok(())
+}
+
+fn transfer_one(
+    sftp: &Sftp,
+    job: &TransferJob,
+    rate_limit: Option<u64>,
+    stats: &TransferStats,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let remote = Path::new(&job.remote_path);
+    let (mut reader, mut writer): (Box<dyn Read>, Box<dyn Write>) = match job.direction {
+        TransferDirection::Upload => (
+            Box::new(File::open(&job.local_path)?),
+            Box::new(sftp.open_mode(
+                remote,
+                OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+                0o644,
+                OpenType::File,
+            )?),
+        ),
+        TransferDirection::Download => (
+            Box::new(sftp.open(remote)?),
+            Box::new(File::create(&job.local_path)?),
+        ),
+    };
+
+    pump(&mut reader, &mut writer, rate_limit, stats, cancel)
+}
+
+/// Same as `transfer_one` over SCP, for hosts without the SFTP subsystem
+fn transfer_one_scp(
+    session: &Session,
+    job: &TransferJob,
+    rate_limit: Option<u64>,
+    stats: &TransferStats,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let remote = Path::new(&job.remote_path);
+    let mut channel = match job.direction {
+        TransferDirection::Upload => {
+            // SCP announces the size up front, so send exactly that many bytes
+            let size = fs::metadata(&job.local_path)?.len();
+            let mut channel = session.scp_send(remote, 0o644, size, None)?;
+            pump(&mut File::open(&job.local_path)?.take(size), &mut channel, rate_limit, stats, cancel)?;
+            channel
+        }
+        TransferDirection::Download => {
+            let (mut channel, stat) = session.scp_recv(remote)?;
+            let mut file = File::create(&job.local_path)?;
+            pump(&mut (&mut channel).take(stat.size()), &mut file, rate_limit, stats, cancel)?;
+            channel
+        }
+    };
+    finish_scp(&mut channel)
+}
+
+fn finish_scp(channel: &mut Channel) -> Result<()> {
+    channel.send_eof()?;
+    channel.wait_eof()?;
+    channel.close()?;
+    channel.wait_close()?;
+    Ok(())
+}
+
+/// Copy `reader` into `writer` in chunks, honouring the rate limit and cancellation
+fn pump(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    rate_limit: Option<u64>,
+    stats: &TransferStats,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let mut throttle = Throttle::new(rate_limit);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        if cancel.is_cancelled() {
+            return Err(anyhow::anyhow!("Transfer cancelled"));
+        }
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        stats.bytes_done.fetch_add(read as u64, Ordering::Relaxed);
+
+        let delay = throttle.delay_after(read as u64, throttle.started.elapsed());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_delays_to_rate_limit() {
+        let mut throttle = Throttle::new(Some(1024));
+        // 2 KiB at 1 KiB/s should take two seconds; half a second has passed
+        let delay = throttle.delay_after(2048, Duration::from_millis(500));
+        assert_eq!(delay, Duration::from_millis(1500));
+        // Already behind schedule, no extra pause
+        assert_eq!(throttle.delay_after(0, Duration::from_secs(5)), Duration::ZERO);
+
+        let mut unlimited = Throttle::new(None);
+        assert_eq!(unlimited.delay_after(1 << 30, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rate_and_eta() {
+        assert_eq!(transfer_rate(10_000, Duration::from_secs(4)), 2_500);
+        assert_eq!(transfer_rate(10_000, Duration::ZERO), 0);
+        assert_eq!(estimate_remaining(5_001, 2_500), Some(3));
+        assert_eq!(estimate_remaining(100, 0), None);
+        assert_eq!(estimate_remaining(0, 0), Some(0));
+    }
+}
@@ -0,0 +1,149 @@
+// Edit files that live behind a VFS provider through a local working copy
+
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::async_vfs::{AsyncVirtualFileSystem, CancellationToken};
+use crate::vfs::VfsPath;
+
+/// A remote file checked out into a temporary local workspace
+#[derive(Debug)]
+pub struct RemoteEditSession {
+    pub remote_path: VfsPath,
+    pub local_path: PathBuf,
+    workspace: PathBuf,
+    remote_size: u64,
+    remote_modified: SystemTime,
+    content_hash: u64,
+    keep_workspace: bool,
+}
+
+impl RemoteEditSession {
+    /// Download `remote_path` into a fresh temp directory
+    pub async fn open(vfs: &AsyncVirtualFileSystem, remote_path: VfsPath) -> Result<Self> {
+        let info = vfs.get_info(&remote_path).await?;
+
+        // Private (0700), unpredictably named directory; cleanup is handled by Drop
+        let workspace = tempfile::Builder::new().prefix("cortex-edit-").tempdir()?.keep();
+
+        let local_path = workspace.join(remote_file_name(&remote_path));
+        let content = match vfs.read_file(&remote_path, CancellationToken::new()).await {
+            Ok(content) => content,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&workspace);
+                return Err(e);
+            }
+        };
+        if let Err(e) = fs::write(&local_path, &content) {
+            let _ = fs::remove_dir_all(&workspace);
+            return Err(e.into());
+        }
+
+        Ok(Self {
+            remote_path,
+            local_path,
+            workspace,
+            remote_size: info.size,
+            remote_modified: info.modified,
+            content_hash: hash_bytes(&content),
+            keep_workspace: false,
+        })
+    }
+
+    /// Whether the local copy differs from what was last downloaded or uploaded
+    pub fn is_modified(&self) -> Result<bool> {
+        let content = fs::read(&self.local_path)?;
+        Ok(hash_bytes(&content) != self.content_hash)
+    }
+
+    /// Whether the remote file changed since it was checked out
+    pub async fn remote_changed(&self, vfs: &AsyncVirtualFileSystem) -> Result<bool> {
+        let info = vfs.get_info(&self.remote_path).await?;
+        Ok(info.size != self.remote_size || info.modified != self.remote_modified)
+    }
+
+    /// Upload the local copy and take the new remote state as the baseline
+    pub async fn upload(&mut self, vfs: &AsyncVirtualFileSystem) -> Result<()> {
+        let content = fs::read(&self.local_path)?;
+        let hash = hash_bytes(&content);
+        vfs.write_file(&self.remote_path, content, CancellationToken::new()).await?;
+
+        let info = vfs.get_info(&self.remote_path).await?;
+        self.remote_size = info.size;
+        self.remote_modified = info.modified;
+        self.content_hash = hash;
+        Ok(())
+    }
+
+    /// Leave the working copy on disk when the session is dropped
+    pub fn keep_local_copy(&mut self) -> &Path {
+        self.keep_workspace = true;
+        &self.local_path
+    }
+}
+
+impl Drop for RemoteEditSession {
+    fn drop(&mut self) {
+        if !self.keep_workspace {
+            let _ = fs::remove_dir_all(&self.workspace);
+        }
+    }
+}
+
+fn remote_file_name(path: &VfsPath) -> String {
+    let full = match path {
+        VfsPath::Local(p) => p.to_string_lossy().to_string(),
+        VfsPath::Archive { internal_path, .. } => internal_path.clone(),
+        VfsPath::Sftp { path, .. } | VfsPath::Ftp { path, .. } => path.clone(),
+        VfsPath::S3 { key, .. } => key.clone(),
+        VfsPath::GitTree { path, .. } => path.clone(),
+    };
+    full.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("untitled")
+        .to_string()
+}
+
+fn hash_bytes(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_edit_session_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let remote = dir.path().join("notes.txt");
+        fs::write(&remote, "before").unwrap();
+
+        let vfs = AsyncVirtualFileSystem::new();
+        let mut session = RemoteEditSession::open(&vfs, VfsPath::Local(remote.clone())).await.unwrap();
+        assert_eq!(session.local_path.file_name().unwrap(), "notes.txt");
+        assert!(!session.is_modified().unwrap());
+        assert!(!session.remote_changed(&vfs).await.unwrap());
+
+        fs::write(&session.local_path, "after").unwrap();
+        assert!(session.is_modified().unwrap());
+        session.upload(&vfs).await.unwrap();
+        assert_eq!(fs::read_to_string(&remote).unwrap(), "after");
+        assert!(!session.is_modified().unwrap());
+
+        fs::write(&remote, "changed elsewhere").unwrap();
+        assert!(session.remote_changed(&vfs).await.unwrap());
+
+        let workspace = session.workspace.clone();
+        drop(session);
+        assert!(!workspace.exists());
+    }
+}
@@ -0,0 +1,415 @@
+// Regex replace across files: plan the changes, preview and pick hunks, then
+// apply them all at once with backups that can be restored
+
+use crate::search::{SearchCriteria, SearchType};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Files above this size are left out of a replace
+const MAX_REPLACE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Backups of older replaces are deleted, so only this many can be undone
+pub const MAX_REPLACE_BACKUPS: usize = 20;
+
+/// Changed lines of one file: the lines a run of overlapping matches touch
+#[derive(Debug, Clone)]
+pub struct ReplaceHunk {
+    /// Zero-based number of the first line
+    pub first_line: usize,
+    pub old_text: String,
+    pub new_text: String,
+    /// Matches replaced within the hunk
+    pub replacements: usize,
+    pub enabled: bool,
+    span: Range<usize>,
+}
+
+/// The planned replacements in one file
+#[derive(Debug, Clone)]
+pub struct FileChanges {
+    pub path: PathBuf,
+    pub hunks: Vec<ReplaceHunk>,
+    original: String,
+}
+
+impl FileChanges {
+    /// Contents with the enabled hunks applied
+    pub fn new_content(&self) -> String {
+        let mut content = String::with_capacity(self.original.len());
+        let mut copied = 0;
+        for hunk in self.hunks.iter().filter(|hunk| hunk.enabled) {
+            content.push_str(&self.original[copied..hunk.span.start]);
+            content.push_str(&hunk.new_text);
+            copied = hunk.span.end;
+        }
+        content.push_str(&self.original[copied..]);
+        content
+    }
+
+    /// Matches the enabled hunks replace
+    pub fn enabled_replacements(&self) -> usize {
+        self.hunks.iter().filter(|hunk| hunk.enabled).map(|hunk| hunk.replacements).sum()
+    }
+}
+
+/// The regex a search's pattern stands for when looking inside files. Exact
+/// and wildcard patterns match whole lines there, so they are anchored to
+/// line starts and ends, either side of a `\r\n` too
+pub fn content_regex(criteria: &SearchCriteria) -> String {
+    let pattern = match criteria.search_type {
+        SearchType::Regex => criteria.pattern.clone(),
+        SearchType::Wildcard => format!(
+            "(?mR)^{}$",
+            regex::escape(&criteria.pattern).replace(r"\*", ".*").replace(r"\?", ".")
+        ),
+        SearchType::Exact => format!("(?mR)^{}$", regex::escape(&criteria.pattern)),
+        SearchType::Contains => regex::escape(&criteria.pattern),
+    };
+    match criteria.case_sensitive {
+        true => pattern,
+        false => format!("(?i){}", pattern),
+    }
+}
+
+/// Find what replacing the regex `pattern` with `replacement` (`$1`, `${name}`
+/// expand to captures) would change in each file; files without matches,
+/// binary files and very large ones are left out
+pub fn plan_replacements(paths: &[PathBuf], pattern: &str, replacement: &str) -> Result<Vec<FileChanges>> {
+    let regex = Regex::new(pattern)?;
+    Ok(paths
+        .iter()
+        .filter_map(|path| {
+            let metadata = fs::metadata(path).ok()?;
+            if !metadata.is_file() || metadata.len() > MAX_REPLACE_FILE_SIZE {
+                return None;
+            }
+            let original = fs::read_to_string(path).ok()?;
+            if original.contains('\0') {
+                return None;
+            }
+            let hunks = plan_hunks(&original, &regex, replacement);
+            (!hunks.is_empty()).then(|| FileChanges {
+                path: path.clone(),
+                hunks,
+                original,
+            })
+        })
+        .collect())
+}
+
+/// A matched range and what replaces it
+type Edit = (Range<usize>, String);
+
+fn plan_hunks(text: &str, regex: &Regex, replacement: &str) -> Vec<ReplaceHunk> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+    let line_end = |line: usize| line_starts.get(line + 1).copied().unwrap_or(text.len());
+
+    // Matches grouped by the lines they touch: first line, last line, edits
+    let mut groups: Vec<(usize, usize, Vec<Edit>)> = Vec::new();
+    for captures in regex.captures_iter(text) {
+        let matched = captures.get(0).expect("group 0 is the whole match");
+        let mut new_text = String::new();
+        captures.expand(replacement, &mut new_text);
+        if new_text == matched.as_str() {
+            continue;
+        }
+        let first = line_of(matched.start());
+        let last = line_of(matched.end().saturating_sub(1).max(matched.start()));
+        match groups.last_mut() {
+            Some((_, group_last, edits)) if first <= *group_last => {
+                *group_last = (*group_last).max(last);
+                edits.push((matched.range(), new_text));
+            }
+            _ => groups.push((first, last, vec![(matched.range(), new_text)])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(first, last, edits)| {
+            let span = line_starts[first]..line_end(last);
+            let mut new_text = String::new();
+            let mut copied = span.start;
+            for (range, replacement) in &edits {
+                new_text.push_str(&text[copied..range.start]);
+                new_text.push_str(replacement);
+                copied = range.end;
+            }
+            new_text.push_str(&text[copied..span.end]);
+            ReplaceHunk {
+                first_line: first,
+                old_text: text[span.clone()].to_string(),
+                new_text,
+                replacements: edits.len(),
+                enabled: true,
+                span,
+            }
+        })
+        .collect()
+}
+
+/// Where a replace keeps the originals of the files it changed
+#[derive(Debug, Clone)]
+pub struct ReplaceUndo {
+    pub files: Vec<PathBuf>,
+    backup_dir: PathBuf,
+    /// Hash of what was written, to notice later edits
+    written: Vec<u64>,
+}
+
+impl ReplaceUndo {
+    /// Put the backed up originals back, unless a file changed since
+    pub fn undo(&self) -> Result<usize> {
+        for (path, written) in self.files.iter().zip(&self.written) {
+            if fs::read_to_string(path).map(|content| hash(&content)).ok() != Some(*written) {
+                return Err(anyhow::anyhow!("{} changed since the replace", path.display()));
+            }
+        }
+        for (index, path) in self.files.iter().enumerate() {
+            let original = fs::read_to_string(self.backup_dir.join(index.to_string()))?;
+            write_atomically(path, &original)?;
+        }
+        let _ = fs::remove_dir_all(&self.backup_dir);
+        Ok(self.files.len())
+    }
+}
+
+/// Directory replace backups go to
+pub fn backup_root() -> Result<PathBuf> {
+    dirs::cache_dir()
+        .map(|dir| dir.join("cortex").join("replace-backups"))
+        .ok_or_else(|| anyhow::anyhow!("No cache directory for replace backups"))
+}
+
+/// Write the enabled hunks of every file, backing the originals up below
+/// `backup_root`. Nothing is written when a file changed since it was planned
+/// or a new version cannot be staged; a failure while swapping the new
+/// versions in restores the files already swapped. Symlinked files are
+/// written through their links.
+pub fn apply_replacements(files: &[FileChanges], backup_root: &Path) -> Result<ReplaceUndo> {
+    let files: Vec<&FileChanges> = files.iter().filter(|file| file.enabled_replacements() > 0).collect();
+    for file in &files {
+        if fs::read_to_string(&file.path)? != file.original {
+            return Err(anyhow::anyhow!("{} changed since the preview", file.path.display()));
+        }
+    }
+    let targets = files
+        .iter()
+        .map(|file| fs::canonicalize(&file.path))
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let backup_dir = backup_root.join(stamp.to_string());
+    fs::create_dir_all(&backup_dir)?;
+    for (index, file) in files.iter().enumerate() {
+        fs::write(backup_dir.join(index.to_string()), &file.original)?;
+    }
+
+    let contents: Vec<String> = files.iter().map(|file| file.new_content()).collect();
+    let mut staged = Vec::with_capacity(files.len());
+    for (target, content) in targets.iter().zip(&contents) {
+        match stage(target, content) {
+            Ok(temp) => staged.push(temp),
+            Err(e) => {
+                for temp in &staged {
+                    let _ = fs::remove_file(temp);
+                }
+                return Err(e);
+            }
+        }
+    }
+    for (index, (target, temp)) in targets.iter().zip(&staged).enumerate() {
+        if let Err(e) = fs::rename(temp, target) {
+            for temp in &staged[index..] {
+                let _ = fs::remove_file(temp);
+            }
+            for (done, target) in files.iter().zip(&targets).take(index) {
+                let _ = write_atomically(target, &done.original);
+            }
+            return Err(e.into());
+        }
+    }
+    if let Err(e) = prune_backups(backup_root, MAX_REPLACE_BACKUPS) {
+        log::warn!("Could not delete old replace backups: {}", e);
+    }
+
+    Ok(ReplaceUndo {
+        files: files.iter().map(|file| file.path.clone()).collect(),
+        backup_dir,
+        written: contents.iter().map(|content| hash(content)).collect(),
+    })
+}
+
+/// Delete all but the newest `keep` backups below `backup_root`
+fn prune_backups(backup_root: &Path, keep: usize) -> Result<()> {
+    let mut backups: Vec<(u128, PathBuf)> = fs::read_dir(backup_root)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.file_name().to_str()?.parse().ok()?, entry.path())))
+        .collect();
+    backups.sort();
+    for (_, dir) in backups.iter().rev().skip(keep) {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Write `content` next to `path` with the same permissions, ready to rename over it
+fn stage(path: &Path, content: &str) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))?;
+    let temp = path.with_file_name(format!(".{}.cortex-replace", name.to_string_lossy()));
+    fs::write(&temp, content)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&temp, metadata.permissions())?;
+    }
+    Ok(temp)
+}
+
+/// Replace the file `path` names, following a symlink rather than replacing it
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let path = fs::canonicalize(path)?;
+    let temp = stage(&path, content)?;
+    fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_plan_apply_and_undo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        let original = "use old_name;\nfn f() {\n    old_name(1);\n    other();\n    old_name(2); old_name(3);\n}\n";
+        fs::write(&path, original).unwrap();
+        let untouched = dir.path().join("notes.txt");
+        fs::write(&untouched, "nothing here\n").unwrap();
+
+        let paths = [path.clone(), untouched];
+        let mut changes = plan_replacements(&paths, r"old_(\w+)", "new_$1").unwrap();
+        assert_eq!(changes.len(), 1);
+        let hunks = &changes[0].hunks;
+        assert_eq!(hunks.len(), 3);
+        assert_eq!(hunks[0].first_line, 0);
+        assert_eq!(hunks[2].old_text, "    old_name(2); old_name(3);\n");
+        assert_eq!(hunks[2].new_text, "    new_name(2); new_name(3);\n");
+        assert_eq!(changes[0].enabled_replacements(), 4);
+
+        assert!(plan_replacements(&paths, "old_(", "").is_err());
+
+        // Leave the second call alone
+        changes[0].hunks[1].enabled = false;
+        let backups = dir.path().join("backups");
+        let undo = apply_replacements(&changes, &backups).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "use new_name;\nfn f() {\n    old_name(1);\n    other();\n    new_name(2); new_name(3);\n}\n"
+        );
+        // Applying a stale plan again is refused
+        assert!(apply_replacements(&changes, &backups).is_err());
+
+        assert_eq!(undo.undo().unwrap(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[tokio::test]
+    async fn test_plan_changes_the_lines_search_found() {
+        use crate::search::{SearchEngine, SearchProgress};
+
+        fn criteria(pattern: &str, search_type: SearchType) -> SearchCriteria {
+            SearchCriteria {
+                pattern: pattern.to_string(),
+                search_type,
+                case_sensitive: false,
+                search_in_files: true,
+                include_hidden: false,
+                include_subdirs: true,
+                max_depth: None,
+                file_extensions: Vec::new(),
+                size_filter: None,
+                date_filter: None,
+                search_archives: false,
+                respect_ignore_files: false,
+                conditions: None,
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("unix.txt"), "todo\nTODO later\n  todo\nnot a todo\ntodo.\n").unwrap();
+        fs::write(dir.path().join("dos.txt"), "TODO\r\nTODO: fix\r\nmore\r\n").unwrap();
+
+        for (pattern, search_type) in [
+            ("todo", SearchType::Exact),
+            ("todo*", SearchType::Wildcard),
+            ("to?o", SearchType::Wildcard),
+            ("todo", SearchType::Contains),
+        ] {
+            let criteria = criteria(pattern, search_type);
+            let (tx, _rx) = tokio::sync::mpsc::unbounded_channel::<SearchProgress>();
+            let mut engine = SearchEngine::new(criteria.clone()).unwrap();
+            let results = engine.search(dir.path(), tx).await.unwrap();
+            let mut found: Vec<(PathBuf, usize)> = results
+                .iter()
+                .flat_map(|result| result.matches.iter().map(|hit| (result.path.clone(), hit.line_number.unwrap())))
+                .collect();
+            found.sort();
+
+            let paths: Vec<PathBuf> = results.iter().map(|result| result.path.clone()).collect();
+            let changes = plan_replacements(&paths, &content_regex(&criteria), "DONE").unwrap();
+            let mut planned: Vec<(PathBuf, usize)> = changes
+                .iter()
+                .flat_map(|file| file.hunks.iter().map(|hunk| (file.path.clone(), hunk.first_line + 1)))
+                .collect();
+            planned.sort();
+            assert_eq!(planned, found, "{:?} {}", criteria.search_type, pattern);
+        }
+
+        // Line ends stay put when a whole line is replaced
+        let dos = dir.path().join("dos.txt");
+        let pattern = content_regex(&criteria("todo", SearchType::Exact));
+        let changes = plan_replacements(&[dos], &pattern, "DONE").unwrap();
+        assert_eq!(changes[0].new_content(), "DONE\r\nTODO: fix\r\nmore\r\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_follows_symlinks_and_prunes_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&real, "old\n").unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let backups = dir.path().join("backups");
+        for stamp in 0..MAX_REPLACE_BACKUPS + 5 {
+            fs::create_dir_all(backups.join(stamp.to_string())).unwrap();
+        }
+        let changes = plan_replacements(std::slice::from_ref(&link), "old", "new").unwrap();
+        let undo = apply_replacements(&changes, &backups).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new\n");
+        assert_eq!(fs::read_dir(&backups).unwrap().count(), MAX_REPLACE_BACKUPS);
+        assert!(!backups.join("0").exists());
+
+        undo.undo().unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "old\n");
+    }
+}
@@ -0,0 +1,162 @@
+// Named searches kept between sessions, along with the last one run
+
+use crate::search::SearchCriteria;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Search criteria and the directory they run from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub root: PathBuf,
+    pub criteria: SearchCriteria,
+}
+
+/// The saved searches file, `searches.json` in the config directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SavedSearches {
+    /// Sorted by name
+    #[serde(default)]
+    searches: Vec<SavedSearch>,
+    #[serde(default)]
+    last: Option<SavedSearch>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl SavedSearches {
+    /// Load the user's saved searches; a missing or unreadable file gives none
+    pub fn load() -> Self {
+        let Some(path) = dirs::config_dir().map(|dir| dir.join("cortex").join("searches.json")) else {
+            return Self::default();
+        };
+        Self::load_from(&path).unwrap_or_else(|e| {
+            log::warn!("Could not read saved searches from {}: {}", path.display(), e);
+            Self {
+                path: Some(path),
+                ..Self::default()
+            }
+        })
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let mut searches: Self = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        searches.searches.sort_by(|a, b| a.name.cmp(&b.name));
+        searches.path = Some(path.to_path_buf());
+        Ok(searches)
+    }
+
+    /// Write the searches back to the file they were loaded from
+    pub fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No config directory for saved searches"))?;
+        fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    pub fn searches(&self) -> &[SavedSearch] {
+        &self.searches
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedSearch> {
+        self.searches.iter().find(|search| search.name == name)
+    }
+
+    /// Add a search, replacing any with the same name
+    pub fn insert(&mut self, search: SavedSearch) {
+        match self.searches.binary_search_by(|probe| probe.name.cmp(&search.name)) {
+            Ok(index) => self.searches[index] = search,
+            Err(index) => self.searches.insert(index, search),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.searches.len();
+        self.searches.retain(|search| search.name != name);
+        self.searches.len() != before
+    }
+
+    pub fn last(&self) -> Option<&SavedSearch> {
+        self.last.as_ref()
+    }
+
+    pub fn set_last(&mut self, root: PathBuf, criteria: SearchCriteria) {
+        self.last = Some(SavedSearch {
+            name: String::new(),
+            root,
+            criteria,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchType;
+    use crate::search_condition::SearchCondition;
+
+    #[test]
+    fn test_saved_searches_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("searches.json");
+        let criteria = SearchCriteria {
+            pattern: "*.log".to_string(),
+            search_type: SearchType::Wildcard,
+            case_sensitive: false,
+            search_in_files: false,
+            include_hidden: false,
+            include_subdirs: true,
+            max_depth: None,
+            file_extensions: Vec::new(),
+            size_filter: None,
+            date_filter: None,
+            search_archives: false,
+            respect_ignore_files: true,
+            conditions: Some(SearchCondition::parse("empty or owner:root").unwrap()),
+        };
+
+        let mut searches = SavedSearches::load_from(&path).unwrap();
+        assert!(searches.searches().is_empty());
+        for name in ["weekly logs", "core dumps"] {
+            searches.insert(SavedSearch {
+                name: name.to_string(),
+                root: PathBuf::from("/var/log"),
+                criteria: criteria.clone(),
+            });
+        }
+        searches.insert(SavedSearch {
+            name: "weekly logs".to_string(),
+            root: PathBuf::from("/srv/log"),
+            criteria: criteria.clone(),
+        });
+        searches.set_last(PathBuf::from("/tmp"), criteria);
+        searches.save().unwrap();
+
+        let loaded = SavedSearches::load_from(&path).unwrap();
+        let names: Vec<&str> = loaded.searches().iter().map(|search| search.name.as_str()).collect();
+        assert_eq!(names, vec!["core dumps", "weekly logs"]);
+        let weekly = loaded.get("weekly logs").unwrap();
+        assert_eq!(weekly.root, PathBuf::from("/srv/log"));
+        assert_eq!(weekly.criteria.pattern, "*.log");
+        assert_eq!(
+            weekly.criteria.conditions,
+            Some(SearchCondition::parse("empty or owner:root").unwrap())
+        );
+        assert_eq!(loaded.last().unwrap().root, PathBuf::from("/tmp"));
+
+        let mut loaded = loaded;
+        assert!(loaded.remove("core dumps"));
+        assert!(!loaded.remove("core dumps"));
+    }
+}
@@ -1,9 +1,18 @@
+use crate::async_vfs::{AsyncVirtualFileSystem, CancellationToken, ListingUpdate};
+use crate::content_index::{ContentIndex, IndexFilter};
+use crate::search_condition::{CompiledCondition, LocalSubject, SearchCondition, VfsSubject};
+use crate::vfs::{is_archive, member_entry, scan_archive, VfsEntry, VfsEntryType, VfsPath};
 use anyhow::Result;
+use ignore::{WalkBuilder, WalkState};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::fmt;
+use std::time::{Duration, SystemTime};
 use tokio::sync::mpsc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +27,15 @@ pub struct SearchCriteria {
     pub file_extensions: Vec<String>,
     pub size_filter: Option<SizeFilter>,
     pub date_filter: Option<DateFilter>,
+    /// Descend into zip and tar archives found along the way
+    #[serde(default)]
+    pub search_archives: bool,
+    /// Skip what `.gitignore`, `.ignore` and global git excludes leave out
+    #[serde(default)]
+    pub respect_ignore_files: bool,
+    /// Further conditions on type, owner, permissions, link state or contents
+    #[serde(default)]
+    pub conditions: Option<SearchCondition>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,16 +46,162 @@ pub enum SearchType {
     Contains, // Contains substring
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SizeFilter {
     pub min_size: Option<u64>,
     pub max_size: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DateFilter {
-    pub after: Option<SystemTime>,
-    pub before: Option<SystemTime>,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DateFilter {
+    /// Modified between two points in time
+    Range {
+        after: Option<SystemTime>,
+        before: Option<SystemTime>,
+    },
+    /// Modified at most this long before the search starts
+    Within(Duration),
+    /// Modified at least this long before the search starts
+    OlderThan(Duration),
+}
+
+const SIZE_UNITS: [(char, u64); 3] = [('G', 1 << 30), ('M', 1 << 20), ('K', 1 << 10)];
+const AGE_UNITS: [(char, u64); 5] = [('y', 365 * 86400), ('w', 7 * 86400), ('d', 86400), ('h', 3600), ('m', 60)];
+
+impl SizeFilter {
+    /// Parse `>10M`, `<512K` or `10K..2M` with either end left open; sizes
+    /// take K, M and G suffixes
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let (min, max) = if let Some(min) = text.strip_prefix('>') {
+            (min, "")
+        } else if let Some(max) = text.strip_prefix('<') {
+            ("", max)
+        } else {
+            text.split_once("..")
+                .ok_or_else(|| anyhow::anyhow!("Sizes look like >10M, <512K or 10K..2M"))?
+        };
+        let size = |text: &str| -> Result<Option<u64>> {
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok(None);
+            }
+            let (number, unit) = match SIZE_UNITS.iter().find(|(suffix, _)| text.to_uppercase().ends_with(*suffix)) {
+                Some((_, unit)) => (&text[..text.len() - 1], *unit),
+                None => (text, 1),
+            };
+            let number: u64 = number.trim().parse().map_err(|_| anyhow::anyhow!("Not a size: {}", text))?;
+            number
+                .checked_mul(unit)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("Size too large: {}", text))
+        };
+        Ok(Self {
+            min_size: size(min)?,
+            max_size: size(max)?,
+        })
+    }
+}
+
+impl fmt::Display for SizeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = |bytes: Option<u64>| match bytes {
+            Some(bytes) => match SIZE_UNITS.iter().find(|(_, unit)| bytes > 0 && bytes % unit == 0) {
+                Some((suffix, unit)) => format!("{}{}", bytes / unit, suffix),
+                None => bytes.to_string(),
+            },
+            None => String::new(),
+        };
+        write!(f, "{}..{}", size(self.min_size), size(self.max_size))
+    }
+}
+
+impl DateFilter {
+    /// Parse an age such as `7d`, `12h` or `2w` (`<7d` alike, `>7d` for older
+    /// than that), or local dates as `2024-01-01..2024-06-30` with either end left open
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if let Some((from, to)) = text.split_once("..") {
+            // The end date is included up to its last second
+            let before = parse_date(to)?.map(|day| day + Duration::from_secs(86400));
+            return Ok(Self::Range {
+                after: parse_date(from)?,
+                before,
+            });
+        }
+        if let Some(age) = text.strip_prefix('>') {
+            return Ok(Self::OlderThan(parse_age(age)?));
+        }
+        Ok(Self::Within(parse_age(text.strip_prefix('<').unwrap_or(text))?))
+    }
+
+    /// The oldest and newest modification times let through by a search
+    /// starting at `now`
+    pub fn bounds(&self, now: SystemTime) -> (Option<SystemTime>, Option<SystemTime>) {
+        match self {
+            Self::Range { after, before } => (*after, *before),
+            Self::Within(age) => (now.checked_sub(*age), None),
+            Self::OlderThan(age) => (None, Some(now.checked_sub(*age).unwrap_or(SystemTime::UNIX_EPOCH))),
+        }
+    }
+}
+
+impl fmt::Display for DateFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Within(age) => write!(f, "{}", format_age(*age)),
+            Self::OlderThan(age) => write!(f, ">{}", format_age(*age)),
+            Self::Range { after, before } => {
+                let date = |time: Option<SystemTime>| {
+                    time.map(|time| chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d").to_string())
+                        .unwrap_or_default()
+                };
+                let last_day = before.and_then(|before| before.checked_sub(Duration::from_secs(1)));
+                write!(f, "{}..{}", date(*after), date(last_day))
+            }
+        }
+    }
+}
+
+/// An age such as `7d` or `12h`
+fn parse_age(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let (number, unit) = text
+        .char_indices()
+        .last()
+        .and_then(|(at, suffix)| Some((&text[..at], AGE_UNITS.iter().find(|(unit, _)| *unit == suffix)?.1)))
+        .ok_or_else(|| anyhow::anyhow!("Dates look like 7d, >7d, 12h, 2w or 2024-01-01..2024-06-30"))?;
+    let number: u64 = number.trim().parse().map_err(|_| anyhow::anyhow!("Not an age: {}", text))?;
+    number
+        .checked_mul(unit)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow::anyhow!("Age too large: {}", text))
+}
+
+/// An age in its largest whole unit, minutes at the least
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match AGE_UNITS.iter().find(|(_, unit)| secs > 0 && secs % unit == 0) {
+        Some((suffix, unit)) => format!("{}{}", secs / unit, suffix),
+        None => format!("{}m", secs.div_ceil(60)),
+    }
+}
+
+/// Local midnight at the start of a `YYYY-MM-DD` date, `None` when empty
+fn parse_date(text: &str) -> Result<Option<SystemTime>> {
+    use chrono::TimeZone;
+
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let day = chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Not a date: {}", text))?;
+    chrono::Local
+        .from_local_datetime(&day.and_time(chrono::NaiveTime::MIN))
+        .earliest()
+        .map(|time| Some(time.into()))
+        .ok_or_else(|| anyhow::anyhow!("No local midnight on {}", text))
 }
 
 #[derive(Debug, Clone)]
@@ -46,6 +210,9 @@ pub struct SearchResult {
     pub size: u64,
     pub modified: SystemTime,
     pub matches: Vec<Match>,
+    /// Where the file lives when it is not a plain local file; `path` is then
+    /// only a readable rendering of it
+    pub location: Option<VfsPath>,
 }
 
 #[derive(Debug, Clone)]
@@ -79,12 +246,49 @@ pub enum SearchProgress {
 }
 
 pub struct SearchEngine {
-    criteria: SearchCriteria,
-    pattern_matcher: Box<dyn PatternMatcher>,
+    matcher: FileMatcher,
     results: Vec<SearchResult>,
-    cancelled: bool,
+    cancelled: CancellationToken,
+    threads: usize,
+    vfs: Option<Arc<AsyncVirtualFileSystem>>,
 }
 
+/// What a search looks for; shared with the traversal threads
+#[derive(Clone)]
+struct FileMatcher {
+    criteria: SearchCriteria,
+    pattern_matcher: Arc<dyn PatternMatcher>,
+    index_filter: Option<Arc<IndexFilter>>,
+    condition: Option<CompiledCondition>,
+    /// Modification time bounds of the date filter, fixed when a search starts
+    modified_bounds: (Option<SystemTime>, Option<SystemTime>),
+}
+
+/// Contents of archive members and remote files above this size are not searched
+const MAX_VFS_CONTENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Archives inside archives are searched down to this many levels
+const MAX_ARCHIVE_NESTING: usize = 4;
+
+/// Where the members of an archive being scanned live
+struct ArchiveScope {
+    /// Local archive the member paths belong to, or a readable rendering of a
+    /// remote one
+    archive_path: PathBuf,
+    /// Path of a nested archive inside `archive_path`, put before its members
+    prefix: String,
+    /// Only members below this path are searched
+    within: String,
+    /// What results open: `None` for members of a local archive, which the
+    /// VFS reads directly, otherwise the outermost file it can read
+    location: Option<VfsPath>,
+    /// Archives opened to reach these members
+    nesting: usize,
+}
+
+/// Upper bound on traversal threads, so a search leaves the UI some cores
+const MAX_SEARCH_THREADS: usize = 8;
+
 trait PatternMatcher: Send + Sync {
     fn matches(&self, text: &str) -> bool;
 }
@@ -164,8 +368,8 @@ impl PatternMatcher for ContainsMatcher {
 
 impl SearchEngine {
     pub fn new(criteria: SearchCriteria) -> Result<Self> {
-        let pattern_matcher: Box<dyn PatternMatcher> = match &criteria.search_type {
-            SearchType::Wildcard => Box::new(WildcardMatcher {
+        let pattern_matcher: Arc<dyn PatternMatcher> = match &criteria.search_type {
+            SearchType::Wildcard => Arc::new(WildcardMatcher {
                 pattern: criteria.pattern.clone(),
                 case_sensitive: criteria.case_sensitive,
             }),
@@ -175,28 +379,71 @@ impl SearchEngine {
                 } else {
                     format!("(?i){}", criteria.pattern)
                 };
-                Box::new(RegexMatcher {
+                Arc::new(RegexMatcher {
                     regex: Regex::new(&pattern)?,
                 })
             }
-            SearchType::Exact => Box::new(ExactMatcher {
+            SearchType::Exact => Arc::new(ExactMatcher {
                 pattern: criteria.pattern.clone(),
                 case_sensitive: criteria.case_sensitive,
             }),
-            SearchType::Contains => Box::new(ContainsMatcher {
+            SearchType::Contains => Arc::new(ContainsMatcher {
                 pattern: criteria.pattern.clone(),
                 case_sensitive: criteria.case_sensitive,
             }),
         };
 
+        let condition = criteria
+            .conditions
+            .as_ref()
+            .map(|condition| condition.compile(criteria.case_sensitive))
+            .transpose()?;
+
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get().min(MAX_SEARCH_THREADS))
+            .unwrap_or(1);
+
         Ok(Self {
-            criteria,
-            pattern_matcher,
+            matcher: FileMatcher {
+                criteria,
+                pattern_matcher,
+                index_filter: None,
+                condition,
+                modified_bounds: (None, None),
+            },
             results: Vec::new(),
-            cancelled: false,
+            cancelled: CancellationToken::new(),
+            threads,
+            vfs: None,
         })
     }
 
+    /// Use `vfs` for archives and remote paths, sharing its connections
+    pub fn with_vfs(mut self, vfs: Arc<AsyncVirtualFileSystem>) -> Self {
+        self.vfs = Some(vfs);
+        self
+    }
+
+    /// Skip reading files the content index rules out
+    pub fn with_index(mut self, index: &Arc<ContentIndex>) -> Self {
+        if self.matcher.criteria.search_in_files {
+            self.matcher.index_filter = index.filter(&self.matcher.criteria).map(Arc::new);
+        }
+        self
+    }
+
+    /// Stop searching once `cancel` is cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancelled = cancel;
+        self
+    }
+
+    /// Walk directories on at most `threads` threads
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
     pub async fn search(
         &mut self,
         start_path: &Path,
@@ -204,16 +451,27 @@ impl SearchEngine {
     ) -> Result<Vec<SearchResult>> {
         let start_time = std::time::Instant::now();
         self.results.clear();
-        self.cancelled = false;
+        self.matcher.resolve_dates();
 
-        // Count total directories for progress
-        let total_dirs = self.count_directories(start_path, 0)?;
-        let _ = progress_sender.send(SearchProgress::Started { total_dirs });
+        let matcher = self.matcher.clone();
+        let cancelled = self.cancelled.clone();
+        let threads = self.threads;
+        let root = start_path.to_path_buf();
+        let sender = progress_sender.clone();
+        let (mut found, mut archives) = tokio::task::spawn_blocking(move || {
+            // Count total directories for progress
+            let total_dirs = matcher.count_directories(&root, &cancelled);
+            let _ = sender.send(SearchProgress::Started { total_dirs });
+            matcher.walk(&root, threads, total_dirs, &sender, &cancelled)
+        })
+        .await?;
+
+        // Workers finish in any order; report a stable one
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+        self.results = found;
 
-        // Perform search
-        let mut searched = 0;
-        self.search_recursive(start_path, &progress_sender, &mut searched, total_dirs, 0)
-            .await?;
+        archives.sort();
+        self.search_local_archives(archives, &progress_sender).await;
 
         // Send completion
         let _ = progress_sender.send(SearchProgress::Completed {
@@ -224,112 +482,606 @@ impl SearchEngine {
         Ok(self.results.clone())
     }
 
-    async fn search_recursive(
+    /// Check only `files`, e.g. to refine the results of an earlier search
+    pub async fn search_files(
+        &mut self,
+        files: &[PathBuf],
+        progress_sender: mpsc::UnboundedSender<SearchProgress>,
+    ) -> Result<Vec<SearchResult>> {
+        let start_time = std::time::Instant::now();
+        self.results.clear();
+        self.matcher.resolve_dates();
+        let _ = progress_sender.send(SearchProgress::Started { total_dirs: files.len() });
+
+        for (searched, path) in files.iter().enumerate() {
+            if self.is_cancelled(&progress_sender) {
+                break;
+            }
+            let _ = progress_sender.send(SearchProgress::Searching {
+                current_path: path.clone(),
+                searched: searched + 1,
+                total: files.len(),
+            });
+            match fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.is_file() || self.matcher.covers_non_files() => {
+                    self.check_file(path, &metadata, &progress_sender).await?
+                }
+                _ => continue,
+            }
+        }
+
+        let _ = progress_sender.send(SearchProgress::Completed {
+            total_found: self.results.len(),
+            elapsed_ms: start_time.elapsed().as_millis(),
+        });
+
+        Ok(self.results.clone())
+    }
+
+    async fn check_file(
         &mut self,
         path: &Path,
+        metadata: &fs::Metadata,
         progress_sender: &mpsc::UnboundedSender<SearchProgress>,
-        searched: &mut usize,
-        total: usize,
-        depth: usize,
     ) -> Result<()> {
-        if self.cancelled {
-            return Ok(());
+        if let Some(result) = self.matcher.check_local(path, metadata) {
+            let _ = progress_sender.send(SearchProgress::Found {
+                result: result.clone(),
+            });
+            self.results.push(result);
         }
 
-        // Check max depth
-        if let Some(max_depth) = self.criteria.max_depth {
-            if depth > max_depth {
-                return Ok(());
+        if self.matcher.criteria.search_archives && metadata.is_file() && is_archive(path) {
+            self.search_local_archives(vec![path.to_path_buf()], progress_sender).await;
+        }
+        Ok(())
+    }
+
+    async fn search_local_archives(
+        &mut self,
+        archives: Vec<PathBuf>,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+    ) {
+        for archive in archives {
+            if self.is_cancelled(progress_sender) {
+                break;
             }
+            let file = match fs::File::open(&archive) {
+                Ok(file) => std::io::BufReader::new(file),
+                Err(e) => {
+                    let _ = progress_sender.send(SearchProgress::Error {
+                        path: archive,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let scope = ArchiveScope {
+                archive_path: archive.clone(),
+                prefix: String::new(),
+                within: String::new(),
+                location: None,
+                nesting: 1,
+            };
+            self.search_archive(archive, file, scope, progress_sender).await;
         }
+    }
 
-        // Send progress
-        *searched += 1;
-        let _ = progress_sender.send(SearchProgress::Searching {
-            current_path: path.to_path_buf(),
-            searched: *searched,
-            total,
+    /// Search an archive found in a VFS listing; archives that are not local
+    /// files are downloaded first
+    async fn search_vfs_archive(&mut self, entry: &VfsEntry, progress_sender: &mpsc::UnboundedSender<SearchProgress>) {
+        if let VfsPath::Local(path) = &entry.path {
+            return self.search_local_archives(vec![path.clone()], progress_sender).await;
+        }
+        if entry.size > MAX_VFS_CONTENT_SIZE {
+            return;
+        }
+        let Some(content) = self.read_vfs_file(&entry.path, progress_sender).await else {
+            return;
+        };
+        let (archive_path, prefix) = match &entry.path {
+            VfsPath::Archive { archive_path, internal_path } => (archive_path.clone(), internal_path.clone()),
+            other => (display_path(other), String::new()),
+        };
+        let scope = ArchiveScope {
+            archive_path,
+            prefix,
+            within: String::new(),
+            location: Some(entry.path.clone()),
+            nesting: 1,
+        };
+        let name = PathBuf::from(&entry.name);
+        self.search_archive(name, std::io::Cursor::new(content), scope, progress_sender).await;
+    }
+
+    /// Run `FileMatcher::search_archive` away from the async threads
+    async fn search_archive(
+        &mut self,
+        name: PathBuf,
+        source: impl Read + Seek + Send + 'static,
+        scope: ArchiveScope,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+    ) {
+        let matcher = self.matcher.clone();
+        let cancelled = self.cancelled.clone();
+        let sender = progress_sender.clone();
+        let error_path = scope.location.as_ref().map_or_else(|| scope.archive_path.clone(), display_path);
+        let searched = tokio::task::spawn_blocking(move || {
+            let mut found = Vec::new();
+            let result = matcher.search_archive(&name, source, &scope, &mut found, &sender, &cancelled);
+            (found, result)
+        })
+        .await;
+        let error = match searched {
+            Ok((found, result)) => {
+                self.results.extend(found);
+                result.err()
+            }
+            Err(e) => Some(e.into()),
+        };
+        if let Some(e) = error {
+            let _ = progress_sender.send(SearchProgress::Error {
+                path: error_path,
+                error: e.to_string(),
+            });
+        }
+    }
+
+    /// Search below any VFS location, e.g. a remote directory or an archive
+    pub async fn search_vfs(
+        &mut self,
+        start_path: &VfsPath,
+        progress_sender: mpsc::UnboundedSender<SearchProgress>,
+    ) -> Result<Vec<SearchResult>> {
+        let start_time = std::time::Instant::now();
+        self.results.clear();
+        self.matcher.resolve_dates();
+
+        // Remote trees are not counted up front, so progress has no total
+        let _ = progress_sender.send(SearchProgress::Started { total_dirs: 0 });
+        match start_path {
+            // Read the archive once instead of listing it directory by directory
+            VfsPath::Archive { archive_path, internal_path } => {
+                let file = std::io::BufReader::new(fs::File::open(archive_path)?);
+                let scope = ArchiveScope {
+                    archive_path: archive_path.clone(),
+                    prefix: String::new(),
+                    within: internal_path.trim_matches('/').to_string(),
+                    location: None,
+                    nesting: 1,
+                };
+                self.search_archive(archive_path.clone(), file, scope, &progress_sender).await;
+            }
+            _ => self.search_vfs_dir(start_path, &progress_sender, &mut 0, 0).await?,
+        }
+
+        let _ = progress_sender.send(SearchProgress::Completed {
+            total_found: self.results.len(),
+            elapsed_ms: start_time.elapsed().as_millis(),
         });
 
-        // Read directory
-        let entries = match fs::read_dir(path) {
-            Ok(entries) => entries,
+        Ok(self.results.clone())
+    }
+
+    fn vfs(&self) -> Result<Arc<AsyncVirtualFileSystem>> {
+        self.vfs
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Searching remote paths needs SearchEngine::with_vfs"))
+    }
+
+    /// Read a remote file or archive member, reporting a failure as a search error
+    async fn read_vfs_file(
+        &self,
+        path: &VfsPath,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+    ) -> Option<Vec<u8>> {
+        let read = match self.vfs() {
+            Ok(vfs) => vfs.read_file(path, self.cancelled.clone()).await,
+            Err(e) => Err(e),
+        };
+        match read {
+            Ok(content) => Some(content),
             Err(e) => {
                 let _ = progress_sender.send(SearchProgress::Error {
-                    path: path.to_path_buf(),
+                    path: display_path(path),
                     error: e.to_string(),
                 });
-                return Ok(());
+                None
             }
-        };
+        }
+    }
 
-        for entry in entries {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
+    /// Cancelled explicitly, or nobody is listening for results any more
+    fn is_cancelled(&self, progress_sender: &mpsc::UnboundedSender<SearchProgress>) -> bool {
+        self.cancelled.is_cancelled() || progress_sender.is_closed()
+    }
 
-            let entry_path = entry.path();
-            let metadata = match entry.metadata() {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
+    async fn search_vfs_dir(
+        &mut self,
+        dir: &VfsPath,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+        searched: &mut usize,
+        depth: usize,
+    ) -> Result<()> {
+        let criteria = &self.matcher.criteria;
+        if self.is_cancelled(progress_sender) || criteria.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return Ok(());
+        }
+
+        *searched += 1;
+        let _ = progress_sender.send(SearchProgress::Searching {
+            current_path: display_path(dir),
+            searched: *searched,
+            total: 0,
+        });
 
-            // Skip hidden files if requested
-            if !self.criteria.include_hidden {
-                if let Some(name) = entry_path.file_name() {
-                    if name.to_string_lossy().starts_with('.') {
-                        continue;
+        let mut listing = self.vfs()?.list_entries(dir.clone());
+        let mut entries = Vec::new();
+        while let Some(update) = listing.next().await {
+            match update {
+                ListingUpdate::Entries(batch) => entries.extend(batch),
+                ListingUpdate::Complete => break,
+                ListingUpdate::Failed(e) => {
+                    let _ = progress_sender.send(SearchProgress::Error {
+                        path: display_path(dir),
+                        error: e.to_string(),
+                    });
+                    return Ok(());
+                }
+            }
+        }
+
+        for entry in entries {
+            let criteria = &self.matcher.criteria;
+            if entry.name == ".." || (!criteria.include_hidden && entry.name.starts_with('.')) {
+                continue;
+            }
+            match entry.entry_type {
+                VfsEntryType::Directory => {
+                    if self.matcher.covers_non_files() {
+                        self.check_vfs_entry(&entry, progress_sender).await;
+                    }
+                    if self.matcher.criteria.include_subdirs {
+                        Box::pin(self.search_vfs_dir(&entry.path, progress_sender, searched, depth + 1))
+                            .await?;
+                    }
+                }
+                _ => {
+                    self.check_vfs_entry(&entry, progress_sender).await;
+                    if self.matcher.criteria.search_archives && is_archive(Path::new(&entry.name)) {
+                        self.search_vfs_archive(&entry, progress_sender).await;
                     }
                 }
             }
+        }
+        Ok(())
+    }
+
+    async fn check_vfs_entry(
+        &mut self,
+        entry: &VfsEntry,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+    ) {
+        if self.is_cancelled(progress_sender)
+            || !self.matcher.matches_file(Path::new(&entry.name), entry.size, Some(entry.modified))
+        {
+            return;
+        }
+        let content = match self.matcher.needs_content(entry) {
+            true => self.read_vfs_file(&entry.path, progress_sender).await,
+            false => None,
+        };
+        if let Some(result) = self.matcher.check_vfs(entry, content.as_deref()) {
+            let _ = progress_sender.send(SearchProgress::Found {
+                result: result.clone(),
+            });
+            self.results.push(result);
+        }
+    }
+
+    /// Stop the running search; it returns what was found so far
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    pub fn get_results(&self) -> &[SearchResult] {
+        &self.results
+    }
+}
+
+impl FileMatcher {
+    /// Directory walker honouring the hidden, depth and ignore-file options
+    fn walk_builder(&self, root: &Path) -> WalkBuilder {
+        let criteria = &self.criteria;
+        let respect_ignore = criteria.respect_ignore_files;
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .hidden(!criteria.include_hidden)
+            .ignore(respect_ignore)
+            .git_ignore(respect_ignore)
+            .git_global(respect_ignore)
+            .git_exclude(respect_ignore)
+            .parents(respect_ignore)
+            // A .gitignore applies even outside a repository, like .ignore does
+            .require_git(false)
+            .follow_links(false)
+            // Files in the start directory are at walker depth 1
+            .max_depth(match criteria.include_subdirs {
+                true => criteria.max_depth.map(|depth| depth + 1),
+                false => Some(1),
+            });
+        if respect_ignore {
+            builder.filter_entry(|entry| entry.file_name() != ".git");
+        }
+        builder
+    }
 
-            if metadata.is_dir() {
-                // Recursively search subdirectories
-                if self.criteria.include_subdirs {
-                    Box::pin(self.search_recursive(
-                        &entry_path,
-                        progress_sender,
-                        searched,
+    fn count_directories(&self, root: &Path, cancelled: &CancellationToken) -> usize {
+        self.walk_builder(root)
+            .build()
+            .take_while(|_| !cancelled.is_cancelled())
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_dir()))
+            .count()
+    }
+
+    /// Check every file below `root` in parallel, returning the matches and
+    /// the archives to descend into afterwards
+    fn walk(
+        &self,
+        root: &Path,
+        threads: usize,
+        total: usize,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+        cancelled: &CancellationToken,
+    ) -> (Vec<SearchResult>, Vec<PathBuf>) {
+        let found = Mutex::new(Vec::new());
+        let archives = Mutex::new(Vec::new());
+        let searched = AtomicUsize::new(0);
+
+        self.walk_builder(root).threads(threads).build_parallel().run(|| {
+            Box::new(|entry| {
+                if cancelled.is_cancelled() || progress_sender.is_closed() {
+                    return WalkState::Quit;
+                }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        let _ = progress_sender.send(SearchProgress::Error {
+                            path: error_path(&e).unwrap_or(root).to_path_buf(),
+                            error: e.to_string(),
+                        });
+                        return WalkState::Continue;
+                    }
+                };
+                let Some(file_type) = entry.file_type() else {
+                    return WalkState::Continue;
+                };
+
+                if file_type.is_dir() {
+                    let _ = progress_sender.send(SearchProgress::Searching {
+                        current_path: entry.path().to_path_buf(),
+                        searched: searched.fetch_add(1, Ordering::Relaxed) + 1,
                         total,
-                        depth + 1,
-                    ))
-                    .await?;
+                    });
                 }
-            } else if metadata.is_file() {
-                // Check if file matches criteria
-                if self.matches_file(&entry_path, &metadata)? {
-                    let mut result = SearchResult {
-                        path: entry_path.clone(),
-                        size: metadata.len(),
-                        modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
-                        matches: Vec::new(),
+                let candidate = file_type.is_file()
+                    || (self.covers_non_files() && (file_type.is_symlink() || entry.depth() > 0));
+                if candidate {
+                    let Ok(metadata) = entry.metadata() else {
+                        return WalkState::Continue;
                     };
-
-                    // Search inside file if requested
-                    if self.criteria.search_in_files {
-                        self.search_in_file(&entry_path, &mut result)?;
+                    if let Some(result) = self.check_local(entry.path(), &metadata) {
+                        let _ = progress_sender.send(SearchProgress::Found {
+                            result: result.clone(),
+                        });
+                        found.lock().unwrap().push(result);
                     }
+                    if self.criteria.search_archives && file_type.is_file() && is_archive(entry.path()) {
+                        archives.lock().unwrap().push(entry.into_path());
+                    }
+                }
+                WalkState::Continue
+            })
+        });
+
+        (found.into_inner().unwrap(), archives.into_inner().unwrap())
+    }
+
+    /// The result for a local entry, if it matches. `metadata` is the entry's
+    /// own, so symlinks are judged as links
+    fn check_local(&self, path: &Path, metadata: &fs::Metadata) -> Option<SearchResult> {
+        if !self.matches_file(path, metadata.len(), metadata.modified().ok()) {
+            return None;
+        }
+        let subject = LocalSubject::new(path, metadata);
+        if self.condition.as_ref().is_some_and(|condition| !condition.matches(&subject)) {
+            return None;
+        }
+        let mut result = SearchResult {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            matches: Vec::new(),
+            location: None,
+        };
+
+        // Search inside file if requested
+        if self.criteria.search_in_files && !self.criteria.pattern.is_empty() {
+            let may_match = self
+                .index_filter
+                .as_ref()
+                .is_none_or(|filter| filter.may_match(path, metadata));
+            if may_match {
+                if let Some(content) = subject.read_text() {
+                    self.search_in_text(content, &mut result);
+                }
+            }
+            if !self.name_matches(path) && result.matches.is_empty() {
+                return None;
+            }
+        }
+        Some(result)
+    }
+
+    /// Fix the date filter's bounds as of now, so ages count from the start of the search
+    fn resolve_dates(&mut self) {
+        self.modified_bounds = match &self.criteria.date_filter {
+            Some(filter) => filter.bounds(SystemTime::now()),
+            None => (None, None),
+        };
+    }
+
+    /// Whether checking `entry` takes its contents
+    fn needs_content(&self, entry: &VfsEntry) -> bool {
+        let criteria = &self.criteria;
+        matches!(entry.entry_type, VfsEntryType::File)
+            && entry.size <= MAX_VFS_CONTENT_SIZE
+            && ((criteria.search_in_files && !criteria.pattern.is_empty())
+                || criteria.conditions.as_ref().is_some_and(|condition| condition.needs_content()))
+    }
+
+    /// The result for an archive member or remote entry that passed
+    /// `matches_file`, given its contents when `needs_content` asks for them
+    fn check_vfs(&self, entry: &VfsEntry, content: Option<&[u8]>) -> Option<SearchResult> {
+        if let Some(condition) = &self.condition {
+            if !condition.matches(&VfsSubject { entry, content }) {
+                return None;
+            }
+        }
+        let mut result = SearchResult {
+            path: display_path(&entry.path),
+            size: entry.size,
+            modified: entry.modified,
+            matches: Vec::new(),
+            location: Some(entry.path.clone()),
+        };
+        if self.criteria.search_in_files && !self.criteria.pattern.is_empty() {
+            if let Some(text) = content.and_then(|content| std::str::from_utf8(content).ok()) {
+                self.search_in_text(text, &mut result);
+            }
+            if !self.name_matches(Path::new(&entry.name)) && result.matches.is_empty() {
+                return None;
+            }
+        }
+        Some(result)
+    }
 
+    /// Search the members of an archive in one pass as they are decoded,
+    /// descending into the archives among them
+    fn search_archive(
+        &self,
+        name: &Path,
+        source: impl Read + Seek,
+        scope: &ArchiveScope,
+        found: &mut Vec<SearchResult>,
+        progress_sender: &mpsc::UnboundedSender<SearchProgress>,
+        cancelled: &CancellationToken,
+    ) -> Result<()> {
+        let criteria = &self.criteria;
+        scan_archive(name, source, |member, reader| {
+            if cancelled.is_cancelled() || progress_sender.is_closed() {
+                return false;
+            }
+            let relative = match scope.within.is_empty() {
+                true => Some(member.path.as_str()),
+                false => member
+                    .path
+                    .strip_prefix(&scope.within)
+                    .and_then(|rest| rest.strip_prefix('/')),
+            };
+            let Some(relative) = relative else {
+                return true;
+            };
+            let depth = relative.matches('/').count();
+            if (!criteria.include_subdirs && depth > 0)
+                || criteria.max_depth.is_some_and(|max_depth| depth > max_depth)
+                || (!criteria.include_hidden && relative.split('/').any(|part| part.starts_with('.')))
+            {
+                return true;
+            }
+
+            let internal_path = match scope.prefix.is_empty() {
+                true => member.path.clone(),
+                false => format!("{}/{}", scope.prefix, member.path),
+            };
+            let entry = member_entry(&scope.archive_path, internal_path.clone(), Some(member));
+            let nested = !member.is_dir
+                && is_archive(Path::new(&member.path))
+                && scope.nesting < MAX_ARCHIVE_NESTING
+                && member.size <= MAX_VFS_CONTENT_SIZE;
+            let wanted = (!member.is_dir || self.covers_non_files())
+                && self.matches_file(Path::new(&entry.name), entry.size, Some(entry.modified));
+            if !nested && !wanted {
+                return true;
+            }
+
+            let mut content = None;
+            if nested || (wanted && self.needs_content(&entry)) {
+                let mut data = Vec::new();
+                match reader.take(MAX_VFS_CONTENT_SIZE).read_to_end(&mut data) {
+                    Ok(_) => content = Some(data),
+                    Err(e) => {
+                        let _ = progress_sender.send(SearchProgress::Error {
+                            path: display_path(&entry.path),
+                            error: e.to_string(),
+                        });
+                        return true;
+                    }
+                }
+            }
+            let location = scope.location.clone().unwrap_or_else(|| entry.path.clone());
+            if wanted {
+                if let Some(mut result) = self.check_vfs(&entry, content.as_deref()) {
+                    result.location = Some(location.clone());
                     let _ = progress_sender.send(SearchProgress::Found {
                         result: result.clone(),
                     });
-
-                    self.results.push(result);
+                    found.push(result);
                 }
             }
-        }
+            if let Some(content) = content.filter(|_| nested) {
+                let inner = ArchiveScope {
+                    archive_path: scope.archive_path.clone(),
+                    prefix: internal_path,
+                    within: String::new(),
+                    location: Some(location),
+                    nesting: scope.nesting + 1,
+                };
+                let source = std::io::Cursor::new(content);
+                let name = Path::new(&member.path);
+                let searched = self.search_archive(name, source, &inner, found, progress_sender, cancelled);
+                if let Err(e) = searched {
+                    let _ = progress_sender.send(SearchProgress::Error {
+                        path: display_path(&entry.path),
+                        error: e.to_string(),
+                    });
+                }
+            }
+            true
+        })
+    }
 
-        Ok(())
+    /// Directories and symlinks are only results when a condition asks for them
+    fn covers_non_files(&self) -> bool {
+        self.criteria
+            .conditions
+            .as_ref()
+            .is_some_and(|condition| condition.covers_non_files())
+    }
+
+    /// An empty pattern leaves the choice to the other criteria
+    fn name_matches(&self, path: &Path) -> bool {
+        self.criteria.pattern.is_empty()
+            || path
+                .file_name()
+                .is_some_and(|name| self.pattern_matcher.matches(&name.to_string_lossy()))
     }
 
-    fn matches_file(&self, path: &Path, metadata: &fs::Metadata) -> Result<bool> {
+    fn matches_file(&self, path: &Path, size: u64, modified: Option<SystemTime>) -> bool {
         // Check filename pattern
-        if let Some(filename) = path.file_name() {
-            let filename_str = filename.to_string_lossy();
-            if !self.pattern_matcher.matches(&filename_str) && !self.criteria.search_in_files {
-                return Ok(false);
-            }
+        if !self.criteria.search_in_files && !self.name_matches(path) {
+            return false;
         }
 
         // Check extensions filter
@@ -342,92 +1094,307 @@ impl SearchEngine {
                     .iter()
                     .any(|e| e.to_lowercase() == ext_str)
                 {
-                    return Ok(false);
+                    return false;
                 }
             } else {
-                return Ok(false);
+                return false;
             }
         }
 
         // Check size filter
         if let Some(ref size_filter) = self.criteria.size_filter {
-            let size = metadata.len();
             if let Some(min) = size_filter.min_size {
                 if size < min {
-                    return Ok(false);
+                    return false;
                 }
             }
             if let Some(max) = size_filter.max_size {
                 if size > max {
-                    return Ok(false);
+                    return false;
                 }
             }
         }
 
         // Check date filter
-        if let Some(ref date_filter) = self.criteria.date_filter {
-            if let Ok(modified) = metadata.modified() {
-                if let Some(after) = date_filter.after {
-                    if modified < after {
-                        return Ok(false);
-                    }
-                }
-                if let Some(before) = date_filter.before {
-                    if modified > before {
-                        return Ok(false);
-                    }
-                }
+        if let Some(modified) = modified {
+            let (after, before) = self.modified_bounds;
+            if after.is_some_and(|after| modified < after) || before.is_some_and(|before| modified > before) {
+                return false;
             }
         }
 
-        Ok(true)
+        true
     }
 
-    fn search_in_file(&self, path: &Path, result: &mut SearchResult) -> Result<()> {
-        // Only search in text files
-        if let Ok(content) = fs::read_to_string(path) {
-            for (line_num, line) in content.lines().enumerate() {
-                if self.pattern_matcher.matches(line) {
-                    result.matches.push(Match {
-                        line_number: Some(line_num + 1),
-                        line_content: Some(line.to_string()),
-                        byte_offset: None,
-                    });
-                }
+    fn search_in_text(&self, content: &str, result: &mut SearchResult) {
+        for (line_num, line) in content.lines().enumerate() {
+            if self.pattern_matcher.matches(line) {
+                result.matches.push(Match {
+                    line_number: Some(line_num + 1),
+                    line_content: Some(line.to_string()),
+                    byte_offset: None,
+                });
             }
         }
-        Ok(())
     }
+}
 
-    fn count_directories(&self, path: &Path, depth: usize) -> Result<usize> {
-        if let Some(max_depth) = self.criteria.max_depth {
-            if depth > max_depth {
-                return Ok(0);
-            }
-        }
+/// A readable path for results and progress outside the local file system
+fn display_path(path: &VfsPath) -> PathBuf {
+    match path {
+        VfsPath::Local(path) => path.clone(),
+        VfsPath::Archive { archive_path, internal_path } => archive_path.join(internal_path),
+        other => PathBuf::from(other.to_string()),
+    }
+}
 
-        let mut count = 1;
+/// The path an error from the directory walker refers to
+fn error_path(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => error_path(err),
+        _ => None,
+    }
+}
 
-        if self.criteria.include_subdirs {
-            if let Ok(entries) = fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    if let Ok(metadata) = entry.metadata() {
-                        if metadata.is_dir() {
-                            count += self.count_directories(&entry.path(), depth + 1)?;
-                        }
-                    }
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_respects_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("src/nested/mod.rs"), "").unwrap();
+        fs::write(dir.path().join("target/debug/build.rs"), "").unwrap();
+        fs::write(dir.path().join("run.log"), "").unwrap();
+
+        let criteria = |respect_ignore_files| SearchCriteria {
+            pattern: "*".to_string(),
+            search_type: SearchType::Wildcard,
+            case_sensitive: false,
+            search_in_files: false,
+            include_hidden: false,
+            include_subdirs: true,
+            max_depth: None,
+            file_extensions: Vec::new(),
+            size_filter: None,
+            date_filter: None,
+            search_archives: false,
+            respect_ignore_files,
+            conditions: None,
+        };
+        let found = |results: Vec<SearchResult>| -> Vec<PathBuf> {
+            results
+                .into_iter()
+                .map(|result| result.path.strip_prefix(dir.path()).unwrap().to_path_buf())
+                .collect()
+        };
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut engine = SearchEngine::new(criteria(true)).unwrap().with_threads(4);
+        let results = found(engine.search(dir.path(), tx.clone()).await.unwrap());
+        assert_eq!(results, vec![PathBuf::from("src/lib.rs"), PathBuf::from("src/nested/mod.rs")]);
+
+        let mut engine = SearchEngine::new(criteria(false)).unwrap();
+        let results = found(engine.search(dir.path(), tx).await.unwrap());
+        assert_eq!(results.len(), 4);
+
+        // A cancelled engine stops before looking at any file
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut engine = SearchEngine::new(criteria(false)).unwrap();
+        engine.cancel();
+        assert!(engine.search(dir.path(), tx).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_finds_hits_in_nested_archives() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut inner = tar::Builder::new(encoder);
+        let data = b"the needle is here\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        inner.append_data(&mut header, "deep/found.txt", &data[..]).unwrap();
+        let inner = inner.into_inner().unwrap().finish().unwrap();
+
+        let archive_path = dir.path().join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, data) in [
+            ("docs/readme.txt", &b"no match\nneedle on line two\n"[..]),
+            ("docs/other.txt", &b"nothing\n"[..]),
+            ("inner.tar.gz", &inner[..]),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
         }
+        zip.finish().unwrap();
+
+        let criteria = SearchCriteria {
+            pattern: "needle".to_string(),
+            search_type: SearchType::Contains,
+            case_sensitive: false,
+            search_in_files: true,
+            include_hidden: false,
+            include_subdirs: true,
+            max_depth: None,
+            file_extensions: Vec::new(),
+            size_filter: None,
+            date_filter: None,
+            search_archives: true,
+            respect_ignore_files: false,
+            conditions: None,
+        };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut engine = SearchEngine::new(criteria.clone()).unwrap();
+        let results = engine.search(dir.path(), tx.clone()).await.unwrap();
+        let paths: Vec<&Path> = results.iter().map(|result| result.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![archive_path.join("docs/readme.txt"), archive_path.join("inner.tar.gz/deep/found.txt")]
+        );
+        assert_eq!(results[0].matches[0].line_number, Some(2));
+        // A hit in the nested archive opens the archive holding it
+        assert!(matches!(&results[1].location,
+            Some(VfsPath::Archive { internal_path, .. }) if internal_path == "inner.tar.gz"));
 
-        Ok(count)
+        // Searching from inside an archive only looks below that directory
+        let mut engine = SearchEngine::new(criteria).unwrap();
+        let docs = VfsPath::Archive {
+            archive_path: archive_path.clone(),
+            internal_path: "docs".to_string(),
+        };
+        let results = engine.search_vfs(&docs, tx.clone()).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, archive_path.join("docs/readme.txt"));
+
+        // Remote paths go through the shared VFS, which has to be handed over
+        let remote = VfsPath::Ftp {
+            host: "files.example.com".to_string(),
+            port: 21,
+            username: "anonymous".to_string(),
+            path: "/".to_string(),
+        };
+        assert!(engine.search_vfs(&remote, tx).await.is_err());
     }
 
-    pub fn cancel(&mut self) {
-        self.cancelled = true;
+    #[tokio::test]
+    async fn test_size_and_date_filters() {
+        let size = SizeFilter::parse(" 10k..2M ").unwrap();
+        assert_eq!((size.min_size, size.max_size), (Some(10 << 10), Some(2 << 20)));
+        assert_eq!(size.to_string(), "10K..2M");
+        assert_eq!(SizeFilter::parse(">1500").unwrap().to_string(), "1500..");
+        assert_eq!(SizeFilter::parse("<1G").unwrap().max_size, Some(1 << 30));
+        assert!(SizeFilter::parse("big").is_err());
+        assert!(SizeFilter::parse(">99999999999G").is_err());
+
+        let week = DateFilter::parse("7d").unwrap();
+        assert_eq!(week, DateFilter::Within(Duration::from_secs(7 * 86400)));
+        assert_eq!(week.to_string(), "1w");
+        assert_eq!(DateFilter::parse("36h").unwrap().to_string(), "36h");
+        let range = DateFilter::parse("2024-01-01..2024-06-30").unwrap();
+        assert_eq!(range.to_string(), "2024-01-01..2024-06-30");
+        assert_eq!(DateFilter::parse(range.to_string().as_str()).unwrap(), range);
+        assert_eq!(DateFilter::parse("..2024-06-30").unwrap().to_string(), "..2024-06-30");
+        assert!(DateFilter::parse("7x").is_err());
+        assert!(DateFilter::parse("99999999999999y").is_err());
+        assert_eq!(DateFilter::parse("<7d").unwrap(), week);
+        let older = DateFilter::parse(">7d").unwrap();
+        assert_eq!(older, DateFilter::OlderThan(Duration::from_secs(7 * 86400)));
+        assert_eq!(DateFilter::parse(older.to_string().as_str()).unwrap(), older);
+        assert!(DateFilter::parse("2024-13-01..").is_err());
+
+        // An age is measured from when the search runs
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("new.txt"), "").unwrap();
+        fs::write(dir.path().join("old.txt"), "").unwrap();
+        let month_ago = SystemTime::now() - Duration::from_secs(30 * 86400);
+        fs::File::options()
+            .write(true)
+            .open(dir.path().join("old.txt"))
+            .unwrap()
+            .set_modified(month_ago)
+            .unwrap();
+        let criteria = SearchCriteria {
+            pattern: "*.txt".to_string(),
+            search_type: SearchType::Wildcard,
+            case_sensitive: false,
+            search_in_files: false,
+            include_hidden: false,
+            include_subdirs: true,
+            max_depth: None,
+            file_extensions: Vec::new(),
+            size_filter: None,
+            date_filter: Some(week),
+            search_archives: false,
+            respect_ignore_files: false,
+            conditions: None,
+        };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut engine = SearchEngine::new(criteria.clone()).unwrap();
+        let results = engine.search(dir.path(), tx).await.unwrap();
+        let paths: Vec<&Path> = results.iter().map(|result| result.path.as_path()).collect();
+        assert_eq!(paths, vec![dir.path().join("new.txt")]);
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut engine = SearchEngine::new(SearchCriteria {
+            date_filter: Some(older),
+            ..criteria
+        })
+        .unwrap();
+        let results = engine.search(dir.path(), tx).await.unwrap();
+        let paths: Vec<&Path> = results.iter().map(|result| result.path.as_path()).collect();
+        assert_eq!(paths, vec![dir.path().join("old.txt")]);
     }
 
-    pub fn get_results(&self) -> &[SearchResult] {
-        &self.results
+    #[tokio::test]
+    async fn test_search_files_refines_earlier_results() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/net.rs"), "fn connect() {}\n").unwrap();
+        fs::write(dir.path().join("src/fs.rs"), "fn list() {}\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "connect later\n").unwrap();
+
+        let criteria = |pattern: &str, search_type, search_in_files| SearchCriteria {
+            pattern: pattern.to_string(),
+            search_type,
+            case_sensitive: false,
+            search_in_files,
+            include_hidden: false,
+            include_subdirs: true,
+            max_depth: None,
+            file_extensions: Vec::new(),
+            size_filter: None,
+            date_filter: None,
+            search_archives: false,
+            respect_ignore_files: false,
+            conditions: None,
+        };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut engine = SearchEngine::new(criteria("connect", SearchType::Contains, true)).unwrap();
+        let mut first: Vec<PathBuf> = engine
+            .search(dir.path(), tx.clone())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|result| result.path)
+            .collect();
+        first.sort();
+        assert_eq!(first, vec![dir.path().join("notes.txt"), dir.path().join("src/net.rs")]);
+
+        // Only the earlier hits are checked; files gone since then are skipped
+        first.push(dir.path().join("src/deleted.rs"));
+        let mut engine = SearchEngine::new(criteria("*.rs", SearchType::Wildcard, false)).unwrap();
+        let refined = engine.search_files(&first, tx).await.unwrap();
+        let refined: Vec<&Path> = refined.iter().map(|result| result.path.as_path()).collect();
+        assert_eq!(refined, vec![dir.path().join("src/net.rs")]);
     }
 }
@@ -0,0 +1,752 @@
+// Extra file conditions for searches, composable with and/or/not
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// A condition on file metadata or contents, written like
+/// `type:image and not owner:root`, `perm:/111 or empty`, `not contains:TODO`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SearchCondition {
+    /// Detected MIME type, e.g. `image/png`; a bare `image` matches the whole
+    /// top-level type, and `archive`, `executable` and `text` cover families
+    FileType(String),
+    /// User name or uid
+    Owner(String),
+    /// Group name or gid
+    Group(String),
+    /// Permission bits: all of `mask` set, or any of them when `any`
+    Permissions { mask: u32, any: bool },
+    /// Empty files and directories
+    Empty,
+    Symlink,
+    /// Symlinks whose target does not exist
+    BrokenSymlink,
+    /// File contents include the text
+    Contains(String),
+    And(Vec<SearchCondition>),
+    Or(Vec<SearchCondition>),
+    Not(Box<SearchCondition>),
+}
+
+/// Bytes read to detect a file's type
+const MAGIC_BYTES: usize = 512;
+
+impl SearchCondition {
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(condition),
+            Some(token) => Err(anyhow::anyhow!("Unexpected '{}' in conditions", token)),
+        }
+    }
+
+    /// Whether directories and symlinks can satisfy the condition, so the
+    /// search has to look at them as well as at regular files
+    pub fn covers_non_files(&self) -> bool {
+        match self {
+            SearchCondition::Empty | SearchCondition::Symlink | SearchCondition::BrokenSymlink => true,
+            SearchCondition::And(items) | SearchCondition::Or(items) => {
+                items.iter().any(|item| item.covers_non_files())
+            }
+            SearchCondition::Not(inner) => inner.covers_non_files(),
+            _ => false,
+        }
+    }
+
+    /// Whether evaluating the condition needs the file contents
+    pub fn needs_content(&self) -> bool {
+        match self {
+            SearchCondition::FileType(_) | SearchCondition::Contains(_) => true,
+            SearchCondition::And(items) | SearchCondition::Or(items) => {
+                items.iter().any(|item| item.needs_content())
+            }
+            SearchCondition::Not(inner) => inner.needs_content(),
+            _ => false,
+        }
+    }
+
+    /// Resolve user and group names, failing on unknown ones
+    pub(crate) fn compile(&self, case_sensitive: bool) -> Result<CompiledCondition> {
+        Ok(match self {
+            SearchCondition::FileType(mime) => CompiledCondition::FileType(mime.to_lowercase()),
+            SearchCondition::Owner(name) => CompiledCondition::Owner(resolve_user(name)?),
+            SearchCondition::Group(name) => CompiledCondition::Group(resolve_group(name)?),
+            SearchCondition::Permissions { mask, any } => CompiledCondition::Permissions {
+                mask: *mask,
+                any: *any,
+            },
+            SearchCondition::Empty => CompiledCondition::Empty,
+            SearchCondition::Symlink => CompiledCondition::Symlink,
+            SearchCondition::BrokenSymlink => CompiledCondition::BrokenSymlink,
+            SearchCondition::Contains(text) => CompiledCondition::Contains {
+                text: if case_sensitive { text.clone() } else { text.to_lowercase() },
+                case_sensitive,
+            },
+            SearchCondition::And(items) => CompiledCondition::And(
+                items.iter().map(|item| item.compile(case_sensitive)).collect::<Result<_>>()?,
+            ),
+            SearchCondition::Or(items) => CompiledCondition::Or(
+                items.iter().map(|item| item.compile(case_sensitive)).collect::<Result<_>>()?,
+            ),
+            SearchCondition::Not(inner) => CompiledCondition::Not(Box::new(inner.compile(case_sensitive)?)),
+        })
+    }
+}
+
+/// Back to the syntax `parse` reads
+impl fmt::Display for SearchCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchCondition::FileType(mime) => write!(f, "type:{}", quote(mime)),
+            SearchCondition::Owner(name) => write!(f, "owner:{}", quote(name)),
+            SearchCondition::Group(name) => write!(f, "group:{}", quote(name)),
+            SearchCondition::Permissions { mask, any } => {
+                write!(f, "perm:{}{:03o}", if *any { "/" } else { "" }, mask)
+            }
+            SearchCondition::Empty => write!(f, "empty"),
+            SearchCondition::Symlink => write!(f, "link"),
+            SearchCondition::BrokenSymlink => write!(f, "broken"),
+            SearchCondition::Contains(text) => write!(f, "contains:{}", quote(text)),
+            SearchCondition::And(items) => write_joined(f, items, " and "),
+            SearchCondition::Or(items) => write_joined(f, items, " or "),
+            SearchCondition::Not(inner) => match inner.as_ref() {
+                SearchCondition::And(_) | SearchCondition::Or(_) => write!(f, "not ({})", inner),
+                _ => write!(f, "not {}", inner),
+            },
+        }
+    }
+}
+
+fn write_joined(f: &mut fmt::Formatter<'_>, items: &[SearchCondition], separator: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{}", separator)?;
+        }
+        match item {
+            SearchCondition::And(_) | SearchCondition::Or(_) => write!(f, "({})", item)?,
+            _ => write!(f, "{}", item)?,
+        }
+    }
+    Ok(())
+}
+
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"')) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Word(word) => write!(f, "{}", word),
+        }
+    }
+}
+
+/// Words, parentheses and `"quoted parts"` inside words; `\"` and `\\` stand
+/// for themselves inside quotes
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => word.extend(chars.next()),
+                                Some(c) => word.push(c),
+                                None => return Err(anyhow::anyhow!("Unclosed quote in conditions")),
+                            }
+                        }
+                    } else {
+                        word.push(c);
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_word(&self) -> Option<&str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn or(&mut self) -> Result<SearchCondition> {
+        let mut items = vec![self.and()?];
+        while matches!(self.peek_word(), Some(word) if word.eq_ignore_ascii_case("or") || word == "|") {
+            self.pos += 1;
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { SearchCondition::Or(items) })
+    }
+
+    /// Adjacent terms are joined with `and` even without the keyword
+    fn and(&mut self) -> Result<SearchCondition> {
+        let mut items = vec![self.unary()?];
+        loop {
+            match self.tokens.get(self.pos) {
+                Some(Token::Word(word)) if word.eq_ignore_ascii_case("and") || word == "&" => {
+                    self.pos += 1;
+                }
+                Some(Token::Word(word)) if word.eq_ignore_ascii_case("or") || word == "|" => break,
+                Some(Token::Close) | None => break,
+                _ => {}
+            }
+            items.push(self.unary()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { SearchCondition::And(items) })
+    }
+
+    fn unary(&mut self) -> Result<SearchCondition> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("not") || word == "!" => {
+                self.pos += 1;
+                Ok(SearchCondition::Not(Box::new(self.unary()?)))
+            }
+            // `!empty`, `-link`
+            Some(Token::Word(word)) if word.len() > 1 && (word.starts_with('!') || word.starts_with('-')) => {
+                self.pos += 1;
+                Ok(SearchCondition::Not(Box::new(parse_term(&word[1..])?)))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let inner = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(anyhow::anyhow!("Missing ')' in conditions")),
+                }
+            }
+            Some(Token::Word(word)) => {
+                self.pos += 1;
+                parse_term(&word)
+            }
+            Some(Token::Close) => Err(anyhow::anyhow!("Unexpected ')' in conditions")),
+            None => Err(anyhow::anyhow!("Conditions end unexpectedly")),
+        }
+    }
+}
+
+fn parse_term(word: &str) -> Result<SearchCondition> {
+    let Some((key, value)) = word.split_once(':') else {
+        return match word.to_ascii_lowercase().as_str() {
+            "empty" => Ok(SearchCondition::Empty),
+            "link" | "symlink" => Ok(SearchCondition::Symlink),
+            "broken" => Ok(SearchCondition::BrokenSymlink),
+            "executable" => Ok(SearchCondition::Permissions { mask: 0o111, any: true }),
+            _ => Err(anyhow::anyhow!("Unknown condition '{}'", word)),
+        };
+    };
+    if value.is_empty() {
+        return Err(anyhow::anyhow!("Condition '{}' needs a value", key));
+    }
+    match key.to_ascii_lowercase().as_str() {
+        "type" | "mime" => Ok(SearchCondition::FileType(value.to_string())),
+        "owner" | "user" => Ok(SearchCondition::Owner(value.to_string())),
+        "group" => Ok(SearchCondition::Group(value.to_string())),
+        "perm" => parse_permissions(value),
+        "contains" => Ok(SearchCondition::Contains(value.to_string())),
+        _ => Err(anyhow::anyhow!("Unknown condition '{}'", key)),
+    }
+}
+
+/// `perm:002` needs all bits, `perm:/111` any of them; a few names are
+/// understood too
+fn parse_permissions(value: &str) -> Result<SearchCondition> {
+    let (mask, any) = match value.to_ascii_lowercase().as_str() {
+        "x" | "exec" | "executable" => (0o111, true),
+        "world-writable" => (0o002, false),
+        "suid" | "setuid" => (0o4000, false),
+        "sgid" | "setgid" => (0o2000, false),
+        "sticky" => (0o1000, false),
+        mode => {
+            let (digits, any) = match mode.strip_prefix('/') {
+                Some(digits) => (digits, true),
+                None => (mode, false),
+            };
+            let mask = u32::from_str_radix(digits, 8)
+                .ok()
+                .filter(|mask| *mask <= 0o7777)
+                .ok_or_else(|| anyhow::anyhow!("Invalid permission mask '{}'", value))?;
+            (mask, any)
+        }
+    };
+    Ok(SearchCondition::Permissions { mask, any })
+}
+
+fn resolve_user(name: &str) -> Result<u32> {
+    if let Ok(uid) = name.parse() {
+        return Ok(uid);
+    }
+    #[cfg(unix)]
+    {
+        let cname = std::ffi::CString::new(name)?;
+        let entry = unsafe { libc::getpwnam(cname.as_ptr()) };
+        if !entry.is_null() {
+            return Ok(unsafe { (*entry).pw_uid });
+        }
+    }
+    Err(anyhow::anyhow!("Unknown user '{}'", name))
+}
+
+fn resolve_group(name: &str) -> Result<u32> {
+    if let Ok(gid) = name.parse() {
+        return Ok(gid);
+    }
+    #[cfg(unix)]
+    {
+        let cname = std::ffi::CString::new(name)?;
+        let entry = unsafe { libc::getgrnam(cname.as_ptr()) };
+        if !entry.is_null() {
+            return Ok(unsafe { (*entry).gr_gid });
+        }
+    }
+    Err(anyhow::anyhow!("Unknown group '{}'", name))
+}
+
+/// A condition ready to evaluate, with names resolved to ids
+#[derive(Debug, Clone)]
+pub(crate) enum CompiledCondition {
+    FileType(String),
+    Owner(u32),
+    Group(u32),
+    Permissions { mask: u32, any: bool },
+    Empty,
+    Symlink,
+    BrokenSymlink,
+    Contains { text: String, case_sensitive: bool },
+    And(Vec<CompiledCondition>),
+    Or(Vec<CompiledCondition>),
+    Not(Box<CompiledCondition>),
+}
+
+impl CompiledCondition {
+    pub(crate) fn matches(&self, subject: &dyn Subject) -> bool {
+        match self {
+            CompiledCondition::FileType(pattern) => {
+                mime_matches(pattern, subject.mime_type())
+            }
+            CompiledCondition::Owner(uid) => subject.owner() == Some(*uid),
+            CompiledCondition::Group(gid) => subject.group() == Some(*gid),
+            CompiledCondition::Permissions { mask, any } => subject.mode().is_some_and(|mode| match any {
+                true => mode & mask != 0,
+                false => mode & mask == *mask,
+            }),
+            CompiledCondition::Empty => subject.is_empty(),
+            CompiledCondition::Symlink => subject.kind() == SubjectKind::Symlink,
+            CompiledCondition::BrokenSymlink => subject.is_broken_link(),
+            CompiledCondition::Contains { text, case_sensitive } => subject.text().is_some_and(|content| {
+                match case_sensitive {
+                    true => content.contains(text.as_str()),
+                    false => content.to_lowercase().contains(text.as_str()),
+                }
+            }),
+            CompiledCondition::And(items) => items.iter().all(|item| item.matches(subject)),
+            CompiledCondition::Or(items) => items.iter().any(|item| item.matches(subject)),
+            CompiledCondition::Not(inner) => !inner.matches(subject),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SubjectKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// What a condition can ask about an entry; unknown facts never match
+pub(crate) trait Subject {
+    fn kind(&self) -> SubjectKind;
+    fn mode(&self) -> Option<u32>;
+    fn owner(&self) -> Option<u32>;
+    fn group(&self) -> Option<u32>;
+    fn is_empty(&self) -> bool;
+    fn is_broken_link(&self) -> bool;
+    fn mime_type(&self) -> &'static str;
+    fn text(&self) -> Option<&str>;
+}
+
+/// A local file, directory or symlink; contents are read on first use
+pub(crate) struct LocalSubject<'a> {
+    path: &'a Path,
+    metadata: &'a fs::Metadata,
+    head: OnceLock<Vec<u8>>,
+    text: OnceLock<Option<String>>,
+}
+
+impl<'a> LocalSubject<'a> {
+    /// `metadata` is the entry's own, not its target's when it is a symlink
+    pub(crate) fn new(path: &'a Path, metadata: &'a fs::Metadata) -> Self {
+        Self {
+            path,
+            metadata,
+            head: OnceLock::new(),
+            text: OnceLock::new(),
+        }
+    }
+
+    /// Contents of a regular file, shared with the content search
+    pub(crate) fn read_text(&self) -> Option<&str> {
+        self.text
+            .get_or_init(|| match self.kind() {
+                SubjectKind::Directory => None,
+                _ => fs::read_to_string(self.path).ok(),
+            })
+            .as_deref()
+    }
+}
+
+impl Subject for LocalSubject<'_> {
+    fn kind(&self) -> SubjectKind {
+        let file_type = self.metadata.file_type();
+        if file_type.is_symlink() {
+            SubjectKind::Symlink
+        } else if file_type.is_dir() {
+            SubjectKind::Directory
+        } else {
+            SubjectKind::File
+        }
+    }
+
+    #[cfg(unix)]
+    fn mode(&self) -> Option<u32> {
+        use std::os::unix::fs::MetadataExt;
+        Some(self.metadata.mode() & 0o7777)
+    }
+
+    #[cfg(not(unix))]
+    fn mode(&self) -> Option<u32> {
+        None
+    }
+
+    #[cfg(unix)]
+    fn owner(&self) -> Option<u32> {
+        use std::os::unix::fs::MetadataExt;
+        Some(self.metadata.uid())
+    }
+
+    #[cfg(not(unix))]
+    fn owner(&self) -> Option<u32> {
+        None
+    }
+
+    #[cfg(unix)]
+    fn group(&self) -> Option<u32> {
+        use std::os::unix::fs::MetadataExt;
+        Some(self.metadata.gid())
+    }
+
+    #[cfg(not(unix))]
+    fn group(&self) -> Option<u32> {
+        None
+    }
+
+    fn is_empty(&self) -> bool {
+        match self.kind() {
+            SubjectKind::File => self.metadata.len() == 0,
+            SubjectKind::Directory => fs::read_dir(self.path).is_ok_and(|mut entries| entries.next().is_none()),
+            SubjectKind::Symlink => false,
+        }
+    }
+
+    fn is_broken_link(&self) -> bool {
+        self.kind() == SubjectKind::Symlink && fs::metadata(self.path).is_err()
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self.kind() {
+            SubjectKind::Directory => "inode/directory",
+            _ if self.is_broken_link() => "inode/symlink",
+            _ => {
+                let head = self.head.get_or_init(|| {
+                    let mut head = Vec::with_capacity(MAGIC_BYTES);
+                    if let Ok(file) = fs::File::open(self.path) {
+                        let _ = file.take(MAGIC_BYTES as u64).read_to_end(&mut head);
+                    }
+                    head
+                });
+                if head.is_empty() && self.metadata.len() == 0 {
+                    "inode/x-empty"
+                } else {
+                    detect_mime(head)
+                }
+            }
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        self.read_text()
+    }
+}
+
+/// An archive member or remote entry, with contents read beforehand if needed
+pub(crate) struct VfsSubject<'a> {
+    pub(crate) entry: &'a crate::vfs::VfsEntry,
+    pub(crate) content: Option<&'a [u8]>,
+}
+
+impl Subject for VfsSubject<'_> {
+    fn kind(&self) -> SubjectKind {
+        match self.entry.entry_type {
+            crate::vfs::VfsEntryType::Directory => SubjectKind::Directory,
+            crate::vfs::VfsEntryType::Symlink => SubjectKind::Symlink,
+            _ => SubjectKind::File,
+        }
+    }
+
+    fn mode(&self) -> Option<u32> {
+        parse_mode(&self.entry.permissions)
+    }
+
+    fn owner(&self) -> Option<u32> {
+        None
+    }
+
+    fn group(&self) -> Option<u32> {
+        None
+    }
+
+    fn is_empty(&self) -> bool {
+        self.kind() == SubjectKind::File && self.entry.size == 0
+    }
+
+    fn is_broken_link(&self) -> bool {
+        false
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match (self.kind(), self.content) {
+            (SubjectKind::Directory, _) => "inode/directory",
+            (_, Some([])) => "inode/x-empty",
+            (_, Some(content)) => detect_mime(&content[..content.len().min(MAGIC_BYTES)]),
+            (_, None) => "application/octet-stream",
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        self.content.and_then(|content| std::str::from_utf8(content).ok())
+    }
+}
+
+/// Mode bits from an `rwxr-xr-x` string, with or without the type character;
+/// `s`/`S` and `t`/`T` in the execute columns are setuid, setgid and sticky
+/// with and without the execute bit
+fn parse_mode(permissions: &str) -> Option<u32> {
+    let bits = permissions.get(permissions.len().checked_sub(9)?..)?;
+    bits.chars().enumerate().try_fold(0, |mode, (i, c)| {
+        let special = match i {
+            2 => 0o4000,
+            5 => 0o2000,
+            8 => 0o1000,
+            _ => 0,
+        };
+        match (c, special) {
+            ('-', _) => Some(mode),
+            ('r' | 'w' | 'x', _) => Some(mode | (0o400 >> i)),
+            ('s', 0o4000 | 0o2000) | ('t', 0o1000) => Some(mode | special | (0o400 >> i)),
+            ('S', 0o4000 | 0o2000) | ('T', 0o1000) => Some(mode | special),
+            _ => None,
+        }
+    })
+}
+
+/// Whether a detected MIME type satisfies a `type:` pattern
+pub fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern {
+        "archive" => matches!(
+            mime,
+            "application/zip"
+                | "application/gzip"
+                | "application/x-bzip2"
+                | "application/x-xz"
+                | "application/x-7z-compressed"
+                | "application/vnd.rar"
+                | "application/x-tar"
+                | "application/zstd"
+        ),
+        "executable" => matches!(
+            mime,
+            "application/x-executable"
+                | "application/x-mach-binary"
+                | "application/vnd.microsoft.portable-executable"
+                | "text/x-shellscript"
+        ),
+        pattern if pattern.contains('/') => mime == pattern,
+        pattern => mime.split('/').next() == Some(pattern),
+    }
+}
+
+/// MIME type of a file from its first bytes
+pub fn detect_mime(head: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+        (b"\0\0\x01\0", "image/x-icon"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"PK\x05\x06", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"BZh", "application/x-bzip2"),
+        (b"\xfd7zXZ\0", "application/x-xz"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"Rar!\x1a\x07", "application/vnd.rar"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"\xfe\xed\xfa\xce", "application/x-mach-binary"),
+        (b"\xfe\xed\xfa\xcf", "application/x-mach-binary"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"MZ", "application/vnd.microsoft.portable-executable"),
+        (b"#!", "text/x-shellscript"),
+        (b"ID3", "audio/mpeg"),
+        (b"fLaC", "audio/flac"),
+        (b"OggS", "audio/ogg"),
+        (b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+    // Container formats with the tag further in
+    match (head.get(..4), head.get(8..12)) {
+        (Some(b"RIFF"), Some(b"WEBP")) => return "image/webp",
+        (Some(b"RIFF"), Some(b"WAVE")) => return "audio/wav",
+        (Some(b"RIFF"), Some(b"AVI ")) => return "video/x-msvideo",
+        _ => {}
+    }
+    match head.get(4..8) {
+        Some(b"ftyp") if head.get(8..11) == Some(b"M4A") => return "audio/mp4",
+        Some(b"ftyp") => return "video/mp4",
+        _ => {}
+    }
+    if head.get(257..262) == Some(b"ustar") {
+        return "application/x-tar";
+    }
+    if head.starts_with(&[0xff, 0xfb]) || head.starts_with(&[0xff, 0xf3]) {
+        return "audio/mpeg";
+    }
+    if head.contains(&0) {
+        return "application/octet-stream";
+    }
+    // A multi-byte character may be cut off at the end of the sample
+    match std::str::from_utf8(head) {
+        Ok(_) => "text/plain",
+        Err(e) if e.error_len().is_none() => "text/plain",
+        Err(_) => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conditions() {
+        let condition = SearchCondition::parse("type:image and not owner:root or (perm:/111 empty)").unwrap();
+        assert_eq!(
+            condition,
+            SearchCondition::Or(vec![
+                SearchCondition::And(vec![
+                    SearchCondition::FileType("image".to_string()),
+                    SearchCondition::Not(Box::new(SearchCondition::Owner("root".to_string()))),
+                ]),
+                SearchCondition::And(vec![
+                    SearchCondition::Permissions { mask: 0o111, any: true },
+                    SearchCondition::Empty,
+                ]),
+            ])
+        );
+        // Display gives back something that parses to the same condition
+        assert_eq!(SearchCondition::parse(&condition.to_string()).unwrap(), condition);
+
+        assert_eq!(
+            SearchCondition::parse("!contains:\"to do\" perm:world-writable").unwrap(),
+            SearchCondition::And(vec![
+                SearchCondition::Not(Box::new(SearchCondition::Contains("to do".to_string()))),
+                SearchCondition::Permissions { mask: 0o002, any: false },
+            ])
+        );
+        // Quotes and backslashes inside a value survive a round trip
+        for text in [r#"say "hi""#, r"C:\dir name\", r#"a"b"#] {
+            let condition = SearchCondition::Contains(text.to_string());
+            assert_eq!(SearchCondition::parse(&condition.to_string()).unwrap(), condition);
+        }
+        assert!(SearchCondition::parse("(empty").is_err());
+        assert!(SearchCondition::parse("size:10").is_err());
+        assert!(SearchCondition::parse("perm:999").is_err());
+    }
+
+    #[test]
+    fn test_detect_mime() {
+        assert_eq!(detect_mime(b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(detect_mime(b"\x7fELF\x02\x01"), "application/x-executable");
+        assert_eq!(detect_mime(b"fn main() {}\n"), "text/plain");
+        assert_eq!(detect_mime(b"\0\x01\x02\x03garbage"), "application/octet-stream");
+        assert!(mime_matches("image", "image/png"));
+        assert!(mime_matches("archive", "application/gzip"));
+        assert!(!mime_matches("image/jpeg", "image/png"));
+        assert_eq!(parse_mode("-rwxr-x---"), Some(0o750));
+        assert_eq!(parse_mode("-rwsr-xr-x"), Some(0o4755));
+        assert_eq!(parse_mode("drwxrwsr-x"), Some(0o2775));
+        assert_eq!(parse_mode("drwxrwxrwt"), Some(0o1777));
+        assert_eq!(parse_mode("-rwSr--r-T"), Some(0o5644));
+        assert_eq!(parse_mode("-rwxr-xr-s"), None);
+    }
+}
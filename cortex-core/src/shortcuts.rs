@@ -111,6 +111,7 @@ pub enum Action {
     // View
     ViewFile,
     EditFile,
+    HexEditFile,
     QuickView,
     ToggleHidden,
     ToggleDetails,
@@ -133,6 +134,8 @@ pub enum Action {
     SearchPrevious,
     QuickFilter,
     FindInFiles,
+    FuzzyFind,
+    RerunSearch,
     ClearFilter,
     GoToLine,
 
@@ -203,6 +206,7 @@ pub enum Action {
     EnterArchive,
     ExtractArchive,
     CreateArchive,
+    GitHistory,
     SftpConnect,
     FtpConnect,
     Disconnect,
@@ -278,10 +282,12 @@ impl ShortcutManager {
         shortcuts.insert(KeyBinding { code: "F1".to_string(), modifiers: vec!["Alt".to_string()] }, Action::ChangeDriveLeft);
         shortcuts.insert(KeyBinding { code: "F2".to_string(), modifiers: vec!["Alt".to_string()] }, Action::ChangeDriveRight);
         shortcuts.insert(KeyBinding { code: "F3".to_string(), modifiers: vec!["Shift".to_string()] }, Action::SearchPrevious);
+        shortcuts.insert(KeyBinding { code: "F3".to_string(), modifiers: vec!["Alt".to_string()] }, Action::HexEditFile);
         shortcuts.insert(KeyBinding { code: "F4".to_string(), modifiers: vec!["Shift".to_string()] }, Action::NewFile);
         shortcuts.insert(KeyBinding { code: "F5".to_string(), modifiers: vec!["Shift".to_string()] }, Action::CopyAs);
         shortcuts.insert(KeyBinding { code: "F6".to_string(), modifiers: vec!["Shift".to_string()] }, Action::Rename);
         shortcuts.insert(KeyBinding { code: "F7".to_string(), modifiers: vec!["Alt".to_string()] }, Action::FindInFiles);
+        shortcuts.insert(KeyBinding { code: "F7".to_string(), modifiers: vec!["Shift".to_string()] }, Action::RerunSearch);
         shortcuts.insert(KeyBinding { code: "F8".to_string(), modifiers: vec!["Alt".to_string()] }, Action::ShowHistory);
 
         // Navigation
@@ -324,6 +330,7 @@ impl ShortcutManager {
         shortcuts.insert(KeyBinding { code: "f".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::QuickFilter);
         shortcuts.insert(KeyBinding { code: "f".to_string(), modifiers: vec!["Ctrl".to_string(), "Shift".to_string()] }, Action::FindInFiles);
         shortcuts.insert(KeyBinding { code: "/".to_string(), modifiers: vec![] }, Action::QuickFilter);
+        shortcuts.insert(KeyBinding { code: "f".to_string(), modifiers: vec!["Alt".to_string()] }, Action::FuzzyFind);
         shortcuts.insert(KeyBinding { code: "l".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::ClearFilter);
         shortcuts.insert(KeyBinding { code: "g".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::GoToLine);
         // Removed: shortcuts.insert(KeyBinding { code: "F3".to_string(), modifiers: vec![] }, Action::SearchNext); // Conflict with Action::ViewFile
@@ -334,6 +341,7 @@ impl ShortcutManager {
         shortcuts.insert(KeyBinding { code: "h".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::ToggleHidden);
         shortcuts.insert(KeyBinding { code: "d".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::ToggleDetails);
         shortcuts.insert(KeyBinding { code: "t".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::ToggleTreeView);
+        shortcuts.insert(KeyBinding { code: "q".to_string(), modifiers: vec!["Alt".to_string()] }, Action::QuickView);
         shortcuts.insert(KeyBinding { code: "1".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::BriefView);
         shortcuts.insert(KeyBinding { code: "2".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::FullView);
         shortcuts.insert(KeyBinding { code: "3".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::WideView);
@@ -371,6 +379,7 @@ impl ShortcutManager {
 
         // Command Line
         shortcuts.insert(KeyBinding { code: ":".to_string(), modifiers: vec![] }, Action::CommandLine);
+        shortcuts.insert(KeyBinding { code: "p".to_string(), modifiers: vec!["Alt".to_string()] }, Action::CommandPalette);
         shortcuts.insert(KeyBinding { code: "!".to_string(), modifiers: vec![] }, Action::ShellCommand);
         shortcuts.insert(KeyBinding { code: "Enter".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::RunInTerminal);
         shortcuts.insert(KeyBinding { code: "Space".to_string(), modifiers: vec!["Ctrl".to_string()] }, Action::Autocomplete);
@@ -379,6 +388,7 @@ impl ShortcutManager {
         shortcuts.insert(KeyBinding { code: "PageDown".to_string(), modifiers: vec!["Alt".to_string()] }, Action::EnterArchive);
         shortcuts.insert(KeyBinding { code: "F6".to_string(), modifiers: vec!["Alt".to_string()] }, Action::ExtractArchive);
         shortcuts.insert(KeyBinding { code: "F5".to_string(), modifiers: vec!["Alt".to_string()] }, Action::CreateArchive);
+        shortcuts.insert(KeyBinding { code: "g".to_string(), modifiers: vec!["Alt".to_string()] }, Action::GitHistory);
         shortcuts.insert(KeyBinding { code: "s".to_string(), modifiers: vec!["Ctrl".to_string(), "Shift".to_string()] }, Action::SftpConnect);
         shortcuts.insert(KeyBinding { code: "f".to_string(), modifiers: vec!["Ctrl".to_string(), "Shift".to_string()] }, Action::FtpConnect);
         shortcuts.insert(KeyBinding { code: "d".to_string(), modifiers: vec!["Ctrl".to_string(), "Shift".to_string()] }, Action::Disconnect);
@@ -544,7 +554,11 @@ impl ShortcutManager {
             Action::CreateDirectory => "Create new directory",
             Action::ViewFile => "View file",
             Action::EditFile => "Edit file",
+            Action::HexEditFile => "Edit file as hex",
+            Action::QuickView => "Preview the current entry in the other panel",
             Action::Search => "Advanced search",
+            Action::FuzzyFind => "Fuzzy find files",
+            Action::RerunSearch => "Rerun last search",
             Action::Refresh => "Refresh panels",
             Action::Help => "Show help",
             Action::Settings => "Open settings",
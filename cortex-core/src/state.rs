@@ -4,8 +4,9 @@ use crate::config::ConfigManager;
 use crate::file_monitor::{ChangeNotification, EventCallback, FileMonitorManager};
 use crate::fs::FileEntry;
 use crate::git::GitInfo;
+use crate::search::SearchResult;
 use crate::terminal::TerminalManager;
-use crate::vfs::{RemoteCredentials, VfsEntry, VfsPath, VirtualFileSystem};
+use crate::vfs::{ConnectionStatus, RemoteCredentials, VfsEntry, VfsPath, VirtualFileSystem};
 use anyhow::Result;
 use cortex_plugins::{PluginContext, PluginManager};
 use serde::{Deserialize, Serialize};
@@ -31,6 +32,28 @@ pub struct PanelState {
     pub filter: Option<String>,
     #[serde(skip)]
     pub git_info: Option<GitInfo>,
+    /// A background VFS listing is still filling `vfs_entries`
+    #[serde(skip)]
+    pub loading: bool,
+    /// Connection health of a remote panel, `None` for local paths
+    #[serde(skip)]
+    pub connection: Option<ConnectionStatus>,
+    /// Set while the panel lists search results instead of `current_dir`
+    #[serde(skip)]
+    pub search_listing: Option<SearchListing>,
+}
+
+/// Origin of a panel fed with search results
+#[derive(Debug, Clone)]
+pub struct SearchListing {
+    pub root: PathBuf,
+    pub pattern: String,
+}
+
+fn search_result_entry(root: &Path, path: &Path) -> Option<FileEntry> {
+    let mut entry = FileEntry::from_path(path).ok()?;
+    entry.name = path.strip_prefix(root).unwrap_or(path).display().to_string();
+    Some(entry)
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -73,6 +96,55 @@ impl PanelState {
             marked_files: Vec::new(),
             filter: None,
             git_info,
+            loading: false,
+            connection: None,
+            search_listing: None,
+        }
+    }
+
+    /// List found files by their path relative to the search root. Hits inside archives
+    /// or on remote locations can't be panel entries; returns how many were left out
+    pub fn show_search_results(&mut self, root: PathBuf, pattern: String, results: &[SearchResult]) -> usize {
+        let (local, elsewhere): (Vec<_>, Vec<_>) = results.iter().partition(|result| result.location.is_none());
+        self.entries = local
+            .into_iter()
+            .filter_map(|result| search_result_entry(&root, &result.path))
+            .collect();
+        self.current_dir = root.clone();
+        self.search_listing = Some(SearchListing { root, pattern });
+        self.marked_files.clear();
+        self.clear_filter();
+        self.sort_entries();
+        elsewhere.len()
+    }
+
+    /// Re-read the listed files, dropping those that no longer exist
+    pub fn refresh_search_results(&mut self) {
+        let Some(listing) = &self.search_listing else {
+            return;
+        };
+        let root = listing.root.clone();
+        self.entries = self
+            .entries
+            .iter()
+            .filter_map(|entry| search_result_entry(&root, &entry.path))
+            .collect();
+        let entries = &self.entries;
+        self.marked_files.retain(|path| entries.iter().any(|entry| &entry.path == path));
+        self.selected_index = self.selected_index.min(self.entries.len().saturating_sub(1));
+        self.sort_entries();
+    }
+
+    /// Keep a renamed file in a search listing
+    pub fn rename_listed(&mut self, from: &Path, to: &Path) {
+        if self.search_listing.is_none() {
+            return;
+        }
+        for entry in self.entries.iter_mut().filter(|entry| entry.path == from) {
+            entry.path = to.to_path_buf();
+        }
+        for marked in self.marked_files.iter_mut().filter(|marked| *marked == from) {
+            *marked = to.to_path_buf();
         }
     }
 
@@ -297,6 +369,8 @@ pub struct AppState {
     pub right_panel: PanelState,
     pub active_panel: ActivePanel,
     pub panels_hidden: bool,
+    /// Set while the inactive panel previews the active panel's entry
+    pub quick_view: Option<crate::QuickView>,
     pub command_line: String,
     pub command_cursor: usize,
     pub command_history: Vec<String>,
@@ -366,6 +440,10 @@ pub enum FileOperation {
     Filter {
         filter: String,
     },
+    MultiRename {
+        paths: Vec<PathBuf>,
+        pattern: String,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -503,6 +581,7 @@ impl AppState {
             right_panel: PanelState::new(current_dir),
             active_panel: ActivePanel::Left,
             panels_hidden: false,
+            quick_view: None,
             command_line: String::new(),
             command_cursor: 0,
             command_history: Vec::new(),
@@ -592,6 +671,13 @@ impl AppState {
         }
     }
 
+    pub fn panel_mut(&mut self, side: ActivePanel) -> &mut PanelState {
+        match side {
+            ActivePanel::Left => &mut self.left_panel,
+            ActivePanel::Right => &mut self.right_panel,
+        }
+    }
+
     pub fn inactive_panel(&self) -> &PanelState {
         match self.active_panel {
             ActivePanel::Left => &self.right_panel,
@@ -655,6 +741,12 @@ impl AppState {
                     "FTP connections are not available in this build"
                 ));
             }
+            #[cfg(not(feature = "s3"))]
+            VfsPath::S3 { .. } => {
+                return Err(anyhow::anyhow!(
+                    "S3 storage is not available in this build"
+                ));
+            }
             _ => {}
         }
 
@@ -685,6 +777,13 @@ impl AppState {
                     active_panel.vfs_entries.clear();
                     active_panel.filtered_vfs_entries.clear();
                 }
+                VfsPath::GitTree { repo, .. } => {
+                    // Leave history for the working tree it was opened from
+                    active_panel.current_dir = repo.clone();
+                    active_panel.current_vfs_path = None;
+                    active_panel.vfs_entries.clear();
+                    active_panel.filtered_vfs_entries.clear();
+                }
                 _ => {
                     // For other VFS types, go back to regular filesystem
                     active_panel.current_vfs_path = None;
@@ -859,3 +958,54 @@ impl AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_results_panel() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/found.rs"), "").unwrap();
+        std::fs::write(root.join("also.rs"), "").unwrap();
+        let result = |path: PathBuf| SearchResult {
+            path,
+            size: 0,
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            matches: Vec::new(),
+            location: None,
+        };
+        let results = [
+            result(root.join("sub/found.rs")),
+            result(root.join("also.rs")),
+            result(root.join("gone.rs")),
+            SearchResult {
+                location: Some(VfsPath::Archive {
+                    archive_path: root.join("bundle.zip"),
+                    internal_path: "inner.rs".to_string(),
+                }),
+                ..result(root.join("bundle.zip/inner.rs"))
+            },
+        ];
+
+        let mut panel = PanelState::new(root.join("sub"));
+        assert_eq!(panel.show_search_results(root.clone(), "*.rs".to_string(), &results), 1);
+        let names: Vec<&str> = panel.entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["also.rs", "sub/found.rs"]);
+        assert_eq!(panel.current_dir, root);
+        assert_eq!(panel.search_listing.as_ref().unwrap().pattern, "*.rs");
+
+        // Renamed files stay listed under their new path, deleted ones drop out
+        panel.marked_files.push(root.join("also.rs"));
+        std::fs::rename(root.join("also.rs"), root.join("renamed.rs")).unwrap();
+        panel.rename_listed(&root.join("also.rs"), &root.join("renamed.rs"));
+        assert_eq!(panel.marked_files, [root.join("renamed.rs")]);
+        std::fs::remove_file(root.join("sub/found.rs")).unwrap();
+        panel.refresh_search_results();
+        let paths: Vec<&Path> = panel.entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(paths, [root.join("renamed.rs")]);
+        assert_eq!(panel.marked_files, [root.join("renamed.rs")]);
+    }
+}
@@ -0,0 +1,800 @@
+// Structured documents: JSON, YAML and TOML parsed into one tree that the
+// viewer folds, narrows with jq-style path queries and pretty-prints
+
+use serde::de::{self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+use std::path::Path;
+
+/// Larger files open in the plain text viewer only
+pub const MAX_DOCUMENT_SIZE: u64 = 32 * 1024 * 1024;
+/// Containers this deep or deeper start folded
+const UNFOLDED_DEPTH: usize = 2;
+/// How toml hands datetimes to a deserializer, as a one-entry map
+const TOML_DATETIME_KEY: &str = "$__toml_private_datetime";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl DocumentFormat {
+    /// The format a file holds, by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        }
+    }
+}
+
+/// A document value; mappings keep the order of the file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Null,
+    Bool(bool),
+    /// Text of the parsed value: 64-bit integers print back unchanged, larger
+    /// integers and floats are re-formatted from an `f64`
+    Number(String),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl Node {
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            Node::Null => NodeKind::Null,
+            Node::Bool(_) => NodeKind::Bool,
+            Node::Number(_) => NodeKind::Number,
+            Node::String(_) => NodeKind::String,
+            Node::Array(_) => NodeKind::Array,
+            Node::Object(_) => NodeKind::Object,
+        }
+    }
+
+    pub fn get(&self, segment: &Segment) -> Option<&Node> {
+        match (self, segment) {
+            (Node::Object(fields), Segment::Key(key)) => {
+                fields.iter().find(|(name, _)| name == key).map(|(_, node)| node)
+            }
+            (Node::Array(items), Segment::Index(index)) => items.get(*index),
+            _ => None,
+        }
+    }
+
+    /// The node at `path` below this one
+    pub fn at(&self, path: &[Segment]) -> Option<&Node> {
+        path.iter().try_fold(self, |node, segment| node.get(segment))
+    }
+
+    /// Child nodes with the segment that leads to each
+    fn children(&self) -> Vec<(Segment, &Node)> {
+        match self {
+            Node::Array(items) => items.iter().enumerate().map(|(i, node)| (Segment::Index(i), node)).collect(),
+            Node::Object(fields) => fields
+                .iter()
+                .map(|(key, node)| (Segment::Key(key.clone()), node))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// One-line text of a scalar, or a count of what a container holds
+    pub fn summary(&self) -> String {
+        let count = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+        match self {
+            Node::Null => "null".to_string(),
+            Node::Bool(value) => value.to_string(),
+            Node::Number(number) => number.clone(),
+            Node::String(text) => serde_json::to_string(text).unwrap_or_default(),
+            Node::Array(items) => format!("[{}]", count(items.len(), "item", "items")),
+            Node::Object(fields) => format!("{{{}}}", count(fields.len(), "key", "keys")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON, YAML or TOML value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        Node::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Node, E> {
+        Ok(Node::Bool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Node, E> {
+        Ok(Node::Number(value.to_string()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Node, E> {
+        Ok(Node::Number(value.to_string()))
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<Node, E> {
+        Ok(Node::Number(value.to_string()))
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Node, E> {
+        Ok(Node::Number(value.to_string()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Node, E> {
+        // Debug keeps the decimal point of whole numbers
+        Ok(Node::Number(format!("{:?}", value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Node, E> {
+        Ok(Node::String(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Node, E> {
+        Ok(Node::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut fields = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key::<Node>()? {
+            // YAML keys may be numbers or booleans
+            let key = match key {
+                Node::String(key) => key,
+                other => other.summary(),
+            };
+            if key == TOML_DATETIME_KEY && fields.is_empty() {
+                return Ok(Node::String(map.next_value()?));
+            }
+            fields.push((key, map.next_value()?));
+        }
+        Ok(Node::Object(fields))
+    }
+
+    /// YAML tagged values, kept as a one-entry mapping from the tag
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Node, A::Error> {
+        let (tag, variant) = data.variant::<String>()?;
+        let value = variant.newtype_variant::<Node>()?;
+        Ok(Node::Object(vec![(format!("!{}", tag), value)]))
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Node::Null => serializer.serialize_unit(),
+            Node::Bool(value) => serializer.serialize_bool(*value),
+            Node::Number(number) => {
+                if let Ok(value) = number.parse::<i64>() {
+                    serializer.serialize_i64(value)
+                } else if let Ok(value) = number.parse::<u64>() {
+                    serializer.serialize_u64(value)
+                } else {
+                    serializer.serialize_f64(number.parse().unwrap_or(f64::NAN))
+                }
+            }
+            Node::String(text) => serializer.serialize_str(text),
+            Node::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Node::Object(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (key, value) in fields {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Why a document did not parse, and where; line and column count from one
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    /// Text of the line the error is on
+    pub excerpt: String,
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+impl DocumentError {
+    /// serde_json and serde_norway end their messages with the location too
+    fn located(text: &str, message: String, line: usize, column: usize) -> Self {
+        let message = match message.rfind(" at line ") {
+            Some(at) => message[..at].to_string(),
+            None => message,
+        };
+        let excerpt = text.lines().nth(line.saturating_sub(1)).unwrap_or_default().to_string();
+        Self {
+            line,
+            column,
+            message,
+            excerpt,
+        }
+    }
+}
+
+pub fn parse_document(text: &str, format: DocumentFormat) -> Result<Node, DocumentError> {
+    match format {
+        DocumentFormat::Json => {
+            serde_json::from_str(text).map_err(|e| DocumentError::located(text, e.to_string(), e.line(), e.column()))
+        }
+        DocumentFormat::Yaml => serde_norway::from_str(text).map_err(|e| {
+            let (line, column) = e.location().map_or((1, 1), |at| (at.line(), at.column()));
+            DocumentError::located(text, e.to_string(), line, column)
+        }),
+        DocumentFormat::Toml => toml::from_str(text).map_err(|e| {
+            let start = e.span().map_or(0, |span| span.start).min(text.len());
+            let before = &text[..start];
+            let line_start = before.rfind('\n').map_or(0, |at| at + 1);
+            let column = before[line_start..].chars().count() + 1;
+            DocumentError::located(text, e.message().to_string(), before.matches('\n').count() + 1, column)
+        }),
+    }
+}
+
+/// One step of a path through a document
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// The jq spelling of a path: `.`, `.name`, `.items[2]`, `.["odd key"]`
+pub fn path_string(path: &[Segment]) -> String {
+    let mut text = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if is_identifier(key) => {
+                let _ = write!(text, ".{}", key);
+            }
+            Segment::Key(key) => {
+                if text.is_empty() {
+                    text.push('.');
+                }
+                let _ = write!(text, "[{}]", serde_json::to_string(key).unwrap_or_default());
+            }
+            Segment::Index(index) => {
+                if text.is_empty() {
+                    text.push('.');
+                }
+                let _ = write!(text, "[{}]", index);
+            }
+        }
+    }
+    if text.is_empty() {
+        text.push('.');
+    }
+    text
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    /// Negative indexes count from the end
+    Index(i64),
+    /// `[]`, every item or value
+    Iterate,
+}
+
+/// Paths of the nodes a jq-style query picks out, e.g. `.a.b`, `.a[0]`,
+/// `.a[-1]`, `.a[].name`, `.["a b"]` or `.a | .b`; keys that are missing
+/// pick out nothing
+pub fn query(root: &Node, query: &str) -> anyhow::Result<Vec<Vec<Segment>>> {
+    let mut paths = vec![Vec::new()];
+    for step in parse_query(query)? {
+        let mut next = Vec::new();
+        for path in paths {
+            let Some(node) = root.at(&path) else {
+                continue;
+            };
+            let mut push = |segment: Segment| {
+                let mut path = path.clone();
+                path.push(segment);
+                next.push(path);
+            };
+            match (&step, node) {
+                (Step::Key(key), Node::Object(_)) if node.get(&Segment::Key(key.clone())).is_some() => {
+                    push(Segment::Key(key.clone()))
+                }
+                (Step::Index(index), Node::Array(items)) => {
+                    let index = match *index < 0 {
+                        true => items.len() as i64 + index,
+                        false => *index,
+                    };
+                    if (0..items.len() as i64).contains(&index) {
+                        push(Segment::Index(index as usize));
+                    }
+                }
+                (Step::Iterate, _) => node.children().into_iter().for_each(|(segment, _)| push(segment)),
+                _ => {}
+            }
+        }
+        paths = next;
+    }
+    Ok(paths)
+}
+
+fn parse_query(query: &str) -> anyhow::Result<Vec<Step>> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut steps = Vec::new();
+    let mut at = 0;
+    let unexpected = |at: usize| match chars.get(at) {
+        Some(ch) => anyhow::anyhow!("Unexpected '{}' at position {} of the query", ch, at + 1),
+        None => anyhow::anyhow!("The query ends too soon"),
+    };
+    while at < chars.len() {
+        match chars[at] {
+            ' ' | '|' => at += 1,
+            '.' => {
+                at += 1;
+                let start = at;
+                while chars.get(at).is_some_and(|ch| ch.is_alphanumeric() || *ch == '_' || *ch == '-') {
+                    at += 1;
+                }
+                if at > start {
+                    steps.push(Step::Key(chars[start..at].iter().collect()));
+                } else if chars.get(at) == Some(&'"') {
+                    let (key, end) = quoted(&chars, at).ok_or_else(|| unexpected(at))?;
+                    steps.push(Step::Key(key));
+                    at = end;
+                }
+            }
+            '[' => {
+                let close = chars[at..]
+                    .iter()
+                    .position(|&ch| ch == ']')
+                    .map(|offset| at + offset)
+                    .ok_or_else(|| unexpected(chars.len()))?;
+                let inner: String = chars[at + 1..close].iter().collect();
+                let inner = inner.trim();
+                if inner.is_empty() {
+                    steps.push(Step::Iterate);
+                    at = close + 1;
+                } else if inner.starts_with('"') {
+                    let start = at + 1 + chars[at + 1..].iter().position(|&ch| ch == '"').unwrap_or(0);
+                    let (key, end) = quoted(&chars, start).ok_or_else(|| unexpected(start))?;
+                    let close = end + chars[end..].iter().position(|&ch| ch == ']').ok_or_else(|| unexpected(end))?;
+                    steps.push(Step::Key(key));
+                    at = close + 1;
+                } else {
+                    let index = inner.parse().map_err(|_| unexpected(at + 1))?;
+                    steps.push(Step::Index(index));
+                    at = close + 1;
+                }
+            }
+            _ => return Err(unexpected(at)),
+        }
+    }
+    Ok(steps)
+}
+
+/// The string in double quotes starting at `start`, and where it ends
+fn quoted(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut at = start + 1;
+    while *chars.get(at)? != '"' {
+        at += if chars[at] == '\\' { 2 } else { 1 };
+    }
+    let literal: String = chars[start..=at].iter().collect();
+    Some((serde_json::from_str(&literal).ok()?, at + 1))
+}
+
+/// A node as a line of the tree view
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub path: Vec<Segment>,
+    /// Key or index, or the whole path for a query match; empty for the root
+    pub label: String,
+    pub kind: NodeKind,
+    pub value: String,
+    /// Whether a container is open, `None` for scalars
+    pub expanded: Option<bool>,
+}
+
+/// A parsed document laid out as rows of nodes that fold open and shut
+#[derive(Debug, Clone)]
+pub struct DocumentTree {
+    pub format: DocumentFormat,
+    pub root: Node,
+    /// Containers shown open
+    expanded: HashSet<Vec<Segment>>,
+    /// Query the tree is narrowed to
+    pub query: Option<String>,
+    /// Nodes shown at the top level
+    roots: Vec<Vec<Segment>>,
+    pub rows: Vec<TreeRow>,
+    pub selected: usize,
+    /// Pretty-printed JSON shown instead of the rows
+    pub pretty: Option<Vec<String>>,
+    /// First row or pretty line on screen
+    pub offset: usize,
+}
+
+impl DocumentTree {
+    pub fn new(root: Node, format: DocumentFormat) -> Self {
+        let mut tree = Self {
+            format,
+            root,
+            expanded: HashSet::new(),
+            query: None,
+            roots: vec![Vec::new()],
+            rows: Vec::new(),
+            selected: 0,
+            pretty: None,
+            offset: 0,
+        };
+        tree.expand_to_depth(Vec::new(), UNFOLDED_DEPTH);
+        tree.rebuild();
+        tree
+    }
+
+    pub fn parse(text: &str, format: DocumentFormat) -> Result<Self, DocumentError> {
+        parse_document(text, format).map(|root| Self::new(root, format))
+    }
+
+    /// Open the containers under `path` down to `depth` levels
+    fn expand_to_depth(&mut self, path: Vec<Segment>, depth: usize) {
+        let Some(node) = self.root.at(&path) else {
+            return;
+        };
+        let mut pending = vec![(path, node, 0)];
+        while let Some((path, node, level)) = pending.pop() {
+            if level >= depth || !matches!(node, Node::Array(_) | Node::Object(_)) {
+                continue;
+            }
+            for (segment, child) in node.children() {
+                let mut child_path = path.clone();
+                child_path.push(segment);
+                pending.push((child_path, child, level + 1));
+            }
+            self.expanded.insert(path);
+        }
+    }
+
+    /// Lay the rows out again, keeping the selection on the same node
+    fn rebuild(&mut self) {
+        let selected = self.rows.get(self.selected).map(|row| row.path.clone());
+        self.rows.clear();
+        for root in &self.roots {
+            if let Some(node) = self.root.at(root) {
+                let label = match self.query {
+                    Some(_) => path_string(root),
+                    None => String::new(),
+                };
+                push_rows(&mut self.rows, &self.expanded, node, root.clone(), label, 0);
+            }
+        }
+        if let Some(index) = selected.and_then(|path| self.rows.iter().position(|row| row.path == path)) {
+            self.selected = index;
+        }
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Rows or pretty lines there are to scroll through
+    pub fn len(&self) -> usize {
+        match &self.pretty {
+            Some(lines) => lines.len(),
+            None => self.rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Move the selection, or scroll the pretty-printed text, by `delta`
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.len().saturating_sub(1);
+        match self.pretty {
+            Some(_) => self.offset = self.offset.saturating_add_signed(delta).min(last),
+            None => self.selected = self.selected.saturating_add_signed(delta).min(last),
+        }
+    }
+
+    /// Keep the selection within the `height` rows on screen
+    pub fn scroll_into_view(&mut self, height: usize) {
+        let height = height.max(1);
+        if self.pretty.is_some() {
+            self.offset = self.offset.min(self.len().saturating_sub(height));
+        } else if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+    }
+
+    /// Open or shut the selected container
+    pub fn toggle(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+        if row.expanded.is_some() && !self.expanded.remove(&row.path) {
+            self.expanded.insert(row.path.clone());
+        }
+        self.rebuild();
+    }
+
+    /// Open the selected container, or step into it when already open
+    pub fn expand(&mut self) {
+        match self.rows.get(self.selected).and_then(|row| row.expanded) {
+            Some(false) => self.toggle(),
+            Some(true) if self.selected + 1 < self.rows.len() => self.selected += 1,
+            _ => {}
+        }
+    }
+
+    /// Shut the selected container, or step out to its parent
+    pub fn collapse(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+        if row.expanded == Some(true) {
+            self.toggle();
+        } else if let Some(parent) = self.rows[..self.selected].iter().rposition(|above| above.depth < row.depth) {
+            self.selected = parent;
+        }
+    }
+
+    pub fn expand_all(&mut self) {
+        for root in self.roots.clone() {
+            self.expand_to_depth(root, usize::MAX);
+        }
+        self.rebuild();
+    }
+
+    /// Shut everything below the top level
+    pub fn collapse_all(&mut self) {
+        self.expanded = self.roots.iter().cloned().collect();
+        self.rebuild();
+    }
+
+    /// Narrow the tree to the nodes a jq-style query picks out; an empty
+    /// query or `.` shows the whole document again. Returns the number of
+    /// matches, and leaves the tree as it was when there are none
+    pub fn set_query(&mut self, text: &str) -> anyhow::Result<usize> {
+        let text = text.trim();
+        if text.is_empty() || text == "." {
+            self.query = None;
+            self.roots = vec![Vec::new()];
+        } else {
+            let paths = query(&self.root, text)?;
+            if paths.is_empty() {
+                return Err(anyhow::anyhow!("Nothing matches {}", text));
+            }
+            self.query = Some(text.to_string());
+            for path in &paths {
+                self.expanded.insert(path.clone());
+            }
+            self.roots = paths;
+        }
+        self.selected = 0;
+        self.offset = 0;
+        if self.pretty.is_some() {
+            self.pretty = Some(self.pretty_lines());
+        }
+        self.rebuild();
+        Ok(self.roots.len())
+    }
+
+    /// jq-style path of the selected node
+    pub fn selected_path(&self) -> Option<String> {
+        self.rows.get(self.selected).map(|row| path_string(&row.path))
+    }
+
+    /// Switch between the rows and the shown nodes pretty-printed as JSON
+    pub fn toggle_pretty(&mut self) {
+        self.offset = 0;
+        self.pretty = match self.pretty {
+            Some(_) => None,
+            None => Some(self.pretty_lines()),
+        };
+    }
+
+    fn pretty_lines(&self) -> Vec<String> {
+        self.roots
+            .iter()
+            .filter_map(|root| self.root.at(root))
+            .filter_map(|node| serde_json::to_string_pretty(node).ok())
+            .flat_map(|text| text.lines().map(str::to_string).collect::<Vec<_>>())
+            .collect()
+    }
+}
+
+fn push_rows(
+    rows: &mut Vec<TreeRow>,
+    expanded: &HashSet<Vec<Segment>>,
+    node: &Node,
+    path: Vec<Segment>,
+    label: String,
+    depth: usize,
+) {
+    let open = match node {
+        Node::Array(_) | Node::Object(_) => Some(expanded.contains(&path)),
+        _ => None,
+    };
+    rows.push(TreeRow {
+        depth,
+        path: path.clone(),
+        label,
+        kind: node.kind(),
+        value: node.summary(),
+        expanded: open,
+    });
+    if open == Some(true) {
+        for (segment, child) in node.children() {
+            let label = match &segment {
+                Segment::Key(key) => key.clone(),
+                Segment::Index(index) => format!("[{}]", index),
+            };
+            let mut child_path = path.clone();
+            child_path.push(segment);
+            push_rows(rows, expanded, child, child_path, label, depth + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_documents() {
+        let text = r#"{"z":1,"a":[true,null,2.5],"n":18446744073709551615}"#;
+        let json = parse_document(text, DocumentFormat::Json).unwrap();
+        let Node::Object(fields) = &json else {
+            panic!("expected an object, got {:?}", json);
+        };
+        let keys: Vec<&str> = fields.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["z", "a", "n"]);
+        assert_eq!(json.at(&[Segment::Key("a".into()), Segment::Index(2)]), Some(&Node::Number("2.5".into())));
+        assert_eq!(serde_json::to_string(&json).unwrap(), text);
+        let big = parse_document("[123456789012345678901234567890, 1.50]", DocumentFormat::Json).unwrap();
+        assert_eq!(
+            big,
+            Node::Array(vec![Node::Number("1.2345678901234568e29".into()), Node::Number("1.5".into())])
+        );
+
+        let yaml = parse_document("name: app\nports:\n  - 80\n  - 443\n1: one\n", DocumentFormat::Yaml).unwrap();
+        assert_eq!(yaml.at(&[Segment::Key("ports".into()), Segment::Index(1)]), Some(&Node::Number("443".into())));
+        assert_eq!(yaml.at(&[Segment::Key("1".into())]), Some(&Node::String("one".into())));
+
+        let toml = parse_document("[package]\nname = \"x\"\nreleased = 1979-05-27\n", DocumentFormat::Toml).unwrap();
+        assert_eq!(
+            toml.at(&[Segment::Key("package".into()), Segment::Key("released".into())]),
+            Some(&Node::String("1979-05-27".into()))
+        );
+
+        let error = parse_document("{\n  \"a\": 1,\n  \"b\" 2\n}", DocumentFormat::Json).unwrap_err();
+        assert_eq!((error.line, error.column), (3, 7));
+        assert_eq!(error.excerpt, "  \"b\" 2");
+        assert!(!error.message.contains("line"));
+        let error = parse_document("a = 1\nb = = 2\n", DocumentFormat::Toml).unwrap_err();
+        assert_eq!(error.line, 2);
+        let error = parse_document("a: [1, 2\nb: 3\n", DocumentFormat::Yaml).unwrap_err();
+        assert!(error.line >= 2);
+    }
+
+    #[test]
+    fn test_query_paths() {
+        let root = parse_document(
+            r#"{"items":[{"name":"a"},{"name":"b"}],"odd key":{"x":1}}"#,
+            DocumentFormat::Json,
+        )
+        .unwrap();
+        let run = |text: &str| -> Vec<String> {
+            query(&root, text).unwrap().iter().map(|path| path_string(path)).collect()
+        };
+        assert_eq!(run("."), ["."]);
+        assert_eq!(run(".items[].name"), [".items[0].name", ".items[1].name"]);
+        assert_eq!(run(".items[-1]"), [".items[1]"]);
+        assert_eq!(run(r#".["odd key"].x"#), [r#".["odd key"].x"#]);
+        assert_eq!(run(r#"."odd key" | .x"#), [r#".["odd key"].x"#]);
+        assert!(run(".missing").is_empty());
+        assert!(query(&root, ".items[x]").is_err());
+        assert!(query(&root, "items").is_err());
+    }
+
+    #[test]
+    fn test_tree_folding() {
+        let mut tree = DocumentTree::parse(r#"{"a":{"b":{"c":1}},"d":[1,2]}"#, DocumentFormat::Json).unwrap();
+        // The root and its children start open, deeper containers folded
+        let labels: Vec<&str> = tree.rows.iter().map(|row| row.label.as_str()).collect();
+        assert_eq!(labels, ["", "a", "b", "d", "[0]", "[1]"]);
+        assert_eq!(tree.rows[2].expanded, Some(false));
+        assert_eq!(tree.rows[2].value, "{1 key}");
+
+        tree.selected = 2;
+        tree.expand();
+        assert_eq!(tree.rows.len(), 7);
+        assert_eq!(tree.selected_path().as_deref(), Some(".a.b"));
+        tree.collapse();
+        tree.collapse();
+        assert_eq!(tree.selected_path().as_deref(), Some(".a"));
+        tree.toggle();
+        assert_eq!(tree.rows.len(), 5);
+        assert_eq!(tree.selected, 1);
+
+        assert_eq!(tree.set_query(".d[]").unwrap(), 2);
+        let labels: Vec<&str> = tree.rows.iter().map(|row| row.label.as_str()).collect();
+        assert_eq!(labels, [".d[0]", ".d[1]"]);
+        assert!(tree.set_query(".nothing").is_err());
+        assert_eq!(tree.rows.len(), 2);
+
+        tree.set_query("").unwrap();
+        tree.expand_all();
+        assert_eq!(tree.rows.len(), 7);
+        tree.toggle_pretty();
+        assert_eq!(tree.pretty.as_ref().unwrap()[..2], ["{".to_string(), "  \"a\": {".to_string()]);
+    }
+}
@@ -0,0 +1,557 @@
+// Lightweight syntax highlighting for the viewer and editor: languages are
+// picked by file name or shebang, lines are tokenized one at a time and the
+// state carried between lines is cached so only new lines get scanned
+
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Comment,
+    String,
+    Number,
+    Keyword,
+    Type,
+    Function,
+    Constant,
+}
+
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    file_names: &'static [&'static str],
+    /// Shebang interpreters
+    interpreters: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// String delimiters, longest first
+    strings: &'static [&'static str],
+    /// Delimiters whose strings may run over several lines
+    multiline_strings: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    constants: &'static [&'static str],
+    /// Capitalized identifiers are types
+    capitalized_types: bool,
+    /// `'` opens a character literal only, so `'a` lifetimes stay plain
+    char_literals: bool,
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "break", "case", "const", "continue", "default", "do", "else", "enum", "extern", "for", "goto", "if",
+    "inline", "return", "sizeof", "static", "struct", "switch", "typedef", "union", "volatile", "while",
+    "class", "namespace", "template", "typename", "public", "private", "protected", "virtual", "new",
+    "delete", "using", "auto", "#include", "#define", "#ifdef", "#ifndef", "#endif", "#if", "#else",
+];
+
+/// Keywords that the name of a function follows
+const DEFINITION_KEYWORDS: &[&str] = &["fn", "def", "func", "fun", "function"];
+
+static LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        file_names: &[],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+        multiline_strings: &["\""],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "fn",
+            "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+            "self", "static", "struct", "super", "trait", "type", "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+            "i128", "isize", "f32", "f64",
+        ],
+        constants: &["true", "false", "None", "Some", "Ok", "Err"],
+        capitalized_types: true,
+        char_literals: true,
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw", "pyi"],
+        file_names: &[],
+        interpreters: &["python", "python2", "python3"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"\"\"", "'''", "\"", "'"],
+        multiline_strings: &["\"\"\"", "'''"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+            "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+            "not", "or", "pass", "raise", "return", "try", "while", "with", "yield", "self",
+        ],
+        types: &["int", "float", "str", "bytes", "bool", "list", "dict", "set", "tuple", "object"],
+        constants: &["True", "False", "None"],
+        capitalized_types: true,
+        char_literals: false,
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+        file_names: &[],
+        interpreters: &["node", "deno"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'", "`"],
+        multiline_strings: &["`"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "do",
+            "else", "export", "extends", "finally", "for", "from", "function", "if", "import", "in",
+            "instanceof", "interface", "let", "new", "of", "return", "static", "switch", "this", "throw", "try",
+            "type", "typeof", "var", "void", "while", "yield",
+        ],
+        types: &["string", "number", "boolean", "any", "unknown", "never", "object"],
+        constants: &["true", "false", "null", "undefined", "NaN"],
+        capitalized_types: true,
+        char_literals: false,
+    },
+    Language {
+        name: "C/C++",
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        file_names: &[],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+        multiline_strings: &[],
+        keywords: C_KEYWORDS,
+        types: &[
+            "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned", "bool", "size_t",
+            "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t",
+        ],
+        constants: &["true", "false", "NULL", "nullptr"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+    Language {
+        name: "Java",
+        extensions: &["java", "kt", "kts", "cs"],
+        file_names: &[],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'"],
+        multiline_strings: &[],
+        keywords: &[
+            "abstract", "break", "case", "catch", "class", "continue", "default", "do", "else", "enum",
+            "extends", "final", "finally", "for", "fun", "if", "implements", "import", "interface", "new",
+            "override", "package", "private", "protected", "public", "return", "static", "super", "switch",
+            "this", "throw", "throws", "try", "val", "var", "void", "while",
+        ],
+        types: &["boolean", "byte", "char", "short", "int", "long", "float", "double", "string"],
+        constants: &["true", "false", "null"],
+        capitalized_types: true,
+        char_literals: false,
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        file_names: &[],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\"", "'", "`"],
+        multiline_strings: &["`"],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for",
+            "func", "go", "goto", "if", "import", "interface", "map", "package", "range", "return", "select",
+            "struct", "switch", "type", "var",
+        ],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32", "int64", "rune",
+            "string", "uint", "uint8", "uint16", "uint32", "uint64", "uintptr", "any",
+        ],
+        constants: &["true", "false", "nil", "iota"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "fish"],
+        file_names: &[".bashrc", ".bash_profile", ".zshrc", ".profile", "PKGBUILD"],
+        interpreters: &["sh", "bash", "zsh", "dash", "ksh", "fish"],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"", "'"],
+        multiline_strings: &["\"", "'"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac", "in",
+            "function", "return", "local", "export", "readonly", "source", "exit",
+        ],
+        types: &[],
+        constants: &["true", "false"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        file_names: &[],
+        interpreters: &["lua", "luajit"],
+        line_comments: &["--"],
+        block_comment: Some(("--[[", "]]")),
+        strings: &["\"", "'"],
+        multiline_strings: &[],
+        keywords: &[
+            "and", "break", "do", "else", "elseif", "end", "for", "function", "goto", "if", "in", "local", "not",
+            "or", "repeat", "return", "then", "until", "while",
+        ],
+        types: &[],
+        constants: &["true", "false", "nil"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        file_names: &["Cargo.lock"],
+        interpreters: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"\"\"", "'''", "\"", "'"],
+        multiline_strings: &["\"\"\"", "'''"],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        file_names: &[],
+        interpreters: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &["\"", "'"],
+        multiline_strings: &[],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "null", "yes", "no", "on", "off"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json", "jsonc", "json5"],
+        file_names: &[],
+        interpreters: &[],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &["\""],
+        multiline_strings: &[],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "null"],
+        capitalized_types: false,
+        char_literals: false,
+    },
+];
+
+/// The language of a file, from its name or else its shebang line
+pub fn detect_language(path: &Path, first_line: &str) -> Option<&'static Language> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+    let by_name = LANGUAGES.iter().find(|language| {
+        language.file_names.contains(&name)
+            || extension.as_deref().is_some_and(|ext| language.extensions.contains(&ext))
+    });
+    if by_name.is_some() {
+        return by_name;
+    }
+
+    // `#!/bin/sh`, `#!/usr/bin/env python3`
+    let command = first_line.strip_prefix("#!")?.split_whitespace().collect::<Vec<_>>();
+    let program = match command.first()?.rsplit('/').next()? {
+        "env" => command.iter().skip(1).find(|arg| !arg.starts_with('-'))?,
+        program => program,
+    };
+    LANGUAGES.iter().find(|language| language.interpreters.contains(&program))
+}
+
+/// What an unfinished construct at the end of a line carries into the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineState {
+    Normal,
+    BlockComment,
+    /// Inside a string opened with `strings[index]`
+    String(usize),
+}
+
+/// Tokenizes the lines of one file, remembering the state each line starts in
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    language: &'static Language,
+    /// Start states of the lines scanned so far, plus the line after them
+    states: Vec<LineState>,
+}
+
+impl Highlighter {
+    pub fn new(language: &'static Language) -> Self {
+        Self {
+            language,
+            states: vec![LineState::Normal],
+        }
+    }
+
+    pub fn language(&self) -> &'static Language {
+        self.language
+    }
+
+    /// Lines before `row` whose end state is known
+    pub fn scanned_lines(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    /// Forget what was scanned from line `row` on, after it was edited
+    pub fn invalidate_from(&mut self, row: usize) {
+        self.states.truncate(row + 1);
+    }
+
+    /// Scan line `row` without keeping its tokens, to learn the next line's
+    /// start state; lines have to be fed in order until they are known
+    pub fn advance(&mut self, row: usize, text: &str) {
+        if row == self.scanned_lines() {
+            self.highlight_line(row, text);
+        }
+    }
+
+    /// Tokens of a line whose number is not known, taken to start outside
+    /// any comment or string
+    pub fn highlight_detached(&self, text: &str) -> Vec<(Range<usize>, TokenKind)> {
+        let mut tokens = Vec::new();
+        scan(self.language, LineState::Normal, text, &mut tokens);
+        tokens
+    }
+
+    /// Tokens of line `row` as byte ranges; a line past the scanned ones is
+    /// taken to start outside any comment or string
+    pub fn highlight_line(&mut self, row: usize, text: &str) -> Vec<(Range<usize>, TokenKind)> {
+        let start = self.states.get(row).copied().unwrap_or(LineState::Normal);
+        let mut tokens = Vec::new();
+        let end = scan(self.language, start, text, &mut tokens);
+        match self.states.get(row + 1) {
+            Some(&known) if known == end => {}
+            Some(_) => {
+                // Everything below depended on the old end state
+                self.states.truncate(row + 1);
+                self.states.push(end);
+            }
+            None if row + 1 == self.states.len() => self.states.push(end),
+            None => {}
+        }
+        tokens
+    }
+}
+
+fn scan(language: &Language, state: LineState, text: &str, tokens: &mut Vec<(Range<usize>, TokenKind)>) -> LineState {
+    let mut i = 0;
+    match state {
+        LineState::Normal => {}
+        LineState::BlockComment => {
+            let (_, close) = language.block_comment.expect("only languages with block comments get here");
+            match text.find(close) {
+                Some(end) => {
+                    i = end + close.len();
+                    tokens.push((0..i, TokenKind::Comment));
+                }
+                None => {
+                    tokens.push((0..text.len(), TokenKind::Comment));
+                    return LineState::BlockComment;
+                }
+            }
+        }
+        LineState::String(index) => match string_end(text, 0, language.strings[index]) {
+            Some(end) => {
+                tokens.push((0..end, TokenKind::String));
+                i = end;
+            }
+            None => {
+                tokens.push((0..text.len(), TokenKind::String));
+                return state;
+            }
+        },
+    }
+
+    let bytes = text.as_bytes();
+    // The word before, to spot the name in `fn name<T>(`
+    let mut previous_word = "";
+    while i < text.len() {
+        let rest = &text[i..];
+        if let Some((open, close)) = language.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+            match rest[open.len()..].find(close) {
+                Some(end) => {
+                    let end = i + open.len() + end + close.len();
+                    tokens.push((i..end, TokenKind::Comment));
+                    i = end;
+                    continue;
+                }
+                None => {
+                    tokens.push((i..text.len(), TokenKind::Comment));
+                    return LineState::BlockComment;
+                }
+            }
+        }
+        if language.line_comments.iter().any(|marker| rest.starts_with(marker)) {
+            tokens.push((i..text.len(), TokenKind::Comment));
+            break;
+        }
+        if let Some(index) = language.strings.iter().position(|delimiter| rest.starts_with(delimiter)) {
+            let delimiter = language.strings[index];
+            if delimiter == "'" && language.char_literals && !is_char_literal(rest) {
+                i += 1;
+                continue;
+            }
+            match string_end(text, i + delimiter.len(), delimiter) {
+                Some(end) => {
+                    tokens.push((i..end, TokenKind::String));
+                    i = end;
+                    continue;
+                }
+                None => {
+                    tokens.push((i..text.len(), TokenKind::String));
+                    return match language.multiline_strings.contains(&delimiter) {
+                        true => LineState::String(index),
+                        false => LineState::Normal,
+                    };
+                }
+            }
+        }
+
+        let c = rest.chars().next().expect("rest is not empty");
+        let follows_word = i > 0 && is_word_byte(bytes[i - 1]);
+        if c.is_ascii_digit() && !follows_word {
+            let end = i + rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            tokens.push((i..end, TokenKind::Number));
+            i = end;
+        } else if (c.is_alphabetic() || c == '_' || c == '#') && !follows_word {
+            let len = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+                .map_or(rest.len(), |(at, _)| at);
+            let word = &rest[..len];
+            let kind = if language.keywords.contains(&word) {
+                Some(TokenKind::Keyword)
+            } else if language.constants.contains(&word) {
+                Some(TokenKind::Constant)
+            } else if language.types.contains(&word)
+                || (language.capitalized_types && word.starts_with(|c: char| c.is_uppercase()))
+            {
+                Some(TokenKind::Type)
+            } else if (rest[len..].trim_start().starts_with('(') && c != '#')
+                || DEFINITION_KEYWORDS.contains(&previous_word)
+            {
+                Some(TokenKind::Function)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                tokens.push((i..i + len, kind));
+            }
+            previous_word = word;
+            i += len;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+    LineState::Normal
+}
+
+/// End of a string whose contents start at `from`, past its closing delimiter
+fn string_end(text: &str, from: usize, delimiter: &str) -> Option<usize> {
+    let mut chars = text[from..].char_indices();
+    while let Some((at, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if text[from + at..].starts_with(delimiter) {
+            return Some(from + at + delimiter.len());
+        }
+    }
+    None
+}
+
+/// `'x'` or `'\n'`, as opposed to a lifetime or label like `'a`
+fn is_char_literal(rest: &str) -> bool {
+    let mut chars = rest.chars().skip(1);
+    match chars.next() {
+        Some('\\') => true,
+        Some(_) => chars.next() == Some('\''),
+        None => false,
+    }
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(highlighter: &mut Highlighter, row: usize, text: &str) -> Vec<(String, TokenKind)> {
+        highlighter
+            .highlight_line(row, text)
+            .into_iter()
+            .map(|(range, kind)| (text[range].to_string(), kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language(Path::new("src/main.rs"), "").unwrap().name, "Rust");
+        assert_eq!(detect_language(Path::new("Cargo.lock"), "").unwrap().name, "TOML");
+        assert_eq!(detect_language(Path::new("run"), "#!/usr/bin/env python3").unwrap().name, "Python");
+        assert_eq!(detect_language(Path::new("build"), "#!/bin/bash -e").unwrap().name, "Shell");
+        assert!(detect_language(Path::new("notes.txt"), "hello").is_none());
+    }
+
+    #[test]
+    fn test_highlight_lines() {
+        let rust = detect_language(Path::new("lib.rs"), "").unwrap();
+        let mut highlighter = Highlighter::new(rust);
+        assert_eq!(
+            kinds(&mut highlighter, 0, "pub fn parse<'a>(s: &'a str) -> Option<u8> { Some(b'x') } // done"),
+            vec![
+                ("pub".to_string(), TokenKind::Keyword),
+                ("fn".to_string(), TokenKind::Keyword),
+                ("parse".to_string(), TokenKind::Function),
+                ("str".to_string(), TokenKind::Type),
+                ("Option".to_string(), TokenKind::Type),
+                ("u8".to_string(), TokenKind::Type),
+                ("Some".to_string(), TokenKind::Constant),
+                ("'x'".to_string(), TokenKind::String),
+                ("// done".to_string(), TokenKind::Comment),
+            ]
+        );
+
+        // A block comment spans lines, and editing its opening line rescans the rest
+        assert_eq!(kinds(&mut highlighter, 1, "let x = 1; /* start"), vec![
+            ("let".to_string(), TokenKind::Keyword),
+            ("1".to_string(), TokenKind::Number),
+            ("/* start".to_string(), TokenKind::Comment),
+        ]);
+        assert_eq!(kinds(&mut highlighter, 2, "still comment */ x"), vec![
+            ("still comment */".to_string(), TokenKind::Comment),
+        ]);
+        assert_eq!(highlighter.scanned_lines(), 3);
+        highlighter.invalidate_from(1);
+        kinds(&mut highlighter, 1, "let x = 1;");
+        assert_eq!(kinds(&mut highlighter, 2, "still comment */ x"), vec![]);
+
+        let python = detect_language(Path::new("a.py"), "").unwrap();
+        let mut highlighter = Highlighter::new(python);
+        highlighter.advance(0, "doc = \"\"\"first");
+        assert_eq!(kinds(&mut highlighter, 1, "second\"\"\" # note"), vec![
+            ("second\"\"\"".to_string(), TokenKind::String),
+            ("# note".to_string(), TokenKind::Comment),
+        ]);
+    }
+}
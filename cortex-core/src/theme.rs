@@ -327,6 +327,20 @@ impl Theme {
             .add_modifier(Modifier::UNDERLINED | Modifier::BOLD)
     }
 
+    /// Style of a syntax token in the viewer and editor
+    pub fn get_syntax_style(&self, kind: crate::syntax::TokenKind) -> Style {
+        use crate::syntax::TokenKind;
+        match kind {
+            TokenKind::Comment => Style::default().fg(self.dim_text).add_modifier(Modifier::ITALIC),
+            TokenKind::String => Style::default().fg(self.success),
+            TokenKind::Number => Style::default().fg(self.warning),
+            TokenKind::Keyword => Style::default().fg(self.directory).add_modifier(Modifier::BOLD),
+            TokenKind::Type => Style::default().fg(self.info),
+            TokenKind::Function => Style::default().fg(self.document),
+            TokenKind::Constant => Style::default().fg(self.image),
+        }
+    }
+
     pub fn get_border_style(&self, is_active: bool) -> Style {
         if is_active {
             Style::default()
@@ -2,14 +2,19 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::SystemTime;
 
+use crate::git::GitTreeProvider;
 #[cfg(feature = "ssh")]
 use crate::remote::{SshConnectionManager, SftpProvider, FtpProvider};
 #[cfg(feature = "ssh")]
 use std::sync::Arc;
+#[cfg(feature = "s3")]
+use crate::remote::S3Provider;
 
 /// Virtual File System - abstraction over regular files and archive contents
 pub struct VirtualFileSystem {
@@ -46,6 +51,286 @@ pub enum VfsPath {
         username: String,
         path: String,
     },
+    S3 {
+        endpoint: String,
+        bucket: String,
+        key: String,
+    },
+    /// A tree in repository history; an empty `rev` lists the available revisions
+    GitTree {
+        repo: PathBuf,
+        rev: String,
+        path: String,
+    },
+}
+
+const SFTP_DEFAULT_PORT: u16 = 22;
+const FTP_DEFAULT_PORT: u16 = 21;
+const S3_DEFAULT_ENDPOINT: &str = "https://s3.amazonaws.com";
+const URL_SCHEMES: &[&str] = &[
+    "file", "sftp", "ftp", "s3", "s3+http", "s3+https", "archive", "git-rev",
+];
+
+impl VfsPath {
+    /// Whether `s` starts with one of the URL schemes `VfsPath` understands
+    pub fn is_url(s: &str) -> bool {
+        s.split_once("://")
+            .is_some_and(|(scheme, _)| URL_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()))
+    }
+
+    /// The directory containing this location, if it has one
+    pub fn parent(&self) -> Option<VfsPath> {
+        fn parent_of(path: &str) -> Option<String> {
+            let trimmed = path.trim_end_matches('/');
+            match trimmed.rsplit_once('/') {
+                Some(("", _)) if path.starts_with('/') => Some("/".to_string()),
+                Some((dir, _)) => Some(dir.to_string()),
+                None if trimmed.is_empty() => None,
+                None => Some(String::new()),
+            }
+        }
+
+        match self {
+            VfsPath::Local(path) => path.parent().map(|parent| VfsPath::Local(parent.to_path_buf())),
+            VfsPath::Archive { archive_path, internal_path } => Some(VfsPath::Archive {
+                archive_path: archive_path.clone(),
+                internal_path: parent_of(internal_path)?,
+            }),
+            VfsPath::Sftp { host, port, username, path } => Some(VfsPath::Sftp {
+                host: host.clone(),
+                port: *port,
+                username: username.clone(),
+                path: parent_of(path)?,
+            }),
+            VfsPath::Ftp { host, port, username, path } => Some(VfsPath::Ftp {
+                host: host.clone(),
+                port: *port,
+                username: username.clone(),
+                path: parent_of(path)?,
+            }),
+            // S3 prefixes keep their trailing slash
+            VfsPath::S3 { endpoint, bucket, key } => {
+                let prefix = parent_of(key)?;
+                Some(VfsPath::S3 {
+                    endpoint: endpoint.clone(),
+                    bucket: bucket.clone(),
+                    key: if prefix.is_empty() { prefix } else { format!("{}/", prefix) },
+                })
+            }
+            VfsPath::GitTree { repo, rev, path } => Some(VfsPath::GitTree {
+                repo: repo.clone(),
+                rev: rev.clone(),
+                path: parent_of(path)?,
+            }),
+        }
+    }
+}
+
+/// URL form of a location, e.g. `sftp://deploy@web1/var/log`,
+/// `archive:///tmp/x.zip#inner/dir` or `git-rev:///src/repo#v1.2:README.md`.
+/// Local paths are shown as plain paths.
+impl fmt::Display for VfsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VfsPath::Local(path) => write!(f, "{}", path.display()),
+            VfsPath::Archive { archive_path, internal_path } => {
+                write!(f, "archive://{}", encode(&archive_path.to_string_lossy()))?;
+                if !internal_path.is_empty() {
+                    write!(f, "#{}", encode(internal_path))?;
+                }
+                Ok(())
+            }
+            VfsPath::Sftp { host, port, username, path } => {
+                write_remote(f, "sftp", username, host, *port, SFTP_DEFAULT_PORT, path)
+            }
+            VfsPath::Ftp { host, port, username, path } => {
+                write_remote(f, "ftp", username, host, *port, FTP_DEFAULT_PORT, path)
+            }
+            VfsPath::S3 { endpoint, bucket, key } => {
+                if endpoint.is_empty() || endpoint == S3_DEFAULT_ENDPOINT {
+                    write!(f, "s3://")?;
+                } else {
+                    write!(f, "s3+{}/", endpoint.trim_end_matches('/'))?;
+                }
+                write!(f, "{}", bucket)?;
+                if !key.is_empty() {
+                    write!(f, "/{}", encode(key))?;
+                }
+                Ok(())
+            }
+            VfsPath::GitTree { repo, rev, path } => {
+                write!(f, "git-rev://{}", encode(&repo.to_string_lossy()))?;
+                if !rev.is_empty() {
+                    write!(f, "#{}:{}", rev, encode(path))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for VfsPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((scheme, rest)) = s.split_once("://").filter(|_| Self::is_url(s)) else {
+            return Ok(VfsPath::Local(PathBuf::from(s)));
+        };
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "file" => Ok(VfsPath::Local(PathBuf::from(decode(rest)?))),
+            "sftp" => {
+                let (username, host, port, path) = parse_authority(rest, SFTP_DEFAULT_PORT)?;
+                Ok(VfsPath::Sftp {
+                    host,
+                    port,
+                    // Like ssh, fall back to the local user name
+                    username: username
+                        .or_else(|| std::env::var("USER").ok())
+                        .unwrap_or_default(),
+                    path,
+                })
+            }
+            "ftp" => {
+                let (username, host, port, path) = parse_authority(rest, FTP_DEFAULT_PORT)?;
+                Ok(VfsPath::Ftp {
+                    host,
+                    port,
+                    username: username.unwrap_or_else(|| "anonymous".to_string()),
+                    path,
+                })
+            }
+            "s3" | "s3+http" | "s3+https" => {
+                let (endpoint, rest) = if scheme.len() > 2 {
+                    let (host, rest) = rest.split_once('/').unwrap_or((rest, ""));
+                    (format!("{}://{}", &scheme[3..], host), rest)
+                } else {
+                    (S3_DEFAULT_ENDPOINT.to_string(), rest)
+                };
+                let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+                Ok(VfsPath::S3 {
+                    endpoint,
+                    bucket: bucket.to_string(),
+                    key: decode(key)?,
+                })
+            }
+            "archive" => {
+                let (archive, inner) = rest.split_once('#').unwrap_or((rest, ""));
+                if archive.is_empty() {
+                    return Err(anyhow::anyhow!("Missing archive file in {}", s));
+                }
+                Ok(VfsPath::Archive {
+                    archive_path: PathBuf::from(decode(archive)?),
+                    internal_path: decode(inner.trim_start_matches('/'))?,
+                })
+            }
+            "git-rev" => {
+                let (repo, tree) = rest.split_once('#').unwrap_or((rest, ""));
+                let (rev, path) = tree.split_once(':').unwrap_or((tree, ""));
+                crate::git::check_rev(rev)?;
+                Ok(VfsPath::GitTree {
+                    repo: PathBuf::from(decode(repo)?),
+                    rev: rev.to_string(),
+                    path: decode(path.trim_matches('/'))?,
+                })
+            }
+            _ => unreachable!("scheme checked by is_url"),
+        }
+    }
+}
+
+fn write_remote(
+    f: &mut fmt::Formatter<'_>,
+    scheme: &str,
+    username: &str,
+    host: &str,
+    port: u16,
+    default_port: u16,
+    path: &str,
+) -> fmt::Result {
+    write!(f, "{}://", scheme)?;
+    if !username.is_empty() {
+        write!(f, "{}@", encode(username))?;
+    }
+    if host.contains(':') {
+        write!(f, "[{}]", host)?;
+    } else {
+        write!(f, "{}", host)?;
+    }
+    if port != default_port {
+        write!(f, ":{}", port)?;
+    }
+    if !path.starts_with('/') {
+        write!(f, "/")?;
+    }
+    write!(f, "{}", encode(path))
+}
+
+/// Split `[user@]host[:port][/path]`; IPv6 hosts go in brackets
+fn parse_authority(rest: &str, default_port: u16) -> Result<(Option<String>, String, u16, String)> {
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let (username, host_port) = match authority.rsplit_once('@') {
+        Some((user, host_port)) => (Some(decode(user)?), host_port),
+        None => (None, authority),
+    };
+
+    let (host, port) = if let Some(bracketed) = host_port.strip_prefix('[') {
+        let (host, after) = bracketed
+            .split_once(']')
+            .ok_or_else(|| anyhow::anyhow!("Unterminated IPv6 address in {}", rest))?;
+        (host, after.strip_prefix(':'))
+    } else {
+        match host_port.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(anyhow::anyhow!("Missing host in {}", rest));
+    }
+    let port = match port {
+        Some(port) => port
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid port: {}", port))?,
+        None => default_port,
+    };
+
+    Ok((username, host.to_string(), port, decode(path)?))
+}
+
+/// Escape the characters that would otherwise be read as URL syntax
+fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '%' | '#' | ' ' | '@' | '?' => out.push_str(&format!("%{:02X}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid percent escape in {}", s))?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(out)?)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,6 +354,14 @@ pub struct RemoteCredentials {
     pub passphrase: Option<String>,
 }
 
+/// Health of a remote connection, shown on panels browsing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connected,
+    Reconnecting,
+    Offline,
+}
+
 /// Trait for VFS providers
 pub trait VfsProvider: Send + Sync {
     fn can_handle(&self, path: &VfsPath) -> bool;
@@ -78,6 +371,31 @@ pub trait VfsProvider: Send + Sync {
     fn create_directory(&self, path: &VfsPath) -> Result<()>;
     fn delete(&self, path: &VfsPath) -> Result<()>;
     fn get_info(&self, path: &VfsPath) -> Result<VfsEntry>;
+
+    /// List `path`, handing entries to `sink` in batches of up to `batch_size` as they
+    /// are read; `sink` returns false to stop early. Local, SFTP and S3 listings stream;
+    /// the rest (archives, FTP, SCP, git) get the listing whole and split it up afterwards
+    fn list_entries_batched(
+        &self,
+        path: &VfsPath,
+        batch_size: usize,
+        sink: &mut dyn FnMut(Vec<VfsEntry>) -> bool,
+    ) -> Result<()> {
+        for batch in self.list_entries(path)?.chunks(batch_size) {
+            if !sink(batch.to_vec()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Health of the connection behind `path`, for providers that keep one open
+    fn connection_status(&self, _path: &VfsPath) -> Option<ConnectionStatus> {
+        None
+    }
+
+    /// Probe open connections so dead ones are noticed and re-established between calls
+    fn keepalive(&self) {}
 }
 
 impl Default for VirtualFileSystem {
@@ -87,27 +405,50 @@ impl Default for VirtualFileSystem {
 }
 
 impl VirtualFileSystem {
+    #[cfg(not(feature = "ssh"))]
+    pub fn new() -> Self {
+        Self {
+            providers: Self::builtin_providers(),
+        }
+    }
+
+    #[cfg(feature = "ssh")]
     pub fn new() -> Self {
+        Self::with_ssh_manager(Arc::new(SshConnectionManager::new()))
+    }
+
+    /// Built-in providers with SFTP sessions pooled in `ssh_manager`, so whoever else
+    /// holds the manager works on the same connections as the panels
+    #[cfg(feature = "ssh")]
+    pub fn with_ssh_manager(ssh_manager: Arc<SshConnectionManager>) -> Self {
+        let credentials = crate::remote::ssh_manager::RemoteCredentials {
+            username: String::new(),
+            password: None,
+            private_key_path: None,
+            passphrase: None,
+        };
+        let mut providers = Self::builtin_providers();
+        providers.push(Box::new(SftpProvider::new(ssh_manager, credentials)));
+        providers.push(Box::new(FtpProvider::new()));
+        Self { providers }
+    }
+
+    fn builtin_providers() -> Vec<Box<dyn VfsProvider>> {
         #[allow(unused_mut)]
         let mut providers: Vec<Box<dyn VfsProvider>> = vec![
             Box::new(LocalFileSystemProvider),
             Box::new(ArchiveProvider::new()),
+            Box::new(GitTreeProvider),
         ];
-        
-        #[cfg(feature = "ssh")]
-        {
-            let ssh_manager = Arc::new(SshConnectionManager::new());
-            let credentials = crate::remote::ssh_manager::RemoteCredentials {
-                username: String::new(),
-                password: None,
-                private_key_path: None,
-                passphrase: None,
-            };
-            providers.push(Box::new(SftpProvider::new(ssh_manager, credentials.clone())));
-            providers.push(Box::new(FtpProvider::new()));
-        }
-        
-        Self { providers }
+
+        #[cfg(feature = "s3")]
+        providers.push(Box::new(S3Provider::new()));
+
+        providers
+    }
+
+    pub(crate) fn into_providers(self) -> Vec<Box<dyn VfsProvider>> {
+        self.providers
     }
 
     pub fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
@@ -127,6 +468,60 @@ impl VirtualFileSystem {
         }
         Err(anyhow::anyhow!("No provider found for path"))
     }
+
+    pub fn write_file(&self, path: &VfsPath, data: Box<dyn Read + Send>) -> Result<()> {
+        for provider in &self.providers {
+            if provider.can_handle(path) {
+                return provider.write_file(path, data);
+            }
+        }
+        Err(anyhow::anyhow!("No provider found for path"))
+    }
+
+    pub fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        for provider in &self.providers {
+            if provider.can_handle(path) {
+                return provider.get_info(path);
+            }
+        }
+        Err(anyhow::anyhow!("No provider found for path"))
+    }
+
+    pub fn connection_status(&self, path: &VfsPath) -> Option<ConnectionStatus> {
+        self.providers
+            .iter()
+            .find(|provider| provider.can_handle(path))
+            .and_then(|provider| provider.connection_status(path))
+    }
+
+    pub fn keepalive(&self) {
+        for provider in &self.providers {
+            provider.keepalive();
+        }
+    }
+
+    /// Copy a file or directory tree out of any provider into `dest_dir`,
+    /// returning the number of bytes written
+    pub fn copy_to_local(&self, entry: &VfsEntry, dest_dir: &Path) -> Result<u64> {
+        let target = dest_dir.join(&entry.name);
+        match entry.entry_type {
+            VfsEntryType::Directory => {
+                std::fs::create_dir_all(&target)?;
+                let mut copied = 0;
+                for child in self.list_entries(&entry.path)? {
+                    if child.name != ".." {
+                        copied += self.copy_to_local(&child, &target)?;
+                    }
+                }
+                Ok(copied)
+            }
+            _ => {
+                let mut reader = self.read_file(&entry.path)?;
+                let mut file = std::fs::File::create(&target)?;
+                Ok(std::io::copy(&mut reader, &mut file)?)
+            }
+        }
+    }
 }
 
 /// Local file system provider
@@ -138,6 +533,20 @@ impl VfsProvider for LocalFileSystemProvider {
     }
 
     fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+        let mut entries = Vec::new();
+        self.list_entries_batched(path, usize::MAX, &mut |batch| {
+            entries.extend(batch);
+            true
+        })?;
+        Ok(entries)
+    }
+
+    fn list_entries_batched(
+        &self,
+        path: &VfsPath,
+        batch_size: usize,
+        sink: &mut dyn FnMut(Vec<VfsEntry>) -> bool,
+    ) -> Result<()> {
         match path {
             VfsPath::Local(local_path) => {
                 let mut entries = Vec::new();
@@ -173,9 +582,16 @@ impl VfsProvider for LocalFileSystemProvider {
                         permissions: String::new(),
                         compressed_size: None,
                     });
+
+                    if entries.len() >= batch_size && !sink(std::mem::take(&mut entries)) {
+                        return Ok(());
+                    }
                 }
 
-                Ok(entries)
+                if !entries.is_empty() {
+                    sink(entries);
+                }
+                Ok(())
             }
             _ => Err(anyhow::anyhow!(
                 "LocalFileSystemProvider can only handle local paths"
@@ -195,9 +611,17 @@ impl VfsProvider for LocalFileSystemProvider {
         }
     }
 
-    fn write_file(&self, _path: &VfsPath, _data: Box<dyn Read + Send>) -> Result<()> {
-        // Implementation would go here
-        Ok(())
+    fn write_file(&self, path: &VfsPath, mut data: Box<dyn Read + Send>) -> Result<()> {
+        match path {
+            VfsPath::Local(local_path) => {
+                let mut file = std::fs::File::create(local_path)?;
+                std::io::copy(&mut data, &mut file)?;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!(
+                "LocalFileSystemProvider can only handle local paths"
+            )),
+        }
     }
 
     fn create_directory(&self, path: &VfsPath) -> Result<()> {
@@ -256,10 +680,8 @@ impl VfsProvider for LocalFileSystemProvider {
     }
 }
 
-/// Archive provider for ZIP, TAR, etc.
-pub struct ArchiveProvider {
-    // Archive handling would go here
-}
+/// Archive provider for ZIP and TAR (optionally gzipped) archives
+pub struct ArchiveProvider {}
 
 impl Default for ArchiveProvider {
     fn default() -> Self {
@@ -273,18 +695,273 @@ impl ArchiveProvider {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn archive_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") || name.ends_with(".jar") {
+        Some(ArchiveFormat::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveFormat::Tar)
+    } else {
+        None
+    }
+}
+
+/// Whether `path` names an archive `ArchiveProvider` can open
+pub fn is_archive(path: &Path) -> bool {
+    archive_format(path).is_some()
+}
+
+pub(crate) struct ArchiveMember {
+    pub(crate) path: String,
+    pub(crate) is_dir: bool,
+    pub(crate) size: u64,
+    compressed_size: Option<u64>,
+    modified: SystemTime,
+}
+
+/// Member paths without leading "./" or trailing "/"
+fn normalize_member(path: &str) -> String {
+    path.trim_start_matches("./").trim_matches('/').to_string()
+}
+
+fn open_tar(archive_path: &Path, format: ArchiveFormat) -> Result<tar::Archive<Box<dyn Read>>> {
+    let file = std::fs::File::open(archive_path)?;
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+fn zip_modified(time: Option<zip::DateTime>) -> SystemTime {
+    use chrono::TimeZone;
+
+    time.and_then(|time| {
+        chrono::NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+            .and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())
+    })
+    .and_then(|local| chrono::Local.from_local_datetime(&local).earliest())
+    .and_then(|time| u64::try_from(time.timestamp()).ok())
+    .map(|secs| SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+    .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn archive_members(archive_path: &Path) -> Result<Vec<ArchiveMember>> {
+    let format = archive_format(archive_path)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported archive: {}", archive_path.display()))?;
+    let mut members = Vec::new();
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path)?)?;
+            for i in 0..archive.len() {
+                let file = archive.by_index_raw(i)?;
+                members.push(ArchiveMember {
+                    path: normalize_member(file.name()),
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    compressed_size: Some(file.compressed_size()),
+                    modified: zip_modified(file.last_modified()),
+                });
+            }
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let mut archive = open_tar(archive_path, format)?;
+            for entry in archive.entries()? {
+                let entry = entry?;
+                let header = entry.header();
+                members.push(ArchiveMember {
+                    path: normalize_member(&entry.path()?.to_string_lossy()),
+                    is_dir: header.entry_type().is_dir(),
+                    size: header.size().unwrap_or(0),
+                    compressed_size: None,
+                    modified: SystemTime::UNIX_EPOCH
+                        + std::time::Duration::from_secs(header.mtime().unwrap_or(0)),
+                });
+            }
+        }
+    }
+    members.retain(|member| !member.path.is_empty());
+    Ok(members)
+}
+
+fn read_member(archive_path: &Path, internal_path: &str) -> Result<Vec<u8>> {
+    let format = archive_format(archive_path)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported archive: {}", archive_path.display()))?;
+    let wanted = normalize_member(internal_path);
+    let mut data = Vec::new();
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(std::fs::File::open(archive_path)?)?;
+            let index = archive
+                .index_for_name(&wanted)
+                .ok_or_else(|| anyhow::anyhow!("{} not found in {}", wanted, archive_path.display()))?;
+            archive.by_index(index)?.read_to_end(&mut data)?;
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let mut archive = open_tar(archive_path, format)?;
+            let mut entry = archive
+                .entries()?
+                .filter_map(|entry| entry.ok())
+                .find(|entry| {
+                    entry
+                        .path()
+                        .is_ok_and(|path| normalize_member(&path.to_string_lossy()) == wanted)
+                })
+                .ok_or_else(|| anyhow::anyhow!("{} not found in {}", wanted, archive_path.display()))?;
+            entry.read_to_end(&mut data)?;
+        }
+    }
+    Ok(data)
+}
+
+/// Read an archive from start to end once, handing every member to `visit`
+/// with a reader over its contents as it is decoded. `name` decides the
+/// format; `visit` returns false to stop early
+pub(crate) fn scan_archive<R: Read + std::io::Seek>(
+    name: &Path,
+    source: R,
+    mut visit: impl FnMut(&ArchiveMember, &mut dyn Read) -> bool,
+) -> Result<()> {
+    let format = archive_format(name).ok_or_else(|| anyhow::anyhow!("Unsupported archive: {}", name.display()))?;
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(source)?;
+            for i in 0..archive.len() {
+                let mut file = archive.by_index(i)?;
+                let member = ArchiveMember {
+                    path: normalize_member(file.name()),
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    compressed_size: Some(file.compressed_size()),
+                    modified: zip_modified(file.last_modified()),
+                };
+                if !member.path.is_empty() && !visit(&member, &mut file) {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        ArchiveFormat::TarGz => scan_tar(tar::Archive::new(flate2::read::GzDecoder::new(source)), visit),
+        ArchiveFormat::Tar => scan_tar(tar::Archive::new(source), visit),
+    }
+}
+
+fn scan_tar<R: Read>(
+    mut archive: tar::Archive<R>,
+    mut visit: impl FnMut(&ArchiveMember, &mut dyn Read) -> bool,
+) -> Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let header = entry.header();
+        let member = ArchiveMember {
+            path: normalize_member(&entry.path()?.to_string_lossy()),
+            is_dir: header.entry_type().is_dir(),
+            size: header.size().unwrap_or(0),
+            compressed_size: None,
+            modified: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(header.mtime().unwrap_or(0)),
+        };
+        if !member.path.is_empty() && !visit(&member, &mut entry) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn member_entry(archive_path: &Path, path: String, member: Option<&ArchiveMember>) -> VfsEntry {
+    let is_dir = member.is_none_or(|member| member.is_dir);
+    VfsEntry {
+        name: path.rsplit('/').next().unwrap_or(&path).to_string(),
+        path: VfsPath::Archive {
+            archive_path: archive_path.to_path_buf(),
+            internal_path: path,
+        },
+        entry_type: if is_dir {
+            VfsEntryType::Directory
+        } else {
+            VfsEntryType::File
+        },
+        size: member.map_or(0, |member| member.size),
+        modified: member.map_or(SystemTime::UNIX_EPOCH, |member| member.modified),
+        permissions: if is_dir { "rwxr-xr-x" } else { "rw-r--r--" }.to_string(),
+        compressed_size: member.and_then(|member| member.compressed_size),
+    }
+}
+
+/// Every member of an archive, at any depth, in archive order
+pub fn archive_entries(archive_path: &Path) -> Result<Vec<VfsEntry>> {
+    Ok(archive_members(archive_path)?
+        .iter()
+        .map(|member| member_entry(archive_path, member.path.clone(), Some(member)))
+        .collect())
+}
+
 impl VfsProvider for ArchiveProvider {
     fn can_handle(&self, path: &VfsPath) -> bool {
         matches!(path, VfsPath::Archive { .. })
     }
 
-    fn list_entries(&self, _path: &VfsPath) -> Result<Vec<VfsEntry>> {
-        // Archive listing would go here
-        Ok(Vec::new())
+    fn list_entries(&self, path: &VfsPath) -> Result<Vec<VfsEntry>> {
+        let VfsPath::Archive { archive_path, internal_path } = path else {
+            return Err(anyhow::anyhow!("ArchiveProvider can only handle archive paths"));
+        };
+        let prefix = normalize_member(internal_path);
+        let members = archive_members(archive_path)?;
+
+        // Archives need not contain entries for their directories, so derive
+        // them from the paths of the members below
+        let mut children: std::collections::BTreeMap<String, VfsEntry> = Default::default();
+        for member in &members {
+            let relative = if prefix.is_empty() {
+                member.path.as_str()
+            } else {
+                match member.path.strip_prefix(&prefix).and_then(|rest| rest.strip_prefix('/')) {
+                    Some(rest) => rest,
+                    None => continue,
+                }
+            };
+            let child_path = |name: &str| {
+                if prefix.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", prefix, name)
+                }
+            };
+            match relative.split_once('/') {
+                Some((dir, _)) => {
+                    children
+                        .entry(dir.to_string())
+                        .or_insert_with(|| member_entry(archive_path, child_path(dir), None));
+                }
+                None => {
+                    children.insert(
+                        relative.to_string(),
+                        member_entry(archive_path, child_path(relative), Some(member)),
+                    );
+                }
+            }
+        }
+
+        let mut entries: Vec<VfsEntry> = children.into_values().collect();
+        entries.sort_by_key(|entry| !matches!(entry.entry_type, VfsEntryType::Directory));
+        Ok(entries)
     }
 
-    fn read_file(&self, _path: &VfsPath) -> Result<Box<dyn Read + Send>> {
-        Err(anyhow::anyhow!("Archive reading not implemented"))
+    fn read_file(&self, path: &VfsPath) -> Result<Box<dyn Read + Send>> {
+        match path {
+            VfsPath::Archive { archive_path, internal_path } => Ok(Box::new(std::io::Cursor::new(
+                read_member(archive_path, internal_path)?,
+            ))),
+            _ => Err(anyhow::anyhow!("ArchiveProvider can only handle archive paths")),
+        }
     }
 
     fn write_file(&self, _path: &VfsPath, _data: Box<dyn Read + Send>) -> Result<()> {
@@ -299,8 +976,23 @@ impl VfsProvider for ArchiveProvider {
         Err(anyhow::anyhow!("Cannot delete from archives"))
     }
 
-    fn get_info(&self, _path: &VfsPath) -> Result<VfsEntry> {
-        Err(anyhow::anyhow!("Archive info not implemented"))
+    fn get_info(&self, path: &VfsPath) -> Result<VfsEntry> {
+        let VfsPath::Archive { archive_path, internal_path } = path else {
+            return Err(anyhow::anyhow!("ArchiveProvider can only handle archive paths"));
+        };
+        let wanted = normalize_member(internal_path);
+        if wanted.is_empty() {
+            return Ok(member_entry(archive_path, wanted, None));
+        }
+        let members = archive_members(archive_path)?;
+        if let Some(member) = members.iter().find(|member| member.path == wanted) {
+            return Ok(member_entry(archive_path, wanted, Some(member)));
+        }
+        let dir_prefix = format!("{}/", wanted);
+        if members.iter().any(|member| member.path.starts_with(&dir_prefix)) {
+            return Ok(member_entry(archive_path, wanted, None));
+        }
+        Err(anyhow::anyhow!("{} not found in {}", wanted, archive_path.display()))
     }
 }
 
@@ -334,14 +1026,114 @@ impl VirtualFileSystemBuilder {
         self
     }
 
+    #[cfg(feature = "s3")]
+    pub fn with_s3_provider(mut self, provider: S3Provider) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
     pub fn with_archive_provider(mut self) -> Self {
         self.providers.push(Box::new(ArchiveProvider::new()));
         self
     }
 
+    pub fn with_git_provider(mut self) -> Self {
+        self.providers.push(Box::new(GitTreeProvider));
+        self
+    }
+
     pub fn build(self) -> VirtualFileSystem {
         VirtualFileSystem {
             providers: self.providers,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_listing_derives_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("src.tar.gz");
+        let encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&archive_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (name, data) in [("./src/main.rs", "fn main() {}\n"), ("README", "hello\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let provider = ArchiveProvider::new();
+        let at = |internal: &str| VfsPath::Archive {
+            archive_path: archive_path.clone(),
+            internal_path: internal.to_string(),
+        };
+        let root = provider.list_entries(&at("")).unwrap();
+        let names: Vec<_> = root.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["src", "README"]);
+        assert!(matches!(root[0].entry_type, VfsEntryType::Directory));
+
+        let src = provider.list_entries(&root[0].path).unwrap();
+        assert_eq!(src.len(), 1);
+        assert_eq!(src[0].size, 13);
+        let mut content = String::new();
+        provider.read_file(&src[0].path).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "fn main() {}\n");
+        assert!(matches!(provider.get_info(&at("src")).unwrap().entry_type, VfsEntryType::Directory));
+    }
+
+    #[test]
+    fn test_url_round_trip() {
+        let path: VfsPath = "sftp://deploy@web1/var/log".parse().unwrap();
+        assert!(matches!(&path, VfsPath::Sftp { host, port: 22, username, path }
+            if host == "web1" && username == "deploy" && path == "/var/log"));
+        assert_eq!(path.to_string(), "sftp://deploy@web1/var/log");
+
+        let path: VfsPath = "ftp://[::1]:2121/pub/my%20file".parse().unwrap();
+        assert!(matches!(&path, VfsPath::Ftp { host, port: 2121, username, path }
+            if host == "::1" && username == "anonymous" && path == "/pub/my file"));
+        assert_eq!(path.to_string(), "ftp://anonymous@[::1]:2121/pub/my%20file");
+
+        let path: VfsPath = "archive:///tmp/x.zip#inner/dir".parse().unwrap();
+        assert!(matches!(&path, VfsPath::Archive { archive_path, internal_path }
+            if archive_path == Path::new("/tmp/x.zip") && internal_path == "inner/dir"));
+        assert_eq!(path.to_string(), "archive:///tmp/x.zip#inner/dir");
+
+        let path: VfsPath = "s3+http://localhost:9000/bucket/logs/a.txt".parse().unwrap();
+        assert!(matches!(&path, VfsPath::S3 { endpoint, bucket, key }
+            if endpoint == "http://localhost:9000" && bucket == "bucket" && key == "logs/a.txt"));
+        assert_eq!(path.to_string(), "s3+http://localhost:9000/bucket/logs/a.txt");
+        assert_eq!("s3://b/k".parse::<VfsPath>().unwrap().to_string(), "s3://b/k");
+
+        let path: VfsPath = "git-rev:///src/repo#v1.2:docs/README.md".parse().unwrap();
+        assert!(matches!(&path, VfsPath::GitTree { rev, path, .. } if rev == "v1.2" && path == "docs/README.md"));
+        assert_eq!(path.to_string(), "git-rev:///src/repo#v1.2:docs/README.md");
+        assert!("git-rev:///src/repo#--output=/tmp/x:x".parse::<VfsPath>().is_err());
+
+        assert!(matches!("/home/me".parse::<VfsPath>().unwrap(), VfsPath::Local(_)));
+        assert!("sftp://:22/x".parse::<VfsPath>().is_err());
+        assert!(!VfsPath::is_url("notes://x"));
+        // git:// is git's own transport and stays a plain path
+        assert!(!VfsPath::is_url("git://github.com/user/repo.git"));
+    }
+
+    #[test]
+    fn test_parent_paths() {
+        let parent = |url: &str| url.parse::<VfsPath>().unwrap().parent().map(|p| p.to_string());
+        assert_eq!(parent("sftp://me@web1/var/log").as_deref(), Some("sftp://me@web1/var"));
+        assert_eq!(parent("sftp://me@web1/var").as_deref(), Some("sftp://me@web1/"));
+        assert_eq!(parent("sftp://me@web1/"), None);
+        assert_eq!(parent("archive:///tmp/x.zip#src/main.rs").as_deref(), Some("archive:///tmp/x.zip#src"));
+        assert_eq!(parent("archive:///tmp/x.zip#main.rs").as_deref(), Some("archive:///tmp/x.zip"));
+        assert_eq!(parent("s3://bucket/logs/2024/app.log").as_deref(), Some("s3://bucket/logs/2024/"));
+        assert_eq!(parent("s3://bucket/app.log").as_deref(), Some("s3://bucket"));
+    }
+}
@@ -1,3 +1,4 @@
+use cortex_core::fuzzy_match;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -41,6 +42,13 @@ impl CommandPaletteDialog {
         }
     }
 
+    /// Add commands known only at runtime, such as saved searches
+    pub fn with_commands(mut self, commands: Vec<CommandInfo>) -> Self {
+        self.all_commands.extend(commands);
+        self.filter_commands();
+        self
+    }
+
     fn get_all_commands() -> Vec<CommandInfo> {
         vec![
             // System
@@ -74,6 +82,48 @@ impl CommandPaletteDialog {
                 shortcut: Some("Alt+F7".to_string()),
                 category: "Search".to_string(),
             },
+            CommandInfo {
+                name: "/files".to_string(),
+                description: "Fuzzy find files in the current tree".to_string(),
+                shortcut: Some("Alt+F".to_string()),
+                category: "Search".to_string(),
+            },
+            CommandInfo {
+                name: "/search-rerun".to_string(),
+                description: "Run the last search again".to_string(),
+                shortcut: Some("Shift+F7".to_string()),
+                category: "Search".to_string(),
+            },
+            CommandInfo {
+                name: "/search-save".to_string(),
+                description: "Save the last search under a name".to_string(),
+                shortcut: None,
+                category: "Search".to_string(),
+            },
+            CommandInfo {
+                name: "/search-delete".to_string(),
+                description: "Delete a saved search".to_string(),
+                shortcut: None,
+                category: "Search".to_string(),
+            },
+            CommandInfo {
+                name: "/replace-undo".to_string(),
+                description: "Undo the last replace in files".to_string(),
+                shortcut: None,
+                category: "Search".to_string(),
+            },
+            CommandInfo {
+                name: "/index".to_string(),
+                description: "Show content index status".to_string(),
+                shortcut: None,
+                category: "Search".to_string(),
+            },
+            CommandInfo {
+                name: "/index-rebuild".to_string(),
+                description: "Build the content index for find-in-files".to_string(),
+                shortcut: None,
+                category: "Search".to_string(),
+            },
             // Navigation
             CommandInfo {
                 name: "/cd".to_string(),
@@ -100,6 +150,12 @@ impl CommandPaletteDialog {
                 shortcut: Some("Ctrl+H".to_string()),
                 category: "View".to_string(),
             },
+            CommandInfo {
+                name: "/quickview".to_string(),
+                description: "Preview the current entry in the other panel".to_string(),
+                shortcut: Some("Alt+Q".to_string()),
+                category: "View".to_string(),
+            },
             CommandInfo {
                 name: "/sort".to_string(),
                 description: "Change sort mode".to_string(),
@@ -229,38 +285,33 @@ impl CommandPaletteDialog {
     }
 
     pub fn filter_commands(&mut self) {
-        let query = self.input.trim().to_lowercase();
+        let query = self.input.trim();
 
         if query == "/" {
             // Show all commands
             self.filtered_commands = self.all_commands.clone();
         } else if let Some(search) = query.strip_prefix('/') {
-            // Filter based on text after /
-            self.filtered_commands = self
+            // Rank by fuzzy match, names counting double
+            let mut scored: Vec<(i64, &CommandInfo)> = self
                 .all_commands
                 .iter()
-                .filter(|cmd| {
-                    cmd.name[1..].to_lowercase().contains(search)
-                        || cmd.description.to_lowercase().contains(search)
-                        || cmd.category.to_lowercase().contains(search)
+                .filter_map(|cmd| {
+                    let name = fuzzy_match(search, &cmd.name[1..]).map(|m| m.score * 2);
+                    let other = [&cmd.description, &cmd.category]
+                        .into_iter()
+                        .filter_map(|text| fuzzy_match(search, text))
+                        .map(|m| m.score)
+                        .max();
+                    Some((name.max(other)?, cmd))
                 })
-                .cloned()
                 .collect();
-
-            // Sort by relevance
-            self.filtered_commands.sort_by(|a, b| {
-                let a_starts = a.name[1..].to_lowercase().starts_with(search);
-                let b_starts = b.name[1..].to_lowercase().starts_with(search);
-
-                match (a_starts, b_starts) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a
-                        .category
-                        .cmp(&b.category)
-                        .then_with(|| a.name.cmp(&b.name)),
-                }
+            scored.sort_by(|(a_score, a), (b_score, b)| {
+                b_score
+                    .cmp(a_score)
+                    .then_with(|| a.category.cmp(&b.category))
+                    .then_with(|| a.name.cmp(&b.name))
             });
+            self.filtered_commands = scored.into_iter().map(|(_, cmd)| cmd.clone()).collect();
         }
 
         // Reset selection if needed
@@ -143,7 +143,7 @@ impl ConfigDialog {
             ConfigTab::Themes => 1,
             ConfigTab::AI => 2,
             ConfigTab::Plugins => 4,
-            ConfigTab::Network => 5,
+            ConfigTab::Network => 8,
             ConfigTab::Keybindings => 1,
         }
     }
@@ -212,6 +212,9 @@ impl ConfigDialog {
                 2 => self.config.network.verify_ssl.to_string(),
                 3 => self.config.network.enable_compression.to_string(),
                 4 => self.config.network.known_hosts.join(","),
+                5 => self.config.network.transfer_workers.to_string(),
+                6 => self.config.network.transfer_rate_limit.to_string(),
+                7 => self.config.network.keepalive_interval.to_string(),
                 _ => String::new(),
             },
             ConfigTab::Themes => match self.selected_index {
@@ -273,6 +276,9 @@ impl ConfigDialog {
                         .filter(|s| !s.is_empty())
                         .collect()
                 }
+                5 => self.config.network.transfer_workers = value.parse().unwrap_or(4).max(1),
+                6 => self.config.network.transfer_rate_limit = value.parse().unwrap_or(0),
+                7 => self.config.network.keepalive_interval = value.parse().unwrap_or(30),
                 _ => {}
             },
             ConfigTab::Themes => match self.selected_index {
@@ -566,6 +572,18 @@ impl ConfigDialog {
                     "Known Hosts".to_string(),
                     self.config.network.known_hosts.join(", "),
                 ),
+                (
+                    "Transfer Workers".to_string(),
+                    self.config.network.transfer_workers.to_string(),
+                ),
+                (
+                    "Transfer Limit (KiB/s, 0 = off)".to_string(),
+                    self.config.network.transfer_rate_limit.to_string(),
+                ),
+                (
+                    "Keepalive (s, 0 = off)".to_string(),
+                    self.config.network.keepalive_interval.to_string(),
+                ),
             ],
             ConfigTab::Themes => vec![
                 (
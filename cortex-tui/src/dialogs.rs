@@ -4,8 +4,11 @@ use crate::command_palette_dialog::CommandPaletteDialog;
 use crate::config_dialog::ConfigDialog;
 use crate::connection_dialog::ConnectionDialog;
 use crate::editor_dialog::EditorDialog;
+use crate::hex_editor_dialog::HexEditorDialog;
 use crate::filter_dialog::FilterDialog;
+use crate::fuzzy_finder_dialog::FuzzyFinderDialog;
 use crate::plugin_dialog::PluginDialog;
+use crate::replace_dialog::ReplaceDialog;
 use crate::search_dialog::SearchDialog;
 use crate::viewer_dialog::ViewerDialog;
 use ratatui::{
@@ -25,14 +28,18 @@ pub enum Dialog {
     Help(HelpDialog),
     Viewer(ViewerDialog),
     Editor(EditorDialog),
+    HexEditor(HexEditorDialog),
     Filter(FilterDialog),
     CommandPalette(CommandPaletteDialog),
     Search(SearchDialog),
+    FuzzyFinder(FuzzyFinderDialog),
+    Replace(ReplaceDialog),
     Connection(ConnectionDialog),
     Plugin(PluginDialog),
     Config(ConfigDialog),
     SaveConfirm(SaveConfirmDialog),
     ThemeSelection(ThemeSelectionDialog),
+    Drive(DriveDialog),
     Suggestions(SuggestionsDialog),
     AIChat(AIChatDialog),
     APIKey(APIKeyDialog),
@@ -144,6 +151,103 @@ impl ThemeSelectionDialog {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DriveKind {
+    Mount,
+    Remote,
+    Bookmark,
+}
+
+#[derive(Debug, Clone)]
+pub struct DriveItem {
+    pub kind: DriveKind,
+    pub label: String,
+    pub detail: String,
+    /// Total and available bytes, for mounted filesystems
+    pub usage: Option<(u64, u64)>,
+    pub location: cortex_core::VfsPath,
+}
+
+/// Drive menu for switching a panel to a mounted filesystem, remote connection or bookmark
+#[derive(Debug, Clone)]
+pub struct DriveDialog {
+    pub target: cortex_core::ActivePanel,
+    pub items: Vec<DriveItem>,
+    pub selected_index: usize,
+}
+
+impl DriveDialog {
+    pub fn new(
+        target: cortex_core::ActivePanel,
+        current_dir: &std::path::Path,
+        mounts: Vec<cortex_core::mounts::MountPoint>,
+        remotes: Vec<cortex_core::VfsPath>,
+        bookmarks: Vec<(u8, std::path::PathBuf)>,
+    ) -> Self {
+        let mut items: Vec<DriveItem> = mounts
+            .into_iter()
+            .map(|mount| DriveItem {
+                kind: DriveKind::Mount,
+                label: mount.path.display().to_string(),
+                detail: format!("{} {}", mount.device, mount.fs_type),
+                usage: (mount.total > 0).then_some((mount.total, mount.available)),
+                location: cortex_core::VfsPath::Local(mount.path),
+            })
+            .collect();
+
+        // Start on the filesystem the panel is on now
+        let selected_index = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| match &item.location {
+                cortex_core::VfsPath::Local(path) if current_dir.starts_with(path) => {
+                    Some((path.components().count(), i))
+                }
+                _ => None,
+            })
+            .max()
+            .map(|(_, i)| i)
+            .unwrap_or(0);
+
+        items.extend(remotes.into_iter().map(|location| DriveItem {
+            kind: DriveKind::Remote,
+            label: location.to_string(),
+            detail: String::new(),
+            usage: None,
+            location,
+        }));
+        items.extend(bookmarks.into_iter().map(|(slot, path)| DriveItem {
+            kind: DriveKind::Bookmark,
+            label: path.display().to_string(),
+            detail: format!("Ctrl+{}", slot),
+            usage: None,
+            location: cortex_core::VfsPath::Local(path),
+        }));
+
+        Self {
+            target,
+            items,
+            selected_index,
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.selected_index > 0 {
+            self.selected_index -= 1;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected_index + 1 < self.items.len() {
+            self.selected_index += 1;
+        }
+    }
+
+    pub fn selected(&self) -> Option<&DriveItem> {
+        self.items.get(self.selected_index)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InputDialog {
     pub title: String,
@@ -207,6 +311,8 @@ pub struct ProgressDialog {
     pub total: u64,
     pub message: String,
     pub can_cancel: bool,
+    pub bytes_per_sec: Option<u64>,
+    pub eta_secs: Option<u64>,
 }
 
 impl ProgressDialog {
@@ -218,6 +324,8 @@ impl ProgressDialog {
             total: 0,
             message: String::new(),
             can_cancel: true,
+            bytes_per_sec: None,
+            eta_secs: None,
         }
     }
 
@@ -227,6 +335,12 @@ impl ProgressDialog {
         self.message = message.into();
     }
 
+    /// Record aggregate throughput and estimated time left for transfers
+    pub fn update_throughput(&mut self, bytes_per_sec: u64, eta_secs: Option<u64>) {
+        self.bytes_per_sec = Some(bytes_per_sec);
+        self.eta_secs = eta_secs;
+    }
+
     pub fn percentage(&self) -> f64 {
         if self.total == 0 {
             0.0
@@ -355,11 +469,14 @@ pub fn render_dialog(frame: &mut Frame, dialog: &mut Dialog, theme: &cortex_core
             render_error_dialog(frame, area, d)
         }
         Dialog::Help(d) => render_help_dialog(frame, d),
-        Dialog::Viewer(d) => d.render(frame),
-        Dialog::Editor(d) => d.render(frame),
+        Dialog::Viewer(d) => d.render(frame, theme),
+        Dialog::Editor(d) => d.render(frame, theme),
+        Dialog::HexEditor(d) => d.render(frame, theme),
         Dialog::Filter(d) => d.render(frame),
         Dialog::CommandPalette(d) => d.render(frame),
         Dialog::Search(d) => d.render(frame),
+        Dialog::FuzzyFinder(d) => d.render(frame),
+        Dialog::Replace(d) => d.render(frame),
         Dialog::Connection(d) => d.render(frame),
         Dialog::Plugin(d) => d.render(frame),
         Dialog::Config(d) => d.render(frame, theme),
@@ -371,6 +488,10 @@ pub fn render_dialog(frame: &mut Frame, dialog: &mut Dialog, theme: &cortex_core
             let area = centered_rect(50, 30, frame.size());
             render_theme_selection_dialog(frame, area, d)
         }
+        Dialog::Drive(d) => {
+            let area = centered_rect(70, 60, frame.size());
+            render_drive_dialog(frame, area, d)
+        }
         Dialog::Suggestions(d) => {
             draw_suggestions_dialog(frame, d, theme)
         }
@@ -488,7 +609,26 @@ fn render_progress_dialog(frame: &mut Frame, area: Rect, dialog: &ProgressDialog
         .label(format!("{}%", dialog.percentage() as u16));
     frame.render_widget(progress, chunks[1]);
 
-    let status = Paragraph::new(format!("{} / {} bytes", dialog.current, dialog.total));
+    let mut status_text = format!(
+        "{} / {}",
+        humansize::format_size(dialog.current, humansize::BINARY),
+        humansize::format_size(dialog.total, humansize::BINARY)
+    );
+    if let Some(rate) = dialog.bytes_per_sec {
+        status_text.push_str(&format!(
+            "  {}/s",
+            humansize::format_size(rate, humansize::BINARY)
+        ));
+        match dialog.eta_secs {
+            Some(eta) => status_text.push_str(&format!(
+                "  ETA {:02}:{:02}",
+                eta / 60,
+                eta % 60
+            )),
+            None => status_text.push_str("  ETA --:--"),
+        }
+    }
+    let status = Paragraph::new(status_text);
     frame.render_widget(status, chunks[2]);
 
     let message = Paragraph::new(dialog.message.as_str()).wrap(Wrap { trim: true });
@@ -744,6 +884,97 @@ fn render_theme_selection_dialog(frame: &mut Frame, area: Rect, dialog: &ThemeSe
     frame.render_widget(status, chunks[2]);
 }
 
+fn render_drive_dialog(frame: &mut Frame, area: Rect, dialog: &DriveDialog) {
+    frame.render_widget(Clear, area);
+
+    let title = match dialog.target {
+        cortex_core::ActivePanel::Left => " Left Panel Drive ",
+        cortex_core::ActivePanel::Right => " Right Panel Drive ",
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(inner);
+
+    const BAR_WIDTH: usize = 12;
+    let mut items = Vec::new();
+    let mut section = None;
+    for (i, item) in dialog.items.iter().enumerate() {
+        if section != Some(item.kind) {
+            section = Some(item.kind);
+            let header = match item.kind {
+                DriveKind::Mount => "Filesystems",
+                DriveKind::Remote => "Connections",
+                DriveKind::Bookmark => "Bookmarks",
+            };
+            items.push(ListItem::new(Line::from(Span::styled(
+                header,
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ))));
+        }
+
+        let selected = i == dialog.selected_index;
+        let style = if selected {
+            Style::default()
+                .bg(Color::Rgb(40, 44, 52))
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+
+        let mut spans = vec![
+            Span::styled(
+                format!("{} {:<24} ", if selected { "  ▶" } else { "   " }, item.label),
+                style,
+            ),
+            Span::styled(format!("{:<22} ", item.detail), Style::default().fg(Color::DarkGray)),
+        ];
+        if let Some((total, available)) = item.usage {
+            let used = total.saturating_sub(available);
+            let filled = (used as f64 / total as f64 * BAR_WIDTH as f64).round() as usize;
+            let color = if available * 10 < total { Color::Red } else { Color::Green };
+            spans.push(Span::styled(
+                format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled.min(BAR_WIDTH))),
+                Style::default().fg(color),
+            ));
+            spans.push(Span::raw(format!(
+                " {} free of {}",
+                humansize::format_size(available, humansize::BINARY),
+                humansize::format_size(total, humansize::BINARY)
+            )));
+        }
+        items.push(ListItem::new(Line::from(spans)));
+    }
+
+    // Keep the selection visible; headers add one line per section above it
+    let headers_before = dialog
+        .items
+        .get(..=dialog.selected_index)
+        .unwrap_or(&dialog.items)
+        .windows(2)
+        .filter(|pair| pair[0].kind != pair[1].kind)
+        .count()
+        + 1;
+    let selected_line = dialog.selected_index + headers_before;
+    let height = chunks[0].height as usize;
+    let skip = (selected_line + 1).saturating_sub(height);
+    frame.render_widget(List::new(items.into_iter().skip(skip).collect::<Vec<_>>()), chunks[0]);
+
+    let help = Paragraph::new("↑/↓ select, Enter switch panel, Esc cancel")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    frame.render_widget(help, chunks[1]);
+}
+
 #[derive(Debug, Clone)]
 pub struct SuggestionsDialog {
     pub suggestions: Vec<(String, String)>, // (display_name, full_path)
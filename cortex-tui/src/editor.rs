@@ -1,5 +1,7 @@
 use anyhow::Result;
+use cortex_core::{Highlighter, LineEnding, TextEncoding, TokenKind};
 use std::fs;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
@@ -19,6 +21,12 @@ pub struct TextEditor {
     pub redo_stack: Vec<EditorState>,
     pub search_term: Option<String>,
     pub status_message: Option<String>,
+    /// Set when the file's language is known
+    pub highlighter: Option<Highlighter>,
+    pub syntax_highlighting: bool,
+    /// Encoding the file is read from and saved in
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
 }
 
 #[derive(Debug, Clone)]
@@ -37,17 +45,21 @@ impl TextEditor {
             .unwrap_or("Untitled")
             .to_string();
 
-        let content = if path.exists() {
-            fs::read_to_string(&path)?
+        let (content, encoding) = if path.exists() {
+            let bytes = fs::read(&path)?;
+            let encoding = cortex_core::detect_encoding(&bytes);
+            (encoding.decode(&bytes), encoding)
         } else {
-            String::new()
+            (String::new(), TextEncoding::Utf8)
         };
+        let line_ending = LineEnding::detect(&content);
 
         let lines: Vec<String> = if content.is_empty() {
             vec![String::new()]
         } else {
             content.lines().map(|s| s.to_string()).collect()
         };
+        let highlighter = cortex_core::detect_language(&path, &lines[0]).map(Highlighter::new);
 
         Ok(Self {
             path,
@@ -65,26 +77,72 @@ impl TextEditor {
             redo_stack: Vec::new(),
             search_term: None,
             status_message: None,
+            highlighter,
+            syntax_highlighting: true,
+            encoding,
+            line_ending,
         })
     }
 
     pub fn save(&mut self) -> Result<()> {
-        let content = self.lines.join("\n");
-        fs::write(&self.path, content)?;
+        self.write(&self.path, self.encoding, self.line_ending)?;
         self.modified = false;
         self.status_message = Some(format!("Saved {}", self.path.display()));
         Ok(())
     }
 
-    pub fn save_as(&mut self, path: impl AsRef<Path>) -> Result<()> {
-        self.path = path.as_ref().to_path_buf();
+    /// Save to another file, converting to `encoding` and `line_ending`,
+    /// and keep editing that file
+    pub fn save_as(&mut self, path: impl AsRef<Path>, encoding: TextEncoding, line_ending: LineEnding) -> Result<()> {
+        let path = path.as_ref();
+        self.write(path, encoding, line_ending)?;
+        self.path = path.to_path_buf();
+        self.encoding = encoding;
+        self.line_ending = line_ending;
         self.title = self
             .path
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("Untitled")
             .to_string();
-        self.save()
+        self.highlighter = cortex_core::detect_language(&self.path, &self.lines[0]).map(Highlighter::new);
+        self.modified = false;
+        self.status_message = Some(format!("Saved {}", self.path.display()));
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, encoding: TextEncoding, line_ending: LineEnding) -> Result<()> {
+        let content = self.lines.join(line_ending.as_str());
+        fs::write(path, encoding.encode(&content)?)?;
+        Ok(())
+    }
+
+    /// Switch to the next encoding. Unchanged text is decoded again from the
+    /// file, to fix a wrong guess; edited text is kept and converted on save
+    pub fn cycle_encoding(&mut self) -> Result<()> {
+        self.encoding = self.encoding.next();
+        if self.modified || !self.path.exists() {
+            self.status_message = Some(format!("Will save as {}", self.encoding.name()));
+            return Ok(());
+        }
+        let content = self.encoding.decode(&fs::read(&self.path)?);
+        self.lines = content.lines().map(|s| s.to_string()).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.cursor_row = self.cursor_row.min(self.lines.len() - 1);
+        self.cursor_col = 0;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.invalidate_highlighting(0);
+        self.status_message = Some(format!("Reopened as {}", self.encoding.name()));
+        Ok(())
+    }
+
+    pub fn toggle_line_ending(&mut self) {
+        self.line_ending = self.line_ending.toggle();
+        self.modified = true;
+        self.status_message = Some(format!("Line endings: {}", self.line_ending.name()));
     }
 
     fn save_state(&mut self) {
@@ -94,6 +152,8 @@ impl TextEditor {
             cursor_col: self.cursor_col,
         });
         self.redo_stack.clear();
+        // Every edit touches the cursor line, or joins it onto the one above
+        self.invalidate_highlighting(self.cursor_row.saturating_sub(1));
 
         // Limit undo stack size
         if self.undo_stack.len() > 100 {
@@ -113,6 +173,7 @@ impl TextEditor {
             self.cursor_row = state.cursor_row;
             self.cursor_col = state.cursor_col;
             self.modified = true;
+            self.invalidate_highlighting(0);
         }
     }
 
@@ -128,6 +189,7 @@ impl TextEditor {
             self.cursor_row = state.cursor_row;
             self.cursor_col = state.cursor_col;
             self.modified = true;
+            self.invalidate_highlighting(0);
         }
     }
 
@@ -286,6 +348,7 @@ impl TextEditor {
 
     pub fn replace(&mut self, search: &str, replace: &str, all: bool) {
         self.save_state();
+        self.invalidate_highlighting(0);
 
         if all {
             for line in &mut self.lines {
@@ -299,6 +362,36 @@ impl TextEditor {
         }
     }
 
+    fn invalidate_highlighting(&mut self, row: usize) {
+        if let Some(highlighter) = &mut self.highlighter {
+            highlighter.invalidate_from(row);
+        }
+    }
+
+    pub fn toggle_syntax_highlighting(&mut self) {
+        self.syntax_highlighting = !self.syntax_highlighting;
+        self.status_message = Some(match (&self.highlighter, self.syntax_highlighting) {
+            (None, _) => "No syntax highlighting for this file type".to_string(),
+            (Some(highlighter), true) => format!("Highlighting as {}", highlighter.language().name),
+            (Some(_), false) => "Syntax highlighting off".to_string(),
+        });
+    }
+
+    /// Syntax tokens of line `row`; the lines above it are scanned first if
+    /// they have not been since the last edit above
+    pub fn line_tokens(&mut self, row: usize) -> Vec<(Range<usize>, TokenKind)> {
+        let Some(highlighter) = self.highlighter.as_mut().filter(|_| self.syntax_highlighting) else {
+            return Vec::new();
+        };
+        for earlier in highlighter.scanned_lines()..row.min(self.lines.len()) {
+            highlighter.advance(earlier, &self.lines[earlier]);
+        }
+        match self.lines.get(row) {
+            Some(line) => highlighter.highlight_line(row, line),
+            None => Vec::new(),
+        }
+    }
+
     pub fn update_view_offset(&mut self, window_height: usize, window_width: usize) {
         // Vertical scrolling
         if self.cursor_row < self.offset_row {
@@ -320,12 +413,14 @@ impl TextEditor {
         let modified = if self.modified { "[+]" } else { "" };
 
         format!(
-            "{} {} | Line {}/{} Col {} {}",
+            "{} {} | Line {}/{} Col {} | {} {} {}",
             mode,
             self.title,
             self.cursor_row + 1,
             self.lines.len(),
             self.cursor_col + 1,
+            self.encoding.name(),
+            self.line_ending.name(),
             modified
         )
     }
@@ -1,7 +1,9 @@
 use crate::editor::TextEditor;
+use crate::highlight::syntax_spans;
+use cortex_core::Theme;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Color, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph},
     Frame,
@@ -14,6 +16,9 @@ pub struct EditorDialog {
     pub search_input: String,
     pub replace_mode: bool,
     pub replace_input: String,
+    /// Asking for the path to save a copy to
+    pub save_as_mode: bool,
+    pub save_as_input: String,
 }
 
 impl EditorDialog {
@@ -24,10 +29,12 @@ impl EditorDialog {
             search_input: String::new(),
             replace_mode: false,
             replace_input: String::new(),
+            save_as_mode: false,
+            save_as_input: String::new(),
         }
     }
 
-    pub fn render(&mut self, frame: &mut Frame) {
+    pub fn render(&mut self, frame: &mut Frame, theme: &Theme) {
         let area = centered_rect(95, 95, frame.size());
         frame.render_widget(Clear, area);
 
@@ -81,6 +88,7 @@ impl EditorDialog {
                 break;
             }
 
+            let tokens = self.editor.line_tokens(line_idx);
             let line = &self.editor.lines[line_idx];
             let line_number = format!("{:>width$} ", line_idx + 1, width = line_number_width - 1);
 
@@ -100,15 +108,15 @@ impl EditorDialog {
 
                     spans.push(Span::raw(before.to_string()));
                     spans.push(Span::styled(
-                        matched,
+                        matched.to_string(),
                         Style::default().bg(Color::Yellow).fg(Color::Black),
                     ));
                     spans.push(Span::raw(after.to_string()));
                 } else {
-                    spans.extend(self.apply_syntax_highlighting(line));
+                    spans.extend(syntax_spans(line, &tokens, theme, Style::default()));
                 }
             } else {
-                spans.extend(self.apply_syntax_highlighting(line));
+                spans.extend(syntax_spans(line, &tokens, theme, Style::default()));
             }
 
             lines_to_render.push(Line::from(spans));
@@ -118,7 +126,7 @@ impl EditorDialog {
         frame.render_widget(content, inner);
 
         // Render cursor
-        if !self.search_mode && !self.replace_mode {
+        if !self.search_mode && !self.replace_mode && !self.save_as_mode {
             let cursor_x = inner.x
                 + line_number_width as u16
                 + (self.editor.cursor_col - self.editor.offset_col) as u16;
@@ -174,8 +182,28 @@ impl EditorDialog {
                 replace_inner.x + self.replace_input.len() as u16,
                 replace_inner.y,
             );
+        } else if self.save_as_mode {
+            let save_as_block = Block::default()
+                .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(format!(
+                    " Save as ({} {}): ",
+                    self.editor.encoding.name(),
+                    self.editor.line_ending.name()
+                ));
+            let save_as_inner = save_as_block.inner(chunks[3]);
+            frame.render_widget(save_as_block, chunks[3]);
+            let save_as_text = Paragraph::new(self.save_as_input.as_str())
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(save_as_text, save_as_inner);
+
+            // Show cursor
+            frame.set_cursor(
+                save_as_inner.x + self.save_as_input.len() as u16,
+                save_as_inner.y,
+            );
         } else {
-            let help_text = " ESC/F4: Exit | Ctrl+S: Save | Ctrl+F: Find | Ctrl+R: Replace | Ctrl+Z: Undo | Ctrl+Y: Redo ";
+            let help_text = " ESC/F4: Exit | Ctrl+S: Save | F2: Save as | Ctrl+E: Encoding | Ctrl+L: CRLF/LF | Ctrl+F: Find | Ctrl+R: Replace | Ctrl+Z: Undo | Ctrl+Y: Redo | Ctrl+T: Syntax ";
 
             let help_block = Block::default()
                 .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
@@ -206,86 +234,6 @@ impl EditorDialog {
             frame.render_widget(msg_text, msg_inner);
         }
     }
-
-    fn apply_syntax_highlighting(&self, line: &str) -> Vec<Span<'_>> {
-        let mut spans = Vec::new();
-
-        // Simple syntax highlighting based on file extension
-        let ext = self
-            .editor
-            .path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("");
-
-        match ext {
-            "rs" => {
-                // Rust syntax highlighting
-                if line.trim_start().starts_with("//") {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default()
-                            .fg(Color::Green)
-                            .add_modifier(Modifier::ITALIC),
-                    ));
-                } else if line.contains("fn ")
-                    || line.contains("impl ")
-                    || line.contains("struct ")
-                    || line.contains("enum ")
-                {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default().fg(Color::Yellow),
-                    ));
-                } else if line.contains("use ") || line.contains("mod ") {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default().fg(Color::Magenta),
-                    ));
-                } else if line.contains("pub ")
-                    || line.contains("let ")
-                    || line.contains("const ")
-                    || line.contains("mut ")
-                {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default().fg(Color::Cyan),
-                    ));
-                } else {
-                    spans.push(Span::raw(line.to_string()));
-                }
-            }
-            "py" => {
-                // Python syntax highlighting
-                if line.trim_start().starts_with("#") {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default()
-                            .fg(Color::Green)
-                            .add_modifier(Modifier::ITALIC),
-                    ));
-                } else if line.contains("def ") || line.contains("class ") {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default().fg(Color::Yellow),
-                    ));
-                } else if line.contains("import ") || line.contains("from ") {
-                    spans.push(Span::styled(
-                        line.to_string(),
-                        Style::default().fg(Color::Magenta),
-                    ));
-                } else {
-                    spans.push(Span::raw(line.to_string()));
-                }
-            }
-            _ => {
-                // Default - no highlighting
-                spans.push(Span::raw(line.to_string()));
-            }
-        }
-
-        spans
-    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
@@ -0,0 +1,319 @@
+use cortex_core::{fuzzy_match, FuzzyMatch};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Bytes of the highlighted file read for the preview
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// fzf-style finder over the files below `root`, ranked as the query is typed
+#[derive(Debug, Clone)]
+pub struct FuzzyFinderDialog {
+    pub root: PathBuf,
+    pub query: String,
+    pub indexing: bool,
+    pub selected: usize,
+    paths: Vec<String>,
+    /// Indexes into `paths` with their match, best first
+    matches: Vec<(usize, FuzzyMatch)>,
+    /// The query `matches` was ranked for
+    ranked_query: String,
+    preview_path: Option<String>,
+    preview: Vec<String>,
+}
+
+impl FuzzyFinderDialog {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            query: String::new(),
+            indexing: true,
+            selected: 0,
+            paths: Vec::new(),
+            matches: Vec::new(),
+            ranked_query: String::new(),
+            preview_path: None,
+            preview: Vec::new(),
+        }
+    }
+
+    /// Add paths found by the background indexer. Only the new batch is sorted;
+    /// it is then merged into the list that is already ranked
+    pub fn add_paths(&mut self, paths: Vec<String>) {
+        let first = self.paths.len();
+        self.paths.extend(paths);
+        let mut added: Vec<(usize, FuzzyMatch)> = (first..self.paths.len())
+            .filter_map(|index| fuzzy_match(&self.ranked_query, &self.paths[index]).map(|m| (index, m)))
+            .collect();
+        added.sort_by(|a, b| rank_order(&self.paths, a, b));
+
+        let ranked = std::mem::take(&mut self.matches);
+        let mut merged = Vec::with_capacity(ranked.len() + added.len());
+        let mut ranked = ranked.into_iter().peekable();
+        let mut added = added.into_iter().peekable();
+        while let (Some(old), Some(new)) = (ranked.peek(), added.peek()) {
+            let next = match rank_order(&self.paths, new, old).is_lt() {
+                true => added.next(),
+                false => ranked.next(),
+            };
+            merged.extend(next);
+        }
+        merged.extend(ranked);
+        merged.extend(added);
+        self.matches = merged;
+
+        self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+        self.update_preview();
+    }
+
+    pub fn finish_indexing(&mut self) {
+        self.indexing = false;
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.query.push(c);
+        self.rank();
+    }
+
+    pub fn delete_char(&mut self) {
+        if self.query.pop().is_some() {
+            self.rank();
+        }
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query.clear();
+        self.rank();
+    }
+
+    pub fn move_selection_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.update_preview();
+    }
+
+    pub fn move_selection_down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+        self.update_preview();
+    }
+
+    pub fn page_up(&mut self, rows: usize) {
+        self.selected = self.selected.saturating_sub(rows);
+        self.update_preview();
+    }
+
+    pub fn page_down(&mut self, rows: usize) {
+        self.selected = (self.selected + rows).min(self.matches.len().saturating_sub(1));
+        self.update_preview();
+    }
+
+    /// Full path of the highlighted file
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.matches
+            .get(self.selected)
+            .map(|(index, _)| self.root.join(&self.paths[*index]))
+    }
+
+    fn rank(&mut self) {
+        // A longer query only narrows what the shorter one matched
+        let candidates: Vec<usize> = if self.query.starts_with(&self.ranked_query) {
+            self.matches.iter().map(|(index, _)| *index).collect()
+        } else {
+            (0..self.paths.len()).collect()
+        };
+        self.matches = candidates
+            .into_iter()
+            .filter_map(|index| fuzzy_match(&self.query, &self.paths[index]).map(|m| (index, m)))
+            .collect();
+        self.ranked_query = self.query.clone();
+        self.sort_matches();
+        self.selected = 0;
+        self.update_preview();
+    }
+
+    fn sort_matches(&mut self) {
+        let paths = &self.paths;
+        self.matches.sort_by(|a, b| rank_order(paths, a, b));
+        self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+    }
+
+    fn update_preview(&mut self) {
+        let current = self.matches.get(self.selected).map(|(index, _)| &self.paths[*index]);
+        if current == self.preview_path.as_ref() {
+            return;
+        }
+        self.preview_path = current.cloned();
+        self.preview = match &self.preview_path {
+            Some(path) => read_preview(&self.root.join(path)),
+            None => Vec::new(),
+        };
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let area = centered_rect(90, 80, frame.size());
+        frame.render_widget(Clear, area);
+
+        let status = if self.indexing { ", indexing..." } else { "" };
+        let block = Block::default()
+            .title(format!(
+                " Find File: {} ({}/{}{}) ",
+                self.root.display(),
+                self.matches.len(),
+                self.paths.len(),
+                status
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(inner);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+            .split(columns[0]);
+
+        // Query line
+        let prompt = Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(self.query.as_str()),
+        ]);
+        frame.render_widget(Paragraph::new(prompt), rows[0]);
+        frame.set_cursor(rows[0].x + 2 + self.query.chars().count() as u16, rows[0].y);
+
+        // Ranked paths, only the visible window is built
+        let height = rows[1].height as usize;
+        let offset = (self.selected + 1).saturating_sub(height);
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|(index, m)| ListItem::new(highlight(&self.paths[*index], &m.positions)))
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(self.selected - offset).filter(|_| !self.matches.is_empty()));
+        let list = List::new(items).highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        );
+        frame.render_stateful_widget(list, rows[1], &mut state);
+
+        let help = Paragraph::new("Enter: Go to file | Ctrl+U: Clear | ESC: Close")
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(help, rows[2]);
+
+        // Preview of the highlighted file
+        let preview_block = Block::default().borders(Borders::LEFT);
+        let preview_area = preview_block.inner(columns[1]);
+        frame.render_widget(preview_block, columns[1]);
+        let lines: Vec<Line> = self
+            .preview
+            .iter()
+            .take(preview_area.height as usize)
+            .map(|line| Line::from(line.as_str()))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines).style(Style::default().fg(Color::Gray)),
+            preview_area,
+        );
+    }
+}
+
+/// A path with its matched characters emphasised
+fn highlight<'a>(path: &'a str, positions: &[usize]) -> Line<'a> {
+    let matched = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in path.chars().enumerate() {
+        let is_matched = positions.binary_search(&i).is_ok();
+        if is_matched != run_matched && !run.is_empty() {
+            let text = std::mem::take(&mut run);
+            spans.push(if run_matched { Span::styled(text, matched) } else { Span::raw(text) });
+        }
+        run_matched = is_matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(if run_matched { Span::styled(run, matched) } else { Span::raw(run) });
+    }
+    Line::from(spans)
+}
+
+/// Best score first, then shorter paths, then alphabetical
+fn rank_order(paths: &[String], (a, am): &(usize, FuzzyMatch), (b, bm): &(usize, FuzzyMatch)) -> std::cmp::Ordering {
+    bm.score
+        .cmp(&am.score)
+        .then_with(|| paths[*a].len().cmp(&paths[*b].len()))
+        .then_with(|| paths[*a].cmp(&paths[*b]))
+}
+
+/// The first lines of a text file, or a note why it cannot be shown
+fn read_preview(path: &std::path::Path) -> Vec<String> {
+    let mut data = Vec::new();
+    let read = std::fs::File::open(path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut data));
+    if let Err(e) = read {
+        return vec![format!("Cannot read file: {}", e)];
+    }
+    if data.contains(&0) {
+        return vec!["Binary file".to_string()];
+    }
+    String::from_utf8_lossy(&data)
+        .lines()
+        .map(|line| line.replace('\t', "    "))
+        .collect()
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_merge_into_the_ranking() {
+        let batch = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        let mut dialog = FuzzyFinderDialog::new(PathBuf::from("/nonexistent"));
+        dialog.insert_char('m');
+        dialog.insert_char('a');
+        dialog.add_paths(batch(&["src/main.rs", "docs/manual.md", "lib/a.rs"]));
+        dialog.add_paths(batch(&["main.rs", "src/app/mod.rs", "Makefile", "x/y/z/mega.rs"]));
+
+        let merged: Vec<usize> = dialog.matches.iter().map(|(index, _)| *index).collect();
+        dialog.ranked_query.clear();
+        dialog.rank();
+        let ranked: Vec<usize> = dialog.matches.iter().map(|(index, _)| *index).collect();
+        assert_eq!(merged, ranked);
+        assert_eq!(dialog.paths[merged[0]], "main.rs");
+    }
+}
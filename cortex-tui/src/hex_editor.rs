@@ -0,0 +1,525 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files above this size are too big to edit in memory
+const MAX_HEX_EDIT_SIZE: u64 = 256 * 1024 * 1024;
+
+pub const BYTES_PER_ROW: usize = 16;
+
+/// Which side of the dump typing goes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HexColumn {
+    Hex,
+    Ascii,
+}
+
+/// One undoable change to the bytes
+#[derive(Debug, Clone)]
+enum HexEdit {
+    Overwrite { offset: usize, old: u8, new: u8, was_changed: bool },
+    Insert { offset: usize, byte: u8 },
+    Delete { offset: usize, byte: u8, was_changed: bool },
+}
+
+impl HexEdit {
+    /// Undoing this now leaves the byte different from what is saved
+    fn mark_changed(&mut self) {
+        if let HexEdit::Overwrite { was_changed, .. } | HexEdit::Delete { was_changed, .. } = self {
+            *was_changed = true;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HexEditor {
+    pub path: PathBuf,
+    pub title: String,
+    pub data: Vec<u8>,
+    /// Bytes edited since the last save, parallel to `data`
+    pub changed: Vec<bool>,
+    pub cursor: usize,
+    /// Typing the low half of the byte under the cursor
+    pub low_nibble: bool,
+    pub column: HexColumn,
+    pub insert_mode: bool,
+    pub offset_row: usize,
+    pub modified: bool,
+    pub search_pattern: Option<Vec<u8>>,
+    pub status_message: Option<String>,
+    undo_stack: Vec<HexEdit>,
+    redo_stack: Vec<HexEdit>,
+    /// Depth of the undo stack at the last save, while undo or redo can
+    /// still get back there
+    saved_at: Option<usize>,
+}
+
+impl HexEditor {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let title = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Untitled")
+            .to_string();
+
+        let size = fs::metadata(&path)?.len();
+        if size > MAX_HEX_EDIT_SIZE {
+            return Err(anyhow::anyhow!(
+                "{} is too large to edit ({})",
+                title,
+                humansize::format_size(size, humansize::BINARY)
+            ));
+        }
+        let data = fs::read(&path)?;
+
+        Ok(Self {
+            path,
+            title,
+            changed: vec![false; data.len()],
+            data,
+            cursor: 0,
+            low_nibble: false,
+            column: HexColumn::Hex,
+            insert_mode: false,
+            offset_row: 0,
+            modified: false,
+            search_pattern: None,
+            status_message: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            saved_at: Some(0),
+        })
+    }
+
+    /// Write the bytes back through a file beside the original, renamed over
+    /// it so a failed write leaves the original whole; the undo history is
+    /// kept
+    pub fn save(&mut self) -> Result<()> {
+        // Replace the file a symlink points at rather than the link
+        let path = fs::canonicalize(&self.path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.cortex-hex", name));
+        let written = fs::write(&temp, &self.data)
+            .and_then(|_| fs::set_permissions(&temp, fs::metadata(&path)?.permissions()))
+            .and_then(|_| fs::rename(&temp, &path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp);
+            return Err(e.into());
+        }
+
+        self.saved_at = Some(self.undo_stack.len());
+        self.undo_stack.iter_mut().for_each(HexEdit::mark_changed);
+        self.redo_stack.iter_mut().for_each(HexEdit::mark_changed);
+        self.update_modified();
+        self.status_message = Some(format!("Saved {}", self.path.display()));
+        Ok(())
+    }
+
+    /// Compare the undo depth with the last save; back at the saved bytes,
+    /// nothing counts as changed
+    fn update_modified(&mut self) {
+        self.modified = self.saved_at != Some(self.undo_stack.len());
+        if !self.modified {
+            self.changed.fill(false);
+        }
+    }
+
+    fn apply(&mut self, edit: HexEdit) {
+        match edit {
+            HexEdit::Overwrite { offset, new, .. } => {
+                self.data[offset] = new;
+                self.changed[offset] = true;
+            }
+            HexEdit::Insert { offset, byte } => {
+                self.data.insert(offset, byte);
+                self.changed.insert(offset, true);
+            }
+            HexEdit::Delete { offset, .. } => {
+                self.data.remove(offset);
+                self.changed.remove(offset);
+            }
+        }
+    }
+
+    fn revert(&mut self, edit: &HexEdit) {
+        match *edit {
+            HexEdit::Overwrite { offset, old, was_changed, .. } => {
+                self.data[offset] = old;
+                self.changed[offset] = was_changed;
+                self.cursor = offset;
+            }
+            HexEdit::Insert { offset, .. } => {
+                self.data.remove(offset);
+                self.changed.remove(offset);
+                self.cursor = offset;
+            }
+            HexEdit::Delete { offset, byte, was_changed } => {
+                self.data.insert(offset, byte);
+                self.changed.insert(offset, was_changed);
+                self.cursor = offset;
+            }
+        }
+    }
+
+    fn record(&mut self, edit: HexEdit) {
+        // A saved state that was undone can no longer be redone
+        if self.saved_at.is_some_and(|depth| depth > self.undo_stack.len()) {
+            self.saved_at = None;
+        }
+        self.apply(edit.clone());
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+        self.update_modified();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(edit) = self.undo_stack.pop() {
+            self.revert(&edit);
+            self.low_nibble = false;
+            self.redo_stack.push(edit);
+            self.update_modified();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(edit) = self.redo_stack.pop() {
+            self.cursor = match edit {
+                HexEdit::Overwrite { offset, .. } | HexEdit::Insert { offset, .. } => offset + 1,
+                HexEdit::Delete { offset, .. } => offset,
+            }
+            .min(self.data.len());
+            self.apply(edit.clone());
+            self.low_nibble = false;
+            self.undo_stack.push(edit);
+            self.update_modified();
+        }
+    }
+
+    /// Put `byte` at the cursor, inserting it in insert mode or at the end
+    fn put_byte(&mut self, byte: u8) {
+        if self.insert_mode || self.cursor >= self.data.len() {
+            self.record(HexEdit::Insert {
+                offset: self.cursor,
+                byte,
+            });
+        } else if self.data[self.cursor] != byte {
+            self.record(HexEdit::Overwrite {
+                offset: self.cursor,
+                old: self.data[self.cursor],
+                new: byte,
+                was_changed: self.changed[self.cursor],
+            });
+        }
+    }
+
+    /// Type a hex digit or, in the ASCII column, a character
+    pub fn type_char(&mut self, c: char) {
+        match self.column {
+            HexColumn::Hex => {
+                let Some(digit) = c.to_digit(16) else {
+                    return;
+                };
+                let digit = digit as u8;
+                if self.low_nibble && self.cursor < self.data.len() {
+                    // The high half went in already, as a new or changed byte
+                    let byte = (self.data[self.cursor] & 0xF0) | digit;
+                    let insert_mode = std::mem::replace(&mut self.insert_mode, false);
+                    self.put_byte(byte);
+                    self.insert_mode = insert_mode;
+                    self.low_nibble = false;
+                    self.cursor += 1;
+                } else {
+                    let low = self.data.get(self.cursor).filter(|_| !self.insert_mode).map_or(0, |b| b & 0x0F);
+                    self.put_byte((digit << 4) | low);
+                    self.low_nibble = true;
+                }
+            }
+            HexColumn::Ascii => {
+                if !c.is_ascii() || c.is_ascii_control() {
+                    return;
+                }
+                self.put_byte(c as u8);
+                self.low_nibble = false;
+                self.cursor += 1;
+            }
+        }
+    }
+
+    /// Remove the byte under the cursor
+    pub fn delete_byte(&mut self) {
+        if self.cursor < self.data.len() {
+            self.record(HexEdit::Delete {
+                offset: self.cursor,
+                byte: self.data[self.cursor],
+                was_changed: self.changed[self.cursor],
+            });
+        }
+        self.low_nibble = false;
+    }
+
+    /// Remove the byte before the cursor
+    pub fn delete_byte_before(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete_byte();
+        }
+    }
+
+    pub fn toggle_column(&mut self) {
+        self.column = match self.column {
+            HexColumn::Hex => HexColumn::Ascii,
+            HexColumn::Ascii => HexColumn::Hex,
+        };
+        self.low_nibble = false;
+    }
+
+    pub fn toggle_insert_mode(&mut self) {
+        self.insert_mode = !self.insert_mode;
+    }
+
+    /// Put the cursor on `offset`, clamped to the end of the data
+    pub fn move_to(&mut self, offset: usize) {
+        self.cursor = offset.min(self.data.len());
+        self.low_nibble = false;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.low_nibble {
+            self.low_nibble = false;
+        } else {
+            self.move_to(self.cursor.saturating_sub(1));
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.move_to(self.cursor + 1);
+    }
+
+    pub fn move_up(&mut self, rows: usize) {
+        self.move_to(self.cursor.saturating_sub(rows * BYTES_PER_ROW));
+    }
+
+    pub fn move_down(&mut self, rows: usize) {
+        if self.cursor + rows * BYTES_PER_ROW <= self.data.len() {
+            self.move_to(self.cursor + rows * BYTES_PER_ROW);
+        }
+    }
+
+    pub fn move_row_start(&mut self) {
+        self.move_to(self.cursor / BYTES_PER_ROW * BYTES_PER_ROW);
+    }
+
+    pub fn move_row_end(&mut self) {
+        self.move_to(self.cursor / BYTES_PER_ROW * BYTES_PER_ROW + BYTES_PER_ROW - 1);
+    }
+
+    /// Move to an offset given in decimal, or in hex with `0x`
+    pub fn goto(&mut self, target: &str) -> Result<()> {
+        let target = target.trim();
+        let offset = match target.strip_prefix("0x").or_else(|| target.strip_prefix("0X")) {
+            Some(hex) => usize::from_str_radix(hex, 16)?,
+            None => target.parse()?,
+        };
+        if offset > self.data.len() {
+            return Err(anyhow::anyhow!("Offset {:#X} is past the end of the file", offset));
+        }
+        self.move_to(offset);
+        Ok(())
+    }
+
+    /// Find a byte pattern after the cursor, wrapping around; the pattern is
+    /// hex bytes (`DE AD BE EF`) or quoted text (`"PNG"`)
+    pub fn search(&mut self, pattern: &str) -> Result<Option<usize>> {
+        let pattern = parse_byte_pattern(pattern)?;
+        self.search_pattern = Some(pattern);
+        Ok(self.search_next())
+    }
+
+    pub fn search_next(&mut self) -> Option<usize> {
+        let pattern = self.search_pattern.as_ref()?;
+        let found = find_bytes(&self.data[(self.cursor + 1).min(self.data.len())..], pattern)
+            .map(|at| self.cursor + 1 + at)
+            .or_else(|| find_bytes(&self.data, pattern));
+        if let Some(offset) = found {
+            self.move_to(offset);
+        }
+        found
+    }
+
+    pub fn update_view_offset(&mut self, rows: usize) {
+        let row = self.cursor / BYTES_PER_ROW;
+        if row < self.offset_row {
+            self.offset_row = row;
+        } else if rows > 0 && row >= self.offset_row + rows {
+            self.offset_row = row + 1 - rows;
+        }
+    }
+
+    pub fn get_status(&self) -> String {
+        let mode = if self.insert_mode { "INSERT" } else { "OVERWRITE" };
+        let column = match self.column {
+            HexColumn::Hex => "HEX",
+            HexColumn::Ascii => "ASCII",
+        };
+        let value = match self.data.get(self.cursor) {
+            Some(byte) => format!("{:02X} ({})", byte, byte),
+            None => "EOF".to_string(),
+        };
+        let changed = self.changed.iter().filter(|&&changed| changed).count();
+
+        format!(
+            "{} {} | Offset {:#010X} / {:#X} | Byte {} | {} changed{}",
+            mode,
+            column,
+            self.cursor,
+            self.data.len(),
+            value,
+            changed,
+            if self.modified { " [+]" } else { "" }
+        )
+    }
+}
+
+/// Bytes of a search pattern: quoted text, or hex digits in pairs
+pub fn parse_byte_pattern(pattern: &str) -> Result<Vec<u8>> {
+    let pattern = pattern.trim();
+    if let Some(text) = pattern
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        return Ok(text.as_bytes().to_vec());
+    }
+    let digits: String = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    let digits = digits.strip_prefix("0x").unwrap_or(&digits);
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(anyhow::anyhow!("Give whole bytes in hex, or text in quotes"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(Into::into))
+        .collect()
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor_with(data: &[u8]) -> (tempfile::TempDir, HexEditor) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blob.bin");
+        fs::write(&path, data).unwrap();
+        let editor = HexEditor::new(&path).unwrap();
+        (dir, editor)
+    }
+
+    #[test]
+    fn test_hex_editing_undo_and_redo() {
+        let (_dir, mut editor) = editor_with(&[0x00, 0x11, 0x22, 0x33]);
+
+        // Each nibble is its own edit
+        editor.type_char('a');
+        assert_eq!((editor.data[0], editor.low_nibble), (0xA0, true));
+        editor.type_char('B');
+        assert_eq!((editor.data[0], editor.cursor), (0xAB, 1));
+        editor.type_char('x');
+        assert_eq!(editor.data, [0xAB, 0x11, 0x22, 0x33]);
+
+        editor.toggle_insert_mode();
+        editor.move_to(2);
+        editor.type_char('F');
+        editor.type_char('F');
+        assert_eq!(editor.data, [0xAB, 0x11, 0xFF, 0x22, 0x33]);
+        editor.toggle_insert_mode();
+        editor.delete_byte();
+        assert_eq!(editor.data, [0xAB, 0x11, 0xFF, 0x33]);
+        editor.toggle_column();
+        editor.move_to(1);
+        editor.type_char('Z');
+        assert_eq!(editor.data, [0xAB, b'Z', 0xFF, 0x33]);
+        assert_eq!(editor.changed, [true, true, true, false]);
+
+        for _ in 0..6 {
+            editor.undo();
+        }
+        assert_eq!(editor.data, [0x00, 0x11, 0x22, 0x33]);
+        assert!(!editor.modified);
+        assert!(editor.changed.iter().all(|&changed| !changed));
+        editor.undo();
+        assert!(!editor.modified);
+
+        editor.redo();
+        editor.redo();
+        assert_eq!(editor.data, [0xAB, 0x11, 0x22, 0x33]);
+        assert!(editor.modified);
+        editor.delete_byte_before();
+        assert_eq!(editor.data, [0x11, 0x22, 0x33]);
+        editor.redo();
+        assert_eq!(editor.data, [0x11, 0x22, 0x33]);
+    }
+
+    #[test]
+    fn test_save_replaces_the_file_and_tracks_the_saved_state() {
+        let (dir, mut editor) = editor_with(b"abcd");
+        editor.toggle_column();
+        editor.type_char('X');
+        editor.save().unwrap();
+        assert_eq!(fs::read(&editor.path).unwrap(), b"Xbcd");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(!editor.modified);
+
+        // Undo after saving differs from the file, redo is back at it
+        editor.undo();
+        assert_eq!(editor.data, b"abcd");
+        assert!(editor.modified);
+        assert!(editor.changed[0]);
+        editor.redo();
+        assert!(!editor.modified);
+        assert!(!editor.changed[0]);
+
+        // Once a new edit replaces the saved one, undo cannot reach it again
+        editor.undo();
+        editor.move_to(1);
+        editor.type_char('Y');
+        editor.undo();
+        assert!(editor.modified);
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.bin");
+            std::os::unix::fs::symlink(&editor.path, &link).unwrap();
+            let mut linked = HexEditor::new(&link).unwrap();
+            linked.type_char('F');
+            linked.type_char('F');
+            linked.save().unwrap();
+            assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+            assert_eq!(fs::read(dir.path().join("blob.bin")).unwrap(), b"\xFFbcd");
+        }
+    }
+
+    #[test]
+    fn test_byte_patterns_and_search() {
+        assert_eq!(parse_byte_pattern("DE AD be ef").unwrap(), [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(parse_byte_pattern("0x0a0B").unwrap(), [0x0A, 0x0B]);
+        assert_eq!(parse_byte_pattern(" \"PNG\" ").unwrap(), b"PNG");
+        assert!(parse_byte_pattern("ABC").is_err());
+        assert!(parse_byte_pattern("").is_err());
+        assert!(parse_byte_pattern("zz").is_err());
+
+        let (_dir, mut editor) = editor_with(b"PNG..PNG..");
+        assert_eq!(editor.search("\"PNG\"").unwrap(), Some(5));
+        assert_eq!(editor.cursor, 5);
+        // Wraps around to the start
+        assert_eq!(editor.search_next(), Some(0));
+        assert_eq!(editor.search_next(), Some(5));
+        assert_eq!(editor.search("00 01").unwrap(), None);
+        assert_eq!(editor.cursor, 5);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::hex_editor::{HexColumn, HexEditor, BYTES_PER_ROW};
+use cortex_core::Theme;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+#[derive(Debug, Clone)]
+pub struct HexEditorDialog {
+    pub editor: HexEditor,
+    pub search_mode: bool,
+    pub search_input: String,
+    pub goto_mode: bool,
+    pub goto_input: String,
+    /// Esc was pressed once with unsaved changes
+    pub confirm_discard: bool,
+}
+
+impl HexEditorDialog {
+    pub fn new(editor: HexEditor) -> Self {
+        Self {
+            editor,
+            search_mode: false,
+            search_input: String::new(),
+            goto_mode: false,
+            goto_input: String::new(),
+            confirm_discard: false,
+        }
+    }
+
+    /// Rows of bytes shown at once on a screen of size `screen`
+    pub fn content_height(screen: Rect) -> usize {
+        Self::layout(centered_rect(95, 95, screen))[1].height as usize
+    }
+
+    fn layout(area: Rect) -> std::rc::Rc<[Rect]> {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Title
+                Constraint::Min(5),    // Content
+                Constraint::Length(2), // Status
+                Constraint::Length(2), // Help/Input
+            ])
+            .split(area)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, theme: &Theme) {
+        let area = centered_rect(95, 95, frame.size());
+        frame.render_widget(Clear, area);
+        let chunks = Self::layout(area);
+
+        // Title bar
+        let title = format!(
+            " Hex Editing: {} {} ",
+            self.editor.title,
+            if self.editor.modified { "[Modified]" } else { "" }
+        );
+        let title_block = Block::default()
+            .title(title)
+            .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+            .border_style(Style::default().fg(Color::Green));
+        frame.render_widget(title_block, chunks[0]);
+
+        // Content area
+        let content_block = Block::default()
+            .borders(Borders::LEFT | Borders::RIGHT)
+            .border_style(Style::default().fg(Color::Green));
+        let inner = content_block.inner(chunks[1]);
+        frame.render_widget(content_block, chunks[1]);
+
+        let height = inner.height as usize;
+        self.editor.update_view_offset(height);
+
+        let editor = &self.editor;
+        let mut rows = Vec::with_capacity(height);
+        for row in editor.offset_row..editor.offset_row + height {
+            let start = row * BYTES_PER_ROW;
+            // The row after the last byte still holds the append position
+            if start > editor.data.len() || (start == editor.data.len() && start > 0 && editor.cursor < start) {
+                break;
+            }
+
+            let mut spans = vec![Span::styled(
+                format!("{:08X}  ", start),
+                Style::default().fg(theme.dim_text),
+            )];
+            let mut ascii = Vec::with_capacity(BYTES_PER_ROW);
+            for offset in start..start + BYTES_PER_ROW {
+                let style = self.byte_style(offset, HexColumn::Hex, theme);
+                let (hex, text) = match editor.data.get(offset) {
+                    Some(&byte) => (
+                        format!("{:02X}", byte),
+                        if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' },
+                    ),
+                    None => ("  ".to_string(), ' '),
+                };
+                spans.push(Span::styled(hex, style));
+                spans.push(Span::raw(if offset % BYTES_PER_ROW == 7 { "  " } else { " " }));
+                ascii.push(Span::styled(
+                    text.to_string(),
+                    self.byte_style(offset, HexColumn::Ascii, theme),
+                ));
+            }
+            spans.push(Span::styled(" |", Style::default().fg(theme.dim_text)));
+            spans.extend(ascii);
+            spans.push(Span::styled("|", Style::default().fg(theme.dim_text)));
+            rows.push(Line::from(spans));
+        }
+        frame.render_widget(Paragraph::new(rows), inner);
+
+        // Status bar
+        let status = match (&self.editor.status_message, self.confirm_discard) {
+            (_, true) => "Unsaved changes: Ctrl+S saves, ESC again discards them".to_string(),
+            (Some(message), false) => message.clone(),
+            (None, false) => self.editor.get_status(),
+        };
+        let status_block = Block::default()
+            .borders(Borders::LEFT | Borders::RIGHT)
+            .border_style(Style::default().fg(Color::Green));
+        let status_inner = status_block.inner(chunks[2]);
+        frame.render_widget(status_block, chunks[2]);
+        frame.render_widget(
+            Paragraph::new(status)
+                .style(Style::default().fg(Color::White))
+                .alignment(Alignment::Center),
+            status_inner,
+        );
+
+        // Help line or search/goto input
+        let input = if self.search_mode {
+            Some((" Search bytes (DE AD BE EF or \"text\"): ", &self.search_input))
+        } else if self.goto_mode {
+            Some((" Go to offset (decimal or 0x hex): ", &self.goto_input))
+        } else {
+            None
+        };
+        if let Some((label, value)) = input {
+            let input_block = Block::default()
+                .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(label);
+            let input_inner = input_block.inner(chunks[3]);
+            frame.render_widget(input_block, chunks[3]);
+            frame.render_widget(
+                Paragraph::new(value.as_str()).style(Style::default().fg(Color::Yellow)),
+                input_inner,
+            );
+            frame.set_cursor(input_inner.x + value.len() as u16, input_inner.y);
+        } else {
+            let help_text = " ESC: Exit | Ctrl+S: Save | Tab: Hex/ASCII | Ins: Insert | Del/Bksp: Delete | Ctrl+G: Go to | Ctrl+F: Find | F3: Next | Ctrl+Z/Y: Undo/Redo ";
+            let help_block = Block::default()
+                .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(Color::Green));
+            let help_inner = help_block.inner(chunks[3]);
+            frame.render_widget(help_block, chunks[3]);
+            frame.render_widget(
+                Paragraph::new(help_text)
+                    .style(Style::default().fg(Color::DarkGray))
+                    .alignment(Alignment::Center),
+                help_inner,
+            );
+        }
+    }
+
+    /// Changed bytes stand out; the cursor is solid in the column being
+    /// typed into and underlined in the other
+    fn byte_style(&self, offset: usize, column: HexColumn, theme: &Theme) -> Style {
+        let editor = &self.editor;
+        let mut style = match editor.changed.get(offset) {
+            Some(true) => Style::default().fg(theme.warning).add_modifier(Modifier::BOLD),
+            _ => Style::default(),
+        };
+        if offset == editor.cursor {
+            style = match column == editor.column {
+                true => style.add_modifier(Modifier::REVERSED),
+                false => style.add_modifier(Modifier::UNDERLINED),
+            };
+        }
+        style
+    }
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
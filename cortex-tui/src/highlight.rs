@@ -0,0 +1,29 @@
+use cortex_core::{Theme, TokenKind};
+use ratatui::{style::Style, text::Span};
+use std::ops::Range;
+
+/// Split a line into spans styled by its syntax tokens; text between tokens
+/// keeps `base`
+pub fn syntax_spans(
+    text: &str,
+    tokens: &[(Range<usize>, TokenKind)],
+    theme: &Theme,
+    base: Style,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::with_capacity(tokens.len() * 2 + 1);
+    let mut copied = 0;
+    for (range, kind) in tokens {
+        if range.start > copied {
+            spans.push(Span::styled(text[copied..range.start].to_string(), base));
+        }
+        spans.push(Span::styled(
+            text[range.clone()].to_string(),
+            base.patch(theme.get_syntax_style(*kind)),
+        ));
+        copied = range.end;
+    }
+    if copied < text.len() || spans.is_empty() {
+        spans.push(Span::styled(text[copied..].to_string(), base));
+    }
+    spans
+}
@@ -8,10 +8,17 @@ pub mod editor;
 pub mod editor_dialog;
 pub mod events;
 pub mod filter_dialog;
+pub mod fuzzy_finder_dialog;
+pub mod hex_editor;
+pub mod hex_editor_dialog;
+pub mod highlight;
 pub mod mouse;
 pub mod notification;
 pub mod plugin_dialog;
+pub mod quick_view_panel;
+pub mod replace_dialog;
 pub mod search_dialog;
+pub mod terminal_image;
 pub mod terminal_view;
 pub mod ui;
 pub mod viewer;
@@ -23,20 +30,26 @@ pub use command_palette_dialog::{CommandInfo, CommandPaletteDialog};
 pub use config_dialog::{ConfigDialog, ConfigTab};
 pub use connection_dialog::{ConnectionDialog, ConnectionType};
 pub use dialogs::{
-    ConfirmDialog, Dialog, ErrorDialog, HelpDialog, InputDialog, ProgressDialog, SaveChoice,
-    SaveConfirmDialog, ThemeSelectionDialog,
+    ConfirmDialog, Dialog, DriveDialog, DriveItem, DriveKind, ErrorDialog, HelpDialog,
+    InputDialog, ProgressDialog, SaveChoice, SaveConfirmDialog, ThemeSelectionDialog,
 };
 pub use editor::TextEditor;
 pub use editor_dialog::EditorDialog;
 pub use events::{Event, EventHandler, KeyBinding};
 pub use filter_dialog::FilterDialog;
+pub use fuzzy_finder_dialog::FuzzyFinderDialog;
+pub use hex_editor::{HexColumn, HexEditor};
+pub use hex_editor_dialog::HexEditorDialog;
 pub use mouse::{
     ContextMenu, ContextMenuAction, MouseAction, MouseHandler, MouseRegion, MouseRegionManager,
     MouseRegionType, Position,
 };
 pub use notification::{Notification, NotificationManager, NotificationType};
 pub use plugin_dialog::PluginDialog;
+pub use quick_view_panel::render_quick_view;
+pub use replace_dialog::{ReplaceDialog, ReplaceField, ReplaceState};
 pub use search_dialog::{SearchDialog, SearchProgressInfo, SearchState};
+pub use terminal_image::{GraphicsProtocol, ImagePlacement};
 pub use ui::UI;
-pub use viewer::FileViewer;
+pub use viewer::{FileViewer, Follow, Lookup, LookupResult};
 pub use viewer_dialog::ViewerDialog;
@@ -0,0 +1,163 @@
+use crate::highlight::syntax_spans;
+use crate::terminal_image::{cell_size, render_picture, GraphicsProtocol, ImagePlacement};
+use crate::ui::UI;
+use cortex_core::{Preview, QuickView, QuickViewContent, Theme, VfsEntryType, VfsPath};
+use humansize::{format_size, BINARY};
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Draw the preview in place of a panel; a picture drawn with escape
+/// sequences comes back to be written out after the frame
+pub fn render_quick_view(
+    frame: &mut Frame,
+    area: Rect,
+    quick_view: &QuickView,
+    theme: &Theme,
+) -> Option<ImagePlacement> {
+    let name = quick_view
+        .path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let detail = match &quick_view.content {
+        QuickViewContent::Ready(Preview::Text { language, size, .. }) => {
+            match language {
+                Some(language) => format!("{}, {}", language, format_size(*size, BINARY)),
+                None => format_size(*size, BINARY),
+            }
+        }
+        QuickViewContent::Ready(Preview::Archive { entries, .. }) => format!("{} entries", entries.len()),
+        QuickViewContent::Ready(Preview::Image { info, .. }) => info.format.to_string(),
+        QuickViewContent::Ready(Preview::Binary { size, .. }) => format_size(*size, BINARY),
+        _ => String::new(),
+    };
+    let title = match detail.is_empty() {
+        true => format!(" Quick View: {} ", name),
+        false => format!(" Quick View: {} ({}) ", name, detail),
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(theme.get_border_style(false))
+        .style(Style::default().bg(theme.panel_background));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let height = inner.height as usize;
+    let text = Style::default().fg(theme.normal_text);
+    let dim = Style::default().fg(theme.dim_text);
+    let lines = match &quick_view.content {
+        QuickViewContent::Loading => vec![Line::styled(format!("{} Loading...", UI::spinner_frame()), dim)],
+        QuickViewContent::Unavailable(reason) => vec![Line::styled(reason.clone(), dim)],
+        QuickViewContent::Ready(Preview::Text { lines, truncated, .. }) => {
+            let mut rendered: Vec<Line> = lines
+                .iter()
+                .take(height)
+                .map(|(line, tokens)| Line::from(syntax_spans(line, tokens, theme, text)))
+                .collect();
+            if *truncated && lines.len() < height {
+                rendered.push(Line::styled("...", dim));
+            }
+            rendered
+        }
+        QuickViewContent::Ready(Preview::Directory(summary)) => {
+            let mut rendered = vec![
+                field("Folders", summary.directories.to_string(), theme),
+                field("Files", summary.files.to_string(), theme),
+                field("Total size", format_size(summary.size, BINARY), theme),
+            ];
+            if summary.unreadable > 0 {
+                rendered.push(Line::styled(
+                    format!("{} entries could not be read", summary.unreadable),
+                    Style::default().fg(theme.warning),
+                ));
+            }
+            rendered
+        }
+        QuickViewContent::Ready(Preview::Archive { entries, size }) => {
+            let unpacked: u64 = entries.iter().map(|entry| entry.size).sum();
+            let mut rendered = vec![
+                field("Archive size", format_size(*size, BINARY), theme),
+                field("Unpacked size", format_size(unpacked, BINARY), theme),
+                Line::default(),
+            ];
+            for entry in entries.iter().take(height.saturating_sub(rendered.len())) {
+                let path = match &entry.path {
+                    VfsPath::Archive { internal_path, .. } => internal_path.as_str(),
+                    _ => entry.name.as_str(),
+                };
+                rendered.push(match entry.entry_type {
+                    VfsEntryType::Directory => Line::styled(
+                        format!("{:>10}  {}/", "", path),
+                        Style::default().fg(theme.directory),
+                    ),
+                    _ => Line::styled(format!("{:>10}  {}", format_size(entry.size, BINARY), path), text),
+                });
+            }
+            rendered
+        }
+        QuickViewContent::Ready(Preview::Image { info, size, .. }) => vec![
+            field("Format", info.format.to_string(), theme),
+            field("Dimensions", format!("{} x {} pixels", info.width, info.height), theme),
+            field("File size", format_size(*size, BINARY), theme),
+        ],
+        QuickViewContent::Ready(Preview::Binary { bytes, .. }) => {
+            // Fit the dump to narrow panels
+            let per_row = if inner.width >= 78 { 16 } else { 8 };
+            bytes
+                .chunks(per_row)
+                .take(height)
+                .enumerate()
+                .map(|(row, chunk)| {
+                    let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    let ascii: String = chunk
+                        .iter()
+                        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+                        .collect();
+                    Line::from(vec![
+                        Span::styled(format!("{:08X}  ", row * per_row), dim),
+                        Span::styled(format!("{:<width$}", hex.join(" "), width = per_row * 3), text),
+                        Span::styled(format!(" {}", ascii), dim),
+                    ])
+                })
+                .collect()
+        }
+    };
+
+    let text_height = lines.len() as u16;
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    // The picture goes below its details
+    let QuickViewContent::Ready(Preview::Image { picture: Some(picture), .. }) = &quick_view.content else {
+        return None;
+    };
+    let picture_area = Rect {
+        y: inner.y + text_height + 1,
+        height: inner.height.saturating_sub(text_height + 1),
+        ..inner
+    };
+    render_picture(
+        picture,
+        picture_area,
+        frame.buffer_mut(),
+        GraphicsProtocol::current(),
+        cell_size(),
+    )
+}
+
+fn field(label: &str, value: String, theme: &Theme) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{:<14}", format!("{}:", label)), Style::default().fg(theme.dim_text)),
+        Span::styled(
+            value,
+            Style::default().fg(theme.normal_text).add_modifier(Modifier::BOLD),
+        ),
+    ])
+}
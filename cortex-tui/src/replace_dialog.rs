@@ -0,0 +1,308 @@
+use cortex_core::FileChanges;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use std::path::PathBuf;
+
+/// Replace a regex across the files a search found, previewing each hunk first
+#[derive(Debug, Clone)]
+pub struct ReplaceDialog {
+    /// Files the replace runs over
+    pub files: Vec<PathBuf>,
+    /// Directory the paths are shown relative to
+    pub root: PathBuf,
+    pub find: String,
+    pub replacement: String,
+    pub field: ReplaceField,
+    pub state: ReplaceState,
+    pub changes: Vec<FileChanges>,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaceField {
+    Find,
+    Replacement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaceState {
+    Setup,
+    Planning,
+    Preview,
+}
+
+/// A line of the preview list: a file, or one of its hunks
+#[derive(Debug, Clone, Copy)]
+enum Row {
+    File(usize),
+    Hunk(usize, usize),
+}
+
+impl ReplaceDialog {
+    pub fn new(files: Vec<PathBuf>, root: PathBuf, find: String) -> Self {
+        let field = if find.is_empty() { ReplaceField::Find } else { ReplaceField::Replacement };
+        Self {
+            files,
+            root,
+            find,
+            replacement: String::new(),
+            field,
+            state: ReplaceState::Setup,
+            changes: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn next_field(&mut self) {
+        self.field = match self.field {
+            ReplaceField::Find => ReplaceField::Replacement,
+            ReplaceField::Replacement => ReplaceField::Find,
+        };
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        match self.field {
+            ReplaceField::Find => self.find.push(c),
+            ReplaceField::Replacement => self.replacement.push(c),
+        }
+    }
+
+    pub fn delete_char(&mut self) {
+        match self.field {
+            ReplaceField::Find => self.find.pop(),
+            ReplaceField::Replacement => self.replacement.pop(),
+        };
+    }
+
+    /// Show the planned changes for review
+    pub fn set_changes(&mut self, changes: Vec<FileChanges>) {
+        self.changes = changes;
+        self.selected = 0;
+        self.state = ReplaceState::Preview;
+    }
+
+    /// Replacements left enabled, and the files they are in
+    pub fn totals(&self) -> (usize, usize) {
+        self.changes.iter().fold((0, 0), |(replacements, files), file| {
+            let count = file.enabled_replacements();
+            (replacements + count, files + usize::from(count > 0))
+        })
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        self.changes
+            .iter()
+            .enumerate()
+            .flat_map(|(file, changes)| {
+                std::iter::once(Row::File(file)).chain((0..changes.hunks.len()).map(move |hunk| Row::Hunk(file, hunk)))
+            })
+            .collect()
+    }
+
+    pub fn move_selection_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_selection_down(&mut self) {
+        if self.selected + 1 < self.rows().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn page_up(&mut self, rows: usize) {
+        self.selected = self.selected.saturating_sub(rows);
+    }
+
+    pub fn page_down(&mut self, rows: usize) {
+        self.selected = (self.selected + rows).min(self.rows().len().saturating_sub(1));
+    }
+
+    /// Toggle the selected hunk, or every hunk of the selected file
+    pub fn toggle_selected(&mut self) {
+        match self.rows().get(self.selected) {
+            Some(Row::File(file)) => {
+                let hunks = &mut self.changes[*file].hunks;
+                let enable = !hunks.iter().all(|hunk| hunk.enabled);
+                hunks.iter_mut().for_each(|hunk| hunk.enabled = enable);
+            }
+            Some(Row::Hunk(file, hunk)) => {
+                let hunk = &mut self.changes[*file].hunks[*hunk];
+                hunk.enabled = !hunk.enabled;
+            }
+            None => {}
+        }
+    }
+
+    fn display_path(&self, path: &std::path::Path) -> String {
+        path.strip_prefix(&self.root).unwrap_or(path).display().to_string()
+    }
+
+    pub fn render(&self, frame: &mut Frame) {
+        let area = centered_rect(90, 80, frame.size());
+        frame.render_widget(Clear, area);
+
+        let title = match self.state {
+            ReplaceState::Preview => {
+                let (replacements, files) = self.totals();
+                format!(" Replace in Files: {} replacements in {} files ", replacements, files)
+            }
+            _ => format!(" Replace in Files: {} files ", self.files.len()),
+        };
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Find
+                Constraint::Length(3), // Replacement
+                Constraint::Min(3),    // Preview
+                Constraint::Length(1), // Help
+            ])
+            .split(inner);
+
+        let editing = self.state == ReplaceState::Setup;
+        for (chunk, label, value, field) in [
+            (chunks[0], " Find (regex) ", &self.find, ReplaceField::Find),
+            (chunks[1], " Replace with ($1, ${name} insert captures) ", &self.replacement, ReplaceField::Replacement),
+        ] {
+            let focused = editing && self.field == field;
+            let input = Block::default()
+                .title(label)
+                .borders(Borders::ALL)
+                .border_style(if focused { Style::default().fg(Color::Cyan) } else { Style::default() });
+            let input_inner = input.inner(chunk);
+            frame.render_widget(input, chunk);
+            frame.render_widget(Paragraph::new(value.as_str()), input_inner);
+            if focused {
+                frame.set_cursor(input_inner.x + value.chars().count() as u16, input_inner.y);
+            }
+        }
+
+        match self.state {
+            ReplaceState::Setup => {}
+            ReplaceState::Planning => {
+                let planning = Paragraph::new("Looking for matches...")
+                    .style(Style::default().fg(Color::DarkGray))
+                    .alignment(Alignment::Center);
+                frame.render_widget(planning, chunks[2]);
+            }
+            ReplaceState::Preview if self.changes.is_empty() => {
+                let empty = Paragraph::new("Nothing to replace")
+                    .style(Style::default().fg(Color::DarkGray))
+                    .alignment(Alignment::Center);
+                frame.render_widget(empty, chunks[2]);
+            }
+            ReplaceState::Preview => self.render_preview(frame, chunks[2]),
+        }
+
+        let help = match self.state {
+            ReplaceState::Setup => "Enter: Preview | Tab: Switch field | ESC: Close",
+            ReplaceState::Planning => "ESC: Close",
+            ReplaceState::Preview => "Space: Toggle | Enter: Apply | ESC: Edit pattern",
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+            chunks[3],
+        );
+    }
+
+    fn render_preview(&self, frame: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
+
+        let rows = self.rows();
+        let checkbox = |enabled: bool| if enabled { "[x]" } else { "[ ]" };
+        let height = columns[0].height as usize;
+        let offset = (self.selected + 1).saturating_sub(height);
+        let items: Vec<ListItem> = rows
+            .iter()
+            .skip(offset)
+            .take(height)
+            .map(|row| match *row {
+                Row::File(file) => {
+                    let changes = &self.changes[file];
+                    let enabled = changes.hunks.iter().all(|hunk| hunk.enabled);
+                    ListItem::new(format!(
+                        "{} {} ({})",
+                        checkbox(enabled),
+                        self.display_path(&changes.path),
+                        changes.enabled_replacements()
+                    ))
+                    .style(Style::default().add_modifier(Modifier::BOLD))
+                }
+                Row::Hunk(file, hunk) => {
+                    let hunk = &self.changes[file].hunks[hunk];
+                    ListItem::new(format!(
+                        "    {} line {} ({})",
+                        checkbox(hunk.enabled),
+                        hunk.first_line + 1,
+                        hunk.replacements
+                    ))
+                }
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(self.selected - offset));
+        let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+        frame.render_stateful_widget(list, columns[0], &mut state);
+
+        // Diff of the selected hunk, or of the whole file
+        let (file, hunks) = match rows.get(self.selected) {
+            Some(Row::File(file)) => (*file, 0..self.changes[*file].hunks.len()),
+            Some(Row::Hunk(file, hunk)) => (*file, *hunk..*hunk + 1),
+            None => return,
+        };
+        let mut lines = Vec::new();
+        for hunk in &self.changes[file].hunks[hunks] {
+            let dim = if hunk.enabled { Modifier::empty() } else { Modifier::DIM };
+            lines.push(Line::from(Span::styled(
+                format!("@@ line {} @@", hunk.first_line + 1),
+                Style::default().fg(Color::Cyan).add_modifier(dim),
+            )));
+            for (text, sign, color) in [(&hunk.old_text, '-', Color::Red), (&hunk.new_text, '+', Color::Green)] {
+                for line in text.lines() {
+                    lines.push(Line::from(Span::styled(
+                        format!("{}{}", sign, line.replace('\t', "    ")),
+                        Style::default().fg(color).add_modifier(dim),
+                    )));
+                }
+            }
+        }
+        let diff_block = Block::default().borders(Borders::LEFT);
+        let diff_area = diff_block.inner(columns[1]);
+        frame.render_widget(diff_block, columns[1]);
+        frame.render_widget(Paragraph::new(lines), diff_area);
+    }
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
@@ -1,4 +1,4 @@
-use cortex_core::{SearchCriteria, SearchResult, SearchType};
+use cortex_core::{DateFilter, SearchCondition, SearchCriteria, SearchResult, SearchType, SizeFilter, VfsPath};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Style},
@@ -17,6 +17,14 @@ pub struct SearchDialog {
     pub search_progress: Option<SearchProgressInfo>,
     pub input_field: InputField,
     pub show_options: bool,
+    /// Directory the last search ran from
+    pub search_root: PathBuf,
+    /// Condition expression as typed, parsed into the criteria on search
+    pub conditions_input: String,
+    /// Size range as typed, e.g. `>10M` or `10K..2M`
+    pub size_input: String,
+    /// Age or date range as typed, e.g. `7d`, `>7d` or `2024-01-01..2024-06-30`
+    pub date_input: String,
 }
 
 #[derive(Debug, Clone)]
@@ -26,10 +34,13 @@ pub enum SearchState {
     Results,   // Viewing results
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputField {
     Pattern,
     Extensions,
+    Size,
+    Date,
+    Conditions,
     Directory,
 }
 
@@ -61,6 +72,9 @@ impl SearchDialog {
                 file_extensions: Vec::new(),
                 size_filter: None,
                 date_filter: None,
+                search_archives: false,
+                respect_ignore_files: true,
+                conditions: None,
             },
             state: SearchState::Setup,
             results: Vec::new(),
@@ -68,9 +82,27 @@ impl SearchDialog {
             search_progress: None,
             input_field: InputField::Pattern,
             show_options: false,
+            search_root: PathBuf::new(),
+            conditions_input: String::new(),
+            size_input: String::new(),
+            date_input: String::new(),
         }
     }
 
+    /// A dialog filled in with earlier criteria
+    pub fn with_criteria(criteria: SearchCriteria) -> Self {
+        let mut dialog = Self::new();
+        dialog.conditions_input = criteria
+            .conditions
+            .as_ref()
+            .map(|conditions| conditions.to_string())
+            .unwrap_or_default();
+        dialog.size_input = criteria.size_filter.as_ref().map(|size| size.to_string()).unwrap_or_default();
+        dialog.date_input = criteria.date_filter.as_ref().map(|date| date.to_string()).unwrap_or_default();
+        dialog.criteria = criteria;
+        dialog
+    }
+
     pub fn render(&self, frame: &mut Frame) {
         match self.state {
             SearchState::Setup => self.render_setup(frame),
@@ -80,7 +112,7 @@ impl SearchDialog {
     }
 
     fn render_setup(&self, frame: &mut Frame) {
-        let area = centered_rect(70, 60, frame.size());
+        let area = centered_rect(70, 70, frame.size());
         frame.render_widget(Clear, area);
 
         let block = Block::default()
@@ -97,6 +129,8 @@ impl SearchDialog {
                 Constraint::Length(3), // Pattern input
                 Constraint::Length(3), // Directory
                 Constraint::Length(3), // Extensions
+                Constraint::Length(3), // Size and date
+                Constraint::Length(3), // Conditions
                 Constraint::Min(5),    // Options
                 Constraint::Length(2), // Help
             ])
@@ -171,10 +205,58 @@ impl SearchDialog {
         });
         frame.render_widget(ext, ext_inner);
 
+        // Size and modification date
+        let filter_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[3]);
+        let filters = [
+            (InputField::Size, " Size (optional) ", &self.size_input, "e.g., >10M, <512K, 10K..2M"),
+            (InputField::Date, " Modified (optional) ", &self.date_input, "e.g., 7d, >30d, 2024-01-01..2024-06-30"),
+        ];
+        for ((field, title, input, hint), area) in filters.into_iter().zip(filter_chunks.iter()) {
+            let style = if self.input_field == field {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            let block = Block::default().title(title).borders(Borders::ALL).border_style(style);
+            let inner = block.inner(*area);
+            frame.render_widget(block, *area);
+            let text = if input.is_empty() {
+                Paragraph::new(hint).style(Style::default().fg(Color::DarkGray))
+            } else {
+                Paragraph::new(input.as_str()).style(Style::default().fg(Color::White))
+            };
+            frame.render_widget(text, inner);
+        }
+
+        // Conditions
+        let cond_style = if matches!(self.input_field, InputField::Conditions) {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default()
+        };
+
+        let cond_block = Block::default()
+            .title(" Conditions (optional) ")
+            .borders(Borders::ALL)
+            .border_style(cond_style);
+        let cond_inner = cond_block.inner(chunks[4]);
+        frame.render_widget(cond_block, chunks[4]);
+
+        let cond = if self.conditions_input.is_empty() {
+            Paragraph::new("e.g., type:image and not owner:root, perm:/111, empty, broken, !contains:TODO")
+                .style(Style::default().fg(Color::DarkGray))
+        } else {
+            Paragraph::new(self.conditions_input.as_str()).style(Style::default().fg(Color::White))
+        };
+        frame.render_widget(cond, cond_inner);
+
         // Options
         let options_block = Block::default().title(" Options ").borders(Borders::ALL);
-        let options_inner = options_block.inner(chunks[3]);
-        frame.render_widget(options_block, chunks[3]);
+        let options_inner = options_block.inner(chunks[5]);
+        frame.render_widget(options_block, chunks[5]);
 
         let mut options = vec![
             format!(
@@ -208,6 +290,21 @@ impl SearchDialog {
                 } else {
                     " "
                 }
+            ),            format!(
+                "[{}] Search inside archives",
+                if self.criteria.search_archives {
+                    "×"
+                } else {
+                    " "
+                }
+            ),
+            format!(
+                "[{}] Skip files ignored by .gitignore",
+                if self.criteria.respect_ignore_files {
+                    "×"
+                } else {
+                    " "
+                }
             ),
         ];
 
@@ -222,12 +319,11 @@ impl SearchDialog {
         frame.render_widget(options_list, options_inner);
 
         // Help
-        let help_text =
-            " Tab: Next field | Space: Toggle option | Enter: Start search | ESC: Cancel ";
+        let help_text = " Tab: Next field | ^T: Type | ^S: Case | ^F: Contents | ^H: Hidden | ^R: Subdirs | ^A: Archives | ^G: Gitignore | Enter: Search | ESC: Cancel ";
         let help = Paragraph::new(help_text)
             .style(Style::default().fg(Color::DarkGray))
             .alignment(Alignment::Center);
-        frame.render_widget(help, chunks[4]);
+        frame.render_widget(help, chunks[6]);
     }
 
     fn render_searching(&self, frame: &mut Frame) {
@@ -367,7 +463,7 @@ impl SearchDialog {
         }
 
         // Help
-        let help = " ↑↓: Navigate | Enter: Go to file | F3: View | F4: Edit | ESC: Close | F7: New search ";
+        let help = " ↑↓: Navigate | Enter: Go to file | F3: View | F4: Edit | F7: New search | F9: Feed to panel | Ctrl+R: Replace | ESC: Close ";
         let help_text = Paragraph::new(help)
             .style(Style::default().fg(Color::DarkGray))
             .alignment(Alignment::Center);
@@ -392,6 +488,89 @@ impl SearchDialog {
             .map(|r| r.path.clone())
     }
 
+    /// VFS location of the selected result when it is inside an archive or remote
+    pub fn get_selected_location(&self) -> Option<VfsPath> {
+        self.results
+            .get(self.selected_result)
+            .and_then(|r| r.location.clone())
+    }
+
+    /// Cycle through the pattern, extensions, size, date and conditions inputs
+    pub fn next_field(&mut self) {
+        self.input_field = match self.input_field {
+            InputField::Pattern => InputField::Extensions,
+            InputField::Extensions => InputField::Size,
+            InputField::Size => InputField::Date,
+            InputField::Date => InputField::Conditions,
+            _ => InputField::Pattern,
+        };
+    }
+
+    /// Parse the typed size, date and conditions into the criteria
+    pub fn apply_inputs(&mut self) -> anyhow::Result<()> {
+        let text = self.size_input.trim();
+        self.criteria.size_filter = match text.is_empty() {
+            true => None,
+            false => Some(SizeFilter::parse(text)?),
+        };
+        let text = self.date_input.trim();
+        self.criteria.date_filter = match text.is_empty() {
+            true => None,
+            false => Some(DateFilter::parse(text)?),
+        };
+        let text = self.conditions_input.trim();
+        self.criteria.conditions = match text.is_empty() {
+            true => None,
+            false => Some(SearchCondition::parse(text)?),
+        };
+        Ok(())
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        match self.input_field {
+            InputField::Extensions => {
+                let mut text = self.criteria.file_extensions.join(",");
+                text.push(c);
+                self.set_extensions(&text);
+            }
+            InputField::Size => self.size_input.push(c),
+            InputField::Date => self.date_input.push(c),
+            InputField::Conditions => self.conditions_input.push(c),
+            _ => self.criteria.pattern.push(c),
+        }
+    }
+
+    pub fn delete_char(&mut self) {
+        match self.input_field {
+            InputField::Extensions => {
+                let mut text = self.criteria.file_extensions.join(",");
+                text.pop();
+                self.set_extensions(&text);
+            }
+            InputField::Size => {
+                self.size_input.pop();
+            }
+            InputField::Date => {
+                self.date_input.pop();
+            }
+            InputField::Conditions => {
+                self.conditions_input.pop();
+            }
+            _ => {
+                self.criteria.pattern.pop();
+            }
+        }
+    }
+
+    // Empty entries are kept while typing so a trailing comma survives
+    fn set_extensions(&mut self, text: &str) {
+        self.criteria.file_extensions = if text.is_empty() {
+            Vec::new()
+        } else {
+            text.split(',').map(|ext| ext.trim().to_string()).collect()
+        };
+    }
+
     pub fn toggle_case_sensitive(&mut self) {
         self.criteria.case_sensitive = !self.criteria.case_sensitive;
     }
@@ -408,6 +587,14 @@ impl SearchDialog {
         self.criteria.include_subdirs = !self.criteria.include_subdirs;
     }
 
+    pub fn toggle_search_archives(&mut self) {
+        self.criteria.search_archives = !self.criteria.search_archives;
+    }
+
+    pub fn toggle_respect_ignore_files(&mut self) {
+        self.criteria.respect_ignore_files = !self.criteria.respect_ignore_files;
+    }
+
     pub fn cycle_search_type(&mut self) {
         self.criteria.search_type = match self.criteria.search_type {
             SearchType::Wildcard => SearchType::Contains,
@@ -0,0 +1,382 @@
+// Pictures in the terminal: the kitty graphics protocol or sixel where the
+// terminal understands them, and truecolor half blocks everywhere else.
+// Escape-sequence pictures cannot live in a ratatui buffer, so their cells
+// are reserved in it and the picture is written out after the frame
+
+use base64::Engine;
+use cortex_core::Picture;
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+use std::io::{self, Write};
+use std::sync::{Arc, OnceLock};
+
+/// Cell size in pixels assumed when the terminal does not report one
+const DEFAULT_CELL: (u16, u16) = (10, 20);
+/// Largest base64 payload in one kitty escape
+const KITTY_CHUNK: usize = 4096;
+/// Removes every picture shown with the kitty protocol
+pub const KITTY_DELETE_ALL: &str = "\x1b_Ga=d,q=2\x1b\\";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Sixel,
+    HalfBlocks,
+}
+
+impl GraphicsProtocol {
+    /// Guess from the environment; `CORTEX_IMAGE_PROTOCOL` set to `kitty`,
+    /// `sixel` or `blocks` overrides the guess
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        match var("CORTEX_IMAGE_PROTOCOL").as_deref() {
+            Some("kitty") => return Self::Kitty,
+            Some("sixel") => return Self::Sixel,
+            Some("blocks") => return Self::HalfBlocks,
+            _ => {}
+        }
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        // Multiplexers do not pass either protocol through
+        if var("TMUX").is_some() || term.starts_with("screen") {
+            Self::HalfBlocks
+        } else if var("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            Self::Kitty
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || matches!(program.as_str(), "iTerm.app" | "mintty")
+        {
+            Self::Sixel
+        } else {
+            Self::HalfBlocks
+        }
+    }
+
+    /// Protocol of the terminal cortex runs in, worked out once
+    pub fn current() -> Self {
+        static PROTOCOL: OnceLock<GraphicsProtocol> = OnceLock::new();
+        *PROTOCOL.get_or_init(|| Self::from_env(|name| std::env::var(name).ok()))
+    }
+}
+
+/// Size of a terminal cell in pixels
+pub fn cell_size() -> (u16, u16) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.columns > 0 && size.rows > 0 && size.width >= size.columns && size.height >= size.rows => {
+            (size.width / size.columns, size.height / size.rows)
+        }
+        _ => DEFAULT_CELL,
+    }
+}
+
+/// A picture to draw with escape sequences over cells reserved for it
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+    pub picture: Arc<Picture>,
+    pub area: Rect,
+    /// Pixel size to scale the picture to
+    pub size: (u32, u32),
+    pub protocol: GraphicsProtocol,
+}
+
+impl PartialEq for ImagePlacement {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.picture, &other.picture)
+            && self.area == other.area
+            && self.size == other.size
+            && self.protocol == other.protocol
+    }
+}
+
+impl ImagePlacement {
+    /// Escape sequence drawing the picture at the cursor
+    pub fn encode(&self) -> Vec<u8> {
+        let picture = resize(&self.picture, self.size.0, self.size.1);
+        match self.protocol {
+            GraphicsProtocol::Kitty => kitty_sequence(&picture),
+            GraphicsProtocol::Sixel => sixel_sequence(&picture),
+            GraphicsProtocol::HalfBlocks => Vec::new(),
+        }
+    }
+
+    /// Draw the picture over its reserved cells
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[{};{}H", self.area.y + 1, self.area.x + 1)?;
+        out.write_all(&self.encode())?;
+        out.flush()
+    }
+}
+
+/// Draw `picture` centered in `area`, never enlarged. Half blocks go
+/// straight into `buf`; for the other protocols the cells are reserved and
+/// the placement to write out after the frame is returned
+pub fn render_picture(
+    picture: &Arc<Picture>,
+    area: Rect,
+    buf: &mut Buffer,
+    protocol: GraphicsProtocol,
+    cell: (u16, u16),
+) -> Option<ImagePlacement> {
+    if area.is_empty() || picture.width == 0 || picture.height == 0 {
+        return None;
+    }
+    // A half block holds two pixels, one above the other
+    let cell = match protocol {
+        GraphicsProtocol::HalfBlocks => (1, 2),
+        _ => (cell.0.max(1), cell.1.max(1)),
+    };
+    let (width, height) = fit(picture, area, cell);
+    let columns = width.div_ceil(cell.0 as u32) as u16;
+    let rows = height.div_ceil(cell.1 as u32) as u16;
+    let target = Rect::new(
+        area.x + (area.width - columns) / 2,
+        area.y + (area.height - rows) / 2,
+        columns,
+        rows,
+    );
+
+    if protocol == GraphicsProtocol::HalfBlocks {
+        draw_half_blocks(&resize(picture, width, height), target, buf);
+        return None;
+    }
+    for y in target.top()..target.bottom() {
+        for x in target.left()..target.right() {
+            buf.get_mut(x, y).reset();
+            buf.get_mut(x, y).set_skip(true);
+        }
+    }
+    Some(ImagePlacement {
+        picture: picture.clone(),
+        area: target,
+        size: (width, height),
+        protocol,
+    })
+}
+
+/// Largest size the picture takes in `area` keeping its shape
+fn fit(picture: &Picture, area: Rect, cell: (u16, u16)) -> (u32, u32) {
+    let room_width = area.width as f64 * cell.0 as f64;
+    let room_height = area.height as f64 * cell.1 as f64;
+    let scale = (room_width / picture.width as f64)
+        .min(room_height / picture.height as f64)
+        .min(1.0);
+    (
+        ((picture.width as f64 * scale) as u32).max(1),
+        ((picture.height as f64 * scale) as u32).max(1),
+    )
+}
+
+/// Scale by averaging the source pixels under each target pixel
+fn resize(picture: &Picture, width: u32, height: u32) -> Picture {
+    if (width, height) == (picture.width, picture.height) {
+        return picture.clone();
+    }
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let (top, bottom) = source_span(y, height, picture.height);
+        for x in 0..width {
+            let (left, right) = source_span(x, width, picture.width);
+            let mut sum = [0u32; 4];
+            for source_y in top..bottom {
+                for source_x in left..right {
+                    for (total, channel) in sum.iter_mut().zip(picture.pixel(source_x, source_y)) {
+                        *total += channel as u32;
+                    }
+                }
+            }
+            let count = (bottom - top) * (right - left);
+            rgba.extend(sum.map(|total| (total / count) as u8));
+        }
+    }
+    Picture { width, height, rgba }
+}
+
+/// Source pixels covered by pixel `index` of `target` pixels
+fn source_span(index: u32, target: u32, source: u32) -> (u32, u32) {
+    let start = (index as u64 * source as u64 / target as u64) as u32;
+    let end = ((index as u64 + 1) * source as u64).div_ceil(target as u64) as u32;
+    (start, end.clamp(start + 1, source))
+}
+
+fn draw_half_blocks(picture: &Picture, area: Rect, buf: &mut Buffer) {
+    // Mostly transparent pixels let the panel show through
+    let color = |x: u32, y: u32| {
+        let [r, g, b, a] = (y < picture.height).then(|| picture.pixel(x, y))?;
+        (a >= 128).then_some(Color::Rgb(r, g, b))
+    };
+    for row in 0..area.height {
+        for column in 0..area.width {
+            let (x, y) = (column as u32, row as u32 * 2);
+            let cell = buf.get_mut(area.x + column, area.y + row);
+            match (color(x, y), color(x, y + 1)) {
+                (Some(top), Some(bottom)) => {
+                    cell.set_symbol("▀").set_fg(top).set_bg(bottom);
+                }
+                (Some(top), None) => {
+                    cell.set_symbol("▀").set_fg(top);
+                }
+                (None, Some(bottom)) => {
+                    cell.set_symbol("▄").set_fg(bottom);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+/// Raw RGBA sent in chunks, shown at the cursor without moving it and
+/// without the terminal answering
+fn kitty_sequence(picture: &Picture) -> Vec<u8> {
+    let payload = base64::engine::general_purpose::STANDARD.encode(&picture.rgba);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = Vec::with_capacity(payload.len() + chunks.len() * 16 + 64);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = match i {
+            0 => format!(
+                "\x1b_Ga=T,f=32,s={},v={},C=1,q=2,m={};",
+                picture.width, picture.height, more
+            ),
+            _ => format!("\x1b_Gm={};", more),
+        };
+        out.extend_from_slice(control.as_bytes());
+        out.extend_from_slice(chunk);
+        out.extend_from_slice(b"\x1b\\");
+    }
+    out
+}
+
+/// Sixel image with colors rounded to a 6x6x6 cube; transparent pixels are
+/// left as they are
+fn sixel_sequence(picture: &Picture) -> Vec<u8> {
+    let level = |channel: u8| (channel as usize * 5 + 127) / 255;
+    let colors: Vec<Option<usize>> = picture
+        .rgba
+        .chunks(4)
+        .map(|pixel| (pixel[3] >= 128).then(|| level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])))
+        .collect();
+    let (width, height) = (picture.width as usize, picture.height as usize);
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let mut used = [false; 216];
+    for &color in colors.iter().flatten() {
+        used[color] = true;
+    }
+    for color in (0..216).filter(|&color| used[color]) {
+        // Palette entries are given in percent
+        out.push_str(&format!("#{};2;{};{};{}", color, color / 36 * 20, color / 6 % 6 * 20, color % 6 * 20));
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let pixels = &colors[band * width..(band + rows) * width];
+        let mut band_colors: Vec<usize> = pixels.iter().flatten().copied().collect();
+        band_colors.sort_unstable();
+        band_colors.dedup();
+        for (n, &color) in band_colors.iter().enumerate() {
+            if n > 0 {
+                // Back to the start of the band for the next color
+                out.push('$');
+            }
+            out.push_str(&format!("#{}", color));
+            let mut runs: Vec<(char, usize)> = Vec::new();
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|&dy| pixels[dy * width + x] == Some(color))
+                    .fold(0u8, |bits, dy| bits | 1 << dy);
+                let symbol = (63 + bits) as char;
+                match runs.last_mut() {
+                    Some((last, count)) if *last == symbol => *count += 1,
+                    _ => runs.push((symbol, 1)),
+                }
+            }
+            // Nothing to draw after the last pixel of this color
+            if runs.last().is_some_and(|(symbol, _)| *symbol == '?') {
+                runs.pop();
+            }
+            for (symbol, count) in runs {
+                match count {
+                    1..=3 => out.extend(std::iter::repeat_n(symbol, count)),
+                    _ => out.push_str(&format!("!{}{}", count, symbol)),
+                }
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two pixels wide: red, green, transparent and blue rows
+    fn striped() -> Arc<Picture> {
+        let rows = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 0, 0], [0, 0, 255, 255]];
+        Arc::new(Picture {
+            width: 2,
+            height: 4,
+            rgba: rows.iter().flat_map(|pixel| pixel.repeat(2)).collect(),
+        })
+    }
+
+    #[test]
+    fn test_protocol_from_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+        assert_eq!(GraphicsProtocol::from_env(env(&[("TERM", "xterm-kitty")])), GraphicsProtocol::Kitty);
+        assert_eq!(GraphicsProtocol::from_env(env(&[("TERM_PROGRAM", "WezTerm")])), GraphicsProtocol::Kitty);
+        assert_eq!(GraphicsProtocol::from_env(env(&[("TERM", "foot")])), GraphicsProtocol::Sixel);
+        assert_eq!(
+            GraphicsProtocol::from_env(env(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")])),
+            GraphicsProtocol::HalfBlocks
+        );
+        assert_eq!(
+            GraphicsProtocol::from_env(env(&[("TERM", "xterm-256color"), ("CORTEX_IMAGE_PROTOCOL", "sixel")])),
+            GraphicsProtocol::Sixel
+        );
+        assert_eq!(GraphicsProtocol::from_env(env(&[])), GraphicsProtocol::HalfBlocks);
+    }
+
+    #[test]
+    fn test_half_blocks_render_into_buffer() {
+        let area = Rect::new(0, 0, 10, 10);
+        let mut buf = Buffer::empty(area);
+        let placement = render_picture(&striped(), area, &mut buf, GraphicsProtocol::HalfBlocks, (8, 16));
+        assert!(placement.is_none());
+
+        // Two columns and two rows of cells, centered
+        let top = buf.get(4, 4);
+        assert_eq!((top.symbol(), top.fg, top.bg), ("▀", Color::Rgb(255, 0, 0), Color::Rgb(0, 255, 0)));
+        let bottom = buf.get(5, 5);
+        assert_eq!((bottom.symbol(), bottom.fg), ("▄", Color::Rgb(0, 0, 255)));
+        assert_eq!(buf.get(3, 4).symbol(), " ");
+        assert_eq!(buf.get(4, 6).symbol(), " ");
+    }
+
+    #[test]
+    fn test_escape_protocols_reserve_cells() {
+        let area = Rect::new(0, 0, 10, 10);
+        let mut buf = Buffer::empty(area);
+        let placement = render_picture(&striped(), area, &mut buf, GraphicsProtocol::Kitty, (1, 2)).unwrap();
+        assert_eq!(placement.area, Rect::new(4, 4, 2, 2));
+        assert!(buf.get(5, 5).skip);
+        assert!(!buf.get(6, 5).skip);
+        let kitty = String::from_utf8(placement.encode()).unwrap();
+        assert!(kitty.starts_with("\x1b_Ga=T,f=32,s=2,v=4,C=1,q=2,m=0;"));
+        assert!(kitty.ends_with("\x1b\\"));
+
+        // Large pictures are shrunk to fit
+        let big = Arc::new(Picture { width: 400, height: 100, rgba: vec![255; 400 * 100 * 4] });
+        let placement = render_picture(&big, area, &mut buf, GraphicsProtocol::Sixel, (8, 16)).unwrap();
+        assert_eq!((placement.size, placement.area), ((80, 20), Rect::new(0, 4, 10, 2)));
+        let sixel = String::from_utf8(placement.encode()).unwrap();
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;80;20#215;2;100;100;100#215!80~-"));
+        assert!(sixel.ends_with("#215!80B-\x1b\\"));
+    }
+}
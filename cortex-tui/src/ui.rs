@@ -1,3 +1,5 @@
+use crate::quick_view_panel::render_quick_view;
+use crate::terminal_image::ImagePlacement;
 use cortex_core::{
     state::{AppState, PanelState, ViewMode},
     ActivePanel, FileEntry, FileType, VfsEntry, VfsEntryType,
@@ -15,7 +17,9 @@ use unicode_width::UnicodeWidthStr;
 pub struct UI;
 
 impl UI {
-    pub fn draw(frame: &mut Frame, app: &AppState) {
+    /// Draw the panels and command line; a QuickView picture drawn with
+    /// escape sequences comes back to be written out after the frame
+    pub fn draw(frame: &mut Frame, app: &AppState) -> Option<ImagePlacement> {
         let theme = app.theme_manager.get_current_theme();
 
         // FIRST: Fill entire terminal with theme background
@@ -68,20 +72,19 @@ impl UI {
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(panels_area);
-        Self::draw_panel(
-            frame,
-            panels[0],
-            &app.left_panel,
-            app.active_panel == ActivePanel::Left,
-            app,
-        );
-        Self::draw_panel(
-            frame,
-            panels[1],
-            &app.right_panel,
-            app.active_panel == ActivePanel::Right,
-            app,
-        );
+        let sides = [
+            (panels[0], &app.left_panel, ActivePanel::Left),
+            (panels[1], &app.right_panel, ActivePanel::Right),
+        ];
+        let mut picture = None;
+        for (area, panel, side) in sides {
+            let is_active = app.active_panel == side;
+            match &app.quick_view {
+                // QuickView takes over the inactive panel
+                Some(quick_view) if !is_active => picture = render_quick_view(frame, area, quick_view, theme),
+                _ => Self::draw_panel(frame, area, panel, is_active, app),
+            }
+        }
 
         // Draw command output area if visible
         if let Some(output_area) = command_output_area {
@@ -90,6 +93,17 @@ impl UI {
 
         Self::draw_command_line(frame, command_area, app, theme);
         Self::draw_status_bar(frame, status_area, app, theme);
+        picture
+    }
+
+    /// Current frame of the loading spinner, advanced by wall-clock time
+    pub(crate) fn spinner_frame() -> char {
+        const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        FRAMES[(millis / 120) as usize % FRAMES.len()]
     }
 
     fn draw_panel(
@@ -115,6 +129,12 @@ impl UI {
                         cortex_core::vfs::VfsPath::Ftp { host, username, .. } => {
                             format!(" [FTP: {}@{}] [Filter: {}] ", username, host, filter)
                         }
+                        cortex_core::vfs::VfsPath::S3 { bucket, .. } => {
+                            format!(" [S3: {}] [Filter: {}] ", bucket, filter)
+                        }
+                        cortex_core::vfs::VfsPath::GitTree { rev, .. } => {
+                            format!(" [Git: {}] [Filter: {}] ", rev, filter)
+                        }
                         _ => format!(" [Remote] [Filter: {}] ", filter),
                     }
                 } else {
@@ -143,14 +163,43 @@ impl UI {
                     } => {
                         format!(" [FTP: {}@{}:{}] ", username, host, path)
                     }
+                    cortex_core::vfs::VfsPath::S3 { bucket, key, .. } => {
+                        format!(" [S3: {}/{}] ", bucket, key)
+                    }
+                    cortex_core::vfs::VfsPath::GitTree { rev, .. } if rev.is_empty() => {
+                        " [Git: history] ".to_string()
+                    }
+                    cortex_core::vfs::VfsPath::GitTree { rev, path, .. } => {
+                        format!(" [Git: {}:/{}] ", rev, path)
+                    }
                     _ => " [Remote] ".to_string(),
                 }
             } else {
                 " [Archive] ".to_string()
             }
+        } else if let Some(ref listing) = panel.search_listing {
+            format!(" [Search: {}] {} ", listing.pattern, listing.root.display())
         } else {
             format!(" {} ", panel.current_dir.display())
         };
+        let title = if panel.loading {
+            format!(
+                "{}{} Loading... ({}) ",
+                title,
+                Self::spinner_frame(),
+                panel.vfs_entries.len()
+            )
+        } else {
+            title
+        };
+        let title = match panel.connection {
+            Some(cortex_core::ConnectionStatus::Reconnecting) => {
+                format!("{}⟳ Reconnecting... ", title)
+            }
+            Some(cortex_core::ConnectionStatus::Offline) => format!("{}✗ Offline ", title),
+            Some(cortex_core::ConnectionStatus::Connected) => format!("{}● ", title),
+            None => title,
+        };
         // First fill panel area with panel background color
         let panel_bg = Block::default().style(Style::default().bg(theme.panel_background));
         frame.render_widget(panel_bg, area);
@@ -469,7 +518,8 @@ impl UI {
         Self::draw_function_key_bar(frame, status_chunks[1], theme);
     }
 
-    fn draw_info_line(frame: &mut Frame, area: Rect, app: &AppState, theme: &cortex_core::Theme) {
+    /// Selection, git status and directory summary shown on the info line
+    fn info_line_texts(app: &AppState, theme: &cortex_core::Theme) -> (String, String, String) {
         let active_panel = app.active_panel();
         let current_entry = active_panel.current_entry();
 
@@ -507,6 +557,26 @@ impl UI {
         let theme_name = format!("{:?}", theme.mode);
         let right_text = format!("{} items | {} | {}", file_count, total_size, theme_name);
 
+        (left_text, middle_text, right_text)
+    }
+
+    /// Columns of the git status widget on an info line `width` cells wide, if shown
+    pub fn git_widget_columns(app: &AppState, theme: &cortex_core::Theme, width: u16) -> Option<std::ops::Range<u16>> {
+        let (left_text, middle_text, right_text) = Self::info_line_texts(app, theme);
+        if middle_text.is_empty() {
+            return None;
+        }
+        let (left_width, middle_width) = (left_text.width(), middle_text.width());
+        let padding_before =
+            ((width as usize).saturating_sub(left_width + middle_width + right_text.width()) / 2).max(1);
+        let start = (left_width + padding_before) as u16;
+        Some(start..start.saturating_add(middle_width as u16))
+    }
+
+    fn draw_info_line(frame: &mut Frame, area: Rect, app: &AppState, theme: &cortex_core::Theme) {
+        let active_panel = app.active_panel();
+        let (left_text, middle_text, right_text) = Self::info_line_texts(app, theme);
+
         // Calculate spacing
         let left_width = left_text.width();
         let middle_width = middle_text.width();
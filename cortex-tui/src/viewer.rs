@@ -1,20 +1,116 @@
 use anyhow::Result;
+use cortex_core::line_index::{self, LineIndex};
+use cortex_core::quick_view::{self, ImageInfo};
+use cortex_core::structured::{DocumentError, DocumentFormat, DocumentTree, MAX_DOCUMENT_SIZE};
+use cortex_core::{CancellationToken, Highlighter, Picture, TextEncoding, TokenKind};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+/// Longest part of a line shown; the rest of a longer line is skipped
+const MAX_LINE_LEN: usize = 16 * 1024;
+/// Bytes read on opening to recognize the language or image format
+const HEAD_LEN: u64 = 64 * 1024;
+
+/// Whether the viewer keeps up with a file that is being appended to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Follow {
+    Off,
+    /// Stay at the end as the file grows
+    On,
+    /// Still following, but scrolled away from the end
+    Paused,
+}
+
+/// A search or line jump, which may read the whole file and so is run off
+/// the UI thread
+#[derive(Debug, Clone)]
+pub struct Lookup {
+    path: PathBuf,
+    index: LineIndex,
+    target: LookupTarget,
+}
+
+#[derive(Debug, Clone)]
+enum LookupTarget {
+    /// The first line containing `term` from `from` on, or from the line
+    /// after it
+    Text {
+        term: String,
+        from: u64,
+        skip_first: bool,
+        encoding: TextEncoding,
+    },
+    /// A one-based line number
+    Line(u64),
+}
+
+/// Where a `Lookup` landed, for `FileViewer::finish_lookup`
+#[derive(Debug)]
+pub struct LookupResult {
+    path: PathBuf,
+    index: LineIndex,
+    target: LookupTarget,
+    found: Result<Option<u64>>,
+}
+
+impl Lookup {
+    /// Read as far as needed; `cancel` stops a search early
+    pub fn run(mut self, cancel: &CancellationToken) -> LookupResult {
+        let found = match &self.target {
+            LookupTarget::Text { term, from, skip_first, encoding } => {
+                let from = match skip_first {
+                    true => line_index::skip_lines(&self.path, *from, 1, *encoding),
+                    false => Ok(*from),
+                };
+                from.and_then(|from| line_index::find_line(&self.path, from, term, MAX_LINE_LEN, *encoding, cancel))
+            }
+            LookupTarget::Line(line) => self.index.line_start(line.saturating_sub(1)),
+        };
+        LookupResult {
+            path: self.path,
+            index: self.index,
+            target: self.target,
+            found,
+        }
+    }
+}
+
+/// Pages through a file of any size by seeking to the visible part only
 #[derive(Debug, Clone)]
 pub struct FileViewer {
     pub path: PathBuf,
+    /// `path` with symlinks and relative parts resolved
+    pub canonical_path: PathBuf,
     pub title: String,
     pub lines: Vec<String>,
-    pub offset: usize,
+    /// Byte offset of the first visible line, or hex row
+    pub top: u64,
+    /// Zero-based number of the first visible line, when known
+    pub top_line: Option<u64>,
+    /// Offset just past the visible lines
+    pub end: u64,
     pub selected_line: usize,
     pub search_term: Option<String>,
     pub hex_mode: bool,
     pub wrap_lines: bool,
     pub file_size: u64,
-    pub encoding: String,
+    /// How the text is decoded, guessed from the start of the file
+    pub encoding: TextEncoding,
+    /// Set when the file's language is known
+    pub highlighter: Option<Highlighter>,
+    pub syntax_highlighting: bool,
+    pub follow: Follow,
+    /// Set for image files, which are shown as pictures outside hex mode
+    pub image: Option<(ImageInfo, Arc<Picture>)>,
+    /// JSON, YAML or TOML laid out as a tree, or where it failed to parse
+    pub document: Option<Result<DocumentTree, DocumentError>>,
+    /// Showing the document tree rather than the text
+    pub tree_mode: bool,
+    index: LineIndex,
+    page_size: usize,
 }
 
 impl FileViewer {
@@ -29,22 +125,62 @@ impl FileViewer {
         let file = File::open(&path)?;
         let metadata = file.metadata()?;
         let file_size = metadata.len();
+        let mut head = Vec::new();
+        file.take(HEAD_LEN).read_to_end(&mut head)?;
+        let encoding = cortex_core::detect_encoding(&head);
+        let first_line = line_index::read_lines(&path, 0, 1, MAX_LINE_LEN, encoding)?
+            .0
+            .pop()
+            .map(|(_, line)| line)
+            .unwrap_or_default();
+        let highlighter = cortex_core::detect_language(&path, &first_line).map(Highlighter::new);
+        let image = quick_view::image_info(&head).and_then(|info| {
+            let picture = quick_view::load_picture(&path, quick_view::PICTURE_MAX_SIDE).ok()?;
+            Some((info, Arc::new(picture)))
+        });
+        let document = match DocumentFormat::from_path(&path) {
+            Some(format) if file_size <= MAX_DOCUMENT_SIZE => {
+                let text = encoding.decode(&std::fs::read(&path)?);
+                Some(DocumentTree::parse(&text, format))
+            }
+            _ => None,
+        };
 
         Ok(Self {
+            index: LineIndex::new(&path, encoding),
+            canonical_path: resolve_path(&path),
             path,
             title,
             lines: Vec::new(),
-            offset: 0,
+            top: 0,
+            top_line: Some(0),
+            end: 0,
             selected_line: 0,
             search_term: None,
             hex_mode: false,
             wrap_lines: true,
             file_size,
-            encoding: "UTF-8".to_string(),
+            encoding,
+            highlighter,
+            syntax_highlighting: true,
+            follow: Follow::Off,
+            image,
+            tree_mode: document.is_some(),
+            document,
+            page_size: 0,
         })
     }
 
+    /// Read the `max_lines` lines from the current position
     pub fn load_content(&mut self, max_lines: usize) -> Result<()> {
+        self.page_size = max_lines;
+        self.file_size = std::fs::metadata(&self.path)?.len();
+        self.index.refresh(self.file_size);
+        if self.top > self.file_size {
+            // Truncated under us
+            self.top = 0;
+            self.top_line = Some(0);
+        }
         if self.hex_mode {
             self.load_hex_content(max_lines)?;
         } else {
@@ -54,38 +190,16 @@ impl FileViewer {
     }
 
     fn load_text_content(&mut self, max_lines: usize) -> Result<()> {
-        let file = File::open(&self.path)?;
-        let reader = BufReader::new(file);
-
-        self.lines.clear();
-
-        for (i, line) in reader.lines().enumerate() {
-            if i < self.offset {
-                continue;
-            }
-            if self.lines.len() >= max_lines {
-                break;
-            }
-
-            match line {
-                Ok(text) => {
-                    self.lines.push(text);
-                }
-                Err(_) => {
-                    self.lines.push("<Error reading line>".to_string());
-                }
-            }
-        }
-
+        let (lines, end) = line_index::read_lines(&self.path, self.top, max_lines, MAX_LINE_LEN, self.encoding)?;
+        self.lines = lines.into_iter().map(|(_, text)| text).collect();
+        self.end = end;
         Ok(())
     }
 
     fn load_hex_content(&mut self, max_lines: usize) -> Result<()> {
-        use std::io::Read;
-
         let mut file = File::open(&self.path)?;
-        let start_pos = self.offset * 16; // 16 bytes per line in hex view
-        file.seek(SeekFrom::Start(start_pos as u64))?;
+        let start_pos = self.top; // 16 bytes per line in hex view
+        file.seek(SeekFrom::Start(start_pos))?;
 
         self.lines.clear();
         let mut buffer = [0u8; 16];
@@ -108,78 +222,435 @@ impl FileViewer {
                         })
                         .collect();
 
-                    let address = start_pos + (self.lines.len() * 16);
+                    let address = start_pos as usize + (self.lines.len() * 16);
                     self.lines
                         .push(format!("{:08X}  {:<48}  {}", address, hex_part, ascii_part));
                 }
                 Err(_) => break,
             }
         }
+        self.end = start_pos + self.lines.len() as u64 * 16;
 
         Ok(())
     }
 
-    pub fn scroll_up(&mut self, amount: usize) {
-        self.offset = self.offset.saturating_sub(amount);
+    /// Where the top of the last page is
+    fn last_page_top(&self) -> Result<u64> {
+        let rows = self.page_size.max(1) as u64;
+        if self.hex_mode {
+            return Ok((self.file_size.div_ceil(16)).saturating_sub(rows) * 16);
+        }
+        line_index::lines_before(&self.path, self.file_size.saturating_sub(1), rows - 1, self.encoding)
     }
 
-    pub fn scroll_down(&mut self, amount: usize) {
-        self.offset += amount;
+    pub fn scroll_up(&mut self, amount: usize) -> Result<()> {
+        if self.follow == Follow::On {
+            self.follow = Follow::Paused;
+        }
+        if self.hex_mode {
+            self.top = self.top.saturating_sub(amount as u64 * 16);
+        } else {
+            self.top = line_index::lines_before(&self.path, self.top, amount as u64, self.encoding)?;
+            self.top_line = match self.top {
+                0 => Some(0),
+                _ => self.top_line.map(|line| line.saturating_sub(amount as u64)),
+            };
+        }
+        Ok(())
     }
 
-    pub fn page_up(&mut self, page_size: usize) {
-        self.scroll_up(page_size);
+    pub fn scroll_down(&mut self, amount: usize) -> Result<()> {
+        let last = self.last_page_top()?;
+        if self.top >= last {
+            return Ok(());
+        }
+        if self.hex_mode {
+            self.top = (self.top + amount as u64 * 16).min(last);
+            return Ok(());
+        }
+        let next = line_index::skip_lines(&self.path, self.top, amount as u64, self.encoding)?;
+        if next > last {
+            self.top = last;
+            self.top_line = self.index.line_at(last)?;
+        } else {
+            self.top = next;
+            self.top_line = self.top_line.map(|line| line + amount as u64);
+        }
+        Ok(())
     }
 
-    pub fn page_down(&mut self, page_size: usize) {
-        self.scroll_down(page_size);
+    pub fn page_up(&mut self, page_size: usize) -> Result<()> {
+        self.scroll_up(page_size)
     }
 
-    pub fn toggle_hex_mode(&mut self) {
+    pub fn page_down(&mut self, page_size: usize) -> Result<()> {
+        self.scroll_down(page_size)
+    }
+
+    pub fn home(&mut self) {
+        if self.follow == Follow::On {
+            self.follow = Follow::Paused;
+        }
+        self.top = 0;
+        self.top_line = Some(0);
+    }
+
+    pub fn end(&mut self) -> Result<()> {
+        if self.follow == Follow::Paused {
+            self.follow = Follow::On;
+        }
+        self.top = self.last_page_top()?;
+        self.top_line = self.index.line_at(self.top)?;
+        Ok(())
+    }
+
+    /// Show the line `offset` falls in at the top
+    pub fn jump_to_offset(&mut self, offset: u64) -> Result<()> {
+        let offset = offset.min(self.file_size.saturating_sub(1));
+        if self.hex_mode {
+            self.top = offset / 16 * 16;
+        } else {
+            self.top = line_index::lines_before(&self.path, offset, 0, self.encoding)?;
+            self.top_line = self.index.line_at(self.top)?;
+        }
+        Ok(())
+    }
+
+    /// Jump to a percentage of the file (`50%`) or a byte offset (`@1024`,
+    /// `0x400`) at once, or give the lookup of a one-based line number to run
+    /// in the background
+    pub fn goto(&mut self, target: &str) -> Result<Option<Lookup>> {
+        if self.follow == Follow::On {
+            self.follow = Follow::Paused;
+        }
+        let target = target.trim();
+        if let Some(percent) = target.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse()?;
+            let offset = (self.file_size as f64 * percent.clamp(0.0, 100.0) / 100.0) as u64;
+            return self.jump_to_offset(offset).map(|_| None);
+        }
+        if let Some(hex) = target.strip_prefix("0x").or_else(|| target.strip_prefix("0X")) {
+            return self.jump_to_offset(u64::from_str_radix(hex, 16)?).map(|_| None);
+        }
+        if let Some(offset) = target.strip_prefix('@') {
+            return self.jump_to_offset(offset.trim().parse()?).map(|_| None);
+        }
+
+        let line: u64 = target.parse()?;
+        Ok(Some(self.lookup(LookupTarget::Line(line))))
+    }
+
+    fn lookup(&self, target: LookupTarget) -> Lookup {
+        Lookup {
+            path: self.path.clone(),
+            index: self.index.clone(),
+            target,
+        }
+    }
+
+    /// Show where a background search or line jump landed
+    pub fn finish_lookup(&mut self, result: LookupResult) -> Result<()> {
+        // Lines end elsewhere once the encoding changed
+        if result.path != self.path || result.index.encoding() != self.encoding {
+            return Ok(());
+        }
+        // Keep how far the lookup indexed the file, unless it shrank since
+        if result.index.scanned_bytes() > self.index.scanned_bytes() {
+            self.index = result.index;
+            self.index.refresh(self.file_size);
+        }
+        let start = match (result.found?, &result.target) {
+            (Some(start), _) => start,
+            (None, LookupTarget::Text { term, .. }) => anyhow::bail!("No more matches for {}", term),
+            (None, LookupTarget::Line(line)) => anyhow::bail!("The file has fewer than {} lines", line),
+        };
+        if self.follow == Follow::On {
+            self.follow = Follow::Paused;
+        }
+        match result.target {
+            LookupTarget::Line(line) if !self.hex_mode => {
+                self.top = start;
+                self.top_line = Some(line.saturating_sub(1));
+            }
+            _ => self.jump_to_offset(start)?,
+        }
+        self.selected_line = 0;
+        self.load_content(self.page_size)
+    }
+
+    /// Whether `path` names the viewed file, however it is spelled
+    pub fn is_viewing(&self, path: &Path) -> bool {
+        resolve_path(path) == self.canonical_path
+    }
+
+    /// Follow the end of the file as it grows, or stop following
+    pub fn toggle_follow(&mut self) -> Result<()> {
+        match self.follow {
+            Follow::Off => {
+                self.follow = Follow::On;
+                self.end()
+            }
+            Follow::On | Follow::Paused => {
+                self.follow = Follow::Off;
+                Ok(())
+            }
+        }
+    }
+
+    /// Stop or resume scrolling along while following
+    pub fn toggle_follow_pause(&mut self) -> Result<()> {
+        match self.follow {
+            Follow::Off => Ok(()),
+            Follow::On => {
+                self.follow = Follow::Paused;
+                Ok(())
+            }
+            Follow::Paused => self.end(),
+        }
+    }
+
+    /// Catch up with changes to the file while following it; true when the
+    /// view changed
+    pub fn refresh_follow(&mut self) -> Result<bool> {
+        let size = std::fs::metadata(&self.path)?.len();
+        if self.follow == Follow::Off || size == self.file_size {
+            return Ok(false);
+        }
+        if size < self.file_size {
+            if let Some(highlighter) = &mut self.highlighter {
+                highlighter.invalidate_from(0);
+            }
+        }
+        self.file_size = size;
+        self.index.refresh(size);
+        if self.follow == Follow::On {
+            self.end()?;
+        } else if self.top > size {
+            self.top = 0;
+            self.top_line = Some(0);
+        }
+        self.load_content(self.page_size)?;
+        Ok(true)
+    }
+
+    pub fn toggle_hex_mode(&mut self) -> Result<()> {
         self.hex_mode = !self.hex_mode;
-        self.offset = 0;
+        // Stay at the same place in the file
+        if self.hex_mode {
+            self.top = self.top / 16 * 16;
+        } else {
+            self.top = line_index::lines_before(&self.path, self.top, 0, self.encoding)?;
+            self.top_line = self.index.line_at(self.top)?;
+        }
+        Ok(())
+    }
+
+    /// The document tree, while it is shown
+    pub fn tree(&mut self) -> Option<&mut DocumentTree> {
+        match &mut self.document {
+            Some(Ok(tree)) if self.tree_mode => Some(tree),
+            _ => None,
+        }
+    }
+
+    /// Switch between the document tree and the text; leaving a document
+    /// that failed to parse goes to the line of the error
+    pub fn toggle_tree_mode(&mut self) -> Result<()> {
+        if self.document.is_none() {
+            return Ok(());
+        }
+        self.tree_mode = !self.tree_mode;
+        if let (false, Some(Err(error))) = (self.tree_mode, &self.document) {
+            self.hex_mode = false;
+            // Documents are small enough to look the line up right away
+            let start = self
+                .index
+                .line_start(error.line.saturating_sub(1) as u64)?
+                .unwrap_or_default();
+            self.top = start;
+            self.top_line = self.index.line_at(start)?;
+        }
+        Ok(())
     }
 
     pub fn toggle_wrap(&mut self) {
         self.wrap_lines = !self.wrap_lines;
     }
 
-    pub fn search(&mut self, term: &str) -> Option<usize> {
-        self.search_term = Some(term.to_string());
-
-        for (i, line) in self.lines.iter().enumerate() {
-            if line.to_lowercase().contains(&term.to_lowercase()) {
-                self.selected_line = i;
-                return Some(i);
-            }
+    /// Decode the text with the next encoding, for files guessed wrong; its
+    /// lines may end elsewhere, so the index starts over
+    pub fn cycle_encoding(&mut self) -> Result<()> {
+        self.encoding = self.encoding.next();
+        self.index = LineIndex::new(&self.path, self.encoding);
+        if let Some(highlighter) = &mut self.highlighter {
+            highlighter.invalidate_from(0);
         }
+        if !self.hex_mode {
+            self.top = line_index::lines_before(&self.path, self.top, 0, self.encoding)?;
+            self.top_line = self.index.line_at(self.top)?;
+        }
+        Ok(())
+    }
 
-        None
+    pub fn toggle_syntax_highlighting(&mut self) {
+        self.syntax_highlighting = !self.syntax_highlighting;
     }
 
-    pub fn search_next(&mut self) -> Option<usize> {
-        if let Some(term) = &self.search_term.clone() {
-            for (i, line) in self.lines.iter().enumerate().skip(self.selected_line + 1) {
-                if line.to_lowercase().contains(&term.to_lowercase()) {
-                    self.selected_line = i;
-                    return Some(i);
+    /// Syntax tokens of the loaded line at `index`, none in hex mode or when
+    /// highlighting is off
+    pub fn line_tokens(&mut self, index: usize) -> Vec<(Range<usize>, TokenKind)> {
+        match (&mut self.highlighter, self.lines.get(index)) {
+            (Some(highlighter), Some(line)) if self.syntax_highlighting && !self.hex_mode => {
+                match self.top_line {
+                    Some(top) => highlighter.highlight_line(top as usize + index, line),
+                    None => highlighter.highlight_detached(line),
                 }
             }
+            _ => Vec::new(),
         }
-        None
+    }
+
+    /// Start looking through the file for `term` from the top line down
+    pub fn search(&mut self, term: &str) -> Lookup {
+        self.search_term = Some(term.to_string());
+        self.lookup(LookupTarget::Text {
+            term: term.to_string(),
+            from: self.top,
+            skip_first: false,
+            encoding: self.encoding,
+        })
+    }
+
+    /// Look for the last search term again below the top line
+    pub fn search_next(&mut self) -> Option<Lookup> {
+        let term = self.search_term.clone()?;
+        Some(self.lookup(LookupTarget::Text {
+            term,
+            from: self.top,
+            skip_first: true,
+            encoding: self.encoding,
+        }))
+    }
+
+    /// The picture to show in place of the text
+    pub fn picture(&self) -> Option<&Arc<Picture>> {
+        self.image.as_ref().filter(|_| !self.hex_mode).map(|(_, picture)| picture)
     }
 
     pub fn get_status(&self) -> String {
+        if let (Some(document), true) = (&self.document, self.tree_mode) {
+            return match document {
+                Ok(tree) => format!(
+                    "TREE | {} | {} | {}/{}{}",
+                    tree.format.name(),
+                    tree.selected_path().unwrap_or_else(|| ".".to_string()),
+                    tree.selected + 1,
+                    tree.rows.len(),
+                    tree.query.as_ref().map_or(String::new(), |query| format!(" | QUERY {}", query))
+                ),
+                Err(error) => format!(
+                    "TREE | Parse error at line {}, column {}",
+                    error.line, error.column
+                ),
+            };
+        }
+        if let (Some((info, _)), false) = (&self.image, self.hex_mode) {
+            return format!(
+                "IMAGE | {} | {} x {} pixels | Size: {}",
+                info.format,
+                info.width,
+                info.height,
+                humansize::format_size(self.file_size, humansize::BINARY)
+            );
+        }
         let mode = if self.hex_mode { "HEX" } else { "TEXT" };
         let wrap = if self.wrap_lines { "WRAP" } else { "NOWRAP" };
+        let syntax = match &self.highlighter {
+            Some(highlighter) if self.syntax_highlighting => highlighter.language().name,
+            Some(_) => "No highlighting",
+            None => "Plain",
+        };
+
+        let line = match self.top_line {
+            Some(line) if !self.hex_mode => (line + self.selected_line as u64 + 1).to_string(),
+            _ => "?".to_string(),
+        };
+        let percent = match self.file_size {
+            0 => 100,
+            size => self.end.min(size) * 100 / size,
+        };
+        let follow = match self.follow {
+            Follow::Off => String::new(),
+            Follow::On => " | FOLLOW".to_string(),
+            Follow::Paused => format!(
+                " | PAUSED (+{})",
+                humansize::format_size(self.file_size.saturating_sub(self.end), humansize::BINARY)
+            ),
+        };
 
         format!(
-            "{} | {} | Line: {} | Size: {} | {}",
+            "{} | {} | {} | Line: {} | {}% | Size: {} | {}{}",
             mode,
-            self.encoding,
-            self.offset + self.selected_line + 1,
+            syntax,
+            self.encoding.name(),
+            line,
+            percent,
             humansize::format_size(self.file_size, humansize::BINARY),
-            wrap
+            wrap,
+            follow
         )
     }
 }
+
+/// `path` with symlinks resolved, going by its directory once the file itself
+/// is gone
+fn resolve_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => std::fs::canonicalize(dir).map_or_else(|_| path.to_path_buf(), |dir| dir.join(name)),
+        _ => path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookups_reach_past_the_loaded_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.log");
+        let content: String = (0..5000).map(|i| format!("entry {}\n", i)).collect();
+        std::fs::write(&path, &content).unwrap();
+        let start_of = |line: usize| content.find(&format!("entry {}\n", line)).unwrap() as u64;
+
+        let mut viewer = FileViewer::new(&path).unwrap();
+        viewer.load_content(20).unwrap();
+        let cancel = CancellationToken::new();
+
+        let lookup = viewer.search("ENTRY 4321");
+        viewer.finish_lookup(lookup.run(&cancel)).unwrap();
+        assert_eq!(viewer.top, start_of(4321));
+        assert_eq!(viewer.lines[0], "entry 4321");
+
+        let lookup = viewer.search_next().unwrap();
+        assert!(viewer.finish_lookup(lookup.run(&cancel)).is_err());
+        assert_eq!(viewer.top, start_of(4321));
+
+        let lookup = viewer.goto("3001").unwrap().unwrap();
+        viewer.finish_lookup(lookup.run(&cancel)).unwrap();
+        assert_eq!((viewer.top, viewer.top_line), (start_of(3000), Some(3000)));
+        let lookup = viewer.goto("6000").unwrap().unwrap();
+        assert!(viewer.finish_lookup(lookup.run(&cancel)).is_err());
+        assert!(viewer.goto("50%").unwrap().is_none());
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("current.log");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            let followed = FileViewer::new(&link).unwrap();
+            assert!(followed.is_viewing(&path));
+            assert!(followed.is_viewing(&dir.path().join(".").join("big.log")));
+            assert!(!followed.is_viewing(&link.with_file_name("other.log")));
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::viewer::FileViewer;
+use crate::highlight::syntax_spans;
+use crate::terminal_image::{cell_size, render_picture, GraphicsProtocol, ImagePlacement};
+use crate::viewer::{FileViewer, Follow};
+use cortex_core::structured::{DocumentError, DocumentTree, NodeKind};
+use cortex_core::{Highlighter, Theme, TokenKind};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -6,12 +10,23 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct ViewerDialog {
     pub viewer: FileViewer,
     pub search_mode: bool,
     pub search_input: String,
+    /// Reading a line, percentage or offset to jump to
+    pub goto_mode: bool,
+    pub goto_input: String,
+    /// Reading a jq-style path to narrow the document tree to
+    pub query_mode: bool,
+    pub query_input: String,
+    /// Shown instead of the status line until the next key
+    pub message: Option<String>,
+    /// Picture drawn with escape sequences by the last render
+    pub image: Option<ImagePlacement>,
 }
 
 impl ViewerDialog {
@@ -20,15 +35,22 @@ impl ViewerDialog {
             viewer,
             search_mode: false,
             search_input: String::new(),
+            goto_mode: false,
+            goto_input: String::new(),
+            query_mode: false,
+            query_input: String::new(),
+            message: None,
+            image: None,
         }
     }
 
-    pub fn render(&mut self, frame: &mut Frame) {
-        let area = centered_rect(90, 90, frame.size());
-        frame.render_widget(Clear, area);
+    /// Lines of the file shown at once on a screen of size `screen`
+    pub fn content_height(screen: Rect) -> usize {
+        Self::layout(centered_rect(90, 90, screen))[1].height as usize
+    }
 
-        // Main layout
-        let chunks = Layout::default()
+    fn layout(area: Rect) -> Rc<[Rect]> {
+        Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3), // Title
@@ -36,10 +58,21 @@ impl ViewerDialog {
                 Constraint::Length(2), // Status
                 Constraint::Length(2), // Help
             ])
-            .split(area);
+            .split(area)
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, theme: &Theme) {
+        let area = centered_rect(90, 90, frame.size());
+        frame.render_widget(Clear, area);
+
+        // Main layout
+        let chunks = Self::layout(area);
 
         // Title
-        let title = format!(" Viewing: {} ", self.viewer.title);
+        let title = match self.viewer.follow {
+            Follow::Off => format!(" Viewing: {} ", self.viewer.title),
+            _ => format!(" Following: {} ", self.viewer.title),
+        };
         let title_block = Block::default()
             .title(title)
             .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
@@ -54,41 +87,29 @@ impl ViewerDialog {
         let inner = content_block.inner(chunks[1]);
         frame.render_widget(content_block, chunks[1]);
 
-        // Render file content
-        let visible_lines: Vec<Line> = self
-            .viewer
-            .lines
-            .iter()
-            .enumerate()
-            .map(|(i, line)| {
-                let mut style = Style::default();
-
-                // Highlight search matches
-                if let Some(ref term) = self.viewer.search_term {
-                    if line.to_lowercase().contains(&term.to_lowercase()) {
-                        style = style.bg(Color::Yellow).fg(Color::Black);
-                    }
-                }
-
-                // Highlight selected line
-                if i == self.viewer.selected_line {
-                    style = style.add_modifier(Modifier::REVERSED);
-                }
-
-                // Apply syntax highlighting for common patterns
-                if !self.viewer.hex_mode {
-                    Line::from(self.apply_syntax_highlighting(line))
-                } else {
-                    Line::from(vec![Span::styled(line.clone(), style)])
-                }
-            })
-            .collect();
-
-        let content = Paragraph::new(visible_lines).wrap(Wrap { trim: false });
-        frame.render_widget(content, inner);
+        // Documents show their tree until switched to text, and image files
+        // the picture until switched to hex
+        let tree_shown = self.viewer.tree_mode && self.viewer.document.is_some();
+        self.image = match self.viewer.picture().cloned() {
+            _ if tree_shown => {
+                self.render_document(frame, inner, theme);
+                None
+            }
+            Some(picture) => render_picture(
+                &picture,
+                inner,
+                frame.buffer_mut(),
+                GraphicsProtocol::current(),
+                cell_size(),
+            ),
+            None => {
+                self.render_text(frame, inner, theme);
+                None
+            }
+        };
 
         // Status bar
-        let status = self.viewer.get_status();
+        let status = self.message.clone().unwrap_or_else(|| self.viewer.get_status());
         let status_block = Block::default()
             .borders(Borders::LEFT | Borders::RIGHT)
             .border_style(Style::default().fg(Color::Cyan));
@@ -99,8 +120,30 @@ impl ViewerDialog {
             .alignment(Alignment::Center);
         frame.render_widget(status_text, status_inner);
 
-        // Help line or search input
-        if self.search_mode {
+        // Help line, or search, goto or query input
+        if self.query_mode {
+            let query_block = Block::default()
+                .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(" Path (.key, [0], [], |), empty for all: ");
+            let query_inner = query_block.inner(chunks[3]);
+            frame.render_widget(query_block, chunks[3]);
+            let query_text = Paragraph::new(self.query_input.as_str())
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(query_text, query_inner);
+            frame.set_cursor(query_inner.x + self.query_input.len() as u16, query_inner.y);
+        } else if self.goto_mode {
+            let goto_block = Block::default()
+                .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(" Go to line, N% or @offset: ");
+            let goto_inner = goto_block.inner(chunks[3]);
+            frame.render_widget(goto_block, chunks[3]);
+            let goto_text = Paragraph::new(self.goto_input.as_str())
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(goto_text, goto_inner);
+            frame.set_cursor(goto_inner.x + self.goto_input.len() as u16, goto_inner.y);
+        } else if self.search_mode {
             let search_block = Block::default()
                 .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
                 .border_style(Style::default().fg(Color::Yellow))
@@ -117,10 +160,18 @@ impl ViewerDialog {
                 search_inner.y,
 );
         } else {
-            let help_text = if self.viewer.hex_mode {
-                " ESC/F3: Exit | H: Toggle Hex | /: Search | F: Search Next | ↑↓: Scroll | PgUp/PgDn: Page "
+            let help_text = if tree_shown {
+                " ESC/F3: Exit | V: Text | Enter: Fold | Left/Right | *: Expand all | -: Collapse all | .: Query | Y: Copy path | J: Pretty JSON "
+            } else if self.viewer.picture().is_some() {
+                " ESC/F3: Exit | H: Hex | E: Edit as hex "
+            } else if self.viewer.hex_mode {
+                " ESC/F3: Exit | H: Toggle Hex | G: Go to | E: Edit | T: Follow | P: Pause | /: Search | F: Search Next | Home/End "
             } else {
-                " ESC/F3: Exit | W: Toggle Wrap | /: Search | F: Find Next | ↑↓: Scroll | PgUp/PgDn: Page "
+                " ESC/F3: Exit | H: Hex | W: Wrap | S: Syntax | C: Encoding | G: Go to | T: Follow | P: Pause | /: Search | F: Find Next | Home/End "
+            };
+            let help_text = match (&self.viewer.document, self.viewer.tree_mode) {
+                (Some(_), false) => format!("{}| V: Tree ", help_text),
+                _ => help_text.to_string(),
             };
 
             let help_block = Block::default()
@@ -135,54 +186,122 @@ impl ViewerDialog {
         }
     }
 
-    fn apply_syntax_highlighting(&self, line: &str) -> Vec<Span<'_>> {
-        let mut spans = Vec::new();
-
-        // Simple syntax highlighting for common patterns
-        if line.trim_start().starts_with("//") || line.trim_start().starts_with("#") {
-            // Comments
-            spans.push(Span::styled(
-                line.to_string(),
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::ITALIC),
-            ));
-        } else if line.contains("fn ")
-            || line.contains("def ")
-            || line.contains("function ")
-            || line.contains("class ")
-        {
-            // Function/class definitions
-            spans.push(Span::styled(
-                line.to_string(),
-                Style::default().fg(Color::Yellow),
-            ));
-        } else if line.contains("use ")
-            || line.contains("import ")
-            || line.contains("include ")
-            || line.contains("require ")
-        {
-            // Imports
-            spans.push(Span::styled(
-                line.to_string(),
-                Style::default().fg(Color::Magenta),
-            ));
-        } else if line.contains("pub ")
-            || line.contains("const ")
-            || line.contains("let ")
-            || line.contains("var ")
-        {
-            // Keywords
-            spans.push(Span::styled(
-                line.to_string(),
-                Style::default().fg(Color::Cyan),
-            ));
-        } else {
-            spans.push(Span::raw(line.to_string()));
+    fn render_document(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let lines = match &mut self.viewer.document {
+            Some(Ok(tree)) => tree_lines(tree, area.height as usize, theme),
+            Some(Err(error)) => error_lines(error, theme),
+            None => Vec::new(),
+        };
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn render_text(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let mut visible_lines = Vec::with_capacity(self.viewer.lines.len());
+        for i in 0..self.viewer.lines.len() {
+            let tokens = self.viewer.line_tokens(i);
+            let line = &self.viewer.lines[i];
+            let mut style = Style::default();
+
+            // Highlight search matches
+            let matched = self
+                .viewer
+                .search_term
+                .as_ref()
+                .is_some_and(|term| line.to_lowercase().contains(&term.to_lowercase()));
+            if matched {
+                style = style.bg(Color::Yellow).fg(Color::Black);
+            }
+
+            // Highlight selected line
+            if i == self.viewer.selected_line {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+
+            if matched {
+                visible_lines.push(Line::from(Span::styled(line.clone(), style)));
+            } else {
+                visible_lines.push(Line::from(syntax_spans(line, &tokens, theme, style)));
+            }
         }
 
-        spans
+        let content = Paragraph::new(visible_lines).wrap(Wrap { trim: false });
+        frame.render_widget(content, area);
+    }
+}
+
+/// The rows of the tree on screen, or its pretty-printed text
+fn tree_lines(tree: &mut DocumentTree, height: usize, theme: &Theme) -> Vec<Line<'static>> {
+    tree.scroll_into_view(height);
+    if let Some(pretty) = &tree.pretty {
+        let highlighter = cortex_core::detect_language(std::path::Path::new("pretty.json"), "").map(Highlighter::new);
+        return pretty
+            .iter()
+            .skip(tree.offset)
+            .take(height)
+            .map(|line| {
+                let tokens = highlighter.as_ref().map(|h| h.highlight_detached(line)).unwrap_or_default();
+                Line::from(syntax_spans(line, &tokens, theme, Style::default()))
+            })
+            .collect();
     }
+
+    let key_style = Style::default().fg(Color::Cyan);
+    tree.rows
+        .iter()
+        .enumerate()
+        .skip(tree.offset)
+        .take(height)
+        .map(|(index, row)| {
+            let marker = match row.expanded {
+                Some(true) => "▾ ",
+                Some(false) => "▸ ",
+                None => "  ",
+            };
+            let value_style = match row.kind {
+                NodeKind::String => theme.get_syntax_style(TokenKind::String),
+                NodeKind::Number => theme.get_syntax_style(TokenKind::Number),
+                NodeKind::Bool | NodeKind::Null => theme.get_syntax_style(TokenKind::Constant),
+                NodeKind::Array | NodeKind::Object => Style::default().fg(theme.dim_text),
+            };
+            let mut spans = vec![Span::raw(format!("{}{}", "  ".repeat(row.depth), marker))];
+            if !row.label.is_empty() {
+                spans.push(Span::styled(row.label.clone(), key_style));
+                spans.push(Span::raw(": "));
+            }
+            spans.push(Span::styled(row.value.clone(), value_style));
+            let line = Line::from(spans);
+            match index == tree.selected {
+                true => line.style(Style::default().add_modifier(Modifier::REVERSED)),
+                false => line,
+            }
+        })
+        .collect()
+}
+
+/// Why the document did not parse, with its line and a caret under the column
+fn error_lines(error: &DocumentError, theme: &Theme) -> Vec<Line<'static>> {
+    let gutter = format!("{} | ", error.line);
+    vec![
+        Line::styled(
+            format!("The document does not parse: {}", error.message),
+            Style::default().fg(theme.error),
+        ),
+        Line::styled(
+            format!("Line {}, column {}", error.line, error.column),
+            Style::default().fg(theme.dim_text),
+        ),
+        Line::default(),
+        Line::from(vec![
+            Span::styled(gutter.clone(), Style::default().fg(theme.dim_text)),
+            Span::raw(error.excerpt.clone()),
+        ]),
+        Line::styled(
+            format!("{}^", " ".repeat(gutter.len() + error.column.saturating_sub(1))),
+            Style::default().fg(theme.error),
+        ),
+        Line::default(),
+        Line::styled("Press V to see the text at the error", Style::default().fg(theme.dim_text)),
+    ]
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {